@@ -0,0 +1,96 @@
+//! Module implementing Solidity custom errors declared in contract ABIs.
+//!
+//! Note that `ethabi` does not support `error` ABI entries and fails to parse
+//! ABIs that contain them, so they are handled separately here.
+
+use crate::hash::{self, H32};
+use ethabi::{Error as AbiError, Param, Token};
+use serde::Deserialize;
+
+/// A Solidity custom error declared in a contract ABI.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct CustomError {
+    /// The name of the error.
+    pub name: String,
+    /// The error parameters.
+    #[serde(default)]
+    pub inputs: Vec<Param>,
+}
+
+impl CustomError {
+    /// Compute the error signature in the standard ABI format.
+    pub fn abi_signature(&self) -> String {
+        format!(
+            "{}({})",
+            self.name,
+            self.inputs
+                .iter()
+                .map(|input| input.kind.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+        )
+    }
+
+    /// Compute the 4-byte selector that prefixes the revert data of this
+    /// error. It is computed the same way as a function selector.
+    pub fn selector(&self) -> H32 {
+        hash::function_selector(self.abi_signature())
+    }
+
+    /// Decodes the error parameters from revert data. Returns an error if the
+    /// data is not prefixed with this error's selector or if the parameters
+    /// cannot be decoded.
+    pub fn decode(&self, data: &[u8]) -> Result<Vec<Token>, AbiError> {
+        if data.len() < 4 || data[..4] != self.selector()[..] {
+            return Err(AbiError::InvalidData);
+        }
+
+        let kinds = self
+            .inputs
+            .iter()
+            .map(|input| input.kind.clone())
+            .collect::<Vec<_>>();
+        ethabi::decode(&kinds, &data[4..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethabi::{ParamType, Uint};
+
+    fn insufficient_balance() -> CustomError {
+        serde_json::from_str(
+            r#"{
+                "type": "error",
+                "name": "InsufficientBalance",
+                "inputs": [
+                    { "name": "available", "type": "uint256" },
+                    { "name": "required", "type": "uint256" }
+                ]
+            }"#,
+        )
+        .expect("error parsing custom error")
+    }
+
+    #[test]
+    fn custom_error_signature() {
+        let error = insufficient_balance();
+        assert_eq!(
+            error.abi_signature(),
+            "InsufficientBalance(uint256,uint256)"
+        );
+        assert_eq!(error.selector(), [0xcf, 0x47, 0x91, 0x81]);
+        assert_eq!(error.inputs[0].kind, ParamType::Uint(256));
+    }
+
+    #[test]
+    fn decode_custom_error() {
+        let error = insufficient_balance();
+        let tokens = vec![Token::Uint(Uint::from(1)), Token::Uint(Uint::from(2))];
+        let data = [&error.selector()[..], &ethabi::encode(&tokens)].concat();
+
+        assert_eq!(error.decode(&data).unwrap(), tokens);
+        assert!(error.decode(&data[4..]).is_err());
+    }
+}
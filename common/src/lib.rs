@@ -5,12 +5,14 @@
 
 pub mod abiext;
 pub mod bytecode;
+pub mod customerror;
 pub mod errors;
 pub mod hash;
 pub mod truffle;
 
 pub use crate::abiext::FunctionExt;
 pub use crate::bytecode::Bytecode;
+pub use crate::customerror::CustomError;
 pub use crate::truffle::Artifact;
 pub use ethabi::{self as abi, Contract as Abi};
 pub use web3::types::Address;
@@ -1,23 +1,28 @@
 //! Module for reading and examining data produced by truffle.
 
 use crate::bytecode::Bytecode;
+use crate::customerror::CustomError;
 use crate::errors::ArtifactError;
 use ethabi::Contract as Abi;
 use serde::Deserialize;
+use serde_json::{Error as JsonError, Value};
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::fs::File;
 use std::path::Path;
 use web3::types::{Address, H256};
 
 /// Represents a truffle artifact.
 #[derive(Clone, Debug, Deserialize)]
-#[serde(default = "Artifact::empty")]
+#[serde(try_from = "RawArtifact")]
 pub struct Artifact {
     /// The contract name
-    #[serde(rename = "contractName")]
     pub contract_name: String,
     /// The contract ABI
     pub abi: Abi,
+    /// The Solidity custom errors declared in the contract ABI by name. These
+    /// are kept separately since they are not supported by `ethabi`.
+    pub errors: HashMap<String, Vec<CustomError>>,
     /// The contract deployment bytecode.
     pub bytecode: Bytecode,
     /// The configured networks by network ID for the contract.
@@ -39,6 +44,7 @@ impl Artifact {
                 events: HashMap::new(),
                 fallback: false,
             },
+            errors: HashMap::new(),
             bytecode: Default::default(),
             networks: HashMap::new(),
             devdoc: Default::default(),
@@ -66,6 +72,50 @@ impl Artifact {
     }
 }
 
+/// The raw JSON representation of a truffle artifact, used for separating the
+/// custom error entries from the rest of the ABI before parsing it.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct RawArtifact {
+    #[serde(rename = "contractName")]
+    contract_name: String,
+    abi: Vec<Value>,
+    bytecode: Bytecode,
+    networks: HashMap<String, Network>,
+    devdoc: Documentation,
+    userdoc: Documentation,
+}
+
+impl TryFrom<RawArtifact> for Artifact {
+    type Error = JsonError;
+
+    fn try_from(raw: RawArtifact) -> Result<Self, Self::Error> {
+        let (errors, entries): (Vec<_>, Vec<_>) = raw
+            .abi
+            .into_iter()
+            .partition(|entry| entry.get("type").and_then(Value::as_str) == Some("error"));
+
+        let mut custom_errors = HashMap::<_, Vec<_>>::new();
+        for error in errors {
+            let error = serde_json::from_value::<CustomError>(error)?;
+            custom_errors
+                .entry(error.name.clone())
+                .or_default()
+                .push(error);
+        }
+
+        Ok(Artifact {
+            contract_name: raw.contract_name,
+            abi: serde_json::from_value(Value::Array(entries))?,
+            errors: custom_errors,
+            bytecode: raw.bytecode,
+            networks: raw.networks,
+            devdoc: raw.devdoc,
+            userdoc: raw.userdoc,
+        })
+    }
+}
+
 /// A contract's network configuration.
 #[derive(Clone, Debug, Deserialize)]
 pub struct Network {
@@ -102,4 +152,39 @@ mod tests {
             panic!("error parsing empty artifact: {:?}", err);
         }
     }
+
+    #[test]
+    fn parse_custom_errors() {
+        let artifact = Artifact::from_json(
+            r#"{
+                "abi": [
+                    {
+                        "type": "function",
+                        "name": "withdraw",
+                        "inputs": [{ "name": "amount", "type": "uint256" }],
+                        "outputs": [],
+                        "stateMutability": "nonpayable"
+                    },
+                    {
+                        "type": "error",
+                        "name": "InsufficientBalance",
+                        "inputs": [
+                            { "name": "available", "type": "uint256" },
+                            { "name": "required", "type": "uint256" }
+                        ]
+                    },
+                    { "type": "error", "name": "Unauthorized", "inputs": [] }
+                ]
+            }"#,
+        )
+        .expect("error parsing artifact with custom errors");
+
+        assert!(artifact.abi.function("withdraw").is_ok());
+        assert_eq!(artifact.errors.len(), 2);
+        assert_eq!(
+            artifact.errors["InsufficientBalance"][0].abi_signature(),
+            "InsufficientBalance(uint256,uint256)"
+        );
+        assert!(artifact.errors["Unauthorized"][0].inputs.is_empty());
+    }
 }
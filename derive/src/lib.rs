@@ -72,7 +72,8 @@ use syn::{
 ///   also provides a workaround for generating code for contracts with multiple
 ///   methods with the same name.
 /// - `event_derives`: A list of additional derives that should be added to
-///   contract event structs and enums.
+///   contract event structs and enums, as well as to the types generated for
+///   Solidity structs.
 ///
 /// Additionally, the ABI source can be preceeded by a visibility modifier such
 /// as `pub` or `pub(crate)`. This visibility modifier is applied to both the
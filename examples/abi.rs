@@ -64,6 +64,21 @@ async fn calls(instance: &AbiTypes) {
 
     debug_call!(instance.get_array());
     debug_call!(instance.get_fixed_array());
+
+    debug_call!(instance.get_struct());
+    debug_call!(instance.get_struct_array());
+
+    let value = instance
+        .get_struct()
+        .call()
+        .await
+        .expect("get_struct failed");
+    let roundtrip = instance
+        .roundtrip_struct(value.clone())
+        .call()
+        .await
+        .expect("roundtrip_struct failed");
+    assert_eq!(value, roundtrip);
 }
 
 async fn events(instance: &AbiTypes) {
@@ -97,6 +112,7 @@ async fn events(instance: &AbiTypes) {
     debug_events!(instance.events().value_bytes());
     debug_events!(instance.events().value_array());
     debug_events!(instance.events().value_indexed());
+    debug_events!(instance.events().value_struct());
 
     let all_events = instance
         .all_events()
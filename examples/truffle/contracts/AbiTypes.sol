@@ -1,9 +1,16 @@
 pragma solidity ^0.5.0;
+pragma experimental ABIEncoderV2;
 
 /**
  * @dev Contract to illustract support for various Solidity types.
  */
 contract AbiTypes {
+  struct S {
+    uint64 value;
+    address owner;
+    int32[] items;
+  }
+
   function getVoid() public pure {}
 
   function getU8() public view returns (uint8) {
@@ -87,6 +94,27 @@ contract AbiTypes {
     return buf;
   }
 
+  function getStruct() public view returns (S memory) {
+    return S(this.getU64(), this.getAddress(), this.getFixedArrayItems());
+  }
+  function getFixedArrayItems() public view returns (int32[] memory) {
+    int32[3] memory value = this.getFixedArray();
+    int32[] memory buf = new int32[](3);
+    for (uint256 i = 0; i < 3; i++) {
+      buf[i] = value[i];
+    }
+    return buf;
+  }
+  function getStructArray() public view returns (S[] memory) {
+    S[] memory buf = new S[](2);
+    buf[0] = this.getStruct();
+    buf[1] = this.getStruct();
+    return buf;
+  }
+  function roundtripStruct(S memory value) public pure returns (S memory) {
+    return value;
+  }
+
   event ValueUint(uint8, uint16, uint32, uint64, uint128, uint256 indexed value);
   event ValueInt(int8, int16, int32, int64, int128, int256 indexed value);
 
@@ -96,6 +124,7 @@ contract AbiTypes {
   event ValueArray(uint64[], int32[3]);
 
   event ValueIndexed(string indexed, uint64[] indexed);
+  event ValueStruct(S value, S[] values);
 
   event Values(bytes32 indexed block, address sender) anonymous;
 
@@ -106,6 +135,7 @@ contract AbiTypes {
     emit ValueBytes(getString(), getBytes(), getFixedBytes(), getAddress());
     emit ValueArray(getArray(), getFixedArray());
     emit ValueIndexed(getString(), getArray());
+    emit ValueStruct(getStruct(), getStructArray());
     emit Values(blockhash(block.number - 1), msg.sender);
   }
 }
@@ -6,10 +6,13 @@
 
 mod common;
 mod deployment;
+mod errors;
 mod events;
 mod methods;
+mod structs;
 mod types;
 
+use crate::contract::structs::Structs;
 use crate::util;
 use crate::Args;
 use anyhow::{anyhow, Context as _, Result};
@@ -26,6 +29,8 @@ pub(crate) struct Context {
     artifact_json: Literal,
     /// The parsed artifact.
     artifact: Artifact,
+    /// The Solidity structs used by the contract ABI.
+    structs: Structs,
     /// The identifier for the runtime crate. Usually this is `ethcontract` but
     /// it can be different if the crate was renamed in the Cargo manifest for
     /// example.
@@ -49,7 +54,7 @@ pub(crate) struct Context {
 impl Context {
     /// Create a context from the code generation arguments.
     fn from_args(args: Args) -> Result<Self> {
-        let (artifact_json, artifact) = {
+        let (artifact_json, artifact, structs) = {
             let artifact_json = args
                 .artifact_source
                 .artifact_json()
@@ -64,7 +69,10 @@ impl Context {
                     )
                 })?;
 
-            (Literal::string(&artifact_json), artifact)
+            let structs = Structs::from_artifact_json(&artifact_json)
+                .context("failed to parse Solidity structs from artifact ABI")?;
+
+            (Literal::string(&artifact_json), artifact, structs)
         };
 
         let raw_contract_name = if let Some(name) = args.contract_name_override.as_ref() {
@@ -116,6 +124,7 @@ impl Context {
         Ok(Context {
             artifact_json,
             artifact,
+            structs,
             runtime_crate,
             visibility,
             contract_mod,
@@ -133,6 +142,7 @@ impl Default for Context {
         Context {
             artifact_json: Literal::string("{}"),
             artifact: Artifact::empty(),
+            structs: Structs::default(),
             runtime_crate: util::ident("ethcontract"),
             visibility: Visibility::Inherited,
             contract_mod: util::ident("contract"),
@@ -161,6 +171,8 @@ fn expand_contract(cx: &Context) -> Result<TokenStream> {
     let deployment = deployment::expand(cx)?;
     let methods = methods::expand(cx)?;
     let events = events::expand(cx)?;
+    let errors = errors::expand(cx)?;
+    let structs = structs::expand(cx)?;
 
     Ok(quote! {
         #[allow(dead_code)]
@@ -172,6 +184,8 @@ fn expand_contract(cx: &Context) -> Result<TokenStream> {
             #deployment
             #methods
             #events
+            #errors
+            #structs
         }
         #vis use self::#contract_mod::Contract as #contract_name;
     })
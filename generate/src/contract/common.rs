@@ -127,6 +127,16 @@ pub(crate) fn expand(cx: &Context) -> TokenStream {
             }
         }
 
+        impl self::ethcontract::trace::TraceableContract for Contract {
+            fn address(&self) -> self::ethcontract::Address {
+                Contract::address(self)
+            }
+
+            fn artifact(&self) -> &self::ethcontract::Artifact {
+                Contract::artifact()
+            }
+        }
+
         impl std::fmt::Debug for Contract {
             fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
                 f.debug_tuple(stringify!(#contract_name))
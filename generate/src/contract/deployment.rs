@@ -109,7 +109,7 @@ fn expand_deploy(cx: &Context) -> Result<TokenStream> {
 
     let (input, arg) = match cx.artifact.abi.constructor() {
         Some(contructor) => (
-            methods::expand_inputs(&contructor.inputs)?,
+            methods::expand_inputs(&contructor.inputs, cx.structs.constructor_inputs())?,
             methods::expand_inputs_call_arg(&contructor.inputs),
         ),
         None => (quote! {}, quote! {()}),
@@ -0,0 +1,373 @@
+//! Module implementing the generation of a typed error enum for the Solidity
+//! custom errors declared in the contract ABI.
+
+use crate::contract::{events, methods, structs, types, Context};
+use crate::util;
+use anyhow::Result;
+use ethcontract_common::abi::ParamType;
+use ethcontract_common::CustomError;
+use inflector::Inflector;
+use proc_macro2::{Literal, TokenStream};
+use quote::quote;
+
+pub(crate) fn expand(cx: &Context) -> Result<TokenStream> {
+    let mut errors = cx.artifact.errors.values().flatten().collect::<Vec<_>>();
+    if errors.is_empty() {
+        return Ok(quote! {});
+    }
+
+    // NOTE: We sort the errors by signature so that the generated code is
+    //   consistent, since they are stored in a `HashMap`.
+    errors.sort_unstable_by_key(|error| error.abi_signature());
+
+    let data_types = errors
+        .iter()
+        .map(|error| expand_data_type(cx, error))
+        .collect::<Result<Vec<_>>>()?;
+    let struct_imports = structs::expand_imports(cx);
+    let error_enum = expand_error_enum(cx, &errors);
+    let error_parse_revert = expand_error_parse_revert(cx, &errors);
+
+    Ok(quote! {
+        /// Module containing all generated data models for this contract's
+        /// custom errors.
+        pub mod error_data {
+            use super::ethcontract;
+            #struct_imports
+
+            #( #data_types )*
+        }
+
+        #error_enum
+        #error_parse_revert
+    })
+}
+
+/// Expands a custom error into a single error data type. Like events, this can
+/// expand either into a structure or a tuple in the case where all error
+/// parameters are anonymous.
+fn expand_data_type(cx: &Context, error: &CustomError) -> Result<TokenStream> {
+    let error_name = expand_struct_name(cx, error);
+
+    let selector = methods::expand_selector(error.selector());
+
+    let abi_signature = error.abi_signature();
+    let abi_signature_lit = Literal::string(&abi_signature);
+    let abi_signature_doc = util::expand_doc(&format!("`{}`", abi_signature));
+
+    let struct_names = cx.structs.error_inputs(error);
+    let params = error
+        .inputs
+        .iter()
+        .enumerate()
+        .map(|(i, input)| {
+            let name = util::expand_input_name(i, &input.name);
+            let ty = types::expand(&input.kind, structs::struct_at(struct_names, i))?;
+            Ok((name, ty))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let all_anonymous_fields = error.inputs.iter().all(|input| input.name.is_empty());
+    let (data_type_definition, data_type_construction) = if all_anonymous_fields {
+        events::expand_data_tuple(&error_name, &params)
+    } else {
+        events::expand_data_struct(&error_name, &params)
+    };
+
+    let (param_names, param_types): (Vec<_>, Vec<_>) = params.into_iter().unzip();
+    let tokenizable = structs::expand_tokenizable_impl(
+        &error_name,
+        &param_names,
+        &param_types,
+        &data_type_construction,
+    );
+
+    let std_derives =
+        structs::expand_std_derives(error.inputs.iter().map(|input| &input.kind), true);
+    let derives = events::expand_derives(&cx.event_derives);
+
+    Ok(quote! {
+        #[derive(Clone, Debug, #std_derives #derives)]
+        pub #data_type_definition
+
+        impl #error_name {
+            /// Retrieves the 4-byte selector that prefixes the revert data for
+            /// the error this data corresponds to.
+            pub fn selector() -> [u8; 4] {
+                #selector
+            }
+
+            /// Retrieves the ABI signature for the error this data
+            /// corresponds to. For this error the value should always be:
+            ///
+            #abi_signature_doc
+            pub fn abi_signature() -> &'static str {
+                #abi_signature_lit
+            }
+        }
+
+        #tokenizable
+    })
+}
+
+/// Expands a custom error into an identifier for its error data type.
+/// Overloaded errors are told apart by suffixing their name with their
+/// parameter types, so that `Unauthorized()` and `Unauthorized(address)` get
+/// the `Unauthorized` and `UnauthorizedAddress` identifiers respectively.
+fn expand_struct_name(cx: &Context, error: &CustomError) -> TokenStream {
+    let overloaded = cx
+        .artifact
+        .errors
+        .get(&error.name)
+        .map(|errors| errors.len() > 1)
+        .unwrap_or(false);
+
+    let mut name = error.name.to_pascal_case();
+    if overloaded {
+        for input in &error.inputs {
+            name.push_str(&expand_type_suffix(&input.kind));
+        }
+    }
+
+    let error_name = util::ident(&name);
+    quote! { #error_name }
+}
+
+/// Expands a parameter type into an identifier suffix for overloaded errors.
+fn expand_type_suffix(kind: &ParamType) -> String {
+    match kind {
+        ParamType::Address => "Address".to_owned(),
+        ParamType::Bytes => "Bytes".to_owned(),
+        ParamType::Int(n) => format!("Int{}", n),
+        ParamType::Uint(n) => format!("Uint{}", n),
+        ParamType::Bool => "Bool".to_owned(),
+        ParamType::String => "String".to_owned(),
+        ParamType::Array(t) => format!("{}Array", expand_type_suffix(t)),
+        ParamType::FixedBytes(n) => format!("Bytes{}", n),
+        ParamType::FixedArray(t, n) => format!("{}Array{}", expand_type_suffix(t), n),
+        ParamType::Tuple(t) => format!(
+            "Tuple{}End",
+            t.iter().map(|t| expand_type_suffix(t)).collect::<String>(),
+        ),
+    }
+}
+
+/// Expands into an enum with one variant for each custom error.
+fn expand_error_enum(cx: &Context, errors: &[&CustomError]) -> TokenStream {
+    let variants = errors
+        .iter()
+        .map(|error| {
+            let struct_name = expand_struct_name(cx, error);
+            quote! {
+                #struct_name(self::error_data::#struct_name)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let std_derives = structs::expand_std_derives(
+        errors
+            .iter()
+            .flat_map(|error| error.inputs.iter().map(|input| &input.kind)),
+        false,
+    );
+    let derives = events::expand_derives(&cx.event_derives);
+
+    quote! {
+        /// A contract custom error.
+        #[derive(Clone, Debug, #std_derives #derives)]
+        pub enum Error {
+            #( #variants, )*
+        }
+    }
+}
+
+/// Expands the `ParseRevert` implementation for the error enum.
+fn expand_error_parse_revert(cx: &Context, errors: &[&CustomError]) -> TokenStream {
+    let match_arms = errors
+        .iter()
+        .map(|error| {
+            let struct_name = expand_struct_name(cx, error);
+            let selector = methods::expand_selector(error.selector());
+
+            let name = Literal::string(&error.name);
+            let abi_signature = Literal::string(&error.abi_signature());
+            quote! {
+                Some(#selector) => {
+                    let tokens = Contract::artifact()
+                        .errors
+                        .get(#name)
+                        .and_then(|errors| {
+                            errors.iter().find(|error| error.abi_signature() == #abi_signature)
+                        })
+                        .expect("generated error decode")
+                        .decode(data)?;
+                    Ok(Error::#struct_name(
+                        self::ethcontract::tokens::Tokenizable::from_token(
+                            self::ethcontract::common::abi::Token::Tuple(tokens),
+                        )?,
+                    ))
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let invalid_data = events::expand_invalid_data();
+
+    quote! {
+        impl self::ethcontract::errors::ParseRevert for Error {
+            fn parse_revert(
+                data: &[u8],
+            ) -> Result<Self, self::ethcontract::errors::ExecutionError> {
+                match data.get(..4) {
+                    #( #match_arms )*
+                    _ => #invalid_data,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethcontract_common::abi::Param;
+
+    #[test]
+    fn expand_empty_errors() {
+        assert_quote!(expand(&Context::default()).unwrap(), {});
+    }
+
+    #[test]
+    fn expand_error_data_struct() {
+        let error = CustomError {
+            name: "InsufficientBalance".into(),
+            inputs: vec![
+                Param {
+                    name: "available".into(),
+                    kind: ParamType::Uint(256),
+                },
+                Param {
+                    name: "required".into(),
+                    kind: ParamType::Uint(256),
+                },
+            ],
+        };
+        let tokenizable = structs::expand_tokenizable_impl(
+            &quote! { InsufficientBalance },
+            &[quote! { available }, quote! { required }],
+            &[
+                quote! { self::ethcontract::U256 },
+                quote! { self::ethcontract::U256 },
+            ],
+            &quote! { InsufficientBalance { available, required } },
+        );
+
+        #[rustfmt::skip]
+        assert_quote!(expand_data_type(&Context::default(), &error).unwrap(), {
+            #[derive(Clone, Debug, Default, Eq, PartialEq, )]
+            pub struct InsufficientBalance {
+                pub available: self::ethcontract::U256,
+                pub required: self::ethcontract::U256,
+            }
+
+            impl InsufficientBalance {
+                /// Retrieves the 4-byte selector that prefixes the revert data for
+                /// the error this data corresponds to.
+                pub fn selector() -> [u8; 4] {
+                    [207, 71, 145, 129]
+                }
+
+                /// Retrieves the ABI signature for the error this data
+                /// corresponds to. For this error the value should always be:
+                ///
+                #[doc = "`InsufficientBalance(uint256,uint256)`"]
+                pub fn abi_signature() -> &'static str {
+                    "InsufficientBalance(uint256,uint256)"
+                }
+            }
+
+            #tokenizable
+        });
+    }
+
+    #[test]
+    fn expand_error_enum_variants() {
+        let mut context = Context::default();
+        for name in &["Unauthorized", "InsufficientBalance"] {
+            context.artifact.errors.insert(
+                name.to_string(),
+                vec![CustomError {
+                    name: name.to_string(),
+                    inputs: Vec::new(),
+                }],
+            );
+        }
+
+        let mut errors = context
+            .artifact
+            .errors
+            .values()
+            .flatten()
+            .collect::<Vec<_>>();
+        errors.sort_unstable_by_key(|error| error.abi_signature());
+
+        #[rustfmt::skip]
+        assert_quote!(expand_error_enum(&context, &errors), {
+            /// A contract custom error.
+            #[derive(Clone, Debug, Eq, PartialEq, )]
+            pub enum Error {
+                InsufficientBalance(self::error_data::InsufficientBalance),
+                Unauthorized(self::error_data::Unauthorized),
+            }
+        });
+    }
+
+    #[test]
+    fn expand_overloaded_error_enum_variants() {
+        let mut context = Context::default();
+        let param = |kind| Param {
+            name: "".into(),
+            kind,
+        };
+        context.artifact.errors.insert(
+            "Unauthorized".into(),
+            vec![
+                CustomError {
+                    name: "Unauthorized".into(),
+                    inputs: Vec::new(),
+                },
+                CustomError {
+                    name: "Unauthorized".into(),
+                    inputs: vec![param(ParamType::Address)],
+                },
+                CustomError {
+                    name: "Unauthorized".into(),
+                    inputs: vec![
+                        param(ParamType::Array(Box::new(ParamType::Address))),
+                        param(ParamType::Uint(256)),
+                    ],
+                },
+            ],
+        );
+
+        let mut errors = context
+            .artifact
+            .errors
+            .values()
+            .flatten()
+            .collect::<Vec<_>>();
+        errors.sort_unstable_by_key(|error| error.abi_signature());
+
+        #[rustfmt::skip]
+        assert_quote!(expand_error_enum(&context, &errors), {
+            /// A contract custom error.
+            #[derive(Clone, Debug, Eq, PartialEq, )]
+            pub enum Error {
+                Unauthorized(self::error_data::Unauthorized),
+                UnauthorizedAddress(self::error_data::UnauthorizedAddress),
+                UnauthorizedAddressArrayUint256(self::error_data::UnauthorizedAddressArrayUint256),
+            }
+        });
+        expand(&context).expect("error expanding overloaded errors");
+    }
+}
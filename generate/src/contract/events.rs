@@ -1,4 +1,4 @@
-use crate::contract::{types, Context};
+use crate::contract::{structs, types, Context};
 use crate::util;
 use anyhow::Result;
 use ethcontract_common::abi::{Event, EventParam, Hash, ParamType};
@@ -26,31 +26,38 @@ fn expand_structs_mod(cx: &Context) -> Result<TokenStream> {
         .artifact
         .abi
         .events()
-        .map(|event| expand_data_type(event, &cx.event_derives))
+        .map(|event| expand_data_type(event, cx.structs.event_inputs(event), &cx.event_derives))
         .collect::<Result<Vec<_>>>()?;
     if data_types.is_empty() {
         return Ok(quote! {});
     }
 
+    let struct_imports = structs::expand_imports(cx);
+
     Ok(quote! {
         /// Module containing all generated data models for this contract's
         /// events.
         pub mod event_data {
             use super::ethcontract;
+            #struct_imports
 
             #( #data_types )*
         }
     })
 }
 
-fn expand_derives(derives: &[Path]) -> TokenStream {
+pub(crate) fn expand_derives(derives: &[Path]) -> TokenStream {
     quote! {#(#derives),*}
 }
 
 /// Expands an ABI event into a single event data type. This can expand either
 /// into a structure or a tuple in the case where all event parameters (topics
 /// and data) are anonymous.
-fn expand_data_type(event: &Event, event_derives: &[Path]) -> Result<TokenStream> {
+fn expand_data_type(
+    event: &Event,
+    struct_names: &[Option<String>],
+    event_derives: &[Path],
+) -> Result<TokenStream> {
     let event_name = expand_struct_name(event);
 
     let signature = expand_hash(event.signature());
@@ -59,7 +66,7 @@ fn expand_data_type(event: &Event, event_derives: &[Path]) -> Result<TokenStream
     let abi_signature_lit = Literal::string(&abi_signature);
     let abi_signature_doc = util::expand_doc(&format!("`{}`", abi_signature));
 
-    let params = expand_params(event)?;
+    let params = expand_params(event, struct_names)?;
 
     let all_anonymous_fields = event.inputs.iter().all(|input| input.name.is_empty());
     let (data_type_definition, data_type_construction) = if all_anonymous_fields {
@@ -68,21 +75,20 @@ fn expand_data_type(event: &Event, event_derives: &[Path]) -> Result<TokenStream
         expand_data_struct(&event_name, &params)
     };
 
-    let params_len = Literal::usize_unsuffixed(params.len());
-    let read_param_token = params
-        .iter()
-        .map(|(name, ty)| {
-            quote! {
-                let #name = <#ty as self::ethcontract::web3::contract::tokens::Tokenizable>
-                    ::from_token(tokens.next().unwrap())?;
-            }
-        })
-        .collect::<Vec<_>>();
+    let (param_names, param_types): (Vec<_>, Vec<_>) = params.into_iter().unzip();
+    let tokenizable = structs::expand_tokenizable_impl(
+        &event_name,
+        &param_names,
+        &param_types,
+        &data_type_construction,
+    );
 
+    let std_derives =
+        structs::expand_std_derives(event.inputs.iter().map(|input| &input.kind), true);
     let derives = expand_derives(event_derives);
 
     Ok(quote! {
-        #[derive(Clone, Debug, Default, Eq, PartialEq, #derives)]
+        #[derive(Clone, Debug, #std_derives #derives)]
         pub #data_type_definition
 
         impl #event_name {
@@ -102,26 +108,7 @@ fn expand_data_type(event: &Event, event_derives: &[Path]) -> Result<TokenStream
             }
         }
 
-        impl self::ethcontract::web3::contract::tokens::Detokenize for #event_name {
-            fn from_tokens(
-                tokens: Vec<self::ethcontract::private::ethabi_9_0::Token>,
-            ) -> Result<Self, self::ethcontract::web3::contract::Error> {
-                if tokens.len() != #params_len {
-                    return Err(self::ethcontract::web3::contract::Error::InvalidOutputType(format!(
-                        "Expected {} tokens, got {}: {:?}",
-                        #params_len,
-                        tokens.len(),
-                        tokens
-                    )));
-                }
-
-                #[allow(unused_mut)]
-                let mut tokens = tokens.into_iter();
-                #( #read_param_token )*
-
-                Ok(#data_type_construction)
-            }
-        }
+        #tokenizable
     })
 }
 
@@ -132,7 +119,10 @@ fn expand_struct_name(event: &Event) -> TokenStream {
 }
 
 /// Expands an ABI event into name-type pairs for each of its parameters.
-fn expand_params(event: &Event) -> Result<Vec<(TokenStream, TokenStream)>> {
+fn expand_params(
+    event: &Event,
+    struct_names: &[Option<String>],
+) -> Result<Vec<(TokenStream, TokenStream)>> {
     event
         .inputs
         .iter()
@@ -140,7 +130,7 @@ fn expand_params(event: &Event) -> Result<Vec<(TokenStream, TokenStream)>> {
         .map(|(i, input)| {
             // NOTE: Events can contain nameless values.
             let name = util::expand_input_name(i, &input.name);
-            let ty = expand_input_type(&input)?;
+            let ty = expand_input_type(&input, structs::struct_at(struct_names, i))?;
 
             Ok((name, ty))
         })
@@ -150,7 +140,7 @@ fn expand_params(event: &Event) -> Result<Vec<(TokenStream, TokenStream)>> {
 /// Expands an event data structure from its name-type parameter pairs. Returns
 /// a tuple with the type definition (i.e. the struct declaration) and
 /// construction (i.e. code for creating an instance of the event data).
-fn expand_data_struct(
+pub(crate) fn expand_data_struct(
     name: &TokenStream,
     params: &[(TokenStream, TokenStream)],
 ) -> (TokenStream, TokenStream) {
@@ -173,7 +163,7 @@ fn expand_data_struct(
 
 /// Expands an event data named tuple from its name-type parameter pairs.
 /// Returns a tuple with the type definition and construction.
-fn expand_data_tuple(
+pub(crate) fn expand_data_tuple(
     name: &TokenStream,
     params: &[(TokenStream, TokenStream)],
 ) -> (TokenStream, TokenStream) {
@@ -308,6 +298,16 @@ fn expand_builder_type(event: &Event) -> Result<TokenStream> {
                 self
             }
 
+            /// Sets the contract addresses to filter events for, replacing the
+            /// address of the contract instance. This allows querying the
+            /// events of multiple contracts with a single filter. Creating a
+            /// query or stream with an empty address list fails, since it
+            /// would match the events of every contract.
+            pub fn addresses(mut self, value: Vec<self::ethcontract::Address>) -> Self {
+                self.0 = (self.0).addresses(value);
+                self
+            }
+
             /// The polling interval. This is used as the interval between
             /// consecutive `eth_getFilterChanges` calls to get filter updates.
             pub fn poll_interval(mut self, value: std::time::Duration) -> Self {
@@ -315,6 +315,50 @@ fn expand_builder_type(event: &Event) -> Result<TokenStream> {
                 self
             }
 
+            /// Sets the checkpoint to resume a block stream from.
+            pub fn checkpoint(
+                mut self,
+                value: self::ethcontract::transaction::confirm::BlockId,
+            ) -> Self {
+                self.0 = (self.0).checkpoint(value);
+                self
+            }
+
+            /// The number of recent blocks a block stream keeps track of for
+            /// detecting chain reorganizations.
+            pub fn reorg_window(mut self, value: usize) -> Self {
+                self.0 = (self.0).reorg_window(value);
+                self
+            }
+
+            /// The page size in blocks to use when querying past events before
+            /// streaming new ones.
+            pub fn block_page_size(mut self, value: u64) -> Self {
+                self.0 = (self.0).block_page_size(value);
+                self
+            }
+
+            /// The maximum number of pages to query concurrently when querying
+            /// past events. Pages are still emitted in block order.
+            pub fn page_concurrency(mut self, value: usize) -> Self {
+                self.0 = (self.0).page_concurrency(value);
+                self
+            }
+
+            /// Enrich the metadata of events with the timestamp of the block
+            /// they were produced in.
+            pub fn block_timestamps(mut self, value: bool) -> Self {
+                self.0 = (self.0).block_timestamps(value);
+                self
+            }
+
+            /// Enrich the metadata of events with the sender of the transaction
+            /// they belong to.
+            pub fn transaction_senders(mut self, value: bool) -> Self {
+                self.0 = (self.0).transaction_senders(value);
+                self
+            }
+
             #topic_filters
 
             /// Returns a future that resolves with a collection of all existing
@@ -333,6 +377,27 @@ fn expand_builder_type(event: &Event) -> Result<TokenStream> {
             > {
                 (self.0).stream().expect("generated event topic filters")
             }
+
+            /// Creates an event stream from the current event builder that
+            /// retrieves events block by block, emitting removed events for
+            /// logs from blocks orphaned by chain reorganizations.
+            pub fn block_stream(self) -> self::ethcontract::contract::EventBlockStream<
+                self::ethcontract::dyns::DynTransport,
+                self::event_data::#event_name,
+            > {
+                (self.0).block_stream().expect("generated event topic filters")
+            }
+
+            /// Creates an event stream from the current event builder that
+            /// first retrieves past events in pages and then continues by
+            /// streaming new events block by block, without missing or
+            /// duplicating events between the two.
+            pub fn past_and_live_stream(self) -> self::ethcontract::contract::EventBlockStream<
+                self::ethcontract::dyns::DynTransport,
+                self::event_data::#event_name,
+            > {
+                (self.0).past_and_live_stream().expect("generated event topic filters")
+            }
         }
     })
 }
@@ -359,13 +424,19 @@ fn expand_builder_topic_filter(topic_index: usize, param: &EventParam) -> Result
         "Adds a filter for the {} event parameter.",
         param.name,
     ));
+    let one_of_doc = util::expand_doc(&format!(
+        "Adds a filter for the {} event parameter matching any of the specified values.",
+        param.name,
+    ));
     let topic = util::ident(&format!("topic{}", topic_index));
     let name = if param.name.is_empty() {
-        topic.clone()
+        topic.to_string()
     } else {
-        util::safe_ident(&param.name.to_snake_case())
+        param.name.to_snake_case()
     };
-    let ty = expand_input_type(&param)?;
+    let one_of_name = util::safe_ident(&format!("{}_one_of", name));
+    let name = util::safe_ident(&name);
+    let ty = expand_input_type(&param, None)?;
 
     Ok(quote! {
         #doc
@@ -373,6 +444,15 @@ fn expand_builder_topic_filter(topic_index: usize, param: &EventParam) -> Result
             self.0 = (self.0).#topic(topic);
             self
         }
+
+        #one_of_doc
+        pub fn #one_of_name<I>(mut self, values: I) -> Self
+        where
+            I: IntoIterator<Item = #ty>,
+        {
+            self.0 = (self.0).#topic(self::ethcontract::Topic::OneOf(values.into_iter().collect()));
+            self
+        }
     })
 }
 
@@ -402,6 +482,41 @@ fn expand_all_events(cx: &Context) -> TokenStream {
                     self.transaction_hash(),
                 )
             }
+
+            /// Returns an event builder for all events emitted by any of the
+            /// specified contract addresses, for example all instances of this
+            /// contract created by a factory.
+            pub fn all_events_at<F, T>(
+                web3: &self::ethcontract::web3::api::Web3<T>,
+                addresses: Vec<self::ethcontract::Address>,
+            ) -> self::ethcontract::dyns::DynAllEventsBuilder<Event>
+            where
+                F: self::ethcontract::web3::futures::Future<
+                    Item = self::ethcontract::json::Value,
+                    Error = self::ethcontract::web3::Error,
+                > + Send + 'static,
+                T: self::ethcontract::web3::Transport<Out = F> + Send + Sync + 'static,
+            {
+                use self::ethcontract::transport::DynTransport;
+                use self::ethcontract::web3::api::Web3;
+
+                let transport = DynTransport::new(web3.transport().clone());
+                let web3 = Web3::new(transport);
+
+                self::ethcontract::dyns::DynAllEventsBuilder::with_addresses(web3, addresses)
+            }
+
+            /// Decodes the events emitted by this contract from the logs of a
+            /// transaction receipt, ignoring logs from other contracts.
+            pub fn receipt_events(
+                &self,
+                receipt: &self::ethcontract::web3::types::TransactionReceipt,
+            ) -> Result<
+                Vec<self::ethcontract::contract::Event<Event>>,
+                self::ethcontract::errors::ExecutionError,
+            > {
+                self::ethcontract::contract::Event::from_receipt(receipt, self.address())
+            }
         }
 
         #event_enum
@@ -431,11 +546,18 @@ fn expand_event_enum(cx: &Context) -> TokenStream {
             .collect::<Vec<_>>()
     };
 
+    let std_derives = structs::expand_std_derives(
+        cx.artifact
+            .abi
+            .events()
+            .flat_map(|event| event.inputs.iter().map(|input| &input.kind)),
+        false,
+    );
     let derives = expand_derives(&cx.event_derives);
 
     quote! {
         /// A contract event.
-        #[derive(Clone, Debug, Eq, PartialEq, #derives)]
+        #[derive(Clone, Debug, #std_derives #derives)]
         pub enum Event {
             #( #variants, )*
         }
@@ -538,7 +660,7 @@ fn expand_event_parse_log(cx: &Context) -> TokenStream {
 /// Note that this is slightly different than an expanding a Solidity type as
 /// complex types like arrays and strings get emited as hashes when they are
 /// indexed.
-fn expand_input_type(input: &EventParam) -> Result<TokenStream> {
+fn expand_input_type(input: &EventParam, struct_name: Option<&str>) -> Result<TokenStream> {
     Ok(match (&input.kind, input.indexed) {
         (ParamType::Array(..), true)
         | (ParamType::Bytes, true)
@@ -547,7 +669,7 @@ fn expand_input_type(input: &EventParam) -> Result<TokenStream> {
         | (ParamType::Tuple(..), true) => {
             quote! { self::ethcontract::H256 }
         }
-        (kind, _) => types::expand(kind)?,
+        (kind, _) => types::expand(kind, struct_name)?,
     })
 }
 
@@ -562,7 +684,7 @@ fn expand_hash(hash: Hash) -> TokenStream {
 }
 
 /// Expands to a generic `InvalidData` error.
-fn expand_invalid_data() -> TokenStream {
+pub(crate) fn expand_invalid_data() -> TokenStream {
     quote! {
         Err(self::ethcontract::errors::ExecutionError::from(
             self::ethcontract::common::abi::Error::InvalidData
@@ -648,11 +770,29 @@ mod tests {
                 self
             }
 
+            #[doc = "Adds a filter for the from event parameter matching any of the specified values."]
+            pub fn from_one_of<I>(mut self, values: I) -> Self
+            where
+                I: IntoIterator<Item = self::ethcontract::Address>,
+            {
+                self.0 = (self.0).topic0(self::ethcontract::Topic::OneOf(values.into_iter().collect()));
+                self
+            }
+
             #[doc = "Adds a filter for the to event parameter."]
             pub fn to(mut self, topic: self::ethcontract::Topic<self::ethcontract::Address>) -> Self {
                 self.0 = (self.0).topic1(topic);
                 self
             }
+
+            #[doc = "Adds a filter for the to event parameter matching any of the specified values."]
+            pub fn to_one_of<I>(mut self, values: I) -> Self
+            where
+                I: IntoIterator<Item = self::ethcontract::Address>,
+            {
+                self.0 = (self.0).topic1(self::ethcontract::Topic::OneOf(values.into_iter().collect()));
+                self
+            }
         });
     }
 
@@ -676,7 +816,7 @@ mod tests {
         };
 
         let name = expand_struct_name(&event);
-        let params = expand_params(&event).unwrap();
+        let params = expand_params(&event, &[]).unwrap();
         let (definition, construction) = expand_data_struct(&name, &params);
 
         assert_quote!(definition, {
@@ -708,7 +848,7 @@ mod tests {
         };
 
         let name = expand_struct_name(&event);
-        let params = expand_params(&event).unwrap();
+        let params = expand_params(&event, &[]).unwrap();
         let (definition, construction) = expand_data_tuple(&name, &params);
 
         assert_quote!(definition, {
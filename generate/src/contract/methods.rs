@@ -1,4 +1,4 @@
-use crate::contract::{types, Context};
+use crate::contract::{structs, types, Context};
 use crate::util;
 use anyhow::{anyhow, Context as _, Result};
 use ethcontract_common::abi::{Function, Param};
@@ -102,8 +102,8 @@ fn expand_function(cx: &Context, function: &Function, alias: Option<Ident>) -> R
         .unwrap_or("Generated by `ethcontract`");
     let doc = util::expand_doc(doc_str);
 
-    let input = expand_inputs(&function.inputs)?;
-    let outputs = expand_fn_outputs(&function.outputs)?;
+    let input = expand_inputs(&function.inputs, cx.structs.function_inputs(function))?;
+    let outputs = expand_fn_outputs(&function.outputs, cx.structs.function_outputs(function))?;
     let (method, result_type_name) = if function.constant {
         (quote! { view_method }, quote! { DynViewMethodBuilder })
     } else {
@@ -121,13 +121,16 @@ fn expand_function(cx: &Context, function: &Function, alias: Option<Ident>) -> R
     })
 }
 
-pub(crate) fn expand_inputs(inputs: &[Param]) -> Result<TokenStream> {
+pub(crate) fn expand_inputs(
+    inputs: &[Param],
+    struct_names: &[Option<String>],
+) -> Result<TokenStream> {
     let params = inputs
         .iter()
         .enumerate()
         .map(|(i, param)| {
             let name = util::expand_input_name(i, &param.name);
-            let kind = types::expand(&param.kind)?;
+            let kind = types::expand(&param.kind, structs::struct_at(struct_names, i))?;
             Ok(quote! { #name: #kind })
         })
         .collect::<Result<Vec<_>>>()?;
@@ -142,21 +145,22 @@ pub(crate) fn expand_inputs_call_arg(inputs: &[Param]) -> TokenStream {
     quote! { ( #( #names ,)* ) }
 }
 
-fn expand_fn_outputs(outputs: &[Param]) -> Result<TokenStream> {
+fn expand_fn_outputs(outputs: &[Param], struct_names: &[Option<String>]) -> Result<TokenStream> {
     match outputs.len() {
         0 => Ok(quote! { self::ethcontract::Void }),
-        1 => types::expand(&outputs[0].kind),
+        1 => types::expand(&outputs[0].kind, structs::struct_at(struct_names, 0)),
         _ => {
             let types = outputs
                 .iter()
-                .map(|param| types::expand(&param.kind))
+                .enumerate()
+                .map(|(i, param)| types::expand(&param.kind, structs::struct_at(struct_names, i)))
                 .collect::<Result<Vec<_>>>()?;
             Ok(quote! { (#( #types ),*) })
         }
     }
 }
 
-fn expand_selector(selector: H32) -> TokenStream {
+pub(crate) fn expand_selector(selector: H32) -> TokenStream {
     let bytes = selector.iter().copied().map(Literal::u8_unsuffixed);
     quote! { [#( #bytes ),*] }
 }
@@ -192,7 +196,7 @@ mod tests {
 
     #[test]
     fn expand_inputs_empty() {
-        assert_quote!(expand_inputs(&[]).unwrap().to_string(), {},);
+        assert_quote!(expand_inputs(&[], &[]).unwrap().to_string(), {},);
     }
 
     #[test]
@@ -210,6 +214,7 @@ mod tests {
                         kind: ParamType::Address,
                     },
                 ],
+                &[],
             )
             .unwrap(),
             { , a: bool, b: self::ethcontract::Address },
@@ -218,7 +223,7 @@ mod tests {
 
     #[test]
     fn expand_fn_outputs_empty() {
-        assert_quote!(expand_fn_outputs(&[],).unwrap(), {
+        assert_quote!(expand_fn_outputs(&[], &[]).unwrap(), {
             self::ethcontract::Void
         });
     }
@@ -226,10 +231,13 @@ mod tests {
     #[test]
     fn expand_fn_outputs_single() {
         assert_quote!(
-            expand_fn_outputs(&[Param {
-                name: "a".to_string(),
-                kind: ParamType::Bool,
-            }])
+            expand_fn_outputs(
+                &[Param {
+                    name: "a".to_string(),
+                    kind: ParamType::Bool,
+                }],
+                &[]
+            )
             .unwrap(),
             { bool },
         );
@@ -238,18 +246,61 @@ mod tests {
     #[test]
     fn expand_fn_outputs_muliple() {
         assert_quote!(
-            expand_fn_outputs(&[
-                Param {
-                    name: "a".to_string(),
-                    kind: ParamType::Bool,
-                },
-                Param {
-                    name: "b".to_string(),
-                    kind: ParamType::Address,
-                },
-            ],)
+            expand_fn_outputs(
+                &[
+                    Param {
+                        name: "a".to_string(),
+                        kind: ParamType::Bool,
+                    },
+                    Param {
+                        name: "b".to_string(),
+                        kind: ParamType::Address,
+                    },
+                ],
+                &[]
+            )
             .unwrap(),
             { (bool, self::ethcontract::Address) },
         );
     }
+
+    #[test]
+    fn expand_fn_outputs_tuple() {
+        assert_quote!(
+            expand_fn_outputs(&[Param {
+                name: "a".to_string(),
+                kind: ParamType::Tuple(vec![
+                    Box::new(ParamType::Bool),
+                    Box::new(ParamType::Array(Box::new(ParamType::Tuple(vec![
+                        Box::new(ParamType::Address)
+                    ])))),
+                ]),
+            }], &[])
+            .unwrap(),
+            { (bool, Vec<(self::ethcontract::Address,)>,) },
+        );
+    }
+
+    #[test]
+    fn expand_fn_outputs_struct() {
+        assert_quote!(
+            expand_fn_outputs(
+                &[
+                    Param {
+                        name: "a".to_string(),
+                        kind: ParamType::Array(Box::new(ParamType::Tuple(vec![
+                            Box::new(ParamType::Address)
+                        ]))),
+                    },
+                    Param {
+                        name: "b".to_string(),
+                        kind: ParamType::Bool,
+                    },
+                ],
+                &[Some("Order.Data".to_string()), None],
+            )
+            .unwrap(),
+            { (Vec<order::Data>, bool) },
+        );
+    }
 }
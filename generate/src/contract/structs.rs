@@ -0,0 +1,723 @@
+//! Module implementing the generation of named Rust structs for Solidity
+//! structs used in the contract ABI.
+//!
+//! Note that `ethabi` does not keep the `internalType` and the component names
+//! of tuple parameters, so the struct information is extracted from the raw
+//! artifact JSON. Additionally, `ethabi` does not correctly parse arrays of
+//! tuples nested in tuple components, so the struct field types are parsed
+//! directly from the JSON as well.
+
+use crate::contract::{types, Context};
+use crate::util;
+use anyhow::{anyhow, Context as _, Result};
+use ethcontract_common::abi::{Event, EventParam, Function, Param, ParamType};
+use ethcontract_common::abiext::{EventExt, FunctionExt, ParamTypeExt};
+use ethcontract_common::CustomError;
+use inflector::Inflector;
+use proc_macro2::{Literal, TokenStream};
+use quote::quote;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Solidity struct definitions along with which contract method and event
+/// parameters reference them.
+#[derive(Debug, Default)]
+pub(crate) struct Structs {
+    /// The struct definitions by their fully qualified Solidity name (for
+    /// example `Order.Data`).
+    definitions: BTreeMap<String, Vec<Field>>,
+    /// The struct names for method inputs by method ABI signature.
+    function_inputs: HashMap<String, Vec<Option<String>>>,
+    /// The struct names for method outputs by method ABI signature.
+    function_outputs: HashMap<String, Vec<Option<String>>>,
+    /// The struct names for event parameters by event ABI signature.
+    event_inputs: HashMap<String, Vec<Option<String>>>,
+    /// The struct names for custom error parameters by error ABI signature.
+    error_inputs: HashMap<String, Vec<Option<String>>>,
+    /// The struct names for the constructor parameters.
+    constructor_inputs: Vec<Option<String>>,
+}
+
+/// A Solidity struct field.
+#[derive(Debug)]
+struct Field {
+    /// The name of the field.
+    name: String,
+    /// The ABI type of the field.
+    kind: ParamType,
+    /// The name of the Solidity struct when the field is itself a struct or an
+    /// array of structs.
+    struct_name: Option<String>,
+}
+
+impl Structs {
+    /// Extracts the Solidity struct information from the raw artifact JSON.
+    pub fn from_artifact_json(json: &str) -> Result<Self> {
+        let artifact = serde_json::from_str::<Value>(json)?;
+
+        let mut structs = Structs::default();
+        let entries = match artifact.get("abi").and_then(Value::as_array) {
+            Some(entries) => entries,
+            None => return Ok(structs),
+        };
+
+        for entry in entries {
+            match entry.get("type").and_then(Value::as_str) {
+                Some("function") | None => {
+                    let inputs = params::<Param>(entry, "inputs")?;
+                    let outputs = params::<Param>(entry, "outputs")?;
+                    let signature = Function {
+                        name: string(entry, "name"),
+                        inputs: inputs.iter().map(|(param, _)| param.clone()).collect(),
+                        outputs: Vec::new(),
+                        constant: false,
+                    }
+                    .abi_signature();
+
+                    let inputs = structs.add_params(&inputs)?;
+                    let outputs = structs.add_params(&outputs)?;
+                    structs.function_inputs.insert(signature.clone(), inputs);
+                    structs.function_outputs.insert(signature, outputs);
+                }
+                Some("event") => {
+                    let inputs = params::<EventParam>(entry, "inputs")?;
+                    let signature = Event {
+                        name: string(entry, "name"),
+                        inputs: inputs.iter().map(|(param, _)| param.clone()).collect(),
+                        anonymous: entry
+                            .get("anonymous")
+                            .and_then(Value::as_bool)
+                            .unwrap_or(false),
+                    }
+                    .abi_signature();
+
+                    let inputs = structs.add_params(&inputs)?;
+                    structs.event_inputs.insert(signature, inputs);
+                }
+                Some("error") => {
+                    let inputs = params::<Param>(entry, "inputs")?;
+                    let signature = CustomError {
+                        name: string(entry, "name"),
+                        inputs: inputs.iter().map(|(param, _)| param.clone()).collect(),
+                    }
+                    .abi_signature();
+
+                    let inputs = structs.add_params(&inputs)?;
+                    structs.error_inputs.insert(signature, inputs);
+                }
+                Some("constructor") => {
+                    let inputs = params::<Param>(entry, "inputs")?;
+                    structs.constructor_inputs = structs.add_params(&inputs)?;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(structs)
+    }
+
+    /// Returns the struct names for the inputs of a method.
+    pub fn function_inputs(&self, function: &Function) -> &[Option<String>] {
+        lookup(&self.function_inputs, &function.abi_signature())
+    }
+
+    /// Returns the struct names for the outputs of a method.
+    pub fn function_outputs(&self, function: &Function) -> &[Option<String>] {
+        lookup(&self.function_outputs, &function.abi_signature())
+    }
+
+    /// Returns the struct names for the parameters of an event.
+    pub fn event_inputs(&self, event: &Event) -> &[Option<String>] {
+        lookup(&self.event_inputs, &event.abi_signature())
+    }
+
+    /// Returns the struct names for the parameters of a custom error.
+    pub fn error_inputs(&self, error: &CustomError) -> &[Option<String>] {
+        lookup(&self.error_inputs, &error.abi_signature())
+    }
+
+    /// Returns the struct names for the constructor parameters.
+    pub fn constructor_inputs(&self) -> &[Option<String>] {
+        &self.constructor_inputs
+    }
+
+    /// Adds the struct definitions for a list of parameters and returns the
+    /// struct name for each of them.
+    fn add_params<P>(&mut self, params: &[(P, &Value)]) -> Result<Vec<Option<String>>> {
+        params
+            .iter()
+            .map(|(_, value)| self.add_param(value))
+            .collect()
+    }
+
+    /// Adds the struct definition for a parameter if it is a Solidity struct
+    /// and returns its struct name.
+    fn add_param(&mut self, value: &Value) -> Result<Option<String>> {
+        let name = match struct_name(value) {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+
+        if !self.definitions.contains_key(&name) {
+            let components = params::<Param>(value, "components")?;
+            let fields = components
+                .into_iter()
+                .map(|(param, value)| {
+                    Ok(Field {
+                        name: param.name,
+                        kind: param_kind(value)?,
+                        struct_name: self.add_param(value)?,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()
+                .with_context(|| format!("invalid components for struct '{}'", name))?;
+            self.definitions.insert(name.clone(), fields);
+        }
+
+        Ok(Some(name))
+    }
+}
+
+/// Reads a string property from an ABI entry.
+fn string(value: &Value, key: &str) -> String {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_owned()
+}
+
+/// Reads a list of parameters from an ABI entry along with their raw JSON.
+fn params<'a, P>(value: &'a Value, key: &str) -> Result<Vec<(P, &'a Value)>>
+where
+    P: serde::de::DeserializeOwned,
+{
+    let params = match value.get(key).and_then(Value::as_array) {
+        Some(params) => params,
+        None => return Ok(Vec::new()),
+    };
+
+    params
+        .iter()
+        .map(|param| Ok((serde_json::from_value(param.clone())?, param)))
+        .collect()
+}
+
+/// Parses the ABI type of a parameter from its raw JSON, including arrays of
+/// tuples at any nesting level.
+fn param_kind(value: &Value) -> Result<ParamType> {
+    let kind = value
+        .get("type")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("parameter is missing its type"))?;
+    if !kind.starts_with("tuple") {
+        return Ok(ParamType::from_str(kind)?);
+    }
+
+    let components = value
+        .get("components")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow!("tuple parameter is missing its components"))?;
+    let mut param_kind = ParamType::Tuple(
+        components
+            .iter()
+            .map(|component| Ok(Box::new(param_kind(component)?)))
+            .collect::<Result<_>>()?,
+    );
+
+    // NOTE: Array suffixes are applied from left to right, so `tuple[2][]` is
+    //   a dynamic array of fixed arrays of two tuples.
+    let mut suffix = &kind["tuple".len()..];
+    while !suffix.is_empty() {
+        let end = match (suffix.starts_with('['), suffix.find(']')) {
+            (true, Some(end)) => end,
+            _ => return Err(anyhow!("invalid tuple type '{}'", kind)),
+        };
+        param_kind = match &suffix[1..end] {
+            "" => ParamType::Array(Box::new(param_kind)),
+            len => ParamType::FixedArray(
+                Box::new(param_kind),
+                len.parse()
+                    .with_context(|| format!("invalid tuple type '{}'", kind))?,
+            ),
+        };
+        suffix = &suffix[end + 1..];
+    }
+
+    Ok(param_kind)
+}
+
+/// Returns the struct names from a lookup table or an empty list if the entry
+/// is not found.
+fn lookup<'a>(table: &'a HashMap<String, Vec<Option<String>>>, key: &str) -> &'a [Option<String>] {
+    table.get(key).map(Vec::as_slice).unwrap_or_default()
+}
+
+/// Returns the Solidity struct name of a parameter from its `internalType`.
+/// For arrays of structs, this is the name of the struct of the array items.
+fn struct_name(value: &Value) -> Option<String> {
+    const PREFIX: &str = "struct ";
+
+    let internal_type = value.get("internalType")?.as_str()?;
+    if !internal_type.starts_with(PREFIX) {
+        return None;
+    }
+
+    let name = &internal_type[PREFIX.len()..];
+    let end = name.find('[').unwrap_or(name.len());
+    Some(name[..end].to_owned())
+}
+
+/// Returns the struct name at the specified index from a list of struct names.
+pub(crate) fn struct_at(struct_names: &[Option<String>], index: usize) -> Option<&str> {
+    struct_names.get(index)?.as_deref()
+}
+
+/// Expands a Solidity struct name into the path of its generated Rust type.
+///
+/// Structs defined within a contract (for example `Order.Data`) are placed in
+/// a module named after the contract (so `order::Data`) while structs defined
+/// at the file level are placed in the contract module root.
+pub(crate) fn expand_path(struct_name: &str) -> TokenStream {
+    match split_name(struct_name) {
+        (Some(module), name) => {
+            let module = expand_module_name(module);
+            let name = util::ident(name);
+            quote! { #module::#name }
+        }
+        (None, name) => {
+            let name = util::ident(name);
+            quote! { #name }
+        }
+    }
+}
+
+/// Splits a Solidity struct name into its contract and struct name.
+fn split_name(struct_name: &str) -> (Option<&str>, &str) {
+    match struct_name.rfind('.') {
+        Some(dot) => (Some(&struct_name[..dot]), &struct_name[dot + 1..]),
+        None => (None, struct_name),
+    }
+}
+
+/// Expands a contract name into the module name containing its structs.
+fn expand_module_name(contract_name: &str) -> TokenStream {
+    let module = util::safe_ident(&contract_name.to_snake_case());
+    quote! { #module }
+}
+
+/// Expands into the generated types for all Solidity structs used in the
+/// contract ABI.
+pub(crate) fn expand(cx: &Context) -> Result<TokenStream> {
+    let mut root = Vec::new();
+    let mut modules = BTreeMap::<_, Vec<_>>::new();
+    for (struct_name, fields) in &cx.structs.definitions {
+        let definition = expand_struct(cx, struct_name, fields)
+            .with_context(|| format!("error expanding struct '{}'", struct_name))?;
+        match split_name(struct_name) {
+            (Some(module), _) => modules.entry(module).or_default().push(definition),
+            (None, _) => root.push(definition),
+        }
+    }
+
+    let imports = expand_imports(cx);
+    let modules = modules.into_iter().map(|(module, definitions)| {
+        let module_doc = util::expand_doc(&format!(
+            "Module containing the generated types for the `{}` Solidity structs.",
+            module,
+        ));
+        let module = expand_module_name(module);
+        quote! {
+            #module_doc
+            pub mod #module {
+                use super::ethcontract;
+                #imports
+
+                #( #definitions )*
+            }
+        }
+    });
+
+    Ok(quote! {
+        #( #root )*
+        #( #modules )*
+    })
+}
+
+/// Expands a single Solidity struct into a Rust struct definition.
+fn expand_struct(cx: &Context, struct_name: &str, fields: &[Field]) -> Result<TokenStream> {
+    let (_, name) = split_name(struct_name);
+    let name = util::ident(name);
+    let doc = util::expand_doc(&format!("Generated type for the `{}` struct.", struct_name));
+
+    let field_names = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| util::expand_input_name(i, &field.name))
+        .collect::<Vec<_>>();
+    let field_types = fields
+        .iter()
+        .map(|field| types::expand(&field.kind, field.struct_name.as_deref()))
+        .collect::<Result<Vec<_>>>()?;
+
+    let std_derives = expand_std_derives(fields.iter().map(|field| &field.kind), true);
+    let derives = &cx.event_derives;
+    let construction = quote! { #name { #( #field_names ),* } };
+    let tokenizable =
+        expand_tokenizable_impl(&quote! { #name }, &field_names, &field_types, &construction);
+
+    Ok(quote! {
+        #doc
+        #[derive(Clone, Debug, #std_derives #( #derives ),*)]
+        pub struct #name {
+            #( pub #field_names: #field_types, )*
+        }
+
+        #tokenizable
+    })
+}
+
+/// Returns whether or not the Rust type for an ABI type implements `Default`,
+/// `Eq` and `PartialEq`. The standard library only implements these traits for
+/// arrays of up to 32 elements and tuples of up to 12 elements.
+///
+/// Note that this is conservative for nested structs, which are checked as if
+/// they were tuples.
+fn supports_std_traits(kind: &ParamType) -> bool {
+    match kind {
+        ParamType::Array(inner) => supports_std_traits(inner),
+        ParamType::FixedArray(inner, len) => *len <= 32 && supports_std_traits(inner),
+        ParamType::Tuple(inner) => {
+            inner.len() <= 12 && inner.iter().all(|kind| supports_std_traits(kind))
+        }
+        _ => true,
+    }
+}
+
+/// Expands into the standard library traits that can be derived, in addition
+/// to `Clone` and `Debug`, for a generated type with fields of the specified
+/// ABI types. `Default` is only included when `default` is set.
+pub(crate) fn expand_std_derives<'a, I>(kinds: I, default: bool) -> TokenStream
+where
+    I: IntoIterator<Item = &'a ParamType>,
+{
+    if !kinds.into_iter().all(supports_std_traits) {
+        return quote! {};
+    }
+
+    if default {
+        quote! { Default, Eq, PartialEq, }
+    } else {
+        quote! { Eq, PartialEq, }
+    }
+}
+
+/// Expands into the `Tokenizable` implementation for a generated type that
+/// tokenizes as a tuple of its fields.
+///
+/// Note that the fields are tokenized directly instead of going through a
+/// Rust tuple, so that types are not limited by the number of elements that
+/// tuples implement `Tokenizable` for.
+pub(crate) fn expand_tokenizable_impl(
+    name: &TokenStream,
+    field_names: &[TokenStream],
+    field_types: &[TokenStream],
+    construction: &TokenStream,
+) -> TokenStream {
+    let len = Literal::usize_unsuffixed(field_names.len());
+    let invalid_output_type = Literal::string(&format!(
+        "Expected `Tuple({})`, got {{:?}}",
+        field_names.len()
+    ));
+    let from_tokens = if field_names.is_empty() {
+        quote! { Ok(#construction) }
+    } else {
+        quote! {
+            let mut tokens = tokens.into_iter();
+            #(
+                let #field_names: #field_types =
+                    self::ethcontract::tokens::Tokenizable::from_token(
+                        tokens.next().expect("tuple length checked"),
+                    )?;
+            )*
+            Ok(#construction)
+        }
+    };
+
+    quote! {
+        impl self::ethcontract::tokens::Tokenizable for #name {
+            fn from_token(
+                token: self::ethcontract::common::abi::Token,
+            ) -> Result<Self, self::ethcontract::errors::Web3ContractError> {
+                match token {
+                    self::ethcontract::common::abi::Token::Tuple(tokens) if tokens.len() == #len => {
+                        #from_tokens
+                    }
+                    other => Err(self::ethcontract::errors::Web3ContractError::InvalidOutputType(
+                        format!(#invalid_output_type, other),
+                    )),
+                }
+            }
+
+            fn into_token(self) -> self::ethcontract::common::abi::Token {
+                let #construction = self;
+                self::ethcontract::common::abi::Token::Tuple(vec![
+                    #( self::ethcontract::tokens::Tokenizable::into_token(#field_names), )*
+                ])
+            }
+
+            fn tuple_len() -> Option<usize> {
+                Some(#len)
+            }
+        }
+
+        impl self::ethcontract::tokens::TokenizableItem for #name {}
+    }
+}
+
+/// Expands into an import of all the root level struct types and modules so
+/// that they can be referenced from generated sub-modules.
+pub(crate) fn expand_imports(cx: &Context) -> TokenStream {
+    let roots = cx
+        .structs
+        .definitions
+        .keys()
+        .map(|struct_name| match split_name(struct_name) {
+            (Some(module), _) => expand_module_name(module).to_string(),
+            (None, name) => name.to_owned(),
+        })
+        .collect::<BTreeSet<_>>();
+    if roots.is_empty() {
+        return quote! {};
+    }
+
+    let roots = roots.iter().map(|root| util::ident(root));
+    quote! {
+        #[allow(unused_imports)]
+        use super::{ #( #roots ),* };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn structs() -> Structs {
+        Structs::from_artifact_json(
+            r#"{
+                "abi": [
+                    {
+                        "type": "function",
+                        "name": "fill",
+                        "inputs": [
+                            {
+                                "name": "order",
+                                "type": "tuple",
+                                "internalType": "struct Order.Data",
+                                "components": [
+                                    { "name": "amount", "type": "uint256", "internalType": "uint256" },
+                                    {
+                                        "name": "leg",
+                                        "type": "tuple",
+                                        "internalType": "struct Leg",
+                                        "components": [
+                                            { "name": "owner", "type": "address", "internalType": "address" }
+                                        ]
+                                    }
+                                ]
+                            },
+                            { "name": "flag", "type": "bool", "internalType": "bool" }
+                        ],
+                        "outputs": [
+                            { "name": "", "type": "tuple[2]", "internalType": "struct Leg[2]", "components": [
+                                { "name": "owner", "type": "address", "internalType": "address" }
+                            ] }
+                        ]
+                    },
+                    {
+                        "type": "event",
+                        "name": "Filled",
+                        "inputs": [
+                            {
+                                "name": "order",
+                                "type": "tuple",
+                                "internalType": "struct Order.Data",
+                                "indexed": false,
+                                "components": [
+                                    { "name": "amount", "type": "uint256", "internalType": "uint256" },
+                                    {
+                                        "name": "leg",
+                                        "type": "tuple",
+                                        "internalType": "struct Leg",
+                                        "components": [
+                                            { "name": "owner", "type": "address", "internalType": "address" }
+                                        ]
+                                    }
+                                ]
+                            }
+                        ],
+                        "anonymous": false
+                    }
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn parse_struct_definitions() {
+        let structs = structs();
+
+        assert_eq!(
+            structs.definitions.keys().collect::<Vec<_>>(),
+            vec!["Leg", "Order.Data"],
+        );
+
+        let data = &structs.definitions["Order.Data"];
+        assert_eq!(data[0].name, "amount");
+        assert_eq!(data[0].struct_name, None);
+        assert_eq!(data[1].name, "leg");
+        assert_eq!(data[1].struct_name.as_deref(), Some("Leg"));
+
+        assert_eq!(
+            structs.function_inputs["fill((uint256,(address)),bool)"],
+            vec![Some("Order.Data".to_owned()), None],
+        );
+        assert_eq!(
+            structs.function_outputs["fill((uint256,(address)),bool)"],
+            vec![Some("Leg".to_owned())],
+        );
+        assert_eq!(
+            structs.event_inputs["Filled((uint256,(address)))"],
+            vec![Some("Order.Data".to_owned())],
+        );
+    }
+
+    #[test]
+    fn parse_nested_tuple_arrays() {
+        let param = serde_json::json!({
+            "name": "legs",
+            "type": "tuple[2][]",
+            "components": [
+                { "name": "owner", "type": "address" },
+                { "name": "hops", "type": "tuple[]", "components": [
+                    { "name": "fee", "type": "uint24" }
+                ] }
+            ]
+        });
+
+        assert_eq!(
+            param_kind(&param).unwrap(),
+            ParamType::Array(Box::new(ParamType::FixedArray(
+                Box::new(ParamType::Tuple(vec![
+                    Box::new(ParamType::Address),
+                    Box::new(ParamType::Array(Box::new(ParamType::Tuple(vec![
+                        Box::new(ParamType::Uint(24)),
+                    ])))),
+                ])),
+                2,
+            ))),
+        );
+    }
+
+    #[test]
+    fn parse_without_internal_types() {
+        let structs = Structs::from_artifact_json(
+            r#"{"abi":[{"type":"function","name":"foo","inputs":[
+                {"name":"a","type":"tuple","components":[{"name":"b","type":"bool"}]}
+            ],"outputs":[]}]}"#,
+        )
+        .unwrap();
+
+        assert!(structs.definitions.is_empty());
+        assert_eq!(structs.function_inputs["foo((bool))"], vec![None]);
+    }
+
+    #[test]
+    fn expand_struct_paths() {
+        assert_quote!(expand_path("Order.Data"), { order::Data });
+        assert_quote!(expand_path("Data"), { Data });
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn expand_structs() {
+        let context = Context {
+            structs: structs(),
+            ..Context::default()
+        };
+
+        let fields = &context.structs.definitions["Order.Data"];
+        assert_quote!(expand_struct(&context, "Order.Data", fields).unwrap(), {
+            #[doc = "Generated type for the `Order.Data` struct."]
+            #[derive(Clone, Debug, Default, Eq, PartialEq,)]
+            pub struct Data {
+                pub amount: self::ethcontract::U256,
+                pub leg: Leg,
+            }
+
+            impl self::ethcontract::tokens::Tokenizable for Data {
+                fn from_token(
+                    token: self::ethcontract::common::abi::Token,
+                ) -> Result<Self, self::ethcontract::errors::Web3ContractError> {
+                    match token {
+                        self::ethcontract::common::abi::Token::Tuple(tokens) if tokens.len() == 2 => {
+                            let mut tokens = tokens.into_iter();
+                            let amount: self::ethcontract::U256 =
+                                self::ethcontract::tokens::Tokenizable::from_token(
+                                    tokens.next().expect("tuple length checked"),
+                                )?;
+                            let leg: Leg =
+                                self::ethcontract::tokens::Tokenizable::from_token(
+                                    tokens.next().expect("tuple length checked"),
+                                )?;
+                            Ok(Data { amount, leg })
+                        }
+                        other => Err(self::ethcontract::errors::Web3ContractError::InvalidOutputType(
+                            format!("Expected `Tuple(2)`, got {:?}", other),
+                        )),
+                    }
+                }
+
+                fn into_token(self) -> self::ethcontract::common::abi::Token {
+                    let Data { amount, leg } = self;
+                    self::ethcontract::common::abi::Token::Tuple(vec![
+                        self::ethcontract::tokens::Tokenizable::into_token(amount),
+                        self::ethcontract::tokens::Tokenizable::into_token(leg),
+                    ])
+                }
+
+                fn tuple_len() -> Option<usize> {
+                    Some(2)
+                }
+            }
+
+            impl self::ethcontract::tokens::TokenizableItem for Data {}
+        });
+        assert_quote!(expand_imports(&context), {
+            #[allow(unused_imports)]
+            use super::{Leg, order};
+        });
+    }
+
+    #[test]
+    fn expand_std_derives_for_large_types() {
+        let small = ParamType::FixedArray(Box::new(ParamType::Uint(256)), 32);
+        let large = ParamType::FixedArray(Box::new(ParamType::Uint(256)), 33);
+        let wide = ParamType::Tuple(vec![Box::new(ParamType::Bool); 13]);
+
+        assert_quote!(expand_std_derives(&[ParamType::Bool, small.clone()], true), {
+            Default, Eq, PartialEq,
+        });
+        assert_quote!(expand_std_derives(&[small], false), { Eq, PartialEq, });
+        assert_quote!(
+            expand_std_derives(&[ParamType::Bool, large.clone()], true),
+            {}
+        );
+        assert_quote!(
+            expand_std_derives(&[ParamType::Array(Box::new(large))], true),
+            {}
+        );
+        assert_quote!(expand_std_derives(&[wide], true), {});
+    }
+}
@@ -1,9 +1,13 @@
+use crate::contract::structs;
 use anyhow::{anyhow, Result};
 use ethcontract_common::abi::ParamType;
 use proc_macro2::{Literal, TokenStream};
 use quote::quote;
 
-pub(crate) fn expand(kind: &ParamType) -> Result<TokenStream> {
+/// Expands a Solidity type into its Rust type. Tuples are expanded into the
+/// generated struct type when the name of the Solidity struct is known, and
+/// into Rust tuples otherwise.
+pub(crate) fn expand(kind: &ParamType, struct_name: Option<&str>) -> Result<TokenStream> {
     match kind {
         ParamType::Address => Ok(quote! { self::ethcontract::Address }),
         ParamType::Bytes => Ok(quote! { Vec<u8> }),
@@ -28,7 +32,7 @@ pub(crate) fn expand(kind: &ParamType) -> Result<TokenStream> {
         ParamType::Bool => Ok(quote! { bool }),
         ParamType::String => Ok(quote! { String }),
         ParamType::Array(t) => {
-            let inner = expand(t)?;
+            let inner = expand(t, struct_name)?;
             Ok(quote! { Vec<#inner> })
         }
         ParamType::FixedBytes(n) => {
@@ -39,10 +43,19 @@ pub(crate) fn expand(kind: &ParamType) -> Result<TokenStream> {
         }
         ParamType::FixedArray(t, n) => {
             // TODO(nlordell): see above
-            let inner = expand(t)?;
+            let inner = expand(t, struct_name)?;
             let size = Literal::usize_unsuffixed(*n);
             Ok(quote! { [#inner; #size] })
         }
-        ParamType::Tuple(_) => Err(anyhow!("ABIEncoderV2 is currently not supported")),
+        ParamType::Tuple(members) => match struct_name {
+            Some(struct_name) => Ok(structs::expand_path(struct_name)),
+            None => {
+                let members = members
+                    .iter()
+                    .map(|member| expand(member, None))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(quote! { (#( #members, )*) })
+            }
+        },
     }
 }
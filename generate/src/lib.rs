@@ -44,7 +44,7 @@ pub(crate) struct Args {
     deployments: HashMap<u32, Address>,
     /// Manually specified contract method aliases.
     method_aliases: HashMap<String, String>,
-    /// Derives added to event structs and enums.
+    /// Derives added to event structs and enums, as well as Solidity structs.
     event_derives: Vec<String>,
 }
 
@@ -209,7 +209,9 @@ impl Builder {
         self
     }
 
-    /// Add a custom derive to the derives for event structs and enums.
+    /// Add a custom derive to the derives for event structs and enums. Note
+    /// that these derives are also added to the types generated for Solidity
+    /// structs, since they can be used as event parameters.
     ///
     /// This makes it possible to for example derive serde::Serialize and
     /// serde::Deserialize for events.
@@ -6,9 +6,12 @@ mod deploy;
 mod deployed;
 mod event;
 mod method;
+mod multicall;
+mod state_override;
 
 use crate::abicompat::AbiCompat;
 use crate::errors::{DeployError, LinkError};
+use crate::tokens::{Tokenizable, Tokenize};
 use ethcontract_common::abi::{Error as AbiError, Result as AbiResult};
 use ethcontract_common::abiext::FunctionExt;
 use ethcontract_common::hash::H32;
@@ -17,20 +20,24 @@ use ethcontract_common::{Abi, Artifact, Bytecode};
 use std::collections::HashMap;
 use std::hash::Hash;
 use web3::api::Web3;
-use web3::contract::tokens::{Detokenize, Tokenize};
 use web3::types::{Address, Bytes, H256};
 use web3::Transport;
 
 pub use self::deploy::{Deploy, DeployBuilder, DeployFuture};
 pub use self::deployed::{DeployedFuture, FromNetwork};
 pub use self::event::{
-    AllEventsBuilder, Event, EventBuilder, EventData, EventMetadata, EventStream, ParseLog,
-    QueryAllFuture, QueryFuture, RawLog, Topic, DEFAULT_POLL_INTERVAL,
+    AllEventsBlockStream, AllEventsBuilder, AllEventsStream, Event, EventBlockStream, EventBuilder,
+    EventData, EventMetadata, EventStream, ParseLog, PastEventPages, QueryAllFuture, QueryFuture,
+    RawLog, StreamAddresses, Topic, DEFAULT_POLL_INTERVAL,
 };
 pub use self::method::{
-    CallFuture, Detokenizable, MethodBuilder, MethodDefaults, MethodFuture, MethodSendFuture,
-    ViewMethodBuilder, Void,
+    CallFuture, Detokenizable, MethodAccessListFuture, MethodBuilder, MethodDefaults,
+    MethodEventsFuture, MethodFuture, MethodSendFuture, MethodTraceFuture, ViewMethodBuilder, Void,
 };
+pub use self::multicall::{
+    MulticallBatch, MulticallBatchFuture, MulticallFuture, MULTICALL3_ADDRESS,
+};
+pub use self::state_override::{StateOverride, StateOverrides};
 
 /// Represents a contract instance at an address. Provides methods for
 /// contract interaction.
@@ -168,7 +175,7 @@ impl<T: Transport> Instance<T> {
             .get(signature)
             .map(|(name, index)| &self.abi.functions[name][*index])
             .ok_or_else(|| AbiError::InvalidName(hex::encode(&signature)))?;
-        let data = function.encode_input(&params.into_tokens().compat())?;
+        let data = function.encode_input(&params.into_tokens())?;
 
         // take ownership here as it greatly simplifies dealing with futures
         // lifetime as it would require the contract Instance to live until
@@ -217,7 +224,7 @@ impl<T: Transport> Instance<T> {
     /// that emits events for the specified Solidity event by name.
     pub fn event<E>(&self, signature: H256) -> AbiResult<EventBuilder<T, E>>
     where
-        E: Detokenize,
+        E: Tokenizable,
     {
         let event = self
             .events
@@ -1,21 +1,21 @@
 //! Implementation for creating instances for deployed contracts and deploying
 //! new contracts.
 
-use crate::abicompat::AbiCompat;
 use crate::errors::{DeployError, ExecutionError};
+use crate::tokens::Tokenize;
 use crate::transaction::send::SendFuture;
+use crate::transaction::simulate::SimulateFuture;
 use crate::transaction::{Account, GasPrice, TransactionBuilder, TransactionResult};
 use ethcontract_common::abi::Error as AbiError;
 use ethcontract_common::{Abi, Bytecode};
 use futures::ready;
-use pin_project::pin_project;
+use pin_project::{pin_project, project};
 use std::future::Future;
 use std::marker::PhantomData;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use web3::api::Web3;
-use web3::contract::tokens::Tokenize;
-use web3::types::{Address, Bytes, H256, U256};
+use web3::types::{Address, BlockNumber, Bytes, TransactionReceipt, H256, U256};
 use web3::Transport;
 
 /// a factory trait for deployable contract instances. this traits provides
@@ -57,6 +57,8 @@ where
     context: I::Context,
     /// The underlying transaction used t
     tx: TransactionBuilder<T>,
+    /// Whether to simulate the deployment before sending it.
+    simulate: bool,
     _instance: PhantomData<I>,
 }
 
@@ -81,7 +83,7 @@ where
         }
 
         let code = bytecode.to_bytes()?;
-        let params = params.into_tokens().compat();
+        let params = params.into_tokens();
         let data = match (I::abi(&context).constructor(), params.is_empty()) {
             (None, false) => return Err(AbiError::InvalidData.into()),
             (None, true) => code,
@@ -92,6 +94,7 @@ where
             web3: web3.clone(),
             context,
             tx: TransactionBuilder::new(web3).data(data).confirmations(0),
+            simulate: false,
             _instance: PhantomData,
         })
     }
@@ -117,6 +120,20 @@ where
         self
     }
 
+    /// Specify the maximum total fee per gas for an EIP-1559 transaction. This
+    /// takes precedence over the gas price.
+    pub fn max_fee_per_gas(mut self, value: U256) -> Self {
+        self.tx = self.tx.max_fee_per_gas(value);
+        self
+    }
+
+    /// Specify the maximum priority fee per gas for an EIP-1559 transaction.
+    /// This takes precedence over the gas price.
+    pub fn max_priority_fee_per_gas(mut self, value: U256) -> Self {
+        self.tx = self.tx.max_priority_fee_per_gas(value);
+        self
+    }
+
     /// Specify what how much ETH to transfer with the transaction, if not
     /// specified then no ETH will be sent.
     pub fn value(mut self, value: U256) -> Self {
@@ -139,6 +156,18 @@ where
         self
     }
 
+    /// Specify whether to simulate the deployment with an `eth_call` against
+    /// the pending block before sending it. If the constructor reverts, the
+    /// deployment is aborted with a `DeployError::Revert` error without
+    /// sending the transaction. Defaults to `false`.
+    ///
+    /// Note that failed deployments are always simulated in order to recover
+    /// the revert reason, this option only prevents wasting gas on them.
+    pub fn simulate(mut self, value: bool) -> Self {
+        self.simulate = value;
+        self
+    }
+
     /// Extract inner `TransactionBuilder` from this `DeployBuilder`. This
     /// exposes `TransactionBuilder` only APIs.
     pub fn into_inner(self) -> TransactionBuilder<T> {
@@ -162,12 +191,32 @@ where
 {
     /// The deployment args
     args: Option<(Web3<T>, I::Context)>,
-    /// The future resolved when the deploy transaction is complete.
+    /// The deployment transaction, used for simulating the deployment when it
+    /// fails in order to recover the revert reason.
+    tx: Option<TransactionBuilder<T>>,
+    /// The current state of the deployment.
     #[pin]
-    send: SendFuture<T>,
+    state: DeployState<T>,
     _instance: PhantomData<Box<I>>,
 }
 
+/// The state of the deploy future.
+#[pin_project]
+#[allow(clippy::large_enum_variant)]
+enum DeployState<T: Transport> {
+    /// The deployment is being simulated before sending it.
+    Simulating(#[pin] SimulateFuture<T>),
+    /// The deployment transaction is being sent.
+    Sending(#[pin] SendFuture<T>),
+    /// The failed deployment is being simulated in order to recover the revert
+    /// reason.
+    Diagnosing {
+        #[pin]
+        simulation: SimulateFuture<T>,
+        receipt: Option<Box<TransactionReceipt>>,
+    },
+}
+
 impl<T, I> DeployFuture<T, I>
 where
     T: Transport,
@@ -175,9 +224,19 @@ where
 {
     /// Create an instance from a `DeployBuilder`.
     pub fn from_builder(builder: DeployBuilder<T, I>) -> Self {
+        let state = if builder.simulate {
+            DeployState::Simulating(SimulateFuture::from_builder(
+                builder.tx.clone(),
+                BlockNumber::Pending,
+            ))
+        } else {
+            DeployState::Sending(builder.tx.clone().send())
+        };
+
         DeployFuture {
             args: Some((builder.web3, builder.context)),
-            send: builder.tx.send(),
+            tx: Some(builder.tx),
+            state,
             _instance: PhantomData,
         }
     }
@@ -190,29 +249,82 @@ where
 {
     type Output = Result<I, DeployError>;
 
+    #[project]
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
-        let tx = match ready!(self.as_mut().project().send.poll(cx)) {
-            Ok(TransactionResult::Receipt(tx)) => tx,
-            Ok(TransactionResult::Hash(tx)) => return Poll::Ready(Err(DeployError::Pending(tx))),
-            Err(err) => return Poll::Ready(Err(err.into())),
-        };
-
-        let address = match tx.contract_address {
-            Some(address) => address,
-            None => {
-                return Poll::Ready(Err(DeployError::Tx(ExecutionError::Failure(Box::new(tx)))));
-            }
-        };
-        let transaction_hash = tx.transaction_hash;
-
-        let (web3, context) = self.args.take().expect("called more than once");
+        loop {
+            #[project]
+            let DeployFuture {
+                args, tx, state, ..
+            } = self.as_mut().project();
+
+            #[project]
+            let next_state = match state.project() {
+                DeployState::Simulating(simulation) => {
+                    if let Err(err) = ready!(simulation.poll(cx)) {
+                        return Poll::Ready(Err(deploy_error(err)));
+                    }
+                    let tx = tx.clone().expect("called more than once");
+                    DeployState::Sending(tx.send())
+                }
+                DeployState::Sending(send) => {
+                    let receipt = match ready!(send.poll(cx)) {
+                        Ok(TransactionResult::Receipt(receipt)) => match receipt.contract_address {
+                            Some(address) => {
+                                let transaction_hash = receipt.transaction_hash;
+                                let (web3, context) = args.take().expect("called more than once");
+                                return Poll::Ready(Ok(I::from_deployment(
+                                    web3,
+                                    address,
+                                    transaction_hash,
+                                    context,
+                                )));
+                            }
+                            None => Box::new(receipt),
+                        },
+                        Ok(TransactionResult::Hash(tx)) => {
+                            return Poll::Ready(Err(DeployError::Pending(tx)))
+                        }
+                        Err(ExecutionError::Failure(receipt)) => receipt,
+                        Err(err) => return Poll::Ready(Err(deploy_error(err))),
+                    };
+
+                    // NOTE: Simulate the deployment on the state of the parent
+                    //   block of the one it was included in, which is the
+                    //   closest we can get to the state it was executed on.
+                    let block = match receipt.block_number {
+                        Some(block) if !block.is_zero() => BlockNumber::Number(block - 1),
+                        _ => BlockNumber::Latest,
+                    };
+                    let tx = tx.take().expect("called more than once");
+                    DeployState::Diagnosing {
+                        simulation: SimulateFuture::from_builder(tx, block),
+                        receipt: Some(receipt),
+                    }
+                }
+                DeployState::Diagnosing {
+                    simulation,
+                    receipt,
+                } => {
+                    let receipt = receipt.take().expect("called more than once");
+                    return Poll::Ready(Err(match ready!(simulation.poll(cx)) {
+                        Err(err) if err.is_revert() => DeployError::Revert(err),
+                        _ => DeployError::Tx(ExecutionError::Failure(receipt)),
+                    }));
+                }
+            };
+
+            self.as_mut().project().state.set(next_state);
+        }
+    }
+}
 
-        Poll::Ready(Ok(I::from_deployment(
-            web3,
-            address,
-            transaction_hash,
-            context,
-        )))
+/// Converts an execution error that occured while deploying a contract into a
+/// deploy error.
+fn deploy_error(err: ExecutionError) -> DeployError {
+    if err.is_revert() {
+        DeployError::Revert(err)
+    } else {
+        DeployError::Tx(err)
     }
 }
 
@@ -220,8 +332,10 @@ where
 mod tests {
     use super::*;
     use crate::contract::{Instance, Linker};
+    use crate::errors::{revert, PanicCode};
     use crate::test::prelude::*;
     use ethcontract_common::{Artifact, Bytecode};
+    use web3::types::H2048;
 
     type InstanceDeployBuilder<T> = DeployBuilder<T, Instance<T>>;
 
@@ -262,6 +376,112 @@ mod tests {
         //   on github
     }
 
+    #[test]
+    fn deploy_simulation_reverts() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let artifact = Artifact {
+            bytecode: Bytecode::from_hex_str("0x42").unwrap(),
+            ..Artifact::empty()
+        };
+        let linker = Linker::new(artifact);
+
+        transport.add_response(json!(revert::encode_reason_hex("not allowed")));
+        let error = InstanceDeployBuilder::new(web3, linker, ())
+            .expect("error creating deploy builder")
+            .from(Account::Local(from, None))
+            .simulate(true)
+            .deploy()
+            .immediate()
+            .expect_err("unexpected deployment success");
+
+        assert!(
+            matches!(
+                &error,
+                DeployError::Revert(ExecutionError::Revert(Some(reason), _)) if reason == "not allowed"
+            ),
+            "unexpected error {:?}",
+            error
+        );
+        transport.assert_request(
+            "eth_call",
+            &[json!({ "from": from, "data": "0x42" }), json!("pending")],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn deploy_failure_recovers_revert_reason() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let hash = H256::repeat_byte(0xff);
+        let artifact = Artifact {
+            bytecode: Bytecode::from_hex_str("0x42").unwrap(),
+            ..Artifact::empty()
+        };
+        let linker = Linker::new(artifact);
+
+        transport.add_response(json!(hash));
+        transport.add_response(json!("0x2"));
+        transport.add_response(json!({
+            "transactionHash": hash,
+            "transactionIndex": "0x0",
+            "blockNumber": "0x2",
+            "blockHash": H256::repeat_byte(2),
+            "cumulativeGasUsed": "0x1337",
+            "gasUsed": "0x1337",
+            "logsBloom": H2048::zero(),
+            "logs": [],
+            "status": "0x0",
+        }));
+        transport.add_response(json!(Bytes(revert::encode_panic(0x12.into()))));
+        let error = InstanceDeployBuilder::new(web3, linker, ())
+            .expect("error creating deploy builder")
+            .from(Account::Local(from, None))
+            .gas(1.into())
+            .gas_price(2.into())
+            .deploy()
+            .immediate()
+            .expect_err("unexpected deployment success");
+
+        assert!(
+            matches!(
+                error,
+                DeployError::Revert(ExecutionError::Panic(PanicCode::DivisionByZero))
+            ),
+            "unexpected error {:?}",
+            error
+        );
+        transport.assert_request(
+            "eth_sendTransaction",
+            &[json!({
+                "from": from,
+                "gas": "0x1",
+                "gasPrice": "0x2",
+                "data": "0x42",
+            })],
+        );
+        transport.assert_request("eth_blockNumber", &[]);
+        transport.assert_request("eth_getTransactionReceipt", &[json!(hash)]);
+        transport.assert_request(
+            "eth_call",
+            &[
+                json!({
+                    "from": from,
+                    "gas": "0x1",
+                    "gasPrice": "0x2",
+                    "data": "0x42",
+                }),
+                json!("0x1"),
+            ],
+        );
+        transport.assert_no_more_requests();
+    }
+
     #[test]
     fn deploy_fails_on_empty_bytecode() {
         let transport = TestTransport::new();
@@ -1,26 +1,38 @@
 //! Module implements type-safe event streams from an ABI event definition with
 //! detokenization of the data included in the log.
 
+mod metadata;
+
+use self::metadata::{MetadataCache, MetadataFuture, MetadataStream};
 use crate::abicompat::AbiCompat;
 use crate::errors::{EventError, ExecutionError};
 use crate::future::CompatCallFuture;
-use crate::log::LogStream;
+use crate::log::{
+    AddressLogStream, BlockLogParams, BlockLogStream, LogPageStream, LogStream,
+    DEFAULT_REORG_WINDOW,
+};
+use crate::tokens::Tokenizable;
+use crate::transaction::confirm::BlockId;
 pub use ethcontract_common::abi::Topic;
 use ethcontract_common::abi::{
-    Event as AbiEvent, RawLog as AbiRawLog, RawTopicFilter, Token, TopicFilter,
+    self, Error as AbiError, Event as AbiEvent, Hash, ParamType, RawLog as AbiRawLog,
+    RawTopicFilter, Token, TopicFilter,
 };
 use futures::compat::Future01CompatExt;
-use futures::stream::Stream;
+use futures::ready;
+use futures::stream::{Stream, TryStreamExt};
 use pin_project::{pin_project, project};
 use std::cmp;
 use std::future::Future;
 use std::marker::PhantomData;
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
 use std::time::Duration;
 use web3::api::Web3;
-use web3::contract::tokens::{Detokenize, Tokenizable};
-use web3::types::{Address, BlockNumber, FilterBuilder, Log, H256};
+use web3::types::{
+    Address, BlockNumber, Filter, FilterBuilder, Log, TransactionReceipt, H256, U256, U64,
+};
 use web3::Transport;
 
 /// A contract event
@@ -46,7 +58,11 @@ pub enum EventData<T> {
 }
 
 /// Additional metadata from the log for the event.
+///
+/// This struct is non-exhaustive so that more metadata can be added without
+/// breaking changes.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
 pub struct EventMetadata {
     /// The hash of the block where the log was produced.
     pub block_hash: H256,
@@ -64,6 +80,16 @@ pub struct EventMetadata {
     /// The log type. Note that this property is non-standard but is supported
     /// by Parity nodes.
     pub log_type: Option<String>,
+    /// The timestamp of the block where the log was produced. This is only
+    /// retrieved when enabled with `block_timestamps` on the event builder.
+    /// Removed events only include it if it was retrieved for the event when
+    /// it was added.
+    pub block_timestamp: Option<U256>,
+    /// The address of the account that sent the transaction this log belongs
+    /// to. This is only retrieved when enabled with `transaction_senders` on
+    /// the event builder. Removed events only include it if it was retrieved
+    /// for the event when it was added.
+    pub transaction_sender: Option<Address>,
 }
 
 impl<T> Event<T> {
@@ -139,6 +165,23 @@ impl<T> Event<T> {
     }
 }
 
+impl<T: ParseLog> Event<T> {
+    /// Parses the events emitted by the contract at the specified address from
+    /// the logs of a transaction receipt. Logs emitted by other contracts are
+    /// ignored.
+    pub fn from_receipt(
+        receipt: &TransactionReceipt,
+        address: Address,
+    ) -> Result<Vec<Self>, ExecutionError> {
+        receipt
+            .logs
+            .iter()
+            .filter(|log| log.address == address)
+            .map(|log| Event::from_log(log.clone(), T::parse_log))
+            .collect()
+    }
+}
+
 impl EventMetadata {
     fn from_log(log: &Log) -> Option<Self> {
         Some(EventMetadata {
@@ -149,6 +192,8 @@ impl EventMetadata {
             log_index: log.log_index?.as_usize(),
             transaction_log_index: log.transaction_log_index.map(|index| index.as_usize()),
             log_type: log.log_type.clone(),
+            block_timestamp: None,
+            transaction_sender: None,
         })
     }
 }
@@ -161,30 +206,67 @@ pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(0);
 
 /// A builder for creating a filtered stream of contract events that are
 #[must_use = "event builders do nothing unless you stream them"]
-pub struct EventBuilder<T: Transport, E: Detokenize> {
+pub struct EventBuilder<T: Transport, E: Tokenizable> {
     /// The underlying web3 instance.
     web3: Web3<T>,
     /// The event ABI data for encoding topic filters and decoding logs.
     event: AbiEvent,
     /// The web3 filter builder used for creating a log filter.
     filter: FilterBuilder,
+    /// The block range to retrieve logs for. This needs to be stored for
+    /// block streams since the web3 filter does not allow access to these
+    /// values once stored.
+    block_range: (Option<BlockNumber>, Option<BlockNumber>),
+    /// The contract addresses to retrieve logs for. This needs to be stored
+    /// so that empty address lists can be rejected.
+    addresses: Vec<Address>,
     /// The topic filters that are encoded based on the event ABI.
     pub topics: RawTopicFilter,
+    /// The topic filter for the fourth indexed property of anonymous events.
+    /// This is stored separately since the ABI raw topic filter only supports
+    /// three indexed properties.
+    pub topic3: Topic<Token>,
     /// The polling interval for querying the node for more events.
     pub poll_interval: Option<Duration>,
+    /// The checkpoint to resume a block stream from.
+    pub checkpoint: Option<BlockId>,
+    /// The number of recent blocks a block stream keeps track of for detecting
+    /// chain reorganizations.
+    pub reorg_window: Option<usize>,
+    /// The page size in blocks to use when querying past events before
+    /// streaming new ones.
+    pub block_page_size: Option<u64>,
+    /// The maximum number of pages to query concurrently when querying past
+    /// events. Pages are still emitted in block order.
+    pub page_concurrency: Option<usize>,
+    /// Whether or not to enrich the metadata of events with the
+    /// timestamp of the block they were produced in.
+    pub block_timestamps: bool,
+    /// Whether or not to enrich the metadata of events with the
+    /// sender of the transaction they belong to.
+    pub transaction_senders: bool,
     _event: PhantomData<E>,
 }
 
-impl<T: Transport, E: Detokenize> EventBuilder<T, E> {
+impl<T: Transport, E: Tokenizable> EventBuilder<T, E> {
     /// Creates a new event builder from a web3 provider and a contract event
     /// and address.
     pub fn new(web3: Web3<T>, event: AbiEvent, address: Address) -> Self {
         EventBuilder {
             web3,
             event,
-            filter: FilterBuilder::default().address(vec![address]),
+            filter: FilterBuilder::default(),
+            block_range: (None, None),
+            addresses: vec![address],
             topics: RawTopicFilter::default(),
+            topic3: Topic::Any,
             poll_interval: None,
+            checkpoint: None,
+            reorg_window: None,
+            block_page_size: None,
+            page_concurrency: None,
+            block_timestamps: false,
+            transaction_senders: false,
             _event: PhantomData,
         }
     }
@@ -195,6 +277,7 @@ impl<T: Transport, E: Detokenize> EventBuilder<T, E> {
     #[allow(clippy::wrong_self_convention)]
     pub fn from_block(mut self, block: BlockNumber) -> Self {
         self.filter = self.filter.from_block(block);
+        self.block_range.0 = Some(block);
         self
     }
 
@@ -204,6 +287,7 @@ impl<T: Transport, E: Detokenize> EventBuilder<T, E> {
     #[allow(clippy::wrong_self_convention)]
     pub fn to_block(mut self, block: BlockNumber) -> Self {
         self.filter = self.filter.to_block(block);
+        self.block_range.1 = Some(block);
         self
     }
 
@@ -215,11 +299,27 @@ impl<T: Transport, E: Detokenize> EventBuilder<T, E> {
         self
     }
 
+    /// Sets the contract addresses to filter logs for, replacing the address
+    /// the builder was created with. This allows querying the events of
+    /// multiple contracts sharing the same event ABI with a single filter.
+    ///
+    /// Note that an empty address list is sent to the node as a wildcard, so
+    /// creating a query or stream with an empty address list fails with an
+    /// `ExecutionError::EmptyFilter` error instead of matching every contract.
+    pub fn addresses(mut self, value: Vec<Address>) -> Self {
+        self.addresses = value;
+        self
+    }
+
     /// Adds a filter for the first indexed topic.
     ///
     /// This corresponds to the first indexed property, which for anonymous
     /// events corresponds to `topic[0]` in the log, and for named events is
     /// actually `topic[1]`.
+    ///
+    /// Note that an empty `Topic::OneOf` set is sent to the node as a
+    /// wildcard, so creating a query or stream with one fails with an
+    /// `ExecutionError::EmptyFilter` error. This applies to all topics.
     pub fn topic0<P>(mut self, topic: Topic<P>) -> Self
     where
         P: Tokenizable,
@@ -246,6 +346,20 @@ impl<T: Transport, E: Detokenize> EventBuilder<T, E> {
         self
     }
 
+    /// Adds a filter for the fourth indexed topic.
+    ///
+    /// This is only supported for anonymous events, since named events use
+    /// `topic[0]` for the event signature and can have at most three indexed
+    /// properties. Creating a query or stream for a named event with this
+    /// filter set fails.
+    pub fn topic3<P>(mut self, topic: Topic<P>) -> Self
+    where
+        P: Tokenizable,
+    {
+        self.topic3 = tokenize_topic(topic);
+        self
+    }
+
     /// The polling interval. This is used as the interval between consecutive
     /// `eth_getFilterChanges` calls to get filter updates.
     pub fn poll_interval(mut self, value: Duration) -> Self {
@@ -253,6 +367,57 @@ impl<T: Transport, E: Detokenize> EventBuilder<T, E> {
         self
     }
 
+    /// Sets the checkpoint to resume a block stream from. The block stream
+    /// continues with the logs from the block following the checkpoint. The
+    /// checkpoint of a running block stream can be retrieved with its
+    /// `checkpoint` method.
+    ///
+    /// Note that resuming from a checkpoint that was orphaned by a chain
+    /// reorganization fails with an `ExecutionError::ReorgTooDeep` error.
+    pub fn checkpoint(mut self, value: BlockId) -> Self {
+        self.checkpoint = Some(value);
+        self
+    }
+
+    /// The number of recent blocks a block stream keeps track of for detecting
+    /// chain reorganizations.
+    pub fn reorg_window(mut self, value: usize) -> Self {
+        self.reorg_window = Some(value);
+        self
+    }
+
+    /// The page size in blocks to use when querying past events before
+    /// streaming new ones.
+    pub fn block_page_size(mut self, value: u64) -> Self {
+        self.block_page_size = Some(value);
+        self
+    }
+
+    /// The maximum number of pages to query concurrently when querying past
+    /// events. Pages are still emitted in block order. This also limits the
+    /// number of blocks and transactions that are retrieved concurrently when
+    /// enriching event metadata.
+    pub fn page_concurrency(mut self, value: usize) -> Self {
+        self.page_concurrency = Some(value);
+        self
+    }
+
+    /// Enrich the metadata of events with the timestamp of the block they were
+    /// produced in. Each block is only retrieved once per query, and event
+    /// streams cache the timestamps of recent blocks.
+    pub fn block_timestamps(mut self, value: bool) -> Self {
+        self.block_timestamps = value;
+        self
+    }
+
+    /// Enrich the metadata of events with the sender of the transaction they
+    /// belong to. Each transaction is only retrieved once per query, and event
+    /// streams cache the senders of recent transactions.
+    pub fn transaction_senders(mut self, value: bool) -> Self {
+        self.transaction_senders = value;
+        self
+    }
+
     /// Returns a future that resolves with a collection of all existing logs
     /// matching the builder parameters.
     pub fn query(self) -> Result<QueryFuture<T, E>, EventError> {
@@ -264,6 +429,125 @@ impl<T: Transport, E: Detokenize> EventBuilder<T, E> {
     pub fn stream(self) -> Result<EventStream<T, E>, EventError> {
         EventStream::from_builder(self)
     }
+
+    /// Creates an event stream from the current event builder that retrieves
+    /// events block by block, detecting chain reorganizations and emitting
+    /// removed events for logs from orphaned blocks.
+    pub fn block_stream(self) -> Result<EventBlockStream<T, E>, EventError> {
+        EventBlockStream::from_builder(self)
+    }
+
+    /// Creates an event stream from the current event builder that first
+    /// retrieves past events starting from the `from_block` (or the earliest
+    /// block if it is not specified) in pages of `block_page_size` blocks, and
+    /// then continues by streaming new events block by block.
+    ///
+    /// The hand-off between past and new events happens at the start of the
+    /// reorganization window, so that the events from the blocks that can
+    /// still be reorganized are tracked for emitting removed events. This
+    /// guarantees that no events are missed or duplicated between the two.
+    pub fn past_and_live_stream(self) -> Result<EventBlockStream<T, E>, EventError> {
+        let page_size = self.block_page_size.unwrap_or(DEFAULT_BLOCK_PAGE_SIZE);
+        EventBlockStream::with_past_page_size(self, Some(page_size))
+    }
+}
+
+/// Creates block log stream parameters from event builder values.
+fn block_log_params(
+    (from_block, to_block): (Option<BlockNumber>, Option<BlockNumber>),
+    checkpoint: Option<BlockId>,
+    poll_interval: Option<Duration>,
+    reorg_window: Option<usize>,
+    past_page_size: Option<u64>,
+    past_page_concurrency: Option<usize>,
+) -> BlockLogParams {
+    let block_number = |block| match block {
+        Some(BlockNumber::Earliest) => Some(0),
+        Some(BlockNumber::Number(value)) => Some(value.as_u64()),
+        None | Some(BlockNumber::Latest) | Some(BlockNumber::Pending) => None,
+    };
+
+    BlockLogParams {
+        from_block: block_number(from_block),
+        to_block: block_number(to_block),
+        checkpoint,
+        poll_interval: poll_interval.unwrap_or(DEFAULT_POLL_INTERVAL),
+        reorg_window: reorg_window.unwrap_or(DEFAULT_REORG_WINDOW),
+        past_page_size,
+        past_page_concurrency: past_page_concurrency.unwrap_or(1),
+    }
+}
+
+/// Creates a web3 filter builder from event builder values, encoding the topic
+/// filters based on the event ABI.
+///
+/// Empty address lists and topic sets are rejected, since they are sent to the
+/// node as wildcards.
+fn event_filter(
+    event: &AbiEvent,
+    filter: FilterBuilder,
+    addresses: Vec<Address>,
+    topics: RawTopicFilter,
+    topic3: Topic<Token>,
+) -> Result<FilterBuilder, EventError> {
+    let is_empty = |topic: &Topic<Token>| match topic {
+        Topic::OneOf(values) => values.is_empty(),
+        _ => false,
+    };
+    if addresses.is_empty()
+        || [&topics.topic0, &topics.topic1, &topics.topic2, &topic3]
+            .iter()
+            .any(|topic| is_empty(topic))
+    {
+        return Err(EventError::new(event, ExecutionError::EmptyFilter));
+    }
+
+    let mut abi_filter = event
+        .filter(topics)
+        .map_err(|err| EventError::new(event, err))?;
+    abi_filter.topic3 = match topic3 {
+        Topic::Any => abi_filter.topic3,
+        topic => encode_fourth_topic(event, topic).map_err(|err| EventError::new(event, err))?,
+    };
+
+    Ok(filter.address(addresses).topic_filter(abi_filter.compat()))
+}
+
+/// Encodes the topic filter for the fourth indexed property of an anonymous
+/// event the same way the ABI encodes the other topic filters.
+fn encode_fourth_topic(event: &AbiEvent, topic: Topic<Token>) -> Result<Topic<Hash>, AbiError> {
+    let param = event
+        .inputs
+        .iter()
+        .filter(|input| input.indexed)
+        .nth(3)
+        .filter(|_| event.anonymous)
+        .ok_or(AbiError::InvalidData)?;
+    // NOTE: Dynamic types are indexed by their hash, so they can only be
+    //   filtered by a 32-byte value.
+    let kind = match param.kind {
+        ParamType::String
+        | ParamType::Bytes
+        | ParamType::Array(_)
+        | ParamType::FixedArray(..)
+        | ParamType::Tuple(_) => ParamType::FixedBytes(32),
+        ref kind => kind.clone(),
+    };
+    let encode = |token: Token| {
+        if token.type_check(&kind) {
+            Ok(Hash::from_slice(&abi::encode(&[token])))
+        } else {
+            Err(AbiError::InvalidData)
+        }
+    };
+
+    Ok(match topic {
+        Topic::Any => Topic::Any,
+        Topic::This(token) => Topic::This(encode(token)?),
+        Topic::OneOf(tokens) => {
+            Topic::OneOf(tokens.into_iter().map(encode).collect::<Result<_, _>>()?)
+        }
+    })
 }
 
 /// Converts a tokenizable topic into a raw topic for filtering.
@@ -271,110 +555,248 @@ fn tokenize_topic<P>(topic: Topic<P>) -> Topic<Token>
 where
     P: Tokenizable,
 {
-    topic.map(|parameter| parameter.into_token().compat())
+    topic.map(Tokenizable::into_token)
 }
 
 /// A future for querying events based on a log filter.
 #[must_use = "futures do nothing unless you await or poll them"]
 #[pin_project]
-pub struct QueryFuture<T: Transport, E: Detokenize> {
+pub struct QueryFuture<T: Transport, E: Tokenizable> {
+    web3: Web3<T>,
     event: AbiEvent,
     #[pin]
     inner: CompatCallFuture<T, Vec<Log>>,
-    _event: PhantomData<E>,
+    metadata: Option<MetadataCache>,
+    #[pin]
+    enrich: Option<MetadataFuture<T, E>>,
 }
 
-impl<T: Transport, E: Detokenize> QueryFuture<T, E> {
+impl<T: Transport, E: Tokenizable> QueryFuture<T, E> {
     /// Create a new query future from event builder parameters.
     pub fn from_builder(builder: EventBuilder<T, E>) -> Result<Self, EventError> {
         let event = builder.event;
 
         let web3 = builder.web3;
-        let filter = {
-            let abi_filter = event
-                .filter(builder.topics)
-                .map_err(|err| EventError::new(&event, err))?;
-            builder.filter.topic_filter(abi_filter.compat()).build()
-        };
+        let filter = event_filter(
+            &event,
+            builder.filter,
+            builder.addresses,
+            builder.topics,
+            builder.topic3,
+        )?
+        .build();
 
         let inner = web3.eth().logs(filter).compat();
+        let metadata = MetadataCache::new(
+            builder.block_timestamps,
+            builder.transaction_senders,
+            builder.page_concurrency.unwrap_or(1),
+        );
 
         Ok(QueryFuture {
+            web3,
             event,
             inner,
-            _event: PhantomData,
+            metadata,
+            enrich: None,
         })
     }
 }
 
-impl<T: Transport, E: Detokenize> Future for QueryFuture<T, E> {
+impl<T: Transport, E: Tokenizable> Future for QueryFuture<T, E> {
     type Output = Result<Vec<Event<E>>, EventError>;
 
     #[project]
     fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
         #[project]
-        let QueryFuture { event, inner, .. } = self.project();
+        let QueryFuture {
+            web3,
+            event,
+            mut inner,
+            metadata,
+            mut enrich,
+        } = self.project();
+
+        loop {
+            if let Some(enrich) = enrich.as_mut().as_pin_mut() {
+                return enrich
+                    .poll(cx)
+                    .map(|result| result.map_err(|err| EventError::new(event, err)));
+            }
 
-        inner
-            .poll(cx)
-            .map(|logs| {
-                logs?
-                    .into_iter()
-                    .map(|log| Event::from_log(log, |raw| raw.decode(event)))
-                    .collect::<Result<Vec<_>, ExecutionError>>()
-            })
-            .map(|result| result.map_err(|err| EventError::new(&event, err)))
+            let events = ready!(inner.as_mut().poll(cx))
+                .map_err(ExecutionError::from)
+                .and_then(|logs| {
+                    logs.into_iter()
+                        .map(|log| Event::from_log(log, |raw| raw.decode(event)))
+                        .collect::<Result<Vec<_>, ExecutionError>>()
+                });
+            match (events, metadata.take()) {
+                (Ok(events), Some(cache)) => {
+                    enrich.set(Some(MetadataFuture::new(web3.clone(), cache, events)))
+                }
+                (events, _) => {
+                    return Poll::Ready(events.map_err(|err| EventError::new(event, err)))
+                }
+            }
+        }
     }
 }
 
 /// An event stream that emits events matching a builder.
 #[must_use = "streams do nothing unless you or poll them"]
 #[pin_project]
-pub struct EventStream<T: Transport, E: Detokenize> {
+pub struct EventStream<T: Transport, E: Tokenizable> {
     event: AbiEvent,
     #[pin]
     inner: LogStream<T>,
-    _event: PhantomData<E>,
+    #[pin]
+    metadata: MetadataStream<T, E>,
 }
 
-impl<T: Transport, E: Detokenize> EventStream<T, E> {
+impl<T: Transport, E: Tokenizable> EventStream<T, E> {
     /// Create a new log stream from a given web3 provider, filter and polling
     /// parameters.
     pub fn from_builder(builder: EventBuilder<T, E>) -> Result<Self, EventError> {
         let event = builder.event;
 
         let web3 = builder.web3;
-        let filter = {
-            let abi_filter = event
-                .filter(builder.topics)
-                .map_err(|err| EventError::new(&event, err))?;
-            builder.filter.topic_filter(abi_filter.compat()).build()
-        };
+        let filter = event_filter(
+            &event,
+            builder.filter,
+            builder.addresses,
+            builder.topics,
+            builder.topic3,
+        )?
+        .build();
 
         let poll_interval = builder.poll_interval.unwrap_or(DEFAULT_POLL_INTERVAL);
+        let metadata = MetadataCache::for_stream(
+            builder.block_timestamps,
+            builder.transaction_senders,
+            builder.page_concurrency.unwrap_or(1),
+        );
 
-        let inner = LogStream::new(web3, filter, poll_interval);
+        let inner = LogStream::new(web3.clone(), filter, poll_interval);
 
         Ok(EventStream {
             event,
             inner,
-            _event: PhantomData,
+            metadata: MetadataStream::new(web3, metadata),
         })
     }
 }
 
-impl<T: Transport, E: Detokenize> Stream for EventStream<T, E> {
+impl<T: Transport, E: Tokenizable> Stream for EventStream<T, E> {
     type Item = Result<Event<E>, EventError>;
 
     #[project]
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
         #[project]
-        let EventStream { event, inner, .. } = self.project();
-        inner.poll_next(cx).map(|next| {
-            next.map(|log| Event::from_log(log?, |raw| raw.decode(event)))
-                .map(|next| next.map_err(|err| EventError::new(&event, err)))
+        let EventStream {
+            event,
+            inner,
+            metadata,
+        } = self.project();
+        metadata
+            .poll_next(cx, |cx| {
+                inner
+                    .poll_next(cx)
+                    .map(|next| next.map(|log| Event::from_log(log?, |raw| raw.decode(event))))
+            })
+            .map(|next| next.map(|next| next.map_err(|err| EventError::new(event, err))))
+    }
+}
+
+/// An event stream that retrieves events matching a builder block by block,
+/// emitting removed events for logs from blocks that were orphaned by a chain
+/// reorganization.
+#[must_use = "streams do nothing unless you or poll them"]
+#[pin_project]
+pub struct EventBlockStream<T: Transport, E: Tokenizable> {
+    event: AbiEvent,
+    #[pin]
+    inner: BlockLogStream<T>,
+    #[pin]
+    metadata: MetadataStream<T, E>,
+}
+
+impl<T: Transport, E: Tokenizable> EventBlockStream<T, E> {
+    /// Create a new block event stream from event builder parameters.
+    pub fn from_builder(builder: EventBuilder<T, E>) -> Result<Self, EventError> {
+        EventBlockStream::with_past_page_size(builder, None)
+    }
+
+    /// Create a new block event stream from event builder parameters that
+    /// first queries past events with the specified page size.
+    fn with_past_page_size(
+        builder: EventBuilder<T, E>,
+        past_page_size: Option<u64>,
+    ) -> Result<Self, EventError> {
+        let event = builder.event;
+
+        let filter = event_filter(
+            &event,
+            builder.filter,
+            builder.addresses,
+            builder.topics,
+            builder.topic3,
+        )?;
+        let params = block_log_params(
+            builder.block_range,
+            builder.checkpoint,
+            builder.poll_interval,
+            builder.reorg_window,
+            past_page_size,
+            builder.page_concurrency,
+        );
+        let metadata = MetadataCache::for_stream(
+            builder.block_timestamps,
+            builder.transaction_senders,
+            builder.page_concurrency.unwrap_or(1),
+        );
+
+        let inner = BlockLogStream::new(builder.web3.clone(), filter, params);
+
+        Ok(EventBlockStream {
+            event,
+            inner,
+            metadata: MetadataStream::new(builder.web3, metadata),
         })
     }
+
+    /// Returns the last block that had all its events emitted by the stream.
+    pub fn checkpoint(&self) -> Option<BlockId> {
+        self.inner.checkpoint()
+    }
+
+    /// Returns the last block of the past events query that had all its
+    /// events emitted by the stream. This is updated after each page of past
+    /// events, before a checkpoint is available.
+    pub fn past_block(&self) -> Option<u64> {
+        self.inner.past_block()
+    }
+}
+
+impl<T: Transport, E: Tokenizable> Stream for EventBlockStream<T, E> {
+    type Item = Result<Event<E>, EventError>;
+
+    #[project]
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        #[project]
+        let EventBlockStream {
+            event,
+            inner,
+            metadata,
+        } = self.project();
+        metadata
+            .poll_next(cx, |cx| {
+                inner
+                    .poll_next(cx)
+                    .map(|next| next.map(|log| Event::from_log(log?, |raw| raw.decode(event))))
+            })
+            .map(|next| next.map(|next| next.map_err(|err| EventError::new(event, err))))
+    }
 }
 
 /// Trait for parsing a transaction log into an some event data when the
@@ -395,23 +817,33 @@ pub struct RawLog {
 
 impl RawLog {
     /// Decode raw log data into a tokenizable for a matching event ABI entry.
+    ///
+    /// The event parameters are detokenized as a tuple, so the data type must
+    /// be tokenizable from a tuple with one element per event parameter. Like
+    /// method return values, events with a single parameter are decoded
+    /// directly into the type of that parameter, unless the data type is a
+    /// one element tuple (such as a generated event struct) as reported by
+    /// `Tokenizable::tuple_len`.
     pub fn decode<D>(self, event: &AbiEvent) -> Result<D, ExecutionError>
     where
-        D: Detokenize,
+        D: Tokenizable,
     {
         let event_log = event.parse_log(AbiRawLog {
             topics: self.topics.compat(),
             data: self.data,
         })?;
 
-        let tokens = event_log
+        let mut tokens = event_log
             .params
             .into_iter()
             .map(|param| param.value)
-            .collect::<Vec<_>>()
-            .compat()
-            .ok_or(ExecutionError::UnsupportedToken)?;
-        let data = D::from_tokens(tokens)?;
+            .collect::<Vec<_>>();
+
+        let data = if tokens.len() == 1 && D::tuple_len() != Some(1) {
+            D::from_token(tokens.pop().expect("one token"))?
+        } else {
+            D::from_token(Token::Tuple(tokens))?
+        };
 
         Ok(data)
     }
@@ -442,6 +874,11 @@ pub struct AllEventsBuilder<T: Transport, E: ParseLog> {
     web3: Web3<T>,
     /// The web3 filter builder used for creating a log filter.
     filter: FilterBuilder,
+    /// The contract addresses to retrieve logs for.
+    ///
+    /// This needs to be stored so that addresses can be added to a running
+    /// event stream.
+    pub addresses: Vec<Address>,
     /// The block to start retrieving logs from.
     ///
     /// This needs to be stored to work around the fact that the web3 filter
@@ -464,21 +901,54 @@ pub struct AllEventsBuilder<T: Transport, E: ParseLog> {
     /// events. This provides no guarantee in how many events will be returned
     /// per page, but used to limit the block range for the query.
     pub block_page_size: Option<u64>,
+    /// The maximum number of pages to query concurrently when querying past
+    /// events. Pages are still emitted in block order.
+    pub page_concurrency: Option<usize>,
+    /// Whether or not to enrich the metadata of events with the
+    /// timestamp of the block they were produced in.
+    pub block_timestamps: bool,
+    /// Whether or not to enrich the metadata of events with the
+    /// sender of the transaction they belong to.
+    pub transaction_senders: bool,
+    /// The checkpoint to resume a block stream from.
+    pub checkpoint: Option<BlockId>,
+    /// The number of recent blocks a block stream keeps track of for detecting
+    /// chain reorganizations.
+    pub reorg_window: Option<usize>,
     _events: PhantomData<E>,
 }
 
 impl<T: Transport, E: ParseLog> AllEventsBuilder<T, E> {
     /// Creates a new all events builder from a web3 provider and and address.
     pub fn new(web3: Web3<T>, address: Address, deployment_transaction: Option<H256>) -> Self {
+        AllEventsBuilder {
+            deployment_transaction,
+            ..AllEventsBuilder::with_addresses(web3, vec![address])
+        }
+    }
+
+    /// Creates a new all events builder for logs emitted by any of the
+    /// specified contract addresses, for example all contracts created by a
+    /// factory contract.
+    ///
+    /// Note that an empty address list matches the logs of every contract,
+    /// since it is sent to the node as a wildcard.
+    pub fn with_addresses(web3: Web3<T>, addresses: Vec<Address>) -> Self {
         AllEventsBuilder {
             web3,
-            filter: FilterBuilder::default().address(vec![address]),
+            filter: FilterBuilder::default(),
+            addresses,
             from_block: None,
             to_block: None,
             topics: TopicFilter::default(),
             poll_interval: None,
-            deployment_transaction,
+            deployment_transaction: None,
             block_page_size: None,
+            page_concurrency: None,
+            block_timestamps: false,
+            transaction_senders: false,
+            checkpoint: None,
+            reorg_window: None,
             _events: PhantomData,
         }
     }
@@ -509,6 +979,22 @@ impl<T: Transport, E: ParseLog> AllEventsBuilder<T, E> {
         self
     }
 
+    /// Sets the contract addresses to filter logs for, replacing the address
+    /// the builder was created with. This allows querying the events of
+    /// multiple contracts with a single filter.
+    ///
+    /// Note that this clears the deployment transaction, since it is only
+    /// known for the original contract.
+    ///
+    /// Also note that an empty address list is sent to the node as a
+    /// wildcard, so it matches the logs of every contract. The same applies to
+    /// empty `Topic::OneOf` sets in topic filters.
+    pub fn addresses(mut self, value: Vec<Address>) -> Self {
+        self.addresses = value;
+        self.deployment_transaction = None;
+        self
+    }
+
     /// Adds a filter for the first indexed topic.
     ///
     /// For regular events, this corresponds to the event signature. For
@@ -530,9 +1016,9 @@ impl<T: Transport, E: ParseLog> AllEventsBuilder<T, E> {
         self
     }
 
-    /// Adds a filter for the third indexed topic.
+    /// Adds a filter for the fourth indexed topic.
     pub fn topic3(mut self, topic: Topic<H256>) -> Self {
-        self.topics.topic2 = topic.map(H256::compat);
+        self.topics.topic3 = topic.map(H256::compat);
         self
     }
 
@@ -550,10 +1036,57 @@ impl<T: Transport, E: ParseLog> AllEventsBuilder<T, E> {
         self
     }
 
+    /// The maximum number of pages to query concurrently when querying past
+    /// events. Pages are still emitted in block order. This also limits the
+    /// number of blocks and transactions that are retrieved concurrently when
+    /// enriching event metadata.
+    pub fn page_concurrency(mut self, value: usize) -> Self {
+        self.page_concurrency = Some(value);
+        self
+    }
+
+    /// Enrich the metadata of events with the timestamp of the block they were
+    /// produced in. Each block is only retrieved once per query, and event
+    /// streams cache the timestamps of recent blocks.
+    pub fn block_timestamps(mut self, value: bool) -> Self {
+        self.block_timestamps = value;
+        self
+    }
+
+    /// Enrich the metadata of events with the sender of the transaction they
+    /// belong to. Each transaction is only retrieved once per query, and event
+    /// streams cache the senders of recent transactions.
+    pub fn transaction_senders(mut self, value: bool) -> Self {
+        self.transaction_senders = value;
+        self
+    }
+
+    /// Sets the checkpoint to resume a block stream from. The block stream
+    /// continues with the logs from the block following the checkpoint. The
+    /// checkpoint of a running block stream can be retrieved with its
+    /// `checkpoint` method.
+    ///
+    /// Note that resuming from a checkpoint that was orphaned by a chain
+    /// reorganization fails with an `ExecutionError::ReorgTooDeep` error.
+    pub fn checkpoint(mut self, value: BlockId) -> Self {
+        self.checkpoint = Some(value);
+        self
+    }
+
+    /// The number of recent blocks a block stream keeps track of for detecting
+    /// chain reorganizations.
+    pub fn reorg_window(mut self, value: usize) -> Self {
+        self.reorg_window = Some(value);
+        self
+    }
+
     /// Returns a web3 provider and filter needed for querying and streaming
     /// events.
     fn prepare(self) -> (Web3<T>, FilterBuilder) {
-        let mut filter_builder = self.filter.topic_filter(self.topics.compat());
+        let mut filter_builder = self
+            .filter
+            .address(self.addresses)
+            .topic_filter(self.topics.compat());
         if let Some(from_block) = self.from_block {
             filter_builder = filter_builder.from_block(from_block);
         }
@@ -574,119 +1107,93 @@ impl<T: Transport, E: ParseLog> AllEventsBuilder<T, E> {
     /// event builder's parameters. This method is similar to `query` with the
     /// notable difference that the logs are fetched in pages by querying
     /// smaller block ranges specified by `block_page_size` instead of using a
-    /// single query.
+    /// single query. Setting `page_concurrency` allows multiple pages to be
+    /// queried in parallel while still returning events in block order.
     ///
     /// Note that if the block range is inconsistent (for example from block is
     /// after the to block, or querying until the earliest block), then the
     /// query will be forwarded to the node as is.
     pub async fn query_past_events_paginated(self) -> Result<Vec<Event<E>>, ExecutionError> {
-        let mut start_block = match self.from_block {
-            None | Some(BlockNumber::Earliest) => 0,
-            Some(BlockNumber::Number(value)) => value.as_u64(),
-            Some(BlockNumber::Latest) | Some(BlockNumber::Pending) => {
-                return self.query().await;
-            }
-        };
-        if let Some(deployment_tx) = self.deployment_transaction {
-            start_block = cmp::max(
-                start_block,
-                block_number_from_transaction_hash(self.web3.clone(), deployment_tx).await?,
-            );
-        }
-
-        let end_block = match self.to_block {
-            None | Some(BlockNumber::Latest) | Some(BlockNumber::Pending) => {
-                self.web3.eth().block_number().compat().await?.as_u64()
-            }
-            Some(BlockNumber::Number(value)) => value.as_u64(),
-            Some(BlockNumber::Earliest) => {
-                return self.query().await;
-            }
-        };
-
-        // NOTE: If the range is invalid, forward the request to the node to the
-        //   node to make sure we behave consistently for these edge cases.
-        if start_block > end_block {
-            return self.query().await;
-        }
-
-        let page_size = self.block_page_size.unwrap_or(DEFAULT_BLOCK_PAGE_SIZE);
-        let (web3, filter) = self.prepare();
-
-        let mut events = Vec::new();
-        let mut current_block = start_block;
-        while current_block <= end_block {
-            let filter = {
-                let mut filter = filter.clone().from_block(current_block.into());
-
-                // NOTE: The last page is handled a bit differently by using the
-                //   `to_block` that was originally specified to the builder and
-                //   is set in the `filter` (from the call to `prepare`). This
-                //   is done in case the to block was "latest" or "pending",
-                //   where we want to make sure that the last call includes
-                //   blocks that have been added since the start of the
-                //   paginated query.
-                if current_block + page_size <= end_block {
-                    filter = filter.to_block((current_block + page_size - 1).into());
-                }
-
-                filter.build()
-            };
-
-            let log_page = web3.eth().logs(filter).compat().await?;
-            events.reserve(log_page.len());
-            for log in log_page {
-                let event = Event::from_log(log, E::parse_log)?;
-                events.push(event);
-            }
-
-            current_block += page_size;
-        }
+        self.query_past_event_pages().try_concat().await
+    }
 
-        Ok(events)
+    /// Returns a stream of pages of events matching the event builder's
+    /// parameters. This is the same as `query_past_events_paginated` but emits
+    /// the events of each page as they are retrieved instead of collecting
+    /// them all.
+    ///
+    /// The page size adapts to the number of events: pages that the node
+    /// rejects for returning too many results are split in half and retried,
+    /// and the page size is grown again up to `block_page_size` when pages
+    /// are sparse. Up to `page_concurrency` pages are queried concurrently.
+    pub fn query_past_event_pages(self) -> PastEventPages<T, E> {
+        PastEventPages::from_builder(self)
     }
 
     /// Creates an event stream from the current event builder.
     pub fn stream(self) -> AllEventsStream<T, E> {
         AllEventsStream::from_builder(self)
     }
-}
 
-/// Retrieves a block number for the specified transaction hash.
-async fn block_number_from_transaction_hash<T: Transport>(
-    web3: Web3<T>,
-    tx_hash: H256,
-) -> Result<u64, ExecutionError> {
-    let tx_receipt = web3
-        .eth()
-        .transaction_receipt(tx_hash)
-        .compat()
-        .await?
-        .ok_or(ExecutionError::MissingTransaction(tx_hash))?;
-    Ok(tx_receipt
-        .block_number
-        .ok_or(ExecutionError::PendingTransaction(tx_hash))?
-        .as_u64())
+    /// Creates an event stream from the current event builder that retrieves
+    /// events block by block, detecting chain reorganizations and emitting
+    /// removed events for logs from orphaned blocks.
+    pub fn block_stream(self) -> AllEventsBlockStream<T, E> {
+        AllEventsBlockStream::from_builder(self)
+    }
+
+    /// Creates an event stream from the current event builder that first
+    /// retrieves past events starting from the `from_block` (or the earliest
+    /// block if it is not specified) in pages of `block_page_size` blocks, and
+    /// then continues by streaming new events block by block.
+    ///
+    /// The hand-off between past and new events happens at the start of the
+    /// reorganization window, so that the events from the blocks that can
+    /// still be reorganized are tracked for emitting removed events. This
+    /// guarantees that no events are missed or duplicated between the two.
+    ///
+    /// Note that unlike `query_past_events_paginated`, the contract deployment
+    /// transaction is not used for determining the first block.
+    pub fn past_and_live_stream(self) -> AllEventsBlockStream<T, E> {
+        let page_size = self.block_page_size.unwrap_or(DEFAULT_BLOCK_PAGE_SIZE);
+        AllEventsBlockStream::with_past_page_size(self, Some(page_size))
+    }
 }
 
 /// A future for querying all contract events based on a log filter.
 #[must_use = "futures do nothing unless you await or poll them"]
 #[pin_project]
 pub struct QueryAllFuture<T: Transport, E: ParseLog> {
+    web3: Web3<T>,
     #[pin]
     inner: CompatCallFuture<T, Vec<Log>>,
-    _event: PhantomData<E>,
+    metadata: Option<MetadataCache>,
+    #[pin]
+    enrich: Option<MetadataFuture<T, E>>,
 }
 
 impl<T: Transport, E: ParseLog> QueryAllFuture<T, E> {
     /// Create a new query future from event builder parameters.
     pub fn from_builder(builder: AllEventsBuilder<T, E>) -> Self {
+        let metadata = MetadataCache::new(
+            builder.block_timestamps,
+            builder.transaction_senders,
+            builder.page_concurrency.unwrap_or(1),
+        );
         let (web3, filter) = builder.prepare();
-        let inner = web3.eth().logs(filter.build()).compat();
+
+        QueryAllFuture::new(web3, filter.build(), metadata)
+    }
+
+    /// Create a new query future for the specified log filter.
+    fn new(web3: Web3<T>, filter: Filter, metadata: Option<MetadataCache>) -> Self {
+        let inner = web3.eth().logs(filter).compat();
 
         QueryAllFuture {
+            web3,
             inner,
-            _event: PhantomData,
+            metadata,
+            enrich: None,
         }
     }
 }
@@ -694,63 +1201,457 @@ impl<T: Transport, E: ParseLog> QueryAllFuture<T, E> {
 impl<T: Transport, E: ParseLog> Future for QueryAllFuture<T, E> {
     type Output = Result<Vec<Event<E>>, ExecutionError>;
 
+    #[project]
     fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
-        self.project().inner.poll(cx).map(|logs| {
-            logs?
-                .into_iter()
-                .map(|log| Event::from_log(log, E::parse_log))
-                .collect::<Result<Vec<_>, ExecutionError>>()
-        })
+        #[project]
+        let QueryAllFuture {
+            web3,
+            mut inner,
+            metadata,
+            mut enrich,
+        } = self.project();
+
+        loop {
+            if let Some(enrich) = enrich.as_mut().as_pin_mut() {
+                return enrich.poll(cx);
+            }
+
+            let events = ready!(inner.as_mut().poll(cx))
+                .map_err(ExecutionError::from)
+                .and_then(|logs| {
+                    logs.into_iter()
+                        .map(|log| Event::from_log(log, E::parse_log))
+                        .collect::<Result<Vec<_>, ExecutionError>>()
+                });
+            match (events, metadata.take()) {
+                (Ok(events), Some(cache)) => {
+                    enrich.set(Some(MetadataFuture::new(web3.clone(), cache, events)))
+                }
+                (events, _) => return Poll::Ready(events),
+            }
+        }
     }
 }
 
-/// An event stream for all contract events.
+/// A stream of pages of past contract events.
 #[must_use = "streams do nothing unless you or poll them"]
 #[pin_project]
-pub struct AllEventsStream<T: Transport, E: ParseLog> {
+pub struct PastEventPages<T: Transport, E: ParseLog> {
+    web3: Web3<T>,
+    filter: FilterBuilder,
+    to_block: Option<BlockNumber>,
+    page_size: u64,
+    concurrency: usize,
+    metadata: Option<MetadataCache>,
     #[pin]
-    inner: LogStream<T>,
-    _events: PhantomData<E>,
+    state: PastEventPagesState<T, E>,
+    #[pin]
+    enrich: Option<MetadataFuture<T, E>>,
 }
 
-impl<T: Transport, E: ParseLog> AllEventsStream<T, E> {
-    /// Create a new log stream from a given web3 provider, filter and polling
-    /// parameters.
+/// The state of the past event pages stream.
+#[pin_project]
+enum PastEventPagesState<T: Transport, E: ParseLog> {
+    /// The stream is retrieving the deployment transaction receipt in order
+    /// to determine the first block to query.
+    DeploymentBlock(
+        #[pin] CompatCallFuture<T, Option<TransactionReceipt>>,
+        H256,
+        u64,
+    ),
+    /// The stream is retrieving the current block number to determine the
+    /// last block to query.
+    BlockNumber(#[pin] CompatCallFuture<T, U64>, u64),
+    /// The stream is retrieving pages of logs.
+    Pages(#[pin] LogPageStream<T>),
+    /// The stream is forwarding the query as is to the node.
+    Query(Pin<Box<QueryAllFuture<T, E>>>),
+    /// All pages have been retrieved.
+    Done,
+}
+
+impl<T: Transport, E: ParseLog> PastEventPages<T, E> {
+    /// Create a new past event pages stream from event builder parameters.
     pub fn from_builder(builder: AllEventsBuilder<T, E>) -> Self {
-        let poll_interval = builder.poll_interval.unwrap_or(DEFAULT_POLL_INTERVAL);
+        let start_block = match builder.from_block {
+            None | Some(BlockNumber::Earliest) => Some(0),
+            Some(BlockNumber::Number(value)) => Some(value.as_u64()),
+            Some(BlockNumber::Latest) | Some(BlockNumber::Pending) => None,
+        };
+        let to_block = builder.to_block;
+        let page_size = builder.block_page_size.unwrap_or(DEFAULT_BLOCK_PAGE_SIZE);
+        let concurrency = builder.page_concurrency.unwrap_or(1);
+        let metadata = MetadataCache::new(
+            builder.block_timestamps,
+            builder.transaction_senders,
+            concurrency,
+        );
+        let deployment_transaction = builder.deployment_transaction;
         let (web3, filter) = builder.prepare();
-        let inner = LogStream::new(web3, filter.build(), poll_interval);
 
-        AllEventsStream {
-            inner,
-            _events: PhantomData,
+        let mut pages = PastEventPages {
+            web3,
+            filter,
+            to_block,
+            page_size,
+            concurrency,
+            metadata,
+            state: PastEventPagesState::Done,
+            enrich: None,
+        };
+        pages.state = match (start_block, deployment_transaction) {
+            (Some(start_block), Some(deployment_tx)) => PastEventPagesState::DeploymentBlock(
+                pages.web3.eth().transaction_receipt(deployment_tx).compat(),
+                deployment_tx,
+                start_block,
+            ),
+            (Some(start_block), None) => pages.end_block(start_block),
+            (None, _) => pages.query(),
+        };
+
+        pages
+    }
+
+    /// Returns the state for determining the last block to query.
+    fn end_block(&self, start_block: u64) -> PastEventPagesState<T, E> {
+        match self.to_block {
+            None | Some(BlockNumber::Latest) | Some(BlockNumber::Pending) => {
+                PastEventPagesState::BlockNumber(
+                    self.web3.eth().block_number().compat(),
+                    start_block,
+                )
+            }
+            Some(BlockNumber::Number(value)) => self.pages(start_block, value.as_u64()),
+            Some(BlockNumber::Earliest) => self.query(),
         }
     }
-}
 
-impl<T: Transport, E: ParseLog> Stream for AllEventsStream<T, E> {
-    type Item = Result<Event<E>, ExecutionError>;
+    /// Returns the state for querying pages in the specified block range.
+    fn pages(&self, start_block: u64, end_block: u64) -> PastEventPagesState<T, E> {
+        // NOTE: If the range is invalid, forward the request to the node to
+        //   make sure we behave consistently for these edge cases.
+        if start_block > end_block {
+            return self.query();
+        }
 
-    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
-        self.project()
-            .inner
-            .poll_next(cx)
-            .map(|next| next.map(|log| Event::from_log(log?, E::parse_log)))
+        let pages = LogPageStream::new(
+            self.web3.clone(),
+            self.filter.clone(),
+            start_block,
+            end_block,
+            self.page_size,
+        )
+        .concurrency(self.concurrency);
+
+        // NOTE: The last page is handled a bit differently by using the
+        //   `to_block` that was originally specified to the builder. This is
+        //   done in case the to block was "latest" or "pending", where we want
+        //   to make sure that the last call includes blocks that have been
+        //   added since the start of the paginated query.
+        PastEventPagesState::Pages(match self.to_block {
+            Some(BlockNumber::Number(_)) => pages,
+            _ => pages.extend_last_page(),
+        })
+    }
+
+    /// Returns the state for forwarding the query as is to the node.
+    fn query(&self) -> PastEventPagesState<T, E> {
+        PastEventPagesState::Query(Box::pin(QueryAllFuture::new(
+            self.web3.clone(),
+            self.filter.build(),
+            self.metadata.clone(),
+        )))
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::test::prelude::*;
-    use ethcontract_common::abi::{EventParam, ParamType};
-    use futures::stream::StreamExt;
-    use serde_json::Value;
-    use web3::types::{Address, H2048, H256, U256, U64};
+impl<T: Transport, E: ParseLog> Stream for PastEventPages<T, E> {
+    type Item = Result<Vec<Event<E>>, ExecutionError>;
 
-    fn test_abi_event() -> (AbiEvent, Value) {
-        let event = AbiEvent {
-            name: "test".to_owned(),
+    #[project]
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        loop {
+            // NOTE: Enrichment errors are returned without discarding the
+            //   page, so that the page is emitted once it succeeds on a
+            //   subsequent poll.
+            if let Some(enrich) = self.as_mut().project().enrich.as_pin_mut() {
+                let events = ready!(enrich.poll(cx));
+                if events.is_ok() {
+                    self.as_mut().project().enrich.set(None);
+                }
+                return Poll::Ready(Some(events));
+            }
+
+            #[project]
+            let next_state = match self.as_mut().project().state.project() {
+                PastEventPagesState::DeploymentBlock(receipt, deployment_tx, start_block) => {
+                    let (deployment_tx, start_block) = (*deployment_tx, *start_block);
+                    let block = ready!(receipt.poll(cx))
+                        .map_err(ExecutionError::from)
+                        .and_then(|receipt| {
+                            receipt
+                                .ok_or(ExecutionError::MissingTransaction(deployment_tx))?
+                                .block_number
+                                .ok_or(ExecutionError::PendingTransaction(deployment_tx))
+                        });
+                    match block {
+                        Ok(block) => self.end_block(cmp::max(start_block, block.as_u64())),
+                        Err(err) => {
+                            self.as_mut().project().state.set(PastEventPagesState::Done);
+                            return Poll::Ready(Some(Err(err)));
+                        }
+                    }
+                }
+                PastEventPagesState::BlockNumber(block_number, start_block) => {
+                    let start_block = *start_block;
+                    match ready!(block_number.poll(cx)) {
+                        Ok(end_block) => self.pages(start_block, end_block.as_u64()),
+                        Err(err) => {
+                            self.as_mut().project().state.set(PastEventPagesState::Done);
+                            return Poll::Ready(Some(Err(err.into())));
+                        }
+                    }
+                }
+                PastEventPagesState::Pages(pages) => {
+                    let page = ready!(pages.poll_next(cx)).map(|logs| {
+                        logs?
+                            .into_iter()
+                            .map(|log| Event::from_log(log, E::parse_log))
+                            .collect::<Result<Vec<_>, ExecutionError>>()
+                    });
+                    match (page, self.metadata.clone()) {
+                        (Some(Ok(events)), Some(cache)) => {
+                            let enrich = MetadataFuture::new(self.web3.clone(), cache, events);
+                            self.as_mut().project().enrich.set(Some(enrich));
+                            continue;
+                        }
+                        (page, _) => return Poll::Ready(page),
+                    }
+                }
+                PastEventPagesState::Query(query) => {
+                    let events = ready!(query.as_mut().poll(cx));
+                    self.as_mut().project().state.set(PastEventPagesState::Done);
+                    return Poll::Ready(Some(events));
+                }
+                PastEventPagesState::Done => return Poll::Ready(None),
+            };
+
+            self.as_mut().project().state.set(next_state);
+        }
+    }
+}
+
+/// An event stream for all contract events.
+#[must_use = "streams do nothing unless you or poll them"]
+#[pin_project]
+pub struct AllEventsStream<T: Transport, E: ParseLog> {
+    addresses: StreamAddresses,
+    revision: u64,
+    #[pin]
+    inner: AddressLogStream<T>,
+    #[pin]
+    metadata: MetadataStream<T, E>,
+}
+
+impl<T: Transport, E: ParseLog> AllEventsStream<T, E> {
+    /// Create a new log stream from a given web3 provider, filter and polling
+    /// parameters.
+    pub fn from_builder(builder: AllEventsBuilder<T, E>) -> Self {
+        let poll_interval = builder.poll_interval.unwrap_or(DEFAULT_POLL_INTERVAL);
+        let addresses = StreamAddresses::new(builder.addresses.clone());
+        let metadata = MetadataCache::for_stream(
+            builder.block_timestamps,
+            builder.transaction_senders,
+            builder.page_concurrency.unwrap_or(1),
+        );
+        let (web3, filter) = builder.prepare();
+        let inner = AddressLogStream::new(web3.clone(), filter, addresses.to_vec(), poll_interval);
+
+        AllEventsStream {
+            addresses,
+            revision: 0,
+            inner,
+            metadata: MetadataStream::new(web3, metadata),
+        }
+    }
+
+    /// Returns a handle to the set of contract addresses this stream is
+    /// retrieving logs for. The handle can be used to add addresses to the
+    /// stream while it is running, for example when a factory contract emits
+    /// an event for a newly created contract.
+    pub fn addresses(&self) -> StreamAddresses {
+        self.addresses.clone()
+    }
+}
+
+impl<T: Transport, E: ParseLog> Stream for AllEventsStream<T, E> {
+    type Item = Result<Event<E>, ExecutionError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+
+        if let Some((addresses, revision)) = this.addresses.changed_since(*this.revision) {
+            this.inner.as_mut().set_addresses(addresses);
+            *this.revision = revision;
+        }
+
+        let inner = this.inner;
+        this.metadata.poll_next(cx, |cx| {
+            inner
+                .poll_next(cx)
+                .map(|next| next.map(|log| Event::from_log(log?, E::parse_log)))
+        })
+    }
+}
+
+/// An event stream for all contract events that retrieves events block by
+/// block, emitting removed events for logs from blocks that were orphaned by a
+/// chain reorganization.
+#[must_use = "streams do nothing unless you or poll them"]
+#[pin_project]
+pub struct AllEventsBlockStream<T: Transport, E: ParseLog> {
+    #[pin]
+    inner: BlockLogStream<T>,
+    #[pin]
+    metadata: MetadataStream<T, E>,
+}
+
+impl<T: Transport, E: ParseLog> AllEventsBlockStream<T, E> {
+    /// Create a new block event stream from all events builder parameters.
+    pub fn from_builder(builder: AllEventsBuilder<T, E>) -> Self {
+        AllEventsBlockStream::with_past_page_size(builder, None)
+    }
+
+    /// Create a new block event stream from all events builder parameters
+    /// that first queries past events with the specified page size.
+    fn with_past_page_size(builder: AllEventsBuilder<T, E>, past_page_size: Option<u64>) -> Self {
+        let filter = builder
+            .filter
+            .address(builder.addresses)
+            .topic_filter(builder.topics.compat());
+        let params = block_log_params(
+            (builder.from_block, builder.to_block),
+            builder.checkpoint,
+            builder.poll_interval,
+            builder.reorg_window,
+            past_page_size,
+            builder.page_concurrency,
+        );
+        let metadata = MetadataCache::for_stream(
+            builder.block_timestamps,
+            builder.transaction_senders,
+            builder.page_concurrency.unwrap_or(1),
+        );
+
+        AllEventsBlockStream {
+            inner: BlockLogStream::new(builder.web3.clone(), filter, params),
+            metadata: MetadataStream::new(builder.web3, metadata),
+        }
+    }
+
+    /// Returns the last block that had all its events emitted by the stream.
+    pub fn checkpoint(&self) -> Option<BlockId> {
+        self.inner.checkpoint()
+    }
+
+    /// Returns the last block of the past events query that had all its
+    /// events emitted by the stream. This is updated after each page of past
+    /// events, before a checkpoint is available.
+    pub fn past_block(&self) -> Option<u64> {
+        self.inner.past_block()
+    }
+}
+
+impl<T: Transport, E: ParseLog> Stream for AllEventsBlockStream<T, E> {
+    type Item = Result<Event<E>, ExecutionError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let inner = this.inner;
+        this.metadata.poll_next(cx, |cx| {
+            inner
+                .poll_next(cx)
+                .map(|next| next.map(|log| Event::from_log(log?, E::parse_log)))
+        })
+    }
+}
+
+/// A shared handle to the set of contract addresses an `AllEventsStream` is
+/// retrieving logs for.
+///
+/// Adding addresses causes the stream to replace its log filter with one that
+/// includes the new addresses once all logs retrieved with the current filter
+/// have been emitted. The replaced filter is polled one last time after the new
+/// one is installed, so no logs are missed or duplicated for the addresses that
+/// were already part of the stream. Note that logs for the new addresses are
+/// only retrieved for blocks mined after the new filter is installed, so use a
+/// paginated query to retrieve past events for newly added addresses.
+#[derive(Clone, Debug)]
+pub struct StreamAddresses(Arc<Mutex<(Vec<Address>, u64)>>);
+
+impl StreamAddresses {
+    fn new(addresses: Vec<Address>) -> Self {
+        StreamAddresses(Arc::new(Mutex::new((addresses, 0))))
+    }
+
+    /// Adds an address to the stream. Returns `false` if the stream is
+    /// already retrieving logs for this address.
+    pub fn add(&self, address: Address) -> bool {
+        self.extend(Some(address)) > 0
+    }
+
+    /// Adds multiple addresses to the stream, returning the number of
+    /// addresses that were not already part of it.
+    pub fn extend<I>(&self, addresses: I) -> usize
+    where
+        I: IntoIterator<Item = Address>,
+    {
+        let mut inner = self.0.lock().expect("poisoned stream addresses");
+        let (current, revision) = &mut *inner;
+
+        let mut added = 0;
+        for address in addresses {
+            if !current.contains(&address) {
+                current.push(address);
+                added += 1;
+            }
+        }
+        if added > 0 {
+            *revision += 1;
+        }
+
+        added
+    }
+
+    /// Returns the addresses the stream is retrieving logs for.
+    pub fn to_vec(&self) -> Vec<Address> {
+        self.0.lock().expect("poisoned stream addresses").0.clone()
+    }
+
+    /// Returns the current addresses along with their revision if they have
+    /// changed since the specified revision. The revision is incremented every
+    /// time addresses are added.
+    fn changed_since(&self, revision: u64) -> Option<(Vec<Address>, u64)> {
+        let inner = self.0.lock().expect("poisoned stream addresses");
+        if inner.1 == revision {
+            return None;
+        }
+        Some(inner.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::Web3ContractError;
+    use crate::test::prelude::*;
+    use ethcontract_common::abi::{EventParam, ParamType};
+    use futures::stream::StreamExt;
+    use serde_json::Value;
+    use web3::types::{Address, H2048, H256, U256, U64};
+
+    fn test_abi_event() -> (AbiEvent, Value) {
+        let event = AbiEvent {
+            name: "test".to_owned(),
             inputs: vec![
                 EventParam {
                     name: "from".to_owned(),
@@ -835,6 +1736,200 @@ mod tests {
         transport.assert_no_more_requests();
     }
 
+    #[test]
+    fn event_query_enriched_metadata() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let (event, log) = test_abi_event();
+        let log_in = |transaction: H256| {
+            let mut log = log.clone();
+            log["transactionHash"] = json!(transaction);
+            log
+        };
+
+        let (tx1, tx2) = (H256::repeat_byte(0x11), H256::repeat_byte(0x22));
+        let (sender1, sender2) = (Address::repeat_byte(0x51), Address::repeat_byte(0x52));
+
+        // get logs filter
+        transport.add_response(json!([log_in(tx1), log_in(tx2), log_in(tx1)]));
+        // get block by hash
+        transport.add_response(json!({ "timestamp": "0x5f5e1000" }));
+        // get transactions by hash
+        transport.add_response(json!({ "hash": tx1, "from": sender1 }));
+        transport.add_response(json!({ "hash": tx2, "from": sender2 }));
+
+        let address = Address::repeat_byte(0x01);
+        let signature = event.signature();
+        let events = EventBuilder::<_, (Address, Address, U256)>::new(web3, event, address)
+            .block_timestamps(true)
+            .transaction_senders(true)
+            .query()
+            .expect("failed to abi-encode filter")
+            .immediate()
+            .expect("failed to get logs");
+
+        let metadata = events
+            .into_iter()
+            .map(|event| {
+                let meta = event.meta.expect("missing metadata");
+                (meta.block_timestamp, meta.transaction_sender)
+            })
+            .collect::<Vec<_>>();
+        let timestamp = Some(U256::from(0x5f5e1000));
+        assert_eq!(
+            metadata,
+            [
+                (timestamp, Some(sender1)),
+                (timestamp, Some(sender2)),
+                (timestamp, Some(sender1)),
+            ]
+        );
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({
+                "address": address,
+                "topics": [signature],
+            })],
+        );
+        transport.assert_request("eth_getBlockByHash", &[json!(H256::zero()), json!(false)]);
+        transport.assert_request("eth_getTransactionByHash", &[json!(tx1)]);
+        transport.assert_request("eth_getTransactionByHash", &[json!(tx2)]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn event_query_multiple_addresses() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let (event, log) = test_abi_event();
+
+        // get logs filter
+        transport.add_response(json!([log]));
+
+        let addresses = vec![Address::repeat_byte(0x01), Address::repeat_byte(0x02)];
+        let signature = event.signature();
+        let events = EventBuilder::<_, (Address, Address, U256)>::new(web3, event, Address::zero())
+            .addresses(addresses.clone())
+            .topic0(Topic::OneOf(vec![
+                Address::repeat_byte(0xf0),
+                Address::repeat_byte(0xf1),
+            ]))
+            .query()
+            .expect("failed to abi-encode filter")
+            .immediate()
+            .expect("failed to get logs");
+
+        assert_eq!(events.len(), 1);
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({
+                "address": addresses,
+                "topics": [
+                    signature,
+                    [
+                        H256::from(Address::repeat_byte(0xf0)),
+                        H256::from(Address::repeat_byte(0xf1)),
+                    ],
+                ],
+            })],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn event_query_rejects_empty_filters() {
+        let transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let (event, _) = test_abi_event();
+
+        let builder = || {
+            EventBuilder::<_, (Address, Address, U256)>::new(
+                web3.clone(),
+                event.clone(),
+                Address::zero(),
+            )
+        };
+        let empty_addresses = builder().addresses(vec![]).query();
+        let empty_topic = builder().topic1(Topic::<Address>::OneOf(vec![])).stream();
+
+        assert!(matches!(
+            empty_addresses,
+            Err(EventError {
+                inner: ExecutionError::EmptyFilter,
+                ..
+            })
+        ));
+        assert!(matches!(
+            empty_topic,
+            Err(EventError {
+                inner: ExecutionError::EmptyFilter,
+                ..
+            })
+        ));
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn anonymous_event_query_fourth_topic() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let indexed = |name: &str, kind| EventParam {
+            name: name.to_owned(),
+            kind,
+            indexed: true,
+        };
+        let event = AbiEvent {
+            name: "test".to_owned(),
+            inputs: vec![
+                indexed("a", ParamType::Address),
+                indexed("b", ParamType::Address),
+                indexed("c", ParamType::Address),
+                indexed("d", ParamType::Uint(256)),
+            ],
+            anonymous: true,
+        };
+
+        // get logs filter
+        transport.add_response(json!([]));
+
+        let events =
+            EventBuilder::<_, (Address, Address, Address, U256)>::new(web3, event, Address::zero())
+                .topic3(Topic::OneOf(vec![U256::from(1), U256::from(2)]))
+                .query()
+                .expect("failed to abi-encode filter")
+                .immediate()
+                .expect("failed to get logs");
+
+        assert!(events.is_empty());
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({
+                "address": Address::zero(),
+                "topics": [
+                    null,
+                    null,
+                    null,
+                    [H256::from_low_u64_be(1), H256::from_low_u64_be(2)],
+                ],
+            })],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn named_event_query_fourth_topic_fails() {
+        let transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let (event, _) = test_abi_event();
+
+        let result = EventBuilder::<_, (Address, Address, U256)>::new(web3, event, Address::zero())
+            .topic3(Topic::This(U256::from(1)))
+            .query();
+
+        assert!(result.is_err());
+        transport.assert_no_more_requests();
+    }
+
     #[test]
     fn event_stream_next_event() {
         let mut transport = TestTransport::new();
@@ -864,21 +1959,204 @@ mod tests {
         assert!(event.is_added());
         assert_eq!(event.inner_data().2, U256::from(42));
         transport.assert_request(
-            "eth_newFilter",
+            "eth_newFilter",
+            &[json!({
+                "address": address,
+                "toBlock": U256::from(99),
+                "topics": [
+                    signature,
+                    null,
+                    [
+                        H256::from(Address::repeat_byte(0x70)),
+                        H256::from(Address::repeat_byte(0x80)),
+                    ],
+                ],
+            })],
+        );
+        transport.assert_request("eth_getFilterChanges", &[json!("0xf0")]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn event_stream_enriched_metadata() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let (event, log) = test_abi_event();
+        let log_in = |transaction: H256, removed: bool| {
+            let mut log = log.clone();
+            log["transactionHash"] = json!(transaction);
+            log["removed"] = json!(removed);
+            log
+        };
+
+        let (tx1, tx2, tx3) = (
+            H256::repeat_byte(0x11),
+            H256::repeat_byte(0x22),
+            H256::repeat_byte(0x33),
+        );
+        let (sender1, sender2) = (Address::repeat_byte(0x51), Address::repeat_byte(0x52));
+
+        // filter created
+        transport.add_response(json!("0xf0"));
+        // get logs filter
+        transport.add_response(json!([
+            log_in(tx1, false),
+            log_in(tx2, false),
+            log_in(tx1, false),
+            log_in(tx3, true),
+        ]));
+        // get block and transaction by hash for the first event
+        transport.add_response(json!({ "timestamp": "0x5f5e1000" }));
+        transport.add_response(json!({ "hash": tx1, "from": sender1 }));
+        // get transaction by hash for the second event
+        transport.add_response(json!({ "hash": tx2, "from": sender2 }));
+
+        let address = Address::repeat_byte(0x01);
+        let signature = event.signature();
+        let events = EventBuilder::<_, (Address, Address, U256)>::new(web3, event, address)
+            .block_timestamps(true)
+            .transaction_senders(true)
+            .stream()
+            .expect("failed to abi-encode filter")
+            .take(4)
+            .collect::<Vec<_>>()
+            .immediate();
+
+        let metadata = events
+            .into_iter()
+            .map(|event| {
+                let event = event.expect("failed to get event");
+                let removed = event.is_removed();
+                let meta = event.meta.expect("missing metadata");
+                (removed, meta.block_timestamp, meta.transaction_sender)
+            })
+            .collect::<Vec<_>>();
+        let timestamp = Some(U256::from(0x5f5e1000));
+        // NOTE: Blocks and transactions are not retrieved for removed events.
+        assert_eq!(
+            metadata,
+            [
+                (false, timestamp, Some(sender1)),
+                (false, timestamp, Some(sender2)),
+                (false, timestamp, Some(sender1)),
+                (true, timestamp, None),
+            ]
+        );
+        transport.assert_request(
+            "eth_newFilter",
+            &[json!({
+                "address": address,
+                "topics": [signature],
+            })],
+        );
+        transport.assert_request("eth_getFilterChanges", &[json!("0xf0")]);
+        transport.assert_request("eth_getBlockByHash", &[json!(H256::zero()), json!(false)]);
+        transport.assert_request("eth_getTransactionByHash", &[json!(tx1)]);
+        transport.assert_request("eth_getTransactionByHash", &[json!(tx2)]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn all_events_block_stream_enriched_metadata_retry() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let (_, log) = test_abi_event();
+
+        // get block
+        transport.add_response(json!({
+            "hash": H256::zero(),
+            "parentHash": H256::zero(),
+        }));
+        // get logs
+        transport.add_response(json!([log]));
+        // get block by hash fails and is retried
+        transport.add_error(web3::Error::Unreachable);
+        transport.add_response(json!({ "timestamp": "0x5f5e1000" }));
+
+        let address = Address::repeat_byte(0x01);
+        let mut stream = AllEventsBuilder::<_, RawLog>::new(web3, address, None)
+            .from_block(BlockNumber::Earliest)
+            .to_block(BlockNumber::Earliest)
+            .block_timestamps(true)
+            .block_stream();
+
+        let err = stream.next().immediate();
+        assert!(
+            matches!(
+                err,
+                Some(Err(ExecutionError::Web3(web3::Error::Unreachable)))
+            ),
+            "unexpected result {:?}",
+            err
+        );
+        let event = stream
+            .next()
+            .immediate()
+            .expect("stream ended early")
+            .expect("failed to get event");
+        assert_eq!(
+            event.meta.unwrap().block_timestamp,
+            Some(U256::from(0x5f5e1000))
+        );
+        assert!(stream.next().immediate().is_none());
+
+        transport.assert_request("eth_getBlockByNumber", &[json!("0x0"), json!(false)]);
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({
+                "address": address,
+                "fromBlock": "0x0",
+                "toBlock": "0x0",
+                "topics": [],
+            })],
+        );
+        transport.assert_request("eth_getBlockByHash", &[json!(H256::zero()), json!(false)]);
+        transport.assert_request("eth_getBlockByHash", &[json!(H256::zero()), json!(false)]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn event_block_stream() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let (event, log) = test_abi_event();
+
+        // get block
+        transport.add_response(json!({
+            "hash": H256::zero(),
+            "parentHash": H256::zero(),
+        }));
+        // get logs
+        transport.add_response(json!([log]));
+
+        let address = Address::repeat_byte(0x01);
+        let signature = event.signature();
+        let mut stream = EventBuilder::<_, (Address, Address, U256)>::new(web3, event, address)
+            .from_block(BlockNumber::Earliest)
+            .to_block(BlockNumber::Earliest)
+            .block_stream()
+            .expect("failed to abi-encode filter");
+        let events = (&mut stream).collect::<Vec<_>>().immediate();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].as_ref().unwrap().inner_data().2, U256::from(42));
+        assert_eq!(
+            stream.checkpoint(),
+            Some(BlockId {
+                number: 0.into(),
+                hash: H256::zero(),
+            }),
+        );
+        transport.assert_request("eth_getBlockByNumber", &[json!("0x0"), json!(false)]);
+        transport.assert_request(
+            "eth_getLogs",
             &[json!({
                 "address": address,
-                "toBlock": U256::from(99),
-                "topics": [
-                    signature,
-                    null,
-                    [
-                        H256::from(Address::repeat_byte(0x70)),
-                        H256::from(Address::repeat_byte(0x80)),
-                    ],
-                ],
+                "fromBlock": "0x0",
+                "toBlock": "0x0",
+                "topics": [signature],
             })],
         );
-        transport.assert_request("eth_getFilterChanges", &[json!("0xf0")]);
         transport.assert_no_more_requests();
     }
 
@@ -938,6 +2216,58 @@ mod tests {
         transport.assert_no_more_requests();
     }
 
+    #[test]
+    fn all_events_query_fourth_topic() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        // get logs
+        transport.add_response(json!([]));
+
+        let addresses = vec![Address::repeat_byte(0x01), Address::repeat_byte(0x02)];
+        let raw_events = AllEventsBuilder::<_, RawLog>::new(web3, addresses[0], None)
+            .addresses(addresses.clone())
+            .topic3(Topic::This(H256::repeat_byte(0x33)))
+            .query()
+            .immediate()
+            .expect("failed to get logs");
+
+        assert!(raw_events.is_empty());
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({
+                "address": addresses,
+                "topics": [null, null, null, H256::repeat_byte(0x33)],
+            })],
+        );
+        transport.assert_no_more_requests();
+    }
+    #[test]
+    fn all_events_query_empty_addresses_matches_any_contract() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        // get logs
+        transport.add_response(json!([]));
+
+        // NOTE: Empty address lists and topic sets are sent to the node as
+        //   wildcards, and the all events builder does not reject them.
+        AllEventsBuilder::<_, RawLog>::with_addresses(web3, vec![])
+            .topic1(Topic::OneOf(vec![]))
+            .query()
+            .immediate()
+            .expect("failed to get logs");
+
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({
+                "address": null,
+                "topics": [null, null],
+            })],
+        );
+        transport.assert_no_more_requests();
+    }
+
     #[test]
     fn all_events_query_paginated() {
         let mut transport = TestTransport::new();
@@ -1008,6 +2338,144 @@ mod tests {
         transport.assert_no_more_requests();
     }
 
+    #[test]
+    fn all_events_query_past_event_pages() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let (_, log) = test_abi_event();
+
+        // get logs pages
+        transport.add_response(json!([log, log]));
+        transport.add_response(json!([log]));
+
+        let address = Address::repeat_byte(0x01);
+        let pages = AllEventsBuilder::<_, RawLog>::new(web3, address, None)
+            .from_block(10.into())
+            .to_block(15.into())
+            .block_page_size(3)
+            .query_past_event_pages()
+            .map(|page| page.expect("failed to get page").len())
+            .collect::<Vec<_>>()
+            .immediate();
+
+        assert_eq!(pages, [2, 1]);
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({
+                "address": address,
+                "fromBlock": U64::from(10),
+                "toBlock": U64::from(12),
+                "topics": [],
+            })],
+        );
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({
+                "address": address,
+                "fromBlock": U64::from(13),
+                "toBlock": U64::from(15),
+                "topics": [],
+            })],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn all_events_query_past_event_pages_shares_metadata_cache() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let (_, log) = test_abi_event();
+
+        // get logs and block of the first page
+        transport.add_response(json!([log]));
+        transport.add_response(json!({ "timestamp": "0x2a" }));
+        // get logs of the second page, the block is already cached
+        transport.add_response(json!([log, log]));
+
+        let address = Address::repeat_byte(0x01);
+        let timestamps = AllEventsBuilder::<_, RawLog>::new(web3, address, None)
+            .from_block(10.into())
+            .to_block(15.into())
+            .block_page_size(3)
+            .block_timestamps(true)
+            .query_past_event_pages()
+            .map(|page| {
+                page.expect("failed to get page")
+                    .into_iter()
+                    .map(|event| event.meta.expect("missing metadata").block_timestamp)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+            .immediate();
+
+        let timestamp = Some(U256::from(42));
+        assert_eq!(timestamps, [vec![timestamp], vec![timestamp, timestamp]]);
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({
+                "address": address,
+                "fromBlock": U64::from(10),
+                "toBlock": U64::from(12),
+                "topics": [],
+            })],
+        );
+        transport.assert_request("eth_getBlockByHash", &[json!(H256::zero()), json!(false)]);
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({
+                "address": address,
+                "fromBlock": U64::from(13),
+                "toBlock": U64::from(15),
+                "topics": [],
+            })],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn all_events_query_past_events_concurrently() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let (_, log) = test_abi_event();
+        let log_at = |block: u64| {
+            let mut log = log.clone();
+            log["blockNumber"] = json!(U64::from(block));
+            log
+        };
+
+        // get logs pages, the first two are queried concurrently
+        transport.add_response(json!([log_at(10), log_at(11)]));
+        transport.add_response(json!([log_at(12)]));
+        transport.add_response(json!([log_at(14), log_at(15)]));
+
+        let address = Address::repeat_byte(0x01);
+        let blocks = AllEventsBuilder::<_, RawLog>::new(web3, address, None)
+            .from_block(10.into())
+            .to_block(15.into())
+            .block_page_size(2)
+            .page_concurrency(2)
+            .query_past_events_paginated()
+            .immediate()
+            .expect("failed to query past events")
+            .into_iter()
+            .map(|event| event.meta.expect("missing metadata").block_number)
+            .collect::<Vec<_>>();
+
+        assert_eq!(blocks, [10, 11, 12, 14, 15]);
+        for (from, to) in &[(10, 11), (12, 13), (14, 15)] {
+            transport.assert_request(
+                "eth_getLogs",
+                &[json!({
+                    "address": address,
+                    "fromBlock": U64::from(*from),
+                    "toBlock": U64::from(*to),
+                    "topics": [],
+                })],
+            );
+        }
+        transport.assert_no_more_requests();
+    }
+
     #[test]
     fn all_events_stream_next_event() {
         let mut transport = TestTransport::new();
@@ -1068,4 +2536,283 @@ mod tests {
         transport.assert_request("eth_getFilterChanges", &[json!("0xf0")]);
         transport.assert_no_more_requests();
     }
+
+    #[test]
+    fn all_events_past_and_live_stream() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let (_, log) = test_abi_event();
+
+        // current block
+        transport.add_response(json!("0x25"));
+        // get past logs
+        transport.add_response(json!([log]));
+        // new block and its logs
+        transport.add_response(json!({
+            "hash": H256::repeat_byte(0x37),
+            "parentHash": H256::repeat_byte(0x36),
+        }));
+        transport.add_response(json!([]));
+
+        let address = Address::repeat_byte(0x01);
+        let events = AllEventsBuilder::<_, RawLog>::new(web3, address, None)
+            .to_block(37.into())
+            .reorg_window(1)
+            .past_and_live_stream()
+            .collect::<Vec<_>>()
+            .immediate();
+
+        assert_eq!(events.len(), 1);
+        assert!(events[0].as_ref().unwrap().is_added());
+        transport.assert_request("eth_blockNumber", &[]);
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({
+                "address": address,
+                "fromBlock": U64::from(0),
+                "toBlock": U64::from(36),
+                "topics": [],
+            })],
+        );
+        transport.assert_request(
+            "eth_getBlockByNumber",
+            &[json!(U64::from(37)), json!(false)],
+        );
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({
+                "address": address,
+                "fromBlock": U64::from(37),
+                "toBlock": U64::from(37),
+                "topics": [],
+            })],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn all_events_stream_add_addresses() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let (_, log) = test_abi_event();
+
+        // filter created
+        transport.add_response(json!("0xf0"));
+        // get logs filter
+        transport.add_response(json!([log]));
+        // filter re-created with new addresses
+        transport.add_response(json!("0xf1"));
+        // get logs of the replaced filter
+        transport.add_response(json!([]));
+        // replaced filter uninstalled
+        transport.add_response(json!(true));
+        // get logs filter
+        transport.add_response(json!([log]));
+
+        let addresses = vec![Address::repeat_byte(0x01), Address::repeat_byte(0x02)];
+        let mut stream =
+            AllEventsBuilder::<_, RawLog>::with_addresses(web3, addresses.clone()).stream();
+        let handle = stream.addresses();
+
+        let first = stream.next().immediate();
+        assert!(first.unwrap().unwrap().is_added());
+        transport.assert_request(
+            "eth_newFilter",
+            &[json!({ "address": addresses, "topics": [] })],
+        );
+        transport.assert_request("eth_getFilterChanges", &[json!("0xf0")]);
+        transport.assert_no_more_requests();
+
+        assert!(!handle.add(addresses[1]));
+        assert!(handle.add(Address::repeat_byte(0x03)));
+        assert_eq!(
+            handle.to_vec(),
+            vec![addresses[0], addresses[1], Address::repeat_byte(0x03)],
+        );
+
+        let second = stream.next().immediate();
+        assert!(second.unwrap().unwrap().is_added());
+        transport.assert_request(
+            "eth_newFilter",
+            &[json!({ "address": handle.to_vec(), "topics": [] })],
+        );
+        transport.assert_request("eth_getFilterChanges", &[json!("0xf0")]);
+        transport.assert_request("eth_uninstallFilter", &[json!("0xf0")]);
+        transport.assert_request("eth_getFilterChanges", &[json!("0xf1")]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn all_events_stream_add_addresses_with_buffered_logs() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let (_, log) = test_abi_event();
+        let log_at = |index: u64| {
+            let mut log = log.clone();
+            log["logIndex"] = json!(U256::from(index));
+            log
+        };
+        let log_index = |event: Option<Result<Event<RawLog>, ExecutionError>>| {
+            event.unwrap().unwrap().meta.unwrap().log_index
+        };
+
+        // filter created
+        transport.add_response(json!("0xf0"));
+        // get logs filter
+        transport.add_response(json!([log_at(0), log_at(1)]));
+
+        let address = Address::repeat_byte(0x01);
+        let mut stream = AllEventsBuilder::<_, RawLog>::new(web3, address, None).stream();
+        let handle = stream.addresses();
+
+        assert_eq!(log_index(stream.next().immediate()), 0);
+        transport.assert_request(
+            "eth_newFilter",
+            &[json!({ "address": address, "topics": [] })],
+        );
+        transport.assert_request("eth_getFilterChanges", &[json!("0xf0")]);
+        transport.assert_no_more_requests();
+
+        // NOTE: The second log is still buffered when the address is added, it
+        //   is emitted before the filter is replaced.
+        assert!(handle.add(Address::repeat_byte(0x02)));
+        assert_eq!(log_index(stream.next().immediate()), 1);
+        transport.assert_no_more_requests();
+
+        // filter re-created with new addresses
+        transport.add_response(json!("0xf1"));
+        // get logs of the replaced filter
+        transport.add_response(json!([log_at(2)]));
+        // replaced filter not found when uninstalling it, which is ignored
+        transport.add_response(json!(false));
+        // get logs filter, including a log from the replaced filter
+        transport.add_response(json!([log_at(2), log_at(3)]));
+
+        assert_eq!(log_index(stream.next().immediate()), 2);
+        assert_eq!(log_index(stream.next().immediate()), 3);
+        transport.assert_request(
+            "eth_newFilter",
+            &[json!({ "address": handle.to_vec(), "topics": [] })],
+        );
+        transport.assert_request("eth_getFilterChanges", &[json!("0xf0")]);
+        transport.assert_request("eth_uninstallFilter", &[json!("0xf0")]);
+        transport.assert_request("eth_getFilterChanges", &[json!("0xf1")]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn event_from_receipt() {
+        let (_, log) = test_abi_event();
+        let mut other = log.clone();
+        other["address"] = json!(Address::repeat_byte(0x01));
+        other["logIndex"] = json!("0x1");
+
+        let receipt = serde_json::from_value::<TransactionReceipt>(json!({
+            "transactionHash": H256::zero(),
+            "transactionIndex": "0x0",
+            "blockHash": H256::zero(),
+            "blockNumber": "0x0",
+            "cumulativeGasUsed": "0x1337",
+            "gasUsed": "0x1337",
+            "logsBloom": H2048::zero(),
+            "logs": [log, other],
+            "status": "0x1",
+        }))
+        .unwrap();
+
+        let events = Event::<RawLog>::from_receipt(&receipt, Address::zero()).unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(events[0].is_added());
+        assert_eq!(events[0].meta.as_ref().unwrap().log_index, 0);
+        assert_eq!(events[0].inner_data().topics.len(), 3);
+
+        let events = Event::<RawLog>::from_receipt(&receipt, Address::repeat_byte(0x02)).unwrap();
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn raw_log_decode_tuple() {
+        let event = AbiEvent {
+            name: "test".to_owned(),
+            inputs: vec![
+                EventParam {
+                    name: "value".to_owned(),
+                    kind: ParamType::Tuple(vec![
+                        Box::new(ParamType::Uint(256)),
+                        Box::new(ParamType::Bool),
+                    ]),
+                    indexed: false,
+                },
+                EventParam {
+                    name: "from".to_owned(),
+                    kind: ParamType::Address,
+                    indexed: true,
+                },
+            ],
+            anonymous: false,
+        };
+        let log = RawLog {
+            topics: vec![
+                event.signature().compat(),
+                Address::repeat_byte(0xf0).into(),
+            ],
+            data: {
+                let mut buf = vec![0u8; 64];
+                buf[31] = 42;
+                buf[63] = 1;
+                buf
+            },
+        };
+
+        let data = log
+            .decode::<((U256, bool), Address)>(&event)
+            .expect("failed to decode log");
+        assert_eq!(data, ((U256::from(42), true), Address::repeat_byte(0xf0)));
+    }
+
+    #[test]
+    fn raw_log_decode_single_parameter() {
+        let event = AbiEvent {
+            name: "test".to_owned(),
+            inputs: vec![EventParam {
+                name: "value".to_owned(),
+                kind: ParamType::Uint(256),
+                indexed: false,
+            }],
+            anonymous: false,
+        };
+        let log = RawLog {
+            topics: vec![event.signature().compat()],
+            data: {
+                let mut buf = vec![0u8; 32];
+                buf[31] = 42;
+                buf
+            },
+        };
+
+        let value = log
+            .clone()
+            .decode::<U256>(&event)
+            .expect("failed to decode log into value");
+        assert_eq!(value, U256::from(42));
+
+        let tuple = log
+            .clone()
+            .decode::<(U256,)>(&event)
+            .expect("failed to decode log into tuple");
+        assert_eq!(tuple, (U256::from(42),));
+
+        // NOTE: Mismatched types report the error for the shape of the data
+        //   type instead of falling back to another shape.
+        assert!(matches!(
+            log.clone().decode::<bool>(&event),
+            Err(ExecutionError::AbiDecode(Web3ContractError::InvalidOutputType(message)))
+                if message.starts_with("Expected `bool`")
+        ));
+        assert!(matches!(
+            log.decode::<(bool,)>(&event),
+            Err(ExecutionError::AbiDecode(Web3ContractError::InvalidOutputType(message)))
+                if message.starts_with("Expected `bool`")
+        ));
+    }
 }
@@ -0,0 +1,361 @@
+//! Module implements enriching event metadata with data that is not included
+//! in logs, such as block timestamps and transaction senders.
+
+use super::Event;
+use crate::errors::ExecutionError;
+use crate::future::CompatCallFuture;
+use futures::compat::Future01CompatExt;
+use futures::future::{self, TryJoin};
+use futures::ready;
+use futures::stream::{self, Buffered, Iter, Map, StreamExt, TryCollect, TryStreamExt};
+use pin_project::pin_project;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::vec;
+use web3::api::Web3;
+use web3::helpers::{self, CallFuture};
+use web3::types::{Address, H256, U256};
+use web3::Transport;
+
+/// The maximum number of block timestamps and transaction senders each that
+/// are cached by event streams.
+const STREAM_CACHE_CAPACITY: usize = 1024;
+
+/// A cache of block timestamps and transaction senders that is shared between
+/// the pages of an event query or the events of a stream, so that each block
+/// and transaction is only retrieved once.
+#[derive(Clone, Debug)]
+pub(crate) struct MetadataCache {
+    /// Whether or not to enrich event metadata with block timestamps.
+    block_timestamps: bool,
+    /// Whether or not to enrich event metadata with transaction senders.
+    transaction_senders: bool,
+    /// The maximum number of blocks and transactions to retrieve
+    /// concurrently.
+    concurrency: usize,
+    /// The maximum number of block timestamps and transaction senders each to
+    /// keep, or `None` if the cache is unbounded.
+    capacity: Option<usize>,
+    /// The cached values.
+    values: Arc<Mutex<CachedValues>>,
+}
+
+/// The values stored in a metadata cache.
+#[derive(Debug, Default)]
+struct CachedValues {
+    /// Block timestamps by block hash.
+    timestamps: HashMap<H256, U256>,
+    /// Transaction senders by transaction hash.
+    senders: HashMap<H256, Address>,
+}
+
+impl MetadataCache {
+    /// Creates a new metadata cache for the specified enrichment options, or
+    /// `None` if event metadata should not be enriched. At most `concurrency`
+    /// blocks and transactions are retrieved at the same time.
+    pub fn new(
+        block_timestamps: bool,
+        transaction_senders: bool,
+        concurrency: usize,
+    ) -> Option<Self> {
+        if !block_timestamps && !transaction_senders {
+            return None;
+        }
+
+        Some(MetadataCache {
+            block_timestamps,
+            transaction_senders,
+            concurrency: concurrency.max(1),
+            capacity: None,
+            values: Default::default(),
+        })
+    }
+
+    /// Creates a new metadata cache for an event stream. Unlike the cache of a
+    /// query, the cache of a stream is bounded since streams can run
+    /// indefinitely, and it is cleared once it is full.
+    pub fn for_stream(
+        block_timestamps: bool,
+        transaction_senders: bool,
+        concurrency: usize,
+    ) -> Option<Self> {
+        let mut cache = MetadataCache::new(block_timestamps, transaction_senders, concurrency)?;
+        cache.capacity = Some(STREAM_CACHE_CAPACITY);
+        Some(cache)
+    }
+}
+
+impl CachedValues {
+    /// Makes room for the specified number of block timestamps and
+    /// transaction senders by clearing them once the capacity would be
+    /// exceeded.
+    fn reserve(&mut self, capacity: Option<usize>, blocks: usize, transactions: usize) {
+        let capacity = match capacity {
+            Some(capacity) => capacity,
+            None => return,
+        };
+        if self.timestamps.len() + blocks > capacity {
+            self.timestamps.clear();
+        }
+        if self.senders.len() + transactions > capacity {
+            self.senders.clear();
+        }
+    }
+}
+
+/// The subset of block fields needed for enriching event metadata.
+#[derive(Debug, Deserialize)]
+struct BlockTimestamp {
+    timestamp: U256,
+}
+
+/// The subset of transaction fields needed for enriching event metadata.
+#[derive(Debug, Deserialize)]
+struct TransactionSender {
+    from: Address,
+}
+
+/// The future for retrieving a collection of blocks or transactions by hash
+/// with bounded concurrency, resolving to them in order.
+type FetchAllFuture<T, R> = TryCollect<
+    Buffered<Map<Iter<vec::IntoIter<(T, H256)>>, fn((T, H256)) -> CompatCallFuture<T, R>>>,
+    Vec<R>,
+>;
+
+/// The future for retrieving the blocks and transactions missing from the
+/// metadata cache.
+type FetchFuture<T> = TryJoin<
+    FetchAllFuture<T, Option<BlockTimestamp>>,
+    FetchAllFuture<T, Option<TransactionSender>>,
+>;
+
+/// A future that enriches the metadata of a collection of events, resolving
+/// to the enriched events.
+///
+/// Blocks and transactions are not retrieved for removed events, since their
+/// blocks may no longer be available from the node, so their metadata is only
+/// enriched with values that are already cached.
+///
+/// If retrieving the blocks or transactions fails, the error is returned and
+/// they are retrieved again if the future is polled once more.
+#[must_use = "futures do nothing unless you await or poll them"]
+#[pin_project]
+pub(crate) struct MetadataFuture<T: Transport, E> {
+    web3: Web3<T>,
+    cache: MetadataCache,
+    events: Vec<Event<E>>,
+    /// The hashes of the blocks being retrieved.
+    blocks: Vec<H256>,
+    /// The hashes of the transactions being retrieved.
+    transactions: Vec<H256>,
+    #[pin]
+    inner: FetchFuture<T>,
+}
+
+impl<T: Transport, E> MetadataFuture<T, E> {
+    /// Creates a new future that enriches the metadata of the specified events
+    /// with values from the cache, retrieving the ones that are missing.
+    pub fn new(web3: Web3<T>, cache: MetadataCache, events: Vec<Event<E>>) -> Self {
+        let mut blocks = Vec::new();
+        let mut transactions = Vec::new();
+        {
+            let values = cache.values.lock().expect("poisoned mutex");
+            let mut seen_blocks = HashSet::new();
+            let mut seen_transactions = HashSet::new();
+            for meta in events
+                .iter()
+                .filter(|event| event.is_added())
+                .filter_map(|event| event.meta.as_ref())
+            {
+                if cache.block_timestamps
+                    && !values.timestamps.contains_key(&meta.block_hash)
+                    && seen_blocks.insert(meta.block_hash)
+                {
+                    blocks.push(meta.block_hash);
+                }
+                if cache.transaction_senders
+                    && !values.senders.contains_key(&meta.transaction_hash)
+                    && seen_transactions.insert(meta.transaction_hash)
+                {
+                    transactions.push(meta.transaction_hash);
+                }
+            }
+        }
+
+        let inner = fetch(&web3, cache.concurrency, &blocks, &transactions);
+        MetadataFuture {
+            web3,
+            cache,
+            events,
+            blocks,
+            transactions,
+            inner,
+        }
+    }
+}
+
+impl<T: Transport, E> Future for MetadataFuture<T, E> {
+    type Output = Result<Vec<Event<E>>, ExecutionError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let mut this = self.project();
+        let fetched = ready!(this.inner.as_mut().poll(cx));
+
+        let mut values = this.cache.values.lock().expect("poisoned mutex");
+        let cached = fetched
+            .map_err(ExecutionError::from)
+            .and_then(|(blocks, transactions)| {
+                values.reserve(this.cache.capacity, blocks.len(), transactions.len());
+                for (hash, block) in this.blocks.iter().zip(blocks) {
+                    let block = block.ok_or(ExecutionError::MissingBlock(*hash))?;
+                    values.timestamps.insert(*hash, block.timestamp);
+                }
+                for (hash, transaction) in this.transactions.iter().zip(transactions) {
+                    let transaction =
+                        transaction.ok_or(ExecutionError::MissingTransaction(*hash))?;
+                    values.senders.insert(*hash, transaction.from);
+                }
+                Ok(())
+            });
+        if let Err(err) = cached {
+            this.inner.set(fetch(
+                this.web3,
+                this.cache.concurrency,
+                this.blocks,
+                this.transactions,
+            ));
+            return Poll::Ready(Err(err));
+        }
+
+        let mut events = mem::take(this.events);
+        for meta in events.iter_mut().filter_map(|event| event.meta.as_mut()) {
+            if this.cache.block_timestamps {
+                meta.block_timestamp = values.timestamps.get(&meta.block_hash).copied();
+            }
+            if this.cache.transaction_senders {
+                meta.transaction_sender = values.senders.get(&meta.transaction_hash).copied();
+            }
+        }
+
+        Poll::Ready(Ok(events))
+    }
+}
+
+/// Enriches the metadata of the events emitted by an event stream one at a
+/// time, delaying each event until its metadata was retrieved.
+///
+/// If retrieving the metadata fails, the error is returned and the event is
+/// enriched again if the stream is polled once more.
+#[must_use = "streams do nothing unless you or poll them"]
+#[pin_project]
+pub(crate) struct MetadataStream<T: Transport, E> {
+    web3: Web3<T>,
+    cache: Option<MetadataCache>,
+    #[pin]
+    enrich: Option<MetadataFuture<T, E>>,
+}
+
+impl<T: Transport, E> MetadataStream<T, E> {
+    /// Creates a new metadata stream that passes events through unchanged if
+    /// no cache is specified.
+    pub fn new(web3: Web3<T>, cache: Option<MetadataCache>) -> Self {
+        MetadataStream {
+            web3,
+            cache,
+            enrich: None,
+        }
+    }
+
+    /// Polls for the next enriched event, polling the underlying stream with
+    /// `poll_event` for the next event if no event is being enriched.
+    pub fn poll_next<F>(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        poll_event: F,
+    ) -> Poll<Option<Result<Event<E>, ExecutionError>>>
+    where
+        F: FnOnce(&mut Context) -> Poll<Option<Result<Event<E>, ExecutionError>>>,
+    {
+        let mut this = self.project();
+        if this.enrich.is_none() {
+            let event = match ready!(poll_event(cx)) {
+                Some(Ok(event)) => event,
+                next => return Poll::Ready(next),
+            };
+            let cache = match this.cache {
+                Some(cache) => cache.clone(),
+                None => return Poll::Ready(Some(Ok(event))),
+            };
+            this.enrich.set(Some(MetadataFuture::new(
+                this.web3.clone(),
+                cache,
+                vec![event],
+            )));
+        }
+
+        let enrich = this.enrich.as_mut().as_pin_mut().expect("missing event");
+        let result = ready!(enrich.poll(cx));
+        if result.is_ok() {
+            this.enrich.set(None);
+        }
+
+        Poll::Ready(Some(
+            result.map(|mut events| events.pop().expect("missing enriched event")),
+        ))
+    }
+}
+
+/// Retrieves the specified blocks and transactions, with at most
+/// `concurrency` requests of each kind in flight at the same time.
+fn fetch<T: Transport>(
+    web3: &Web3<T>,
+    concurrency: usize,
+    blocks: &[H256],
+    transactions: &[H256],
+) -> FetchFuture<T> {
+    future::try_join(
+        fetch_all(web3, concurrency, blocks, fetch_block),
+        fetch_all(web3, concurrency, transactions, fetch_transaction),
+    )
+}
+
+/// Retrieves a collection of blocks or transactions by hash with bounded
+/// concurrency. Requests are only sent once there is room for them.
+fn fetch_all<T: Transport, R: DeserializeOwned>(
+    web3: &Web3<T>,
+    concurrency: usize,
+    hashes: &[H256],
+    fetch_one: fn((T, H256)) -> CompatCallFuture<T, R>,
+) -> FetchAllFuture<T, R> {
+    let requests = hashes
+        .iter()
+        .map(|hash| (web3.transport().clone(), *hash))
+        .collect::<Vec<_>>();
+    stream::iter(requests)
+        .map(fetch_one)
+        .buffered(concurrency)
+        .try_collect()
+}
+
+/// Retrieves the block with the specified hash.
+fn fetch_block<T: Transport>(
+    (transport, hash): (T, H256),
+) -> CompatCallFuture<T, Option<BlockTimestamp>> {
+    let hash = helpers::serialize(&hash);
+    let include_txs = helpers::serialize(&false);
+    CallFuture::new(transport.execute("eth_getBlockByHash", vec![hash, include_txs])).compat()
+}
+
+/// Retrieves the transaction with the specified hash.
+fn fetch_transaction<T: Transport>(
+    (transport, hash): (T, H256),
+) -> CompatCallFuture<T, Option<TransactionSender>> {
+    let hash = helpers::serialize(&hash);
+    CallFuture::new(transport.execute("eth_getTransactionByHash", vec![hash])).compat()
+}
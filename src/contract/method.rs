@@ -2,27 +2,33 @@
 //! intended to be used directly but to be used by a contract `Instance` with
 //! [Instance::method](ethcontract::contract::Instance::method).
 
-use crate::abicompat::AbiCompat;
-use crate::errors::{revert, ExecutionError, MethodError};
+use crate::contract::{Event, ParseLog, StateOverride, StateOverrides};
+use crate::errors::{revert, ExecutionError, MethodError, Web3ContractError};
 use crate::future::CompatCallFuture;
+use crate::tokens::Tokenizable;
+use crate::trace::{TraceFuture, Tracer};
+use crate::transaction::access_list::CreateAccessListFuture;
 use crate::transaction::send::SendFuture;
-use crate::transaction::{Account, GasPrice, TransactionBuilder};
+use crate::transaction::{
+    AccessList, Account, GasPrice, NonceManager, TransactionBuilder, TransactionResult,
+};
 use ethcontract_common::abi::{Function, Token};
 use futures::compat::Future01CompatExt;
+use futures::ready;
 use pin_project::pin_project;
 use std::future::Future;
 use std::marker::PhantomData;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use web3::api::Web3;
-use web3::contract::tokens::Detokenize;
-use web3::contract::Error as Web3ContractError;
-use web3::types::{Address, BlockNumber, Bytes, CallRequest, U256};
+use web3::helpers;
+use web3::types::{Address, BlockNumber, Bytes, CallRequest, TransactionReceipt, U256};
 use web3::Transport;
 
 /// A void type to represent methods with empty return types.
 ///
-/// This is used to work around the fact that `(): !Detokenize`.
+/// This is used to differentiate methods with empty return types from methods
+/// returning an empty tuple.
 pub struct Void(());
 
 /// Represents a type can detokenize a result.
@@ -59,15 +65,19 @@ impl Detokenizable for Void {
     }
 }
 
-impl<T: Detokenize> Detokenizable for T {
+impl<T: Tokenizable> Detokenizable for T {
     type Output = Self;
 
     fn from_tokens(tokens: Vec<Token>) -> Result<Self::Output, ExecutionError> {
-        let tokens = match tokens.compat() {
-            Some(tokens) => tokens,
-            None => return Err(ExecutionError::UnsupportedToken),
+        // NOTE: Methods with a single return value detokenize directly into
+        //   that value while methods with multiple return values detokenize
+        //   into a tuple.
+        let token = if tokens.len() == 1 {
+            tokens.into_iter().next().unwrap()
+        } else {
+            Token::Tuple(tokens)
         };
-        let result = <T as Detokenize>::from_tokens(tokens)?;
+        let result = T::from_token(token)?;
         Ok(result)
     }
 }
@@ -81,6 +91,15 @@ pub struct MethodDefaults {
     pub gas: Option<U256>,
     /// Default gas price to use for transaction.
     pub gas_price: Option<GasPrice>,
+    /// Default maximum total fee per gas to use for EIP-1559 transactions.
+    pub max_fee_per_gas: Option<U256>,
+    /// Default maximum priority fee per gas to use for EIP-1559 transactions.
+    pub max_priority_fee_per_gas: Option<U256>,
+    /// Default nonce manager to use for reserving transaction nonces.
+    pub nonce_manager: Option<NonceManager>,
+    /// Whether to simulate transactions against the pending block before
+    /// sending them by default.
+    pub simulate: Option<bool>,
 }
 
 /// Data used for building a contract method call or transaction. The method
@@ -90,7 +109,7 @@ pub struct MethodDefaults {
 #[must_use = "methods do nothing unless you `.call()` or `.send()` them"]
 pub struct MethodBuilder<T: Transport, R: Detokenizable> {
     web3: Web3<T>,
-    function: Function,
+    pub(crate) function: Function,
     /// transaction parameters
     pub tx: TransactionBuilder<T>,
     _result: PhantomData<R>,
@@ -127,6 +146,16 @@ impl<T: Transport, R: Detokenizable> MethodBuilder<T, R> {
         self.tx.from = self.tx.from.or_else(|| defaults.from.clone());
         self.tx.gas = self.tx.gas.or(defaults.gas);
         self.tx.gas_price = self.tx.gas_price.or(defaults.gas_price);
+        self.tx.max_fee_per_gas = self.tx.max_fee_per_gas.or(defaults.max_fee_per_gas);
+        self.tx.max_priority_fee_per_gas = self
+            .tx
+            .max_priority_fee_per_gas
+            .or(defaults.max_priority_fee_per_gas);
+        self.tx.nonce_manager = self
+            .tx
+            .nonce_manager
+            .or_else(|| defaults.nonce_manager.clone());
+        self.tx.simulate_before_send = self.tx.simulate_before_send.or(defaults.simulate);
         self
     }
 
@@ -151,6 +180,20 @@ impl<T: Transport, R: Detokenizable> MethodBuilder<T, R> {
         self
     }
 
+    /// Specify the maximum total fee per gas for an EIP-1559 transaction. This
+    /// takes precedence over the gas price.
+    pub fn max_fee_per_gas(mut self, value: U256) -> Self {
+        self.tx = self.tx.max_fee_per_gas(value);
+        self
+    }
+
+    /// Specify the maximum priority fee per gas for an EIP-1559 transaction.
+    /// This takes precedence over the gas price.
+    pub fn max_priority_fee_per_gas(mut self, value: U256) -> Self {
+        self.tx = self.tx.max_priority_fee_per_gas(value);
+        self
+    }
+
     /// Specify what how much ETH to transfer with the transaction, if not
     /// specified then no ETH will be sent.
     pub fn value(mut self, value: U256) -> Self {
@@ -165,6 +208,43 @@ impl<T: Transport, R: Detokenizable> MethodBuilder<T, R> {
         self
     }
 
+    /// Specify the nonce manager to reserve the nonce with when sending the
+    /// transaction, if not specified then the current transaction count for
+    /// the signing account will be used.
+    pub fn nonce_manager(mut self, value: NonceManager) -> Self {
+        self.tx = self.tx.nonce_manager(value);
+        self
+    }
+
+    /// Specify the access list for the transaction, if not specified then no
+    /// access list will be used.
+    pub fn access_list(mut self, value: AccessList) -> Self {
+        self.tx = self.tx.access_list(value);
+        self
+    }
+
+    /// Specify whether to simulate the transaction with an `eth_call` against
+    /// the pending block before signing and sending it. If the simulation
+    /// reverts then the transaction is not sent and the send fails with the
+    /// decoded revert error. Note that the simulation is done from the address
+    /// of the account the transaction is sent from, regardless of its signing
+    /// method.
+    pub fn simulate(mut self, value: bool) -> Self {
+        self.tx = self.tx.simulate_before_send(value);
+        self
+    }
+
+    /// Create an access list for the method transaction with the node (using
+    /// `eth_createAccessList`) and attach it to the transaction. Note that the
+    /// access list is created for the current transaction parameters, so this
+    /// should be called after they have been specified.
+    pub fn attach_access_list(self) -> MethodAccessListFuture<T, R> {
+        MethodAccessListFuture {
+            create: self.tx.clone().create_access_list(),
+            builder: Some(self),
+        }
+    }
+
     /// Specify the number of confirmations to wait for when confirming the
     /// transaction, if not specified will wait for the transaction to be mined
     /// without any extra confirmations.
@@ -184,6 +264,30 @@ impl<T: Transport, R: Detokenizable> MethodBuilder<T, R> {
         MethodFuture::new(self.function, self.tx.send())
     }
 
+    /// Sign (if required) and send the method call transaction, resolving to
+    /// the transaction receipt along with the events emitted by the contract
+    /// being called, decoded into the specified event type. This is usually
+    /// the generated contract's `Event` enum.
+    ///
+    /// Note that this requires the transaction to be mined, so it fails with
+    /// `ExecutionError::PendingTransaction` if the transaction is configured to
+    /// resolve once it is pending.
+    pub fn send_with_events<E: ParseLog>(self) -> MethodEventsFuture<T, E> {
+        MethodEventsFuture {
+            function: self.function,
+            address: self.tx.to.unwrap_or_default(),
+            send: self.tx.send(),
+            _events: PhantomData,
+        }
+    }
+
+    /// Trace the method call transaction against the latest block without
+    /// sending it, resolving to its call tree. This can be used to find out
+    /// where a transaction that interacts with multiple contracts fails.
+    pub fn trace(self, tracer: Tracer) -> MethodTraceFuture<T> {
+        MethodFuture::new(self.function, self.tx.trace(tracer))
+    }
+
     /// Demotes a `MethodBuilder` into a `ViewMethodBuilder` which has a more
     /// restricted API and cannot actually send transactions.
     pub fn view(self) -> ViewMethodBuilder<T, R> {
@@ -235,6 +339,71 @@ where
 /// A type alias for a `MethodFuture` wrapped `SendFuture`.
 pub type MethodSendFuture<T> = MethodFuture<SendFuture<T>>;
 
+/// Future for sending a method call transaction and decoding the events emitted
+/// by the called contract from its receipt.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[pin_project]
+pub struct MethodEventsFuture<T: Transport, E: ParseLog> {
+    function: Function,
+    address: Address,
+    #[pin]
+    send: SendFuture<T>,
+    _events: PhantomData<Box<E>>,
+}
+
+impl<T: Transport, E: ParseLog> Future for MethodEventsFuture<T, E> {
+    type Output = Result<(TransactionReceipt, Vec<Event<E>>), MethodError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+        let function = this.function;
+        let address = *this.address;
+        this.send
+            .poll(cx)
+            .map(|result| {
+                let receipt = match result {
+                    Ok(TransactionResult::Receipt(receipt)) => receipt,
+                    Ok(TransactionResult::Hash(hash)) => {
+                        return Err(ExecutionError::PendingTransaction(hash))
+                    }
+                    Err(err) => return Err(err),
+                };
+                let events = Event::from_receipt(&receipt, address)?;
+
+                Ok((receipt, events))
+            })
+            .map(|result| result.map_err(|err| MethodError::new(function, err)))
+    }
+}
+
+/// A type alias for a `MethodFuture` wrapped `TraceFuture`.
+pub type MethodTraceFuture<T> = MethodFuture<TraceFuture<T>>;
+
+/// Future for creating an access list for a method call transaction, resolving
+/// to the method builder with the access list attached.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[pin_project]
+pub struct MethodAccessListFuture<T: Transport, R: Detokenizable> {
+    builder: Option<MethodBuilder<T, R>>,
+    #[pin]
+    create: CreateAccessListFuture<T>,
+}
+
+impl<T: Transport, R: Detokenizable> Future for MethodAccessListFuture<T, R> {
+    type Output = Result<MethodBuilder<T, R>, MethodError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+        let access_list = ready!(this.create.poll(cx));
+        let builder = this.builder.take().expect("future polled more than once");
+
+        Poll::Ready(match access_list {
+            Ok(access_list) => Ok(builder.access_list(access_list)),
+            Err(err) => Err(MethodError::new(&builder.function, err)),
+        })
+    }
+}
+
 /// Data used for building a contract method call. The view method builder can't
 /// directly send transactions and is for read only method calls.
 #[derive(Debug, Clone)]
@@ -244,6 +413,8 @@ pub struct ViewMethodBuilder<T: Transport, R: Detokenizable> {
     pub m: MethodBuilder<T, R>,
     /// optional block number
     pub block: Option<BlockNumber>,
+    /// optional state overrides
+    pub state_overrides: Option<StateOverrides>,
 }
 
 impl<T: Transport, R: Detokenizable> ViewMethodBuilder<T, R> {
@@ -252,6 +423,7 @@ impl<T: Transport, R: Detokenizable> ViewMethodBuilder<T, R> {
         ViewMethodBuilder {
             m: method,
             block: None,
+            state_overrides: None,
         }
     }
 
@@ -294,6 +466,23 @@ impl<T: Transport, R: Detokenizable> ViewMethodBuilder<T, R> {
         self.block = Some(value);
         self
     }
+
+    /// Specify the state overrides to apply when executing the call, replacing
+    /// any previously specified overrides. Note that state overrides are not
+    /// supported by all nodes.
+    pub fn state_overrides(mut self, value: StateOverrides) -> Self {
+        self.state_overrides = Some(value);
+        self
+    }
+
+    /// Specify the state override for a single account to apply when
+    /// executing the call.
+    pub fn state_override(mut self, address: Address, value: StateOverride) -> Self {
+        self.state_overrides
+            .get_or_insert_with(StateOverrides::new)
+            .insert(address, value);
+        self
+    }
 }
 
 impl<T: Transport, R: Detokenizable> ViewMethodBuilder<T, R> {
@@ -318,27 +507,28 @@ pub struct CallFuture<T: Transport, R: Detokenizable> {
 impl<T: Transport, R: Detokenizable> CallFuture<T, R> {
     /// Construct a new `CallFuture` from a `ViewMethodBuilder`.
     fn from_builder(builder: ViewMethodBuilder<T, R>) -> Self {
+        let request = CallRequest {
+            from: builder.m.tx.from.map(|account| account.address()),
+            to: builder.m.tx.to.unwrap_or_default(),
+            gas: builder.m.tx.gas,
+            gas_price: builder
+                .m
+                .tx
+                .gas_price
+                .and_then(|gas_price| gas_price.value()),
+            value: builder.m.tx.value,
+            data: builder.m.tx.data,
+        };
+        let block = builder.block.unwrap_or(BlockNumber::Latest);
+
+        let mut params = vec![helpers::serialize(&request), helpers::serialize(&block)];
+        if let Some(state_overrides) = &builder.state_overrides {
+            params.push(helpers::serialize(state_overrides));
+        }
+
         CallFuture {
             function: builder.m.function,
-            call: builder
-                .m
-                .web3
-                .eth()
-                .call(
-                    CallRequest {
-                        from: builder.m.tx.from.map(|account| account.address()),
-                        to: builder.m.tx.to.unwrap_or_default(),
-                        gas: builder.m.tx.gas,
-                        gas_price: builder
-                            .m
-                            .tx
-                            .gas_price
-                            .and_then(|gas_price| gas_price.value()),
-                        value: builder.m.tx.value,
-                        data: builder.m.tx.data,
-                    },
-                    builder.block,
-                )
+            call: helpers::CallFuture::new(builder.m.web3.transport().execute("eth_call", params))
                 .compat(),
             _result: PhantomData,
         }
@@ -366,21 +556,23 @@ impl<T: Transport, R: Detokenizable> Future for CallFuture<T, R> {
 /// reverts (or if an invalid opcode is executed) while other nodes like Ganache
 /// encode this information in a JSON RPC error. On a revert or invalid opcode,
 /// the result is `0x` (empty data), while on a revert with message, it is an
-/// ABI encoded `Error(string)` function call data.
-fn decode_geth_call_result<R: Detokenizable>(
+/// ABI encoded `Error(string)` function call data and on a panic it is an ABI
+/// encoded `Panic(uint256)` function call data. Any other revert data, such as
+/// a custom error, is returned as is.
+pub(crate) fn decode_geth_call_result<R: Detokenizable>(
     function: &Function,
     bytes: Vec<u8>,
 ) -> Result<R::Output, ExecutionError> {
-    if let Some(reason) = revert::decode_reason(&bytes) {
-        // This is an encoded revert message from Geth nodes.
-        Err(ExecutionError::Revert(Some(reason)))
+    if let Some(err) = revert::decode_call_result(&bytes, false) {
+        // This is encoded revert data from Geth nodes.
+        Err(err)
     } else if !R::is_void() && bytes.is_empty() {
         // Geth does this on `revert()` without a message and `invalid()`,
         // just treat them all as `invalid()` as generally contracts revert
         // with messages. Unfortunately, for methods with empty return types
         // errors cannot be distringuished from success in this case so do not
         // error in those cases.
-        Err(ExecutionError::InvalidOpcode)
+        Err(ExecutionError::InvalidOpcode(None))
     } else {
         // just a plain ol' regular result, try and decode it
         let tokens = function.decode_output(&bytes)?;
@@ -392,8 +584,13 @@ fn decode_geth_call_result<R: Detokenizable>(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::contract::RawLog;
     use crate::test::prelude::*;
+    use crate::transaction::{AccessListItem, ResolveCondition};
     use ethcontract_common::abi::{Param, ParamType};
+    use jsonrpc_core::{Error as RpcError, ErrorCode};
+    use web3::error::Error as Web3Error;
+    use web3::types::{H2048, H256};
 
     fn test_abi_function() -> (Function, Bytes) {
         let function = Function {
@@ -481,6 +678,153 @@ mod tests {
         transport.assert_no_more_requests();
     }
 
+    #[test]
+    fn view_method_call_with_state_overrides() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let (function, data) = test_abi_function();
+        let tx = MethodBuilder::<_, U256>::new(web3, function, address, data.clone())
+            .view()
+            .from(from)
+            .state_override(from, StateOverride::default().balance(1000.into()))
+            .state_override(
+                address,
+                StateOverride::default()
+                    .code(Bytes(vec![0x13, 0x37]))
+                    .storage(H256::from_low_u64_be(1), H256::from_low_u64_be(42)),
+            );
+
+        transport.add_response(json!(
+            "0x000000000000000000000000000000000000000000000000000000000000002a"
+        )); // call response
+        let result = tx.call().immediate().expect("call error");
+
+        assert_eq!(result, 42.into());
+        transport.assert_request(
+            "eth_call",
+            &[
+                json!({
+                    "from": from,
+                    "to": address,
+                    "data": data,
+                }),
+                json!("latest"),
+                json!({
+                    format!("{:?}", from): {
+                        "balance": "0x3e8",
+                    },
+                    format!("{:?}", address): {
+                        "code": "0x1337",
+                        "stateDiff": {
+                            format!("{:?}", H256::from_low_u64_be(1)): H256::from_low_u64_be(42),
+                        },
+                    },
+                }),
+            ],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn view_method_call_with_state_overrides_at_block() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let (function, data) = test_abi_function();
+
+        let mut storage = std::collections::HashMap::new();
+        storage.insert(H256::from_low_u64_be(1), H256::from_low_u64_be(42));
+        let mut state_overrides = StateOverrides::new();
+        state_overrides.insert(
+            from,
+            StateOverride::default()
+                .balance(1000.into())
+                .nonce(7.into()),
+        );
+        state_overrides.insert(
+            address,
+            StateOverride::default()
+                .code(Bytes(vec![0x13, 0x37]))
+                .state(storage),
+        );
+
+        // NOTE: Specifying all state overrides replaces the previously
+        //   specified overrides.
+        let tx = MethodBuilder::<_, U256>::new(web3, function, address, data.clone())
+            .view()
+            .from(from)
+            .block(BlockNumber::Number(42.into()))
+            .state_override(address, StateOverride::default().balance(1.into()))
+            .state_overrides(state_overrides);
+
+        transport.add_response(json!(
+            "0x000000000000000000000000000000000000000000000000000000000000002a"
+        )); // call response
+        let result = tx.call().immediate().expect("call error");
+
+        assert_eq!(result, 42.into());
+        transport.assert_request(
+            "eth_call",
+            &[
+                json!({
+                    "from": from,
+                    "to": address,
+                    "data": data,
+                }),
+                json!("0x2a"),
+                json!({
+                    format!("{:?}", from): {
+                        "balance": "0x3e8",
+                        "nonce": "0x7",
+                    },
+                    format!("{:?}", address): {
+                        "code": "0x1337",
+                        "state": {
+                            format!("{:?}", H256::from_low_u64_be(1)): H256::from_low_u64_be(42),
+                        },
+                    },
+                }),
+            ],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn view_method_call_without_state_overrides() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let (function, data) = test_abi_function();
+        let tx = MethodBuilder::<_, U256>::new(web3, function, address, data.clone())
+            .view()
+            .block(BlockNumber::Number(42.into()));
+
+        transport.add_response(json!(
+            "0x000000000000000000000000000000000000000000000000000000000000002a"
+        )); // call response
+        tx.call().immediate().expect("call error");
+
+        // NOTE: The state overrides parameter is omitted entirely so that the
+        //   call works with nodes that don't support state overrides.
+        transport.assert_request(
+            "eth_call",
+            &[
+                json!({
+                    "to": address,
+                    "data": data,
+                }),
+                json!("0x2a"),
+            ],
+        );
+        transport.assert_no_more_requests();
+    }
+
     #[test]
     fn method_to_view_method_preserves_options() {
         let mut transport = TestTransport::new();
@@ -511,6 +855,46 @@ mod tests {
         transport.assert_no_more_requests();
     }
 
+    #[test]
+    fn method_attach_access_list() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let (function, data) = test_abi_function();
+        let access_list = vec![AccessListItem {
+            address,
+            storage_keys: vec![H256::from_low_u64_be(1)],
+        }];
+
+        transport.add_response(json!({
+            "accessList": access_list,
+            "gasUsed": "0x5208",
+        }));
+        let tx = MethodBuilder::<_, U256>::new(web3, function, address, data.clone())
+            .from(Account::Local(from, None))
+            .attach_access_list()
+            .immediate()
+            .expect("failed to attach access list")
+            .into_inner();
+
+        transport.assert_request(
+            "eth_createAccessList",
+            &[
+                json!({
+                    "from": from,
+                    "to": address,
+                    "data": data,
+                }),
+                json!("pending"),
+            ],
+        );
+        transport.assert_no_more_requests();
+
+        assert_eq!(tx.access_list, Some(access_list));
+    }
+
     #[test]
     fn method_defaults_are_applied() {
         let transport = TestTransport::new();
@@ -524,15 +908,245 @@ mod tests {
                 from: Some(Account::Local(from, None)),
                 gas: Some(1.into()),
                 gas_price: Some(2.into()),
+                max_fee_per_gas: Some(3.into()),
+                max_priority_fee_per_gas: Some(4.into()),
+                nonce_manager: Some(NonceManager::new()),
+                simulate: Some(true),
             })
             .into_inner();
 
         assert_eq!(tx.from.map(|a| a.address()), Some(from));
         assert_eq!(tx.gas, Some(1.into()));
         assert_eq!(tx.gas_price, Some(2.into()));
+        assert_eq!(tx.max_fee_per_gas, Some(3.into()));
+        assert_eq!(tx.max_priority_fee_per_gas, Some(4.into()));
+        assert!(tx.nonce_manager.is_some());
+        assert_eq!(tx.simulate_before_send, Some(true));
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn method_defaults_do_not_override_options() {
+        let transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let (function, data) = test_abi_function();
+        let tx = MethodBuilder::<_, U256>::new(web3, function, address, data)
+            .gas(1.into())
+            .simulate(false)
+            .with_defaults(&MethodDefaults {
+                gas: Some(2.into()),
+                simulate: Some(true),
+                ..Default::default()
+            })
+            .into_inner();
+
+        assert_eq!(tx.gas, Some(1.into()));
+        assert_eq!(tx.simulate_before_send, Some(false));
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn method_send_simulated() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let hash = hash!("0x4242424242424242424242424242424242424242424242424242424242424242");
+        let (function, data) = test_abi_function();
+        let mut tx = MethodBuilder::<_, U256>::new(web3, function, address, data.clone())
+            .from(Account::Local(from, None))
+            .gas(1.into())
+            .gas_price(2.into())
+            .nonce(0.into())
+            .simulate(true);
+        tx.tx.resolve = Some(ResolveCondition::Pending);
+
+        transport.add_response(json!("0x")); // simulation response
+        transport.add_response(json!(hash)); // send transaction response
+        tx.send().immediate().expect("transaction failed");
+
+        let request = json!({
+            "from": from,
+            "to": address,
+            "gas": "0x1",
+            "gasPrice": "0x2",
+            "data": data,
+        });
+        transport.assert_request("eth_call", &[request.clone(), json!("pending")]);
+        let mut send = request;
+        send["nonce"] = json!("0x0");
+        transport.assert_request("eth_sendTransaction", &[send]);
         transport.assert_no_more_requests();
     }
 
+    #[test]
+    fn method_send_simulated_revert() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let (function, data) = test_abi_function();
+        let tx = MethodBuilder::<_, U256>::new(web3, function, address, data.clone())
+            .from(Account::Local(from, None))
+            .nonce_manager(NonceManager::new())
+            .simulate(true);
+
+        transport.add_error(Web3Error::Rpc(RpcError {
+            code: ErrorCode::ServerError(3),
+            message: "execution reverted: message".to_owned(),
+            data: Some(json!(revert::encode_reason_hex("message"))),
+        }));
+        let result = tx.send().immediate();
+        assert!(
+            matches!(
+                &result,
+                Err(MethodError {
+                    inner: ExecutionError::Revert(Some(reason), _),
+                    ..
+                }) if reason == "message"
+            ),
+            "unexpected result {:?}",
+            result
+        );
+
+        // NOTE: No nonce is reserved and nothing is sent for reverting
+        //   transactions.
+        transport.assert_request(
+            "eth_call",
+            &[
+                json!({
+                    "from": from,
+                    "to": address,
+                    "data": data,
+                }),
+                json!("pending"),
+            ],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn method_send_with_events() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let hash = hash!("0x4242424242424242424242424242424242424242424242424242424242424242");
+        let log = |address| {
+            json!({
+                "address": address,
+                "topics": [H256::repeat_byte(1)],
+                "data": "0x",
+                "blockHash": H256::zero(),
+                "blockNumber": "0x1",
+                "transactionHash": hash,
+                "transactionIndex": "0x0",
+                "logIndex": "0x0",
+            })
+        };
+        let (function, data) = test_abi_function();
+        let tx = MethodBuilder::<_, U256>::new(web3, function, address, data)
+            .from(Account::Local(from, None))
+            .gas(1.into())
+            .gas_price(2.into())
+            .nonce(0.into());
+
+        transport.add_response(json!(hash)); // send transaction response
+        transport.add_response(json!("0x1")); // block number response
+        transport.add_response(json!({
+            "transactionHash": hash,
+            "transactionIndex": "0x0",
+            "blockNumber": "0x1",
+            "blockHash": H256::zero(),
+            "cumulativeGasUsed": "0x1337",
+            "gasUsed": "0x1337",
+            "logsBloom": H2048::zero(),
+            "logs": [log(from), log(address)],
+            "status": "0x1",
+        })); // transaction receipt response
+        let (receipt, events) = tx
+            .send_with_events::<RawLog>()
+            .immediate()
+            .expect("transaction failed");
+
+        assert_eq!(receipt.transaction_hash, hash);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].inner_data().topics, vec![H256::repeat_byte(1)]);
+    }
+
+    #[test]
+    fn method_send_with_events_requires_receipt() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let hash = hash!("0x4242424242424242424242424242424242424242424242424242424242424242");
+        let (function, data) = test_abi_function();
+        let mut tx = MethodBuilder::<_, U256>::new(web3, function, address, data)
+            .from(Account::Local(Address::zero(), None))
+            .gas(1.into())
+            .gas_price(2.into())
+            .nonce(0.into());
+        tx.tx.resolve = Some(ResolveCondition::Pending);
+
+        transport.add_response(json!(hash)); // send transaction response
+        let err = tx
+            .send_with_events::<RawLog>()
+            .immediate()
+            .expect_err("unexpected success");
+        assert!(
+            matches!(err.inner, ExecutionError::PendingTransaction(h) if h == hash),
+            "unexpected error {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn method_trace() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let (function, data) = test_abi_function();
+        let tx = MethodBuilder::<_, U256>::new(web3, function, address, data.clone())
+            .from(Account::Local(from, None));
+
+        transport.add_response(json!({
+            "type": "CALL",
+            "from": from,
+            "to": address,
+            "input": data,
+            "output": "0x",
+            "error": "execution reverted",
+        }));
+        let trace = tx.trace(Tracer::Geth).immediate().expect("trace failed");
+
+        transport.assert_request(
+            "debug_traceCall",
+            &[
+                json!({
+                    "from": from,
+                    "to": address,
+                    "data": data,
+                }),
+                json!("latest"),
+                json!({ "tracer": "callTracer" }),
+            ],
+        );
+        transport.assert_no_more_requests();
+
+        assert!(matches!(
+            trace.execution_error(),
+            Some(ExecutionError::Revert(None, _))
+        ));
+    }
+
     #[test]
     fn method_call_geth_revert_with_message() {
         let mut transport = TestTransport::new();
@@ -549,7 +1163,7 @@ mod tests {
         assert!(
             match &result {
                 Err(MethodError {
-                    inner: ExecutionError::Revert(Some(ref reason)),
+                    inner: ExecutionError::Revert(Some(ref reason), _),
                     ..
                 }) if reason == "message" => true,
                 _ => false,
@@ -575,7 +1189,7 @@ mod tests {
         assert!(
             match &result {
                 Err(MethodError {
-                    inner: ExecutionError::InvalidOpcode,
+                    inner: ExecutionError::InvalidOpcode(_),
                     ..
                 }) => true,
                 _ => false,
@@ -584,4 +1198,65 @@ mod tests {
             result
         );
     }
+
+    #[test]
+    fn method_call_geth_custom_error() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let (function, data) = test_abi_function();
+        let tx = ViewMethodBuilder::<_, U256>::from_method(MethodBuilder::new(
+            web3, function, address, data,
+        ));
+
+        let revert_data = vec![0xcf, 0x47, 0x91, 0x81];
+        transport.add_response(json!(Bytes(revert_data.clone())));
+        let err = tx.call().immediate().expect_err("unexpected success");
+        assert!(
+            matches!(&err.inner, ExecutionError::CustomError(data) if *data == revert_data),
+            "unexpected error {:?}",
+            err
+        );
+        assert_eq!(err.inner.revert_data(), Some(revert_data));
+    }
+
+    #[test]
+    fn view_method_call_tuple() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let function = Function {
+            name: "test".to_owned(),
+            inputs: Vec::new(),
+            outputs: vec![
+                Param {
+                    name: "".to_owned(),
+                    kind: ParamType::Tuple(vec![
+                        Box::new(ParamType::Uint(256)),
+                        Box::new(ParamType::Bool),
+                    ]),
+                },
+                Param {
+                    name: "".to_owned(),
+                    kind: ParamType::Address,
+                },
+            ],
+            constant: false,
+        };
+        let data = Bytes(function.encode_input(&[]).unwrap());
+        let tx = ViewMethodBuilder::<_, ((U256, bool), Address)>::from_method(MethodBuilder::new(
+            web3, function, address, data,
+        ));
+
+        transport.add_response(json!(
+            "0x000000000000000000000000000000000000000000000000000000000000002a\
+               0000000000000000000000000000000000000000000000000000000000000001\
+               0000000000000000000000000123456789012345678901234567890123456789"
+        )); // call response
+        let result = tx.call().immediate().expect("call error");
+
+        assert_eq!(result, ((42.into(), true), address));
+    }
 }
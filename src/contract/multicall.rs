@@ -0,0 +1,423 @@
+//! Implementation of a batch of contract calls that are aggregated into a
+//! single `eth_call` with a Multicall contract.
+
+use crate::contract::method::{decode_geth_call_result, Detokenizable, ViewMethodBuilder};
+use crate::errors::{revert, ExecutionError, MethodError};
+use crate::future::{CompatCallFuture, MaybeReady};
+use crate::tokens::Tokenizable;
+use ethcontract_common::abi::{Function, Param, ParamType};
+use futures::channel::oneshot;
+use futures::compat::Future01CompatExt;
+use futures::future::{MapErr, TryFutureExt};
+use futures::ready;
+use pin_project::pin_project;
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use web3::api::Web3;
+use web3::types::{Address, BlockNumber, Bytes, CallRequest, H160};
+use web3::Transport;
+
+/// The address of the Multicall3 contract
+/// `0xcA11bde05977b3631167028862bE2a173976CA11`. It is deployed at this address
+/// on most EVM chains.
+pub const MULTICALL3_ADDRESS: Address = H160([
+    0xca, 0x11, 0xbd, 0xe0, 0x59, 0x77, 0xb3, 0x63, 0x11, 0x67, 0x02, 0x88, 0x62, 0xbe, 0x2a, 0x17,
+    0x39, 0x76, 0xca, 0x11,
+]);
+
+/// A batch of contract calls that are executed with a single `eth_call` by
+/// aggregating them with a Multicall contract.
+///
+/// Calls are added with `push`, which returns a future that resolves to the
+/// decoded result of the individual call once the batch is executed. Calls
+/// can fail individually without failing the whole batch.
+///
+/// Note that the calls are executed by the Multicall contract, so the
+/// `from`, `gas`, `gas_price` and `value` parameters as well as the block of
+/// the individual `ViewMethodBuilder`s are ignored.
+#[must_use = "batches do nothing unless you `.execute()` them"]
+pub struct MulticallBatch<T: Transport> {
+    /// The underlying `web3` provider.
+    web3: Web3<T>,
+    /// The address of the Multicall contract.
+    address: Address,
+    /// The block to execute the calls at.
+    block: Option<BlockNumber>,
+    /// The calls in the batch.
+    calls: Vec<Call>,
+}
+
+/// A call in a `MulticallBatch`.
+struct Call {
+    /// The address of the contract being called.
+    target: Address,
+    /// The call data.
+    data: Bytes,
+    /// The callback for resolving the call.
+    resolve: Resolver,
+}
+
+/// A callback for decoding and sending the result of a call to its
+/// `MulticallFuture` given whether the call succeeded and its return data.
+type Resolver = Box<dyn FnOnce(bool, Vec<u8>) + Send>;
+
+impl<T: Transport> MulticallBatch<T> {
+    /// Creates a new empty batch that uses the Multicall3 contract deployed at
+    /// its canonical address.
+    pub fn new(web3: Web3<T>) -> Self {
+        MulticallBatch::with_address(web3, MULTICALL3_ADDRESS)
+    }
+
+    /// Creates a new empty batch that uses a Multicall3 compatible contract
+    /// deployed at the specified address.
+    pub fn with_address(web3: Web3<T>, address: Address) -> Self {
+        MulticallBatch {
+            web3,
+            address,
+            block: None,
+            calls: Vec::new(),
+        }
+    }
+
+    /// Specify the block to execute the calls at, if not specified then the
+    /// calls will be executed at the latest block.
+    pub fn block(mut self, value: BlockNumber) -> Self {
+        self.block = Some(value);
+        self
+    }
+
+    /// Returns the number of calls in the batch.
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    /// Returns true if the batch does not contain any calls.
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// Adds a call to the batch. The returned future resolves to the result of
+    /// the call once the batch is executed.
+    pub fn push<R>(&mut self, call: ViewMethodBuilder<T, R>) -> MulticallFuture<R::Output>
+    where
+        R: Detokenizable + 'static,
+        R::Output: Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        let function = call.m.function;
+        let tx = call.m.tx;
+
+        let resolve_function = function.clone();
+        let resolve = move |success: bool, data: Vec<u8>| {
+            let result = if success {
+                decode_geth_call_result::<R>(&resolve_function, data)
+            } else {
+                Err(revert::decode_revert_data(&data))
+            };
+            let _ = sender.send(result.map_err(|err| MethodError::new(&resolve_function, err)));
+        };
+
+        self.calls.push(Call {
+            target: tx.to.unwrap_or_default(),
+            data: tx.data.unwrap_or_default(),
+            resolve: Box::new(resolve),
+        });
+
+        MulticallFuture { function, receiver }
+    }
+
+    /// Executes all the calls in the batch with a single `eth_call`. The
+    /// futures returned by `push` resolve once the batch is executed.
+    ///
+    /// If the aggregated call itself fails, then the error is returned and
+    /// the futures for the individual calls resolve to a
+    /// `ExecutionError::BatchFailed` error.
+    pub fn execute(self) -> MulticallBatchFuture<T> {
+        if self.calls.is_empty() {
+            return MulticallBatchFuture {
+                aggregate: None,
+                resolvers: Vec::new(),
+                call: MaybeReady::ready(Ok(Bytes::default())),
+            };
+        }
+
+        let aggregate = aggregate3_function();
+        let (calls, resolvers): (Vec<_>, Vec<_>) = self
+            .calls
+            .into_iter()
+            .map(|call| ((call.target, true, call.data), call.resolve))
+            .unzip();
+        let call = match aggregate.encode_input(&[calls.into_token()]) {
+            Ok(data) => MaybeReady::future(
+                self.web3
+                    .eth()
+                    .call(
+                        CallRequest {
+                            from: None,
+                            to: self.address,
+                            gas: None,
+                            gas_price: None,
+                            value: None,
+                            data: Some(Bytes(data)),
+                        },
+                        self.block,
+                    )
+                    .compat()
+                    .map_err(ExecutionError::from as _),
+            ),
+            Err(err) => MaybeReady::ready(Err(err.into())),
+        };
+
+        MulticallBatchFuture {
+            aggregate: Some(aggregate),
+            resolvers,
+            call,
+        }
+    }
+}
+
+impl<T: Transport> Debug for MulticallBatch<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("MulticallBatch")
+            .field("address", &self.address)
+            .field("block", &self.block)
+            .field("calls", &self.calls.len())
+            .finish()
+    }
+}
+
+/// The future for the aggregated `eth_call` of a `MulticallBatch`.
+type AggregateCallFuture<T> = MapErr<CompatCallFuture<T, Bytes>, fn(web3::Error) -> ExecutionError>;
+
+/// Future for executing a `MulticallBatch`, resolving once the results of the
+/// individual calls have been sent to their `MulticallFuture`s.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[pin_project]
+pub struct MulticallBatchFuture<T: Transport> {
+    /// The `aggregate3` function used for decoding the results, `None` if the
+    /// batch is empty.
+    aggregate: Option<Function>,
+    /// The callbacks for resolving the individual calls.
+    resolvers: Vec<Resolver>,
+    /// The aggregated call.
+    #[pin]
+    call: MaybeReady<AggregateCallFuture<T>>,
+}
+
+impl<T: Transport> Future for MulticallBatchFuture<T> {
+    type Output = Result<(), ExecutionError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+        let result = ready!(this.call.poll(cx));
+
+        let aggregate = match this.aggregate.take() {
+            Some(aggregate) => aggregate,
+            None => return Poll::Ready(Ok(())),
+        };
+        let resolvers = mem::take(this.resolvers);
+
+        Poll::Ready(result.and_then(|result| {
+            let mut tokens = aggregate.decode_output(&result.0)?;
+            let results = Vec::<(bool, Bytes)>::from_token(tokens.remove(0))?;
+            if results.len() != resolvers.len() {
+                return Err(ExecutionError::BatchFailed);
+            }
+
+            for ((success, data), resolve) in results.into_iter().zip(resolvers) {
+                resolve(success, data.0);
+            }
+
+            Ok(())
+        }))
+    }
+}
+
+/// Future resolving to the result of a call that was added to a
+/// `MulticallBatch` once the batch is executed.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[pin_project]
+pub struct MulticallFuture<O> {
+    /// The function being called, used for error reporting.
+    function: Function,
+    /// The receiver for the result of the call.
+    #[pin]
+    receiver: oneshot::Receiver<Result<O, MethodError>>,
+}
+
+impl<O> Future for MulticallFuture<O> {
+    type Output = Result<O, MethodError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+        let function = this.function;
+        this.receiver.poll(cx).map(|result| {
+            result.unwrap_or_else(|_| Err(MethodError::new(function, ExecutionError::BatchFailed)))
+        })
+    }
+}
+
+/// Returns the ABI of the Multicall3 `aggregate3` function:
+/// ```text
+/// function aggregate3(Call3[] calldata calls)
+///     external payable returns (Result[] memory returnData);
+/// ```
+/// Where `Call3` is `(address target, bool allowFailure, bytes callData)` and
+/// `Result` is `(bool success, bytes returnData)`.
+fn aggregate3_function() -> Function {
+    Function {
+        name: "aggregate3".into(),
+        inputs: vec![Param {
+            name: "calls".into(),
+            kind: ParamType::Array(Box::new(ParamType::Tuple(vec![
+                Box::new(ParamType::Address),
+                Box::new(ParamType::Bool),
+                Box::new(ParamType::Bytes),
+            ]))),
+        }],
+        outputs: vec![Param {
+            name: "returnData".into(),
+            kind: ParamType::Array(Box::new(ParamType::Tuple(vec![
+                Box::new(ParamType::Bool),
+                Box::new(ParamType::Bytes),
+            ]))),
+        }],
+        constant: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::MethodBuilder;
+    use crate::test::prelude::*;
+    use crate::tokens::Tokenize;
+    use ethcontract_common::abi;
+    use web3::types::U256;
+
+    fn view_method(address: Address, value: u64) -> ViewMethodBuilder<TestTransport, U256> {
+        let function = Function {
+            name: "balanceOf".to_owned(),
+            inputs: vec![Param {
+                name: "".to_owned(),
+                kind: ParamType::Uint(256),
+            }],
+            outputs: vec![Param {
+                name: "".to_owned(),
+                kind: ParamType::Uint(256),
+            }],
+            constant: true,
+        };
+        let data = function
+            .encode_input(&(U256::from(value),).into_tokens())
+            .expect("error encoding input");
+
+        MethodBuilder::new(
+            Web3::new(TestTransport::new()),
+            function,
+            address,
+            Bytes(data),
+        )
+        .view()
+    }
+
+    #[test]
+    fn multicall_batch() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let call1 = view_method(address, 1);
+        let call2 = view_method(address, 2);
+        let (data1, data2) = (call1.m.tx.data.clone(), call2.m.tx.data.clone());
+
+        let mut batch = MulticallBatch::new(web3);
+        let result1 = batch.push(call1);
+        let result2 = batch.push(call2);
+        assert_eq!(batch.len(), 2);
+
+        let return_data = vec![
+            (true, Bytes(abi::encode(&[U256::from(42).into_token()]))),
+            (false, Bytes(revert::encode_reason("insufficient balance"))),
+        ];
+        transport.add_response(json!(Bytes(abi::encode(&[return_data.into_token()]))));
+        batch.execute().immediate().expect("batch execution failed");
+
+        let calls = vec![
+            (address, true, data1.unwrap()),
+            (address, true, data2.unwrap()),
+        ];
+        let data = aggregate3_function()
+            .encode_input(&[calls.into_token()])
+            .expect("error encoding aggregate call");
+        transport.assert_request(
+            "eth_call",
+            &[
+                json!({
+                    "to": MULTICALL3_ADDRESS,
+                    "data": Bytes(data),
+                }),
+                json!("latest"),
+            ],
+        );
+        transport.assert_no_more_requests();
+
+        assert_eq!(result1.immediate().expect("call failed"), U256::from(42));
+        assert!(
+            match result2.immediate() {
+                Err(MethodError {
+                    inner: ExecutionError::Revert(Some(reason), _),
+                    ..
+                }) => reason == "insufficient balance",
+                _ => false,
+            },
+            "expected call to revert"
+        );
+    }
+
+    #[test]
+    fn multicall_batch_failure() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        let mut batch = MulticallBatch::new(web3).block(BlockNumber::Number(42.into()));
+        let call = view_method(address, 1);
+        let call_data = call.m.tx.data.clone().unwrap();
+        let result = batch.push(call);
+
+        transport.add_response(json!("0x"));
+        batch
+            .execute()
+            .immediate()
+            .expect_err("unexpected batch success");
+        let data = aggregate3_function()
+            .encode_input(&[vec![(address, true, call_data)].into_token()])
+            .expect("error encoding aggregate call");
+        transport.assert_request(
+            "eth_call",
+            &[
+                json!({
+                    "to": MULTICALL3_ADDRESS,
+                    "data": Bytes(data),
+                }),
+                json!("0x2a"),
+            ],
+        );
+        transport.assert_no_more_requests();
+
+        assert!(
+            matches!(
+                result.immediate(),
+                Err(MethodError {
+                    inner: ExecutionError::BatchFailed,
+                    ..
+                })
+            ),
+            "expected batch failure"
+        );
+    }
+}
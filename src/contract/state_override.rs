@@ -0,0 +1,120 @@
+//! Module containing types for overriding account state when executing an
+//! `eth_call`. State overrides are supported by Geth and most nodes following
+//! its JSON RPC API, and are specified as an optional third parameter of the
+//! call that maps addresses to the state to override for each account.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use web3::types::{Address, Bytes, H256, U256, U64};
+
+/// A set of state overrides for an `eth_call`, indexed by account address.
+pub type StateOverrides = HashMap<Address, StateOverride>;
+
+/// The state to override for a single account during an `eth_call`. Fields
+/// that are not specified keep their value from the state of the block the
+/// call is executed at.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateOverride {
+    /// Fake balance to set for the account before executing the call.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<U256>,
+    /// Fake nonce to set for the account before executing the call.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<U64>,
+    /// Fake EVM bytecode to inject into the account before executing the call.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<Bytes>,
+    /// Fake key-value mapping to replace the entire account storage with
+    /// before executing the call.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<HashMap<H256, H256>>,
+    /// Fake key-value mapping of individual storage slots to override before
+    /// executing the call, leaving all other slots untouched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_diff: Option<HashMap<H256, H256>>,
+}
+
+impl StateOverride {
+    /// Override the balance of the account.
+    pub fn balance(mut self, value: U256) -> Self {
+        self.balance = Some(value);
+        self
+    }
+
+    /// Override the nonce of the account.
+    pub fn nonce(mut self, value: U64) -> Self {
+        self.nonce = Some(value);
+        self
+    }
+
+    /// Override the code of the account.
+    pub fn code(mut self, value: Bytes) -> Self {
+        self.code = Some(value);
+        self
+    }
+
+    /// Replace the entire storage of the account.
+    pub fn state(mut self, value: HashMap<H256, H256>) -> Self {
+        self.state = Some(value);
+        self
+    }
+
+    /// Override a single storage slot of the account, leaving the remaining
+    /// storage untouched. Note that nodes reject overrides that both replace
+    /// the entire storage and override individual slots.
+    pub fn storage(mut self, slot: H256, value: H256) -> Self {
+        self.state_diff
+            .get_or_insert_with(HashMap::new)
+            .insert(slot, value);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+
+    #[test]
+    fn serialize_state_override() {
+        let slot = H256::from_low_u64_be(1);
+        let value = H256::from_low_u64_be(42);
+        let state_override = StateOverride::default()
+            .balance(1000.into())
+            .nonce(1.into())
+            .code(Bytes(vec![0x13, 0x37]))
+            .storage(slot, value);
+
+        assert_eq!(
+            json!(state_override),
+            json!({
+                "balance": "0x3e8",
+                "nonce": "0x1",
+                "code": "0x1337",
+                "stateDiff": {
+                    "0x0000000000000000000000000000000000000000000000000000000000000001":
+                        "0x000000000000000000000000000000000000000000000000000000000000002a",
+                },
+            })
+        );
+        assert_eq!(json!(StateOverride::default()), json!({}));
+    }
+
+    #[test]
+    fn serialize_state_replacement() {
+        let mut storage = HashMap::new();
+        storage.insert(H256::from_low_u64_be(1), H256::from_low_u64_be(42));
+        let state_override = StateOverride::default().state(storage);
+
+        assert_eq!(
+            json!(state_override),
+            json!({
+                "state": {
+                    "0x0000000000000000000000000000000000000000000000000000000000000001":
+                        "0x000000000000000000000000000000000000000000000000000000000000002a",
+                },
+            })
+        );
+    }
+}
@@ -1,11 +1,16 @@
 //! Module with common error types.
 
+mod anvil;
 mod ganache;
+mod geth;
+mod hardhat;
 mod parity;
 pub(crate) mod revert;
 mod web3contract;
 
+pub use self::revert::PanicCode;
 pub use self::web3contract::Web3ContractError;
+use crate::transaction::confirm::{BlockId, ReorgEvent};
 use ethcontract_common::abi::{Error as AbiError, Event, Function};
 use ethcontract_common::abiext::EventExt;
 pub use ethcontract_common::errors::*;
@@ -14,7 +19,7 @@ use std::num::ParseIntError;
 use thiserror::Error;
 use uint::FromDecStrErr;
 use web3::error::Error as Web3Error;
-use web3::types::{TransactionReceipt, H256};
+use web3::types::{Address, TransactionReceipt, H256};
 
 /// Error that can occur while locating a deployed contract.
 #[derive(Debug, Error)]
@@ -45,6 +50,11 @@ pub enum DeployError {
     #[error("error executing contract deployment transaction: {0}")]
     Tx(#[from] ExecutionError),
 
+    /// The contract constructor reverted. The inner execution error contains
+    /// the decoded revert reason, panic code or custom error data.
+    #[error("contract constructor reverted: {0}")]
+    Revert(ExecutionError),
+
     /// Transaction was unable to confirm and is still pending. The contract
     /// address cannot be determined.
     #[error("contract deployment transaction pending: {0}")]
@@ -72,13 +82,31 @@ pub enum ExecutionError {
     #[error("no local accounts")]
     NoLocalAccounts,
 
-    /// A contract call reverted.
+    /// A contract call reverted. This contains the revert reason if there was
+    /// one, and the raw revert data as returned by the node if it is known.
     #[error("contract call reverted with message: {0:?}")]
-    Revert(Option<String>),
+    Revert(Option<String>, Option<Vec<u8>>),
 
-    /// A contract call executed an invalid opcode.
+    /// A contract call executed an invalid opcode. This contains the raw data
+    /// returned by the node if there was any.
     #[error("contract call executed an invalid opcode")]
-    InvalidOpcode,
+    InvalidOpcode(Option<Vec<u8>>),
+
+    /// A contract call or transaction ran out of gas.
+    #[error("contract call ran out of gas")]
+    OutOfGas,
+
+    /// A contract call panicked, this happens for example on failed assertions
+    /// or arithmetic overflows.
+    #[error("contract call panicked: {0}")]
+    Panic(PanicCode),
+
+    /// A contract call reverted with data that is neither a revert reason nor
+    /// a panic code, usually a Solidity custom error. This contains the raw
+    /// revert data including the 4-byte error selector and can be decoded
+    /// into a contract's generated error type with `ExecutionError::decode`.
+    #[error("contract call reverted with custom error 0x{}", hex::encode(.0))]
+    CustomError(Vec<u8>),
 
     /// A contract transaction failed to confirm within the block timeout limit.
     #[error("transaction confirmation timed-out")]
@@ -91,7 +119,8 @@ pub enum ExecutionError {
     /// A call returned an unsupported token. This happens when using the
     /// experimental `ABIEncoderV2` option.
     ///
-    /// This is intended to be implemented in future version of `ethcontract`.
+    /// Note that this error is no longer returned now that tuple tokens are
+    /// supported, and will be removed in a future version of `ethcontract`.
     #[error("unsupported ABI token")]
     UnsupportedToken,
 
@@ -99,14 +128,60 @@ pub enum ExecutionError {
     #[error("missing transaction {0:?}")]
     MissingTransaction(H256),
 
+    /// A transaction could not be cancelled since it was sent by a different
+    /// account than the one used for cancelling it. This contains the hash
+    /// and the actual sender of the transaction.
+    #[error("transaction {0:?} was sent by {1:?} and not by the cancelling account")]
+    UnexpectedSender(H256, Address),
+
+    /// Failed to find a block by hash.
+    #[error("missing block {0:?}")]
+    MissingBlock(H256),
+
     /// Failed to get a block for a pending transaction that has not yet been
     /// mined.
     #[error("pending transaction {0:?}, not yet part of a block")]
     PendingTransaction(H256),
+
+    /// A chain reorganization dropped the transaction from the chain or moved
+    /// it to a different block while it was being confirmed.
+    #[error("transaction affected by chain reorganization: {0:?}")]
+    Reorg(Box<ReorgEvent>),
+
+    /// A chain reorganization orphaned a block that is older than the recent
+    /// blocks tracked by a block log stream, so the logs that were removed by
+    /// the reorganization could not be determined. This is typically returned
+    /// when resuming a stream from a checkpoint that was orphaned while the
+    /// stream was not running.
+    ///
+    /// The stream can not recover from this error on its own and returns it
+    /// again if it is polled once more. Instead, resume a new stream from a
+    /// checkpoint further back, for example by rewinding the orphaned block
+    /// number by the reorganization window and retrieving the hash of the
+    /// block at that height, and discard the events the application already
+    /// processed from the blocks in between by their block hash and log
+    /// index. Events from the orphaned blocks are not emitted as removed, so
+    /// the application has to revert them itself.
+    #[error("chain reorganization deeper than tracked blocks orphaned block {:?}", .0.hash)]
+    ReorgTooDeep(BlockId),
+
+    /// A call that was part of a batch could not be executed because the batch
+    /// failed or was dropped before being executed.
+    #[error("batch failed to execute")]
+    BatchFailed,
+
+    /// An event filter contains an empty address list or an empty set of
+    /// topic values. These are sent to the node as wildcards, so such a
+    /// filter would match the logs of every contract instead of none.
+    #[error("event filter contains an empty address list or topic set")]
+    EmptyFilter,
 }
 
 impl From<Web3Error> for ExecutionError {
     fn from(err: Web3Error) -> Self {
+        // NOTE: The decoders must only match errors reporting a failed
+        // execution. Other RPC errors, such as nonce errors, are kept as is
+        // since the nonce manager matches them by message.
         if let Web3Error::Rpc(jsonrpc_err) = &err {
             if let Some(err) = ganache::get_encoded_error(&jsonrpc_err) {
                 return err;
@@ -114,12 +189,61 @@ impl From<Web3Error> for ExecutionError {
             if let Some(err) = parity::get_encoded_error(&jsonrpc_err) {
                 return err;
             }
+            if let Some(err) = hardhat::get_encoded_error(jsonrpc_err) {
+                return err;
+            }
+            if let Some(err) = anvil::get_encoded_error(jsonrpc_err) {
+                return err;
+            }
+            if let Some(err) = geth::get_encoded_error(jsonrpc_err) {
+                return err;
+            }
         }
 
         ExecutionError::Web3(err)
     }
 }
 
+impl ExecutionError {
+    /// Returns true if the error indicates that the execution of a contract
+    /// call or transaction reverted.
+    pub fn is_revert(&self) -> bool {
+        matches!(
+            self,
+            ExecutionError::Revert(..)
+                | ExecutionError::InvalidOpcode(_)
+                | ExecutionError::OutOfGas
+                | ExecutionError::Panic(_)
+                | ExecutionError::CustomError(_)
+        )
+    }
+
+    /// Returns the raw data that a contract call reverted with, as returned by
+    /// the node, so that it can be decoded against any ABI. Returns `None` if
+    /// the error is not a revert or if the node did not return any data.
+    ///
+    /// Note that panic codes are only decoded from data that is exactly an ABI
+    /// encoded `Panic(uint256)`, so their data is re-encoded from the code.
+    pub fn revert_data(&self) -> Option<Vec<u8>> {
+        match self {
+            ExecutionError::Revert(_, data) | ExecutionError::InvalidOpcode(data) => data.clone(),
+            ExecutionError::Panic(code) => Some(revert::encode_panic(code.code())),
+            ExecutionError::CustomError(data) => Some(data.clone()),
+            _ => None,
+        }
+    }
+
+    /// Decodes the Solidity custom error that caused a contract call to revert
+    /// into a contract specific error type. Returns `None` if this is not a
+    /// custom error or if the error selector is not one of the contract's.
+    pub fn decode<E: ParseRevert>(&self) -> Option<E> {
+        match self {
+            ExecutionError::CustomError(data) => E::parse_revert(data).ok(),
+            _ => None,
+        }
+    }
+}
+
 impl From<web3::contract::Error> for ExecutionError {
     fn from(err: web3::contract::Error) -> Self {
         ExecutionError::AbiDecode(err.into())
@@ -155,6 +279,21 @@ impl MethodError {
     pub fn from_parts(signature: String, inner: ExecutionError) -> Self {
         MethodError { signature, inner }
     }
+
+    /// Decodes the Solidity custom error that caused the method to revert into
+    /// a contract specific error type, usually the generated `Error` enum of
+    /// the contract.
+    pub fn decode<E: ParseRevert>(&self) -> Option<E> {
+        self.inner.decode()
+    }
+}
+
+/// A type that can be parsed from the raw revert data of a Solidity custom
+/// error.
+pub trait ParseRevert: Sized {
+    /// Create a new instance by parsing raw revert data, including the 4-byte
+    /// error selector.
+    fn parse_revert(data: &[u8]) -> Result<Self, ExecutionError>;
 }
 
 /// Error that can occur while streaming contract events.
@@ -234,6 +373,7 @@ impl From<FromDecStrErr> for ParseI256Error {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use jsonrpc_core::{Error as JsonrpcError, ErrorCode};
     use std::error::Error;
 
     #[test]
@@ -243,7 +383,7 @@ mod tests {
 
         assert!(
             match err {
-                ExecutionError::InvalidOpcode => true,
+                ExecutionError::InvalidOpcode(_) => true,
                 _ => false,
             },
             "bad error conversion {:?}",
@@ -258,7 +398,7 @@ mod tests {
 
         assert!(
             match err {
-                ExecutionError::InvalidOpcode => true,
+                ExecutionError::InvalidOpcode(_) => true,
                 _ => false,
             },
             "bad error conversion {:?}",
@@ -266,6 +406,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn from_geth_encoded_error() {
+        let web3_err = Web3Error::Rpc(JsonrpcError {
+            code: ErrorCode::ServerError(3),
+            message: "execution reverted: message".to_owned(),
+            data: Some(revert::encode_reason_hex("message").into()),
+        });
+        let err = ExecutionError::from(web3_err);
+
+        assert!(
+            matches!(&err, ExecutionError::Revert(Some(reason), _) if reason == "message"),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn revert_data_round_trip() {
+        let mut padded_reason = revert::encode_reason("message");
+        padded_reason.extend_from_slice(&[0; 32]);
+
+        for data in &[
+            revert::encode_reason("message"),
+            padded_reason,
+            revert::encode_panic(0x11.into()),
+            vec![0xcf, 0x47, 0x91, 0x81],
+        ] {
+            let err = revert::decode_revert_data(data);
+            assert_eq!(err.revert_data().as_ref(), Some(data));
+        }
+        assert_eq!(revert::decode_revert_data(&[]).revert_data(), None);
+        assert_eq!(ExecutionError::InvalidOpcode(None).revert_data(), None);
+        assert_eq!(
+            ExecutionError::InvalidOpcode(Some(vec![0xfe])).revert_data(),
+            Some(vec![0xfe])
+        );
+    }
+
     #[test]
     fn all_errors_are_boxable_errors() {
         fn assert_boxable_error<T: Error + Send + Sync + 'static>() {}
@@ -0,0 +1,165 @@
+//! This module implements Anvil specific error decoding in order to try and
+//! provide more accurate errors from Anvil nodes.
+//!
+//! Note that Anvil reports reverts in the same format as Geth, so they are
+//! decoded by the Geth specific error decoding.
+
+use crate::errors::ExecutionError;
+use jsonrpc_core::Error as JsonrpcError;
+
+/// EVM error message prefix.
+const EVM_ERROR: &str = "EVM error ";
+/// Out of gas error message prefix returned when estimating gas.
+const OUT_OF_GAS: &str = "Out of gas";
+
+/// Tries to get a more accurate error from a generic Anvil JSON RPC error.
+/// Returns `None` when a more accurate error cannot be determined.
+pub fn get_encoded_error(err: &JsonrpcError) -> Option<ExecutionError> {
+    if err.message.starts_with(OUT_OF_GAS) {
+        return Some(ExecutionError::OutOfGas);
+    }
+
+    let evm_error = err
+        .message
+        .find(EVM_ERROR)
+        .map(|index| &err.message[index + EVM_ERROR.len()..])?;
+    if evm_error.starts_with("OutOfGas") {
+        Some(ExecutionError::OutOfGas)
+    } else if evm_error.starts_with("InvalidFEOpcode") || evm_error.starts_with("OpcodeNotFound") {
+        Some(ExecutionError::InvalidOpcode(None))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::PanicCode;
+    use crate::test::prelude::*;
+    use jsonrpc_core::ErrorCode;
+    use web3::error::Error as Web3Error;
+
+    fn rpc_error(code: i64, message: &str, data: Option<&str>) -> JsonrpcError {
+        JsonrpcError {
+            code: ErrorCode::from(code),
+            message: message.to_owned(),
+            data: data.map(|data| json!(data)),
+        }
+    }
+
+    fn convert(code: i64, message: &str, data: Option<&str>) -> ExecutionError {
+        ExecutionError::from(Web3Error::Rpc(rpc_error(code, message, data)))
+    }
+
+    #[test]
+    fn execution_error_from_revert_with_message() {
+        let err = convert(
+            3,
+            "execution reverted: Ownable: caller is not the owner",
+            Some(
+                "0x08c379a0\
+                 0000000000000000000000000000000000000000000000000000000000000020\
+                 0000000000000000000000000000000000000000000000000000000000000020\
+                 4f776e61626c653a2063616c6c6572206973206e6f7420746865206f776e6572",
+            ),
+        );
+
+        assert!(
+            matches!(
+                &err,
+                ExecutionError::Revert(Some(reason), _) if reason == "Ownable: caller is not the owner"
+            ),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn execution_error_from_revert() {
+        let err = convert(3, "execution reverted", Some("0x"));
+
+        assert!(
+            matches!(err, ExecutionError::Revert(None, _)),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn execution_error_from_panic() {
+        let err = convert(
+            3,
+            "execution reverted: panic: arithmetic underflow or overflow (0x11)",
+            Some("0x4e487b710000000000000000000000000000000000000000000000000000000000000011"),
+        );
+
+        assert!(
+            matches!(err, ExecutionError::Panic(PanicCode::ArithmeticOverflow)),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn execution_error_from_malformed_data() {
+        for data in &[None, Some("0xnothex"), Some("not even hex")] {
+            let err = convert(3, "execution reverted: message", *data);
+
+            assert!(
+                matches!(&err, ExecutionError::Revert(Some(reason), None) if reason == "message"),
+                "bad error conversion {:?}",
+                err
+            );
+        }
+
+        let err = ExecutionError::from(Web3Error::Rpc(JsonrpcError {
+            code: ErrorCode::from(3),
+            message: "execution reverted".to_owned(),
+            data: Some(json!(42)),
+        }));
+        assert!(
+            matches!(err, ExecutionError::Revert(None, None)),
+            "bad error conversion {:?}",
+            err
+        );
+
+        // NOTE: Truncated revert data can't be decoded, but the raw data is
+        //   kept so that it can be inspected.
+        let err = convert(3, "execution reverted", Some("0x08c379a0"));
+        assert!(
+            matches!(&err, ExecutionError::CustomError(data) if data == &[0x08, 0xc3, 0x79, 0xa0]),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn execution_error_from_invalid_opcode() {
+        for message in &["EVM error InvalidFEOpcode", "EVM error OpcodeNotFound"] {
+            let err = get_encoded_error(&rpc_error(-32603, message, None));
+
+            assert!(
+                matches!(err, Some(ExecutionError::InvalidOpcode(_))),
+                "bad error conversion {:?}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn execution_error_from_out_of_gas() {
+        for message in &[
+            "EVM error OutOfGas(Basic)",
+            "Out of gas: gas required exceeds allowance: 21000",
+        ] {
+            let err = get_encoded_error(&rpc_error(-32003, message, None));
+
+            assert!(
+                matches!(err, Some(ExecutionError::OutOfGas)),
+                "bad error conversion {:?}",
+                err
+            );
+        }
+    }
+}
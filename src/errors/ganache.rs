@@ -1,41 +1,104 @@
 //! This module implements Ganache specific error decoding in order to try and
 //! provide more accurate errors from Ganache nodes.
 
-use crate::errors::ExecutionError;
+use crate::errors::{revert, ExecutionError};
 use jsonrpc_core::Error as JsonrpcError;
+use serde_json::Value;
 use web3::types::H256;
 
+/// VM exception error message prefix used by Ganache 7 and later.
+const VM_EXCEPTION: &str = "VM Exception while processing transaction: ";
+
+/// The parameters of a Ganache error.
+struct ErrorParams<'a> {
+    /// The kind of error, for example `revert` or `invalid opcode`.
+    error: &'a str,
+    /// The decoded revert reason.
+    reason: Option<&'a str>,
+    /// The hex encoded data that was returned.
+    data: Option<&'a str>,
+}
+
 /// Tries to get a more accurate error from a generic Ganache JSON RPC error.
 /// Returns `None` when a more accurate error cannot be determined.
 pub fn get_encoded_error(err: &JsonrpcError) -> Option<ExecutionError> {
-    match get_error_param(&err, "error") {
-        Some("revert") => {
-            let reason = get_error_param(&err, "reason").map(|reason| reason.to_owned());
-            Some(ExecutionError::Revert(reason))
-        }
-        Some("invalid opcode") => Some(ExecutionError::InvalidOpcode),
+    let params = get_legacy_error_params(err).or_else(|| get_error_params(err))?;
+    let data = params
+        .data
+        .and_then(|data| hex::decode(data.trim_start_matches("0x")).ok())
+        .filter(|data| !data.is_empty());
+
+    match params.error {
+        "revert" => Some(match (data, params.reason) {
+            (Some(data), _) => revert::decode_revert_data(&data),
+            (None, Some(reason)) => ExecutionError::Revert(Some(reason.to_owned()), None),
+            (None, None) => ExecutionError::Revert(None, None),
+        }),
+        "invalid opcode" => Some(ExecutionError::InvalidOpcode(data)),
         _ => None,
     }
 }
 
-/// Gets an error parameters from a Ganache JSON RPC error.
+/// Gets the error parameters from a JSON RPC error returned by Ganache 6 and
+/// earlier.
 ///
-/// these parameters are the fields inside the transaction object (by tx hash)
+/// These parameters are the fields inside the transaction object (by tx hash)
 /// inside the error data object. Note that we don't need to know the fake tx
 /// hash for getting the error params as there should only be one.
-fn get_error_param<'a>(err: &'a JsonrpcError, name: &str) -> Option<&'a str> {
+fn get_legacy_error_params(err: &JsonrpcError) -> Option<ErrorParams<'_>> {
     fn is_hash_str(s: &str) -> bool {
         s.len() == 66 && s[2..].parse::<H256>().is_ok()
     }
 
-    err.data
+    let params = err
+        .data
         .as_ref()?
         .as_object()?
         .iter()
         .filter_map(|(k, v)| if is_hash_str(k) { Some(v) } else { None })
-        .next()?
-        .get(name)?
-        .as_str()
+        .next()?;
+
+    Some(ErrorParams {
+        error: params.get("error")?.as_str()?,
+        reason: params.get("reason").and_then(Value::as_str),
+        data: params.get("return").and_then(Value::as_str),
+    })
+}
+
+/// Gets the error parameters from a JSON RPC error returned by Ganache 7 and
+/// later.
+///
+/// The kind of error is part of the error message. The returned data is either
+/// the error data itself for calls, or the `result` field of the error data
+/// object for transactions.
+fn get_error_params(err: &JsonrpcError) -> Option<ErrorParams<'_>> {
+    if !err.message.starts_with(VM_EXCEPTION) {
+        return None;
+    }
+
+    let message = &err.message[VM_EXCEPTION.len()..];
+    let error = if message == "revert" || message.starts_with("revert ") {
+        "revert"
+    } else if message.starts_with("invalid opcode") {
+        "invalid opcode"
+    } else {
+        return None;
+    };
+
+    let (reason, data) = match err.data.as_ref() {
+        Some(Value::String(data)) => (None, Some(data.as_str())),
+        Some(Value::Object(data)) => (
+            data.get("reason").and_then(Value::as_str),
+            data.get("result").and_then(Value::as_str),
+        ),
+        _ => (None, None),
+    };
+
+    Some(ErrorParams {
+        error,
+        reason,
+        data,
+    })
 }
 
 #[cfg(test)]
@@ -44,7 +107,6 @@ pub use tests::*;
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::errors::revert;
     use crate::test::prelude::*;
     use jsonrpc_core::ErrorCode;
     use std::borrow::Cow;
@@ -79,7 +141,7 @@ mod tests {
 
         assert!(
             match &err {
-                Some(ExecutionError::Revert(Some(reason))) if reason == "message" => true,
+                Some(ExecutionError::Revert(Some(reason), _)) if reason == "message" => true,
                 _ => false,
             },
             "bad error conversion {:?}",
@@ -94,7 +156,7 @@ mod tests {
 
         assert!(
             match err {
-                Some(ExecutionError::Revert(None)) => true,
+                Some(ExecutionError::Revert(None, _)) => true,
                 _ => false,
             },
             "bad error conversion {:?}",
@@ -102,6 +164,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn execution_error_from_custom_error() {
+        let mut jsonrpc_err = rpc_error("revert", None);
+        jsonrpc_err.data.as_mut().unwrap()
+            ["0x000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"]["return"] =
+            json!("0xcf479181");
+        let err = get_encoded_error(&jsonrpc_err);
+
+        assert!(
+            matches!(&err, Some(ExecutionError::CustomError(data)) if data == &[0xcf, 0x47, 0x91, 0x81]),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
     #[test]
     fn execution_error_from_invalid_opcode() {
         let jsonrpc_err = rpc_error("invalid opcode", None);
@@ -109,11 +186,93 @@ mod tests {
 
         assert!(
             match err {
-                Some(ExecutionError::InvalidOpcode) => true,
+                Some(ExecutionError::InvalidOpcode(_)) => true,
                 _ => false,
             },
             "bad error conversion {:?}",
             err
         );
     }
+
+    fn rpc_error_v7(message: &str, data: Value) -> JsonrpcError {
+        JsonrpcError {
+            code: ErrorCode::from(-32000),
+            message: format!("VM Exception while processing transaction: {}", message),
+            data: Some(data),
+        }
+    }
+
+    #[test]
+    fn execution_error_from_v7_call_revert_with_message() {
+        let data = revert::encode_reason_hex("message");
+        let jsonrpc_err = rpc_error_v7("revert message", json!(data));
+        let err = get_encoded_error(&jsonrpc_err);
+
+        assert!(
+            matches!(
+                &err,
+                Some(ExecutionError::Revert(Some(reason), Some(raw)))
+                    if reason == "message" && *raw == revert::encode_reason("message")
+            ),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn execution_error_from_v7_transaction_revert() {
+        let jsonrpc_err = rpc_error_v7(
+            "revert",
+            json!({
+                "hash": "0x000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+                "programCounter": 42,
+                "result": "0xcf479181",
+                "reason": null,
+                "message": "revert",
+            }),
+        );
+        let err = get_encoded_error(&jsonrpc_err);
+
+        assert!(
+            matches!(&err, Some(ExecutionError::CustomError(data)) if data == &[0xcf, 0x47, 0x91, 0x81]),
+            "bad error conversion {:?}",
+            err
+        );
+
+        let jsonrpc_err = rpc_error_v7("revert", json!("0x"));
+        let err = get_encoded_error(&jsonrpc_err);
+
+        assert!(
+            matches!(&err, Some(ExecutionError::Revert(None, None))),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn execution_error_from_v7_invalid_opcode() {
+        let jsonrpc_err = rpc_error_v7(
+            "invalid opcode",
+            json!({
+                "hash": "0x000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+                "programCounter": 42,
+                "result": "0x",
+                "reason": null,
+                "message": "invalid opcode",
+            }),
+        );
+        let err = get_encoded_error(&jsonrpc_err);
+
+        assert!(
+            matches!(err, Some(ExecutionError::InvalidOpcode(None))),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn unrelated_vm_exception() {
+        let jsonrpc_err = rpc_error_v7("reverted with reason string 'message'", json!("0x"));
+        assert!(get_encoded_error(&jsonrpc_err).is_none());
+    }
 }
@@ -0,0 +1,146 @@
+//! This module implements Geth specific error decoding in order to try and
+//! provide more accurate errors from Geth nodes and nodes that follow the same
+//! error format like Erigon.
+//!
+//! Newer Geth versions return a JSON RPC error with an `execution reverted`
+//! message for reverted `eth_call`s and `eth_estimateGas`s instead of a success
+//! result, with the raw revert data as a hex string in the error data.
+
+use crate::errors::{revert, ExecutionError};
+use jsonrpc_core::Error as JsonrpcError;
+use serde_json::Value;
+
+/// Revert error message.
+const REVERTED: &str = "execution reverted";
+/// Invalid op-code error message prefix.
+const INVALID: &str = "invalid opcode";
+
+/// Tries to get a more accurate error from a generic Geth JSON RPC error.
+/// Returns `None` when a more accurate error cannot be determined.
+pub fn get_encoded_error(err: &JsonrpcError) -> Option<ExecutionError> {
+    if err.message.starts_with(REVERTED) {
+        match get_revert_data(err) {
+            Some(data) if !data.is_empty() => Some(revert::decode_revert_data(&data)),
+            _ => {
+                let reason = err.message[REVERTED.len()..].trim_start_matches(':').trim();
+                if reason.is_empty() {
+                    Some(ExecutionError::Revert(None, None))
+                } else {
+                    Some(ExecutionError::Revert(Some(reason.to_owned()), None))
+                }
+            }
+        }
+    } else if err.message.starts_with(INVALID) {
+        let data = get_revert_data(err).filter(|data| !data.is_empty());
+        Some(ExecutionError::InvalidOpcode(data))
+    } else {
+        None
+    }
+}
+
+/// Gets the raw revert data from a JSON RPC error. This supports the revert
+/// data as a hex string directly in the error data as returned by Geth and
+/// Erigon, as well as nested in an error data object as returned by Hardhat.
+pub(crate) fn get_revert_data(err: &JsonrpcError) -> Option<Vec<u8>> {
+    let data = match err.data.as_ref()? {
+        Value::String(data) => data,
+        Value::Object(data) => data.get("data")?.as_str()?,
+        _ => return None,
+    };
+
+    hex::decode(data.trim_start_matches("0x")).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use jsonrpc_core::ErrorCode;
+
+    fn rpc_error(message: &str, data: Option<Value>) -> JsonrpcError {
+        JsonrpcError {
+            code: ErrorCode::from(3),
+            message: message.to_owned(),
+            data,
+        }
+    }
+
+    #[test]
+    fn execution_error_from_revert_with_message() {
+        let jsonrpc_err = rpc_error(
+            "execution reverted: message",
+            Some(json!(revert::encode_reason_hex("message"))),
+        );
+        let err = get_encoded_error(&jsonrpc_err);
+
+        assert!(
+            matches!(&err, Some(ExecutionError::Revert(Some(reason), _)) if reason == "message"),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn execution_error_from_revert_without_data() {
+        let jsonrpc_err = rpc_error("execution reverted: message", None);
+        let err = get_encoded_error(&jsonrpc_err);
+
+        assert!(
+            matches!(&err, Some(ExecutionError::Revert(Some(reason), _)) if reason == "message"),
+            "bad error conversion {:?}",
+            err
+        );
+
+        let jsonrpc_err = rpc_error("execution reverted", None);
+        let err = get_encoded_error(&jsonrpc_err);
+
+        assert!(
+            matches!(&err, Some(ExecutionError::Revert(None, _))),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn execution_error_from_custom_error() {
+        let jsonrpc_err = rpc_error("execution reverted", Some(json!("0xcf479181")));
+        let err = get_encoded_error(&jsonrpc_err);
+
+        assert!(
+            matches!(&err, Some(ExecutionError::CustomError(data)) if data == &[0xcf, 0x47, 0x91, 0x81]),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn execution_error_from_nested_revert_data() {
+        let jsonrpc_err = JsonrpcError {
+            code: ErrorCode::InternalError,
+            message: "execution reverted".to_owned(),
+            data: Some(json!({
+                "message": "Error: VM Exception while processing transaction",
+                "data": format!("0x{}", hex::encode(revert::encode_panic(0x01.into()))),
+            })),
+        };
+        let err = get_encoded_error(&jsonrpc_err);
+
+        assert!(
+            matches!(err, Some(ExecutionError::Panic(revert::PanicCode::Assert))),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn execution_error_from_invalid_opcode() {
+        let jsonrpc_err = rpc_error("invalid opcode: INVALID", None);
+        let err = get_encoded_error(&jsonrpc_err);
+
+        assert!(
+            matches!(err, Some(ExecutionError::InvalidOpcode(_))),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+}
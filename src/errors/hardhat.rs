@@ -0,0 +1,331 @@
+//! This module implements Hardhat specific error decoding in order to try and
+//! provide more accurate errors from Hardhat nodes.
+
+use crate::errors::{geth, revert, ExecutionError};
+use jsonrpc_core::Error as JsonrpcError;
+
+/// VM exception error message prefix.
+const VM_EXCEPTION: &str = "VM Exception while processing transaction: ";
+/// Revert with reason error message discriminant.
+const REVERTED_WITH_REASON: &str = "reverted with reason string '";
+/// Transaction reverted error message prefix, used for reverts that are not
+/// reported as VM exceptions.
+const TRANSACTION_REVERTED: &str = "Transaction reverted";
+/// Revert without reason error message.
+const REVERTED_WITHOUT_REASON: &str = "Transaction reverted without a reason string";
+/// Invalid op-code error message.
+const INVALID: &str = "invalid opcode";
+/// Out of gas error message.
+const OUT_OF_GAS: &str = "Transaction ran out of gas";
+
+/// Tries to get a more accurate error from a generic Hardhat JSON RPC error.
+/// Returns `None` when a more accurate error cannot be determined.
+pub fn get_encoded_error(err: &JsonrpcError) -> Option<ExecutionError> {
+    let message = err.message.trim_start_matches("Error: ");
+    if message.starts_with(OUT_OF_GAS) {
+        return Some(ExecutionError::OutOfGas);
+    }
+    if message.starts_with(TRANSACTION_REVERTED) {
+        if let Some(data) = geth::get_revert_data(err).filter(|data| !data.is_empty()) {
+            return Some(revert::decode_revert_data(&data));
+        }
+        if message.starts_with(REVERTED_WITHOUT_REASON) {
+            return Some(ExecutionError::Revert(None, None));
+        }
+
+        // NOTE: Hardhat describes reverts it detects itself in the message,
+        //   for example calls to non-existent functions, so keep it as the
+        //   revert reason.
+        let reason = message
+            .trim_start_matches(TRANSACTION_REVERTED)
+            .trim_start_matches(':')
+            .trim();
+        return Some(ExecutionError::Revert(
+            Some(reason.to_owned()).filter(|reason| !reason.is_empty()),
+            None,
+        ));
+    }
+
+    let message = message
+        .find(VM_EXCEPTION)
+        .map(|index| &message[index + VM_EXCEPTION.len()..])?;
+    if message.starts_with(INVALID) {
+        let data = geth::get_revert_data(err).filter(|data| !data.is_empty());
+        return Some(ExecutionError::InvalidOpcode(data));
+    }
+    if !message.starts_with("reverted") {
+        return None;
+    }
+
+    // NOTE: The revert reason in the error message is used even if the revert
+    //   data is malformed, in which case the raw data is still kept.
+    let data = geth::get_revert_data(err).filter(|data| !data.is_empty());
+    if let Some(index) = message.find(REVERTED_WITH_REASON) {
+        let reason = &message[index + REVERTED_WITH_REASON.len()..];
+        return Some(ExecutionError::Revert(
+            Some(reason.trim_end_matches('\'').to_owned()),
+            data,
+        ));
+    }
+
+    Some(match data {
+        Some(data) => revert::decode_revert_data(&data),
+        None => ExecutionError::Revert(None, None),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use jsonrpc_core::ErrorCode;
+    use serde_json::Value;
+
+    const REASON_DATA: &str = "0x08c379a0\
+        0000000000000000000000000000000000000000000000000000000000000020\
+        0000000000000000000000000000000000000000000000000000000000000020\
+        4f776e61626c653a2063616c6c6572206973206e6f7420746865206f776e6572";
+
+    fn rpc_error(message: &str, data: Option<&str>) -> JsonrpcError {
+        let data = match data {
+            Some(data) => json!({ "message": message, "data": data }),
+            None => json!({ "message": message }),
+        };
+
+        JsonrpcError {
+            code: ErrorCode::InternalError,
+            message: message.to_owned(),
+            data: Some(data),
+        }
+    }
+
+    fn decode(message: &str, data: Option<&str>) -> Option<ExecutionError> {
+        get_encoded_error(&rpc_error(message, data))
+    }
+
+    #[test]
+    fn execution_error_from_revert_with_message() {
+        let err = decode(
+            "Error: VM Exception while processing transaction: reverted with reason \
+             string 'Ownable: caller is not the owner'",
+            Some(REASON_DATA),
+        );
+
+        assert!(
+            matches!(
+                &err,
+                Some(ExecutionError::Revert(Some(reason), _))
+                    if reason == "Ownable: caller is not the owner"
+            ),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn execution_error_from_revert_with_message_without_data() {
+        let err = decode(
+            "VM Exception while processing transaction: reverted with reason string 'message'",
+            None,
+        );
+
+        assert!(
+            matches!(&err, Some(ExecutionError::Revert(Some(reason), _)) if reason == "message"),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn execution_error_from_revert() {
+        for message in &[
+            "Error: Transaction reverted without a reason string",
+            "Error: Transaction reverted",
+        ] {
+            let err = decode(message, None);
+
+            assert!(
+                matches!(err, Some(ExecutionError::Revert(None, None))),
+                "bad error conversion {:?}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn execution_error_from_transaction_reverted_with_message() {
+        let err = decode(
+            "Error: Transaction reverted: function selector was not recognized and there's no \
+             fallback function",
+            None,
+        );
+
+        assert!(
+            matches!(
+                &err,
+                Some(ExecutionError::Revert(Some(reason), None))
+                    if reason == "function selector was not recognized and there's no \
+                                  fallback function"
+            ),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn execution_error_from_transaction_reverted_with_data() {
+        let err = decode(
+            "Error: Transaction reverted without a reason string",
+            Some(REASON_DATA),
+        );
+
+        assert!(
+            matches!(
+                &err,
+                Some(ExecutionError::Revert(Some(reason), Some(_)))
+                    if reason == "Ownable: caller is not the owner"
+            ),
+            "bad error conversion {:?}",
+            err
+        );
+
+        let err = decode(
+            "Error: Transaction reverted: library was called directly",
+            Some("0x4e487b710000000000000000000000000000000000000000000000000000000000000011"),
+        );
+
+        assert!(
+            matches!(
+                err,
+                Some(ExecutionError::Panic(revert::PanicCode::ArithmeticOverflow))
+            ),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn execution_error_from_custom_error() {
+        let err = decode(
+            "Error: VM Exception while processing transaction: reverted with custom error \
+             'InsufficientBalance(1, 2)'",
+            Some(
+                "0xcf479181\
+                 0000000000000000000000000000000000000000000000000000000000000001\
+                 0000000000000000000000000000000000000000000000000000000000000002",
+            ),
+        );
+
+        assert!(
+            matches!(
+                &err,
+                Some(ExecutionError::CustomError(data))
+                    if data.len() == 68 && data[..4] == [0xcf, 0x47, 0x91, 0x81]
+            ),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn execution_error_from_panic() {
+        let err = decode(
+            "Error: VM Exception while processing transaction: reverted with panic code 0x11 \
+             (Arithmetic operation underflowed or overflowed outside of an unchecked block)",
+            Some("0x4e487b710000000000000000000000000000000000000000000000000000000000000011"),
+        );
+
+        assert!(
+            matches!(
+                err,
+                Some(ExecutionError::Panic(revert::PanicCode::ArithmeticOverflow))
+            ),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn execution_error_from_invalid_opcode() {
+        let err = decode(
+            "Error: VM Exception while processing transaction: invalid opcode",
+            None,
+        );
+
+        assert!(
+            matches!(err, Some(ExecutionError::InvalidOpcode(_))),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn execution_error_from_out_of_gas() {
+        let err = decode("Error: Transaction ran out of gas", None);
+
+        assert!(
+            matches!(err, Some(ExecutionError::OutOfGas)),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn execution_error_from_malformed_data() {
+        let message = "Error: VM Exception while processing transaction: reverted with reason \
+                       string 'message'";
+        for data in &["0xnothex", "0x08c379a0", "0x08c379a00000"] {
+            let err = decode(message, Some(data));
+
+            assert!(
+                matches!(&err, Some(ExecutionError::Revert(Some(reason), _)) if reason == "message"),
+                "bad error conversion {:?}",
+                err
+            );
+        }
+
+        let err = get_encoded_error(&JsonrpcError {
+            code: ErrorCode::InternalError,
+            message: message.to_owned(),
+            data: Some(json!({ "message": message, "data": 42 })),
+        });
+        assert!(
+            matches!(&err, Some(ExecutionError::Revert(Some(reason), None)) if reason == "message"),
+            "bad error conversion {:?}",
+            err
+        );
+
+        // NOTE: Malformed data without a reason in the message is kept as raw
+        //   data for custom errors.
+        let err = decode(
+            "Error: VM Exception while processing transaction: reverted with custom error \
+             'InsufficientBalance(1, 2)'",
+            Some("0xcf4791"),
+        );
+        assert!(
+            matches!(&err, Some(ExecutionError::CustomError(data)) if data == &[0xcf, 0x47, 0x91]),
+            "bad error conversion {:?}",
+            err
+        );
+
+        let err = decode(
+            "Error: VM Exception while processing transaction: reverted with panic code 0x11",
+            Some("0xnothex"),
+        );
+        assert!(
+            matches!(err, Some(ExecutionError::Revert(None, None))),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn unrelated_error() {
+        let err = JsonrpcError {
+            code: ErrorCode::InvalidParams,
+            message: "Invalid params".to_owned(),
+            data: Some(Value::Null),
+        };
+        assert!(get_encoded_error(&err).is_none());
+    }
+}
@@ -5,25 +5,29 @@ use crate::errors::{revert, ExecutionError};
 use jsonrpc_core::Error as JsonrpcError;
 
 /// Revert error discriminant.
-const REVERTED: &str = "Reverted 0x";
+const REVERTED: &str = "Reverted";
 /// Invalid op-code error discriminant.
 const INVALID: &str = "Bad instruction";
+/// The JSON RPC error code for VM execution errors.
+const VM_EXECUTION_ERROR: i64 = -32015;
 
 /// Tries to get a more accurate error from a generic Parity JSON RPC error.
 /// Returns `None` when a more accurate error cannot be determined.
+///
+/// Parity and OpenEthereum return the revert data prefixed with `Reverted` in
+/// the error data, newer versions and Nethermind may also return the revert
+/// data as a plain hex string for VM execution errors.
 pub fn get_encoded_error(err: &JsonrpcError) -> Option<ExecutionError> {
     let message = get_error_message(err)?;
     if message.starts_with(REVERTED) {
-        let hex = &message[REVERTED.len()..];
-        if hex.is_empty() {
-            return Some(ExecutionError::Revert(None));
-        } else {
-            let bytes = hex::decode(&hex).ok()?;
-            let reason = revert::decode_reason(&bytes)?;
-            return Some(ExecutionError::Revert(Some(reason)));
-        }
+        let hex = message[REVERTED.len()..].trim();
+        let bytes = hex::decode(hex.trim_start_matches("0x")).ok()?;
+        return Some(revert::decode_revert_data(&bytes));
     } else if message.starts_with(INVALID) {
-        return Some(ExecutionError::InvalidOpcode);
+        return Some(ExecutionError::InvalidOpcode(None));
+    } else if err.code.code() == VM_EXECUTION_ERROR && message.starts_with("0x") {
+        let bytes = hex::decode(&message[2..]).ok()?;
+        return Some(revert::decode_revert_data(&bytes));
     }
 
     None
@@ -61,7 +65,7 @@ mod tests {
 
         assert!(
             match &err {
-                Some(ExecutionError::Revert(Some(reason))) if reason == "message" => true,
+                Some(ExecutionError::Revert(Some(reason), _)) if reason == "message" => true,
                 _ => false,
             },
             "bad error conversion {:?}",
@@ -76,7 +80,7 @@ mod tests {
 
         assert!(
             match &err {
-                Some(ExecutionError::Revert(None)) => true,
+                Some(ExecutionError::Revert(None, _)) => true,
                 _ => false,
             },
             "bad error conversion {:?}",
@@ -84,6 +88,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn execution_error_from_panic() {
+        let jsonrpc_err = rpc_error(&format!(
+            "Reverted 0x{}",
+            hex::encode(revert::encode_panic(0x12.into()))
+        ));
+        let err = get_encoded_error(&jsonrpc_err);
+
+        assert!(
+            matches!(
+                err,
+                Some(ExecutionError::Panic(revert::PanicCode::DivisionByZero))
+            ),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
     #[test]
     fn execution_error_from_invalid_opcode() {
         let jsonrpc_err = rpc_error("Bad instruction fd");
@@ -91,11 +113,50 @@ mod tests {
 
         assert!(
             match err {
-                Some(ExecutionError::InvalidOpcode) => true,
+                Some(ExecutionError::InvalidOpcode(_)) => true,
                 _ => false,
             },
             "bad error conversion {:?}",
             err
         );
     }
+
+    #[test]
+    fn execution_error_keeps_revert_data() {
+        let data = revert::encode_reason_hex("message");
+        let jsonrpc_err = rpc_error(&format!("Reverted {}", data));
+        let err = get_encoded_error(&jsonrpc_err).expect("unknown error");
+
+        assert_eq!(err.revert_data(), Some(revert::encode_reason("message")));
+    }
+
+    #[test]
+    fn execution_error_from_revert_without_data() {
+        let jsonrpc_err = rpc_error("Reverted");
+        let err = get_encoded_error(&jsonrpc_err);
+
+        assert!(
+            matches!(&err, Some(ExecutionError::Revert(None, None))),
+            "bad error conversion {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn execution_error_from_hex_data() {
+        let jsonrpc_err = rpc_error(&revert::encode_reason_hex("message"));
+        let err = get_encoded_error(&jsonrpc_err);
+
+        assert!(
+            matches!(&err, Some(ExecutionError::Revert(Some(reason), Some(_))) if reason == "message"),
+            "bad error conversion {:?}",
+            err
+        );
+
+        let jsonrpc_err = JsonrpcError {
+            code: ErrorCode::InternalError,
+            ..rpc_error(&revert::encode_reason_hex("message"))
+        };
+        assert!(get_encoded_error(&jsonrpc_err).is_none());
+    }
 }
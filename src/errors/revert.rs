@@ -1,12 +1,114 @@
-//! Module implements decoding ABI encoded revert reasons.
+//! Module implements decoding ABI encoded revert reasons, panic codes and
+//! custom errors.
 
+use crate::errors::ExecutionError;
+use crate::tokens::Tokenizable;
 use ethcontract_common::abi::{self, ParamType};
 use ethcontract_common::hash::{self, H32};
 use lazy_static::lazy_static;
+use std::fmt::{self, Display, Formatter};
+use web3::types::U256;
 
 lazy_static! {
     /// The ABI function selector for identifying encoded revert reasons.
     static ref ERROR_SELECTOR: H32 = hash::function_selector("Error(string)");
+
+    /// The ABI function selector for identifying encoded panic codes.
+    static ref PANIC_SELECTOR: H32 = hash::function_selector("Panic(uint256)");
+}
+
+/// A Solidity panic code. Contracts compiled with Solidity 0.8 and later
+/// revert with an ABI encoded `Panic(uint256)` on failed assertions, arithmetic
+/// errors and other internal errors instead of executing an invalid opcode.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PanicCode {
+    /// Generic compiler inserted panic (`0x00`).
+    Generic,
+    /// An `assert` failed (`0x01`).
+    Assert,
+    /// An arithmetic operation overflowed or underflowed outside of an
+    /// `unchecked` block (`0x11`).
+    ArithmeticOverflow,
+    /// Division or modulo by zero (`0x12`).
+    DivisionByZero,
+    /// A value that is too big or negative was converted into an enum type
+    /// (`0x21`).
+    InvalidEnumValue,
+    /// An incorrectly encoded storage byte array was accessed (`0x22`).
+    InvalidStorageByteArray,
+    /// `.pop()` was called on an empty array (`0x31`).
+    EmptyArrayPop,
+    /// An array, `bytesN` or array slice was accessed at an out-of-bounds or
+    /// negative index (`0x32`).
+    IndexOutOfBounds,
+    /// Too much memory was allocated or an array that is too large was
+    /// created (`0x41`).
+    OutOfMemory,
+    /// A zero-initialized variable of internal function type was called
+    /// (`0x51`).
+    UninitializedFunction,
+    /// A panic code that is not known.
+    Unknown(U256),
+}
+
+impl PanicCode {
+    /// Creates a panic code from its numeric value.
+    pub fn from_code(code: U256) -> Self {
+        if code > U256::from(0xff) {
+            return PanicCode::Unknown(code);
+        }
+
+        match code.low_u32() {
+            0x00 => PanicCode::Generic,
+            0x01 => PanicCode::Assert,
+            0x11 => PanicCode::ArithmeticOverflow,
+            0x12 => PanicCode::DivisionByZero,
+            0x21 => PanicCode::InvalidEnumValue,
+            0x22 => PanicCode::InvalidStorageByteArray,
+            0x31 => PanicCode::EmptyArrayPop,
+            0x32 => PanicCode::IndexOutOfBounds,
+            0x41 => PanicCode::OutOfMemory,
+            0x51 => PanicCode::UninitializedFunction,
+            _ => PanicCode::Unknown(code),
+        }
+    }
+
+    /// Returns the numeric value of the panic code.
+    pub fn code(&self) -> U256 {
+        let code: u64 = match self {
+            PanicCode::Generic => 0x00,
+            PanicCode::Assert => 0x01,
+            PanicCode::ArithmeticOverflow => 0x11,
+            PanicCode::DivisionByZero => 0x12,
+            PanicCode::InvalidEnumValue => 0x21,
+            PanicCode::InvalidStorageByteArray => 0x22,
+            PanicCode::EmptyArrayPop => 0x31,
+            PanicCode::IndexOutOfBounds => 0x32,
+            PanicCode::OutOfMemory => 0x41,
+            PanicCode::UninitializedFunction => 0x51,
+            PanicCode::Unknown(code) => return *code,
+        };
+        code.into()
+    }
+}
+
+impl Display for PanicCode {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let description = match self {
+            PanicCode::Generic => "generic panic",
+            PanicCode::Assert => "assertion failed",
+            PanicCode::ArithmeticOverflow => "arithmetic overflow or underflow",
+            PanicCode::DivisionByZero => "division or modulo by zero",
+            PanicCode::InvalidEnumValue => "invalid enum value",
+            PanicCode::InvalidStorageByteArray => "invalid storage byte array",
+            PanicCode::EmptyArrayPop => "pop on empty array",
+            PanicCode::IndexOutOfBounds => "index out of bounds",
+            PanicCode::OutOfMemory => "out of memory",
+            PanicCode::UninitializedFunction => "call to uninitialized function",
+            PanicCode::Unknown(_) => "unknown panic",
+        };
+        write!(f, "{} (0x{:02x})", description, self.code())
+    }
 }
 
 /// Decodes an ABI encoded revert reason. Returns `Some(reason)` when the ABI
@@ -32,27 +134,73 @@ pub fn decode_reason(bytes: &[u8]) -> Option<String> {
     Some(reason)
 }
 
+/// Decodes an ABI encoded panic code. Returns `Some(code)` when the ABI encoded
+/// bytes represent a `Panic(uint256)` and `None` otherwise.
+pub fn decode_panic(bytes: &[u8]) -> Option<PanicCode> {
+    if bytes.len() != 36 || bytes[0..4] != PANIC_SELECTOR[..] {
+        return None;
+    }
+
+    Some(PanicCode::from_code(U256::from_big_endian(&bytes[4..])))
+}
+
+/// Decodes the raw data of a reverted contract call into an execution error.
+///
+/// Empty data is a revert without a message, revert reasons and panic codes are
+/// decoded and any other data is assumed to be a Solidity custom error. The raw
+/// data is kept for revert reasons so that it can be decoded against any ABI.
+pub fn decode_revert_data(bytes: &[u8]) -> ExecutionError {
+    if bytes.is_empty() {
+        ExecutionError::Revert(None, None)
+    } else if let Some(reason) = decode_reason(bytes) {
+        ExecutionError::Revert(Some(reason), Some(bytes.to_vec()))
+    } else if let Some(code) = decode_panic(bytes) {
+        ExecutionError::Panic(code)
+    } else {
+        ExecutionError::CustomError(bytes.to_vec())
+    }
+}
+
+/// Decodes revert data that older Geth nodes return as the successful result
+/// of a reverting `eth_call`. Returns `None` if the result is not revert data.
+///
+/// Revert reasons and panic codes are identified by their selectors. ABI
+/// encoded return data is always a multiple of 32 bytes long, so any result
+/// that is 4 bytes longer than that is assumed to be a Solidity custom error.
+/// This does not hold for contract deployments, which return the deployed
+/// code, so only revert reasons and panic codes are decoded for those.
+pub fn decode_call_result(bytes: &[u8], deployment: bool) -> Option<ExecutionError> {
+    if let Some(reason) = decode_reason(bytes) {
+        Some(ExecutionError::Revert(Some(reason), Some(bytes.to_vec())))
+    } else if let Some(code) = decode_panic(bytes) {
+        Some(ExecutionError::Panic(code))
+    } else if !deployment && bytes.len() % 32 == 4 {
+        Some(ExecutionError::CustomError(bytes.to_vec()))
+    } else {
+        None
+    }
+}
+
+/// ABI encodes a panic code. This is the inverse of `decode_panic`.
+pub fn encode_panic(code: U256) -> Vec<u8> {
+    let mut encoded = PANIC_SELECTOR.to_vec();
+    encoded.extend_from_slice(&abi::encode(&[code.into_token()]));
+    encoded
+}
+
 #[cfg(test)]
 pub use tests::*;
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use ethcontract_common::abi::{Function, Param, Token};
+    use ethcontract_common::abi::Token;
 
+    /// ABI encodes a revert reason. This is the inverse of `decode_reason`.
     pub fn encode_reason(reason: &str) -> Vec<u8> {
-        let revert = Function {
-            name: "Error".into(),
-            inputs: vec![Param {
-                name: "".into(),
-                kind: ParamType::String,
-            }],
-            outputs: Vec::new(),
-            constant: true,
-        };
-        revert
-            .encode_input(&[Token::String(reason.into())])
-            .expect("error encoding revert reason")
+        let mut encoded = ERROR_SELECTOR.to_vec();
+        encoded.extend_from_slice(&abi::encode(&[Token::String(reason.into())]));
+        encoded
     }
 
     pub fn encode_reason_hex(reason: &str) -> String {
@@ -67,4 +215,48 @@ mod tests {
 
         assert_eq!(decode_reason(&encoded).as_deref(), Some(reason));
     }
+
+    #[test]
+    fn decode_panic_code() {
+        assert_eq!(
+            decode_panic(&encode_panic(0x11.into())),
+            Some(PanicCode::ArithmeticOverflow)
+        );
+        assert_eq!(
+            decode_panic(&encode_panic(0x12.into())),
+            Some(PanicCode::DivisionByZero)
+        );
+        assert_eq!(
+            decode_panic(&encode_panic(0x42.into())),
+            Some(PanicCode::Unknown(0x42.into()))
+        );
+        assert_eq!(decode_panic(&encode_reason("message")), None);
+
+        for &code in &[
+            0x00, 0x01, 0x11, 0x12, 0x21, 0x22, 0x31, 0x32, 0x41, 0x51, 0x99,
+        ] {
+            let code = U256::from(code);
+            assert_eq!(PanicCode::from_code(code).code(), code);
+        }
+    }
+
+    #[test]
+    fn decode_revert_data_kinds() {
+        assert!(matches!(
+            decode_revert_data(&[]),
+            ExecutionError::Revert(None, _)
+        ));
+        assert!(matches!(
+            decode_revert_data(&encode_reason("message")),
+            ExecutionError::Revert(Some(reason), _) if reason == "message"
+        ));
+        assert!(matches!(
+            decode_revert_data(&encode_panic(0x01.into())),
+            ExecutionError::Panic(PanicCode::Assert)
+        ));
+        assert!(matches!(
+            decode_revert_data(&[0xde, 0xad, 0xbe, 0xef]),
+            ExecutionError::CustomError(data) if data == [0xde, 0xad, 0xbe, 0xef]
+        ));
+    }
 }
@@ -9,7 +9,6 @@ use std::iter;
 use std::ops;
 use std::str;
 use std::{i128, i64, u64};
-use web3::contract::{self, tokens};
 use web3::types::U256;
 
 /// Compute the two's complement of a U256.
@@ -1018,26 +1017,14 @@ impl iter::Product for I256 {
     }
 }
 
-impl tokens::Tokenizable for I256 {
-    fn from_token(token: ethabi_9_0::Token) -> Result<Self, contract::Error> {
-        // NOTE: U256 accepts both `Int` and `Uint` kind tokens. In fact, all
-        //   integer types are expected to accept both.
-        Ok(I256(U256::from_token(token)?))
-    }
-
-    fn into_token(self) -> ethabi_9_0::Token {
-        ethabi_9_0::Token::Int(self.0)
-    }
-}
-
-impl tokens::TokenizableItem for I256 {}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::abicompat::AbiCompat;
+    use crate::tokens::Tokenizable;
+    use ethcontract_common::abi::Token;
     use lazy_static::lazy_static;
     use serde_json::json;
-    use web3::contract::tokens::Tokenizable;
 
     lazy_static! {
         static ref MIN_ABS: U256 = U256::from(1) << 255;
@@ -1394,7 +1381,7 @@ mod tests {
         assert_eq!(I256::from(42).into_token(), 42i32.into_token());
         assert_eq!(
             I256::minus_one().into_token(),
-            ethabi_9_0::Token::Int(U256::MAX),
+            Token::Int(U256::MAX.compat()),
         );
 
         assert_eq!(
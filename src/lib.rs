@@ -102,6 +102,8 @@ mod int;
 pub mod log;
 pub mod secret;
 pub mod sign;
+pub mod tokens;
+pub mod trace;
 pub mod transaction;
 pub mod transport;
 
@@ -2,16 +2,23 @@
 //! emitted by a contract.
 
 use crate::errors::ExecutionError;
+use crate::future::{CompatCallFuture, MaybeReady};
+use crate::transaction::confirm::BlockId;
 use futures::compat::{Compat01As03, Future01CompatExt, Stream01CompatExt};
 use futures::ready;
 use futures::stream::Stream;
+use futures_timer::Delay;
 use pin_project::{pin_project, project};
+use serde::Deserialize;
+use std::collections::VecDeque;
 use std::future::Future;
+use std::mem;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use std::time::Duration;
-use web3::api::{CreateFilter, FilterStream, Web3};
-use web3::types::{Filter, Log};
+use web3::api::{BaseFilter, CreateFilter, FilterStream, Web3};
+use web3::helpers::{self, CallFuture};
+use web3::types::{Address, BlockNumber, Filter, FilterBuilder, Log, H256, U256, U64};
 use web3::Transport;
 
 /// A log stream that emits logs matching a certain filter.
@@ -74,9 +81,856 @@ impl<T: Transport> Stream for LogStream<T> {
     }
 }
 
+/// A log stream that emits logs matching a log filter whose contract
+/// addresses can be replaced while streaming.
+///
+/// Replacing the addresses installs a new log filter once all logs retrieved
+/// with the current filter have been emitted. The replaced filter is then
+/// polled one last time, so that no logs are missed for the addresses that
+/// were already part of it, and the logs from that final poll are not emitted
+/// again by the new filter. Finally, the replaced filter is uninstalled.
+#[must_use = "streams do nothing unless you poll them"]
+#[pin_project]
+pub struct AddressLogStream<T: Transport> {
+    web3: Web3<T>,
+    /// The log filter, the addresses are set for each installed filter.
+    filter: FilterBuilder,
+    poll_interval: Duration,
+    /// The addresses to install a new filter for once all pending logs have
+    /// been emitted.
+    next_addresses: Option<Vec<Address>>,
+    /// Logs that are waiting to be emitted.
+    pending: VecDeque<Log>,
+    /// The logs emitted by the final poll of a replaced filter, which can be
+    /// returned again by the first poll of the filter replacing it.
+    drained: Vec<LogId>,
+    #[pin]
+    state: AddressLogState<T>,
+}
+
+/// The state of the address log stream.
+#[pin_project]
+enum AddressLogState<T: Transport> {
+    /// The stream is installing a log filter for the specified addresses,
+    /// along with the filter it is replacing if there is one.
+    CreatingFilter(
+        #[pin] CompatCreateFilter<T, Log>,
+        Vec<Address>,
+        Option<BaseFilter<T, Log>>,
+    ),
+    /// The stream is retrieving the changes of a replaced filter one last
+    /// time, after the filter replacing it was installed.
+    Draining(
+        #[pin] CompatFilterChanges<T>,
+        BaseFilter<T, Log>,
+        BaseFilter<T, Log>,
+    ),
+    /// The stream is uninstalling a replaced filter after draining it.
+    Uninstalling(#[pin] CompatCallFuture<T, bool>, BaseFilter<T, Log>),
+    /// The stream is retrieving the changes of the installed filter.
+    Polling(#[pin] CompatFilterChanges<T>, BaseFilter<T, Log>),
+    /// The stream is waiting to poll the installed filter again.
+    Waiting(#[pin] MaybeReady<Delay>, BaseFilter<T, Log>),
+}
+
+/// The block hash, log index and removed flag that identify a log.
+type LogId = (Option<H256>, Option<U256>, Option<bool>);
+
+impl<T: Transport> AddressLogStream<T> {
+    /// Create a new address log stream from a given web3 provider, filter,
+    /// addresses and polling interval.
+    pub fn new(
+        web3: Web3<T>,
+        filter: FilterBuilder,
+        addresses: Vec<Address>,
+        poll_interval: Duration,
+    ) -> Self {
+        let create_filter = create_filter(&web3, &filter, addresses.clone());
+        AddressLogStream {
+            web3,
+            filter,
+            poll_interval,
+            next_addresses: None,
+            pending: VecDeque::new(),
+            drained: Vec::new(),
+            state: AddressLogState::CreatingFilter(create_filter, addresses, None),
+        }
+    }
+
+    /// Replaces the addresses of the log filter. The new filter is installed
+    /// once all logs retrieved with the current filter have been emitted.
+    pub fn set_addresses(self: Pin<&mut Self>, addresses: Vec<Address>) {
+        *self.project().next_addresses = Some(addresses);
+    }
+}
+
+impl<T: Transport> Stream for AddressLogStream<T> {
+    type Item = Result<Log, ExecutionError>;
+
+    #[project]
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        loop {
+            let mut this = self.as_mut().project();
+            if let Some(log) = this.pending.pop_front() {
+                return Poll::Ready(Some(Ok(log)));
+            }
+
+            #[project]
+            let next_state = match this.state.as_mut().project() {
+                AddressLogState::CreatingFilter(create, addresses, replaced) => {
+                    let log_filter = match ready!(create.poll(cx)) {
+                        Ok(log_filter) => log_filter,
+                        Err(err) => {
+                            // NOTE: Install the filter again if the stream is
+                            //   polled once more after the error.
+                            let create = create_filter(this.web3, this.filter, addresses.clone());
+                            let addresses = mem::take(addresses);
+                            let replaced = replaced.take();
+                            this.state
+                                .set(AddressLogState::CreatingFilter(create, addresses, replaced));
+                            return Poll::Ready(Some(Err(err.into())));
+                        }
+                    };
+                    match replaced.take() {
+                        Some(replaced) => AddressLogState::Draining(
+                            replaced.poll().compat(),
+                            replaced,
+                            log_filter,
+                        ),
+                        None => AddressLogState::Polling(log_filter.poll().compat(), log_filter),
+                    }
+                }
+                AddressLogState::Draining(changes, replaced, log_filter) => {
+                    let result = ready!(changes.poll(cx));
+                    let next_state = AddressLogState::Uninstalling(
+                        replaced.clone().uninstall().compat(),
+                        log_filter.clone(),
+                    );
+                    match result {
+                        Ok(logs) => {
+                            let logs = logs.unwrap_or_default();
+                            *this.drained = logs.iter().map(log_id).collect();
+                            this.pending.extend(logs);
+                        }
+                        Err(err) => {
+                            this.state.set(next_state);
+                            return Poll::Ready(Some(Err(err.into())));
+                        }
+                    }
+                    next_state
+                }
+                AddressLogState::Uninstalling(uninstall, log_filter) => {
+                    // NOTE: Errors uninstalling the replaced filter are
+                    //   ignored, nodes eventually remove filters that are no
+                    //   longer polled on their own.
+                    let _ = ready!(uninstall.poll(cx));
+                    AddressLogState::Polling(log_filter.poll().compat(), log_filter.clone())
+                }
+                AddressLogState::Polling(changes, log_filter) => {
+                    let result = ready!(changes.poll(cx));
+                    let next_state =
+                        AddressLogState::Waiting(delay(*this.poll_interval), log_filter.clone());
+                    match result {
+                        Ok(logs) => {
+                            let drained = mem::take(this.drained);
+                            this.pending.extend(
+                                logs.unwrap_or_default()
+                                    .into_iter()
+                                    .filter(|log| !drained.contains(&log_id(log))),
+                            );
+                        }
+                        Err(err) => {
+                            this.state.set(next_state);
+                            return Poll::Ready(Some(Err(err.into())));
+                        }
+                    }
+                    next_state
+                }
+                AddressLogState::Waiting(delay, log_filter) => {
+                    if let Some(addresses) = this.next_addresses.take() {
+                        let create = create_filter(this.web3, this.filter, addresses.clone());
+                        AddressLogState::CreatingFilter(create, addresses, Some(log_filter.clone()))
+                    } else {
+                        ready!(delay.poll(cx));
+                        AddressLogState::Polling(log_filter.poll().compat(), log_filter.clone())
+                    }
+                }
+            };
+
+            this.state.set(next_state);
+        }
+    }
+}
+
+/// Installs a log filter for the specified addresses.
+fn create_filter<T: Transport>(
+    web3: &Web3<T>,
+    filter: &FilterBuilder,
+    addresses: Vec<Address>,
+) -> CompatCreateFilter<T, Log> {
+    let filter = filter.clone().address(addresses).build();
+    web3.eth_filter().create_logs_filter(filter).compat()
+}
+
+/// Returns the values that identify a log.
+fn log_id(log: &Log) -> LogId {
+    (log.block_hash, log.log_index, log.removed)
+}
+
+/// The number of logs below which a page of logs is considered sparse, in
+/// which case the page size is grown again after it was reduced.
+const SPARSE_PAGE_LOG_COUNT: usize = 1000;
+
+/// A stream of pages of logs for a block range, retrieved with `eth_getLogs`
+/// queries for consecutive block ranges of at most the page size. Multiple
+/// pages can be queried concurrently, in which case they are still emitted in
+/// block order.
+///
+/// The page size adapts to the number of logs in the queried range: if the
+/// node rejects a query because it would return too many results, the page
+/// is split in half and retried, while the page size is grown again up to its
+/// initial size when pages are sparse. Other errors are emitted by the stream
+/// and polling it again retries the failed page.
+#[must_use = "streams do nothing unless you poll them"]
+#[pin_project]
+pub struct LogPageStream<T: Transport> {
+    web3: Web3<T>,
+    /// The log filter, the block range is set for each page.
+    filter: FilterBuilder,
+    /// The first block that is not yet part of a queued page.
+    next_block: u64,
+    /// The last block to retrieve logs for.
+    to_block: u64,
+    /// Whether to keep the end of the block range of the filter for the last
+    /// page instead of `to_block`.
+    extend_last_page: bool,
+    /// The current page size.
+    page_size: u64,
+    /// The maximum page size.
+    max_page_size: u64,
+    /// The maximum number of pages to query concurrently.
+    concurrency: usize,
+    /// The queued pages in block order.
+    pages: VecDeque<LogPage<T>>,
+    /// The last block of the most recently emitted page.
+    last_block: Option<u64>,
+}
+
+/// A pending `eth_getLogs` query for a page of logs.
+type LogQuery<T> = Pin<Box<CompatCallFuture<T, Vec<Log>>>>;
+
+/// A page of logs that is queued for retrieval.
+struct LogPage<T: Transport> {
+    /// The first block of the page.
+    from_block: u64,
+    /// The last block of the page.
+    to_block: u64,
+    /// The pending query for the logs of the page, if it was started.
+    query: Option<LogQuery<T>>,
+    /// The result of the query, if it has completed.
+    result: Option<Result<Vec<Log>, web3::Error>>,
+}
+
+impl<T: Transport> LogPage<T> {
+    /// Creates a new page that is not yet queried.
+    fn new(from_block: u64, to_block: u64) -> Self {
+        LogPage {
+            from_block,
+            to_block,
+            query: None,
+            result: None,
+        }
+    }
+}
+
+impl<T: Transport> LogPageStream<T> {
+    /// Create a new log page stream for the logs matching a filter between the
+    /// specified blocks (inclusive) with an initial page size.
+    pub fn new(
+        web3: Web3<T>,
+        filter: FilterBuilder,
+        from_block: u64,
+        to_block: u64,
+        page_size: u64,
+    ) -> Self {
+        let page_size = page_size.max(1);
+        LogPageStream {
+            web3,
+            filter,
+            next_block: from_block,
+            to_block,
+            extend_last_page: false,
+            page_size,
+            max_page_size: page_size,
+            concurrency: 1,
+            pages: VecDeque::new(),
+            last_block: None,
+        }
+    }
+
+    /// Query the last page up to the end of the block range of the filter
+    /// instead of the stream's last block. This is useful when the filter is
+    /// for the latest or pending block, so that the last page also includes
+    /// logs from blocks that were mined since the stream was created.
+    pub fn extend_last_page(mut self) -> Self {
+        self.extend_last_page = true;
+        self
+    }
+
+    /// Sets the maximum number of pages to query concurrently. Defaults to 1,
+    /// which queries pages one after the other.
+    pub fn concurrency(mut self, value: usize) -> Self {
+        self.concurrency = value.max(1);
+        self
+    }
+
+    /// Returns the last block of the most recently emitted page, or `None` if
+    /// no page has been emitted yet.
+    pub fn last_block(&self) -> Option<u64> {
+        self.last_block
+    }
+}
+
+impl<T: Transport> Stream for LogPageStream<T> {
+    type Item = Result<Vec<Log>, ExecutionError>;
+
+    #[project]
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        #[project]
+        let LogPageStream {
+            web3,
+            filter,
+            next_block,
+            to_block,
+            extend_last_page,
+            page_size,
+            max_page_size,
+            concurrency,
+            pages,
+            last_block,
+        } = self.project();
+
+        loop {
+            // NOTE: Queue new pages until there are enough to saturate the
+            //   concurrency limit, and start the queries for the pages that
+            //   are within it.
+            while pages.len() < *concurrency && *next_block <= *to_block {
+                let page_end = (*to_block).min(next_block.saturating_add(*page_size - 1));
+                pages.push_back(LogPage::new(*next_block, page_end));
+                *next_block = page_end.saturating_add(1);
+                if page_end == *to_block {
+                    break;
+                }
+            }
+            for page in pages.iter_mut().take(*concurrency) {
+                if page.query.is_none() && page.result.is_none() {
+                    let query = if page.to_block == *to_block && *extend_last_page {
+                        filter.clone().from_block(page.from_block.into())
+                    } else {
+                        filter
+                            .clone()
+                            .from_block(page.from_block.into())
+                            .to_block(page.to_block.into())
+                    };
+                    page.query = Some(Box::pin(web3.eth().logs(query.build()).compat()));
+                }
+                if let Some(query) = page.query.as_mut() {
+                    if let Poll::Ready(result) = query.as_mut().poll(cx) {
+                        page.query = None;
+                        page.result = Some(result);
+                    }
+                }
+            }
+
+            let (from_block, page_end, result) = match pages.front_mut() {
+                Some(page) => match page.result.take() {
+                    Some(result) => (page.from_block, page.to_block, result),
+                    None => return Poll::Pending,
+                },
+                None => return Poll::Ready(None),
+            };
+
+            match result {
+                Ok(logs) => {
+                    if logs.len() < SPARSE_PAGE_LOG_COUNT {
+                        *page_size = page_size.saturating_mul(2).min(*max_page_size);
+                    }
+                    pages.pop_front();
+                    *last_block = Some(page_end);
+                    return Poll::Ready(Some(Ok(logs)));
+                }
+                Err(err) => {
+                    let err = ExecutionError::from(err);
+                    let range = page_end - from_block + 1;
+                    if range > 1 && is_too_many_results(&err) {
+                        // NOTE: Split the page in half and query both halves
+                        //   in its place. Pages that were already queued keep
+                        //   their range and get split if they also fail.
+                        let half = range / 2;
+                        *page_size = half;
+                        pages.pop_front();
+                        pages.push_front(LogPage::new(from_block + half, page_end));
+                        pages.push_front(LogPage::new(from_block, from_block + half - 1));
+                        continue;
+                    }
+
+                    // NOTE: Leave the failed page at the front of the queue so
+                    //   that it gets queried again when polled.
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
+        }
+    }
+}
+
+/// Returns true if the error indicates that a log query was rejected by the
+/// node because the block range contains too many logs, or is too large.
+/// Since there is no standard error code for this, the error message is
+/// checked against the ones used by popular nodes and providers.
+fn is_too_many_results(err: &ExecutionError) -> bool {
+    const MESSAGES: &[&str] = &[
+        "query returned more than",
+        "more than 10000 results",
+        "response size exceeded",
+        "response size should not greater than",
+        "log response size exceeded",
+        "block range is too wide",
+        "block range too large",
+        "exceed maximum block range",
+        "query timeout exceeded",
+        "too many logs",
+        "too many results",
+    ];
+
+    match err {
+        ExecutionError::Web3(web3::Error::Rpc(err)) => {
+            let message = err.message.to_lowercase();
+            MESSAGES.iter().any(|pattern| message.contains(pattern))
+        }
+        _ => false,
+    }
+}
+
+/// The default number of recent blocks a `BlockLogStream` keeps track of for
+/// detecting chain reorganizations.
+pub const DEFAULT_REORG_WINDOW: usize = 64;
+
+/// Parameters for a block log stream.
+#[derive(Clone, Debug)]
+pub struct BlockLogParams {
+    /// The first block to retrieve logs for. If neither this nor a checkpoint
+    /// is specified, the stream starts at the current block.
+    pub from_block: Option<u64>,
+    /// The last block to retrieve logs for, the stream ends once all logs up
+    /// to and including this block have been emitted. If left unset, the
+    /// stream never ends.
+    pub to_block: Option<u64>,
+    /// A checkpoint to resume the stream from. The stream continues with the
+    /// block following the checkpoint, verifying that the checkpoint block is
+    /// still part of the chain. This takes precedence over `from_block`.
+    pub checkpoint: Option<BlockId>,
+    /// The interval to wait for before checking for a new block.
+    pub poll_interval: Duration,
+    /// The number of recent blocks, along with their logs, to keep track of
+    /// for detecting chain reorganizations. Reorganizations that are deeper
+    /// than this window cause the stream to fail.
+    pub reorg_window: usize,
+    /// The page size in blocks for retrieving past logs. If set, the logs of
+    /// blocks that are older than the reorganization window are first queried
+    /// in pages starting from `from_block` (or the earliest block if it is not
+    /// specified), after which the stream continues block by block with the
+    /// following block. Note that no checkpoint is available while past logs
+    /// are being emitted, only the block number of the last past page that had
+    /// all its logs emitted. This parameter is ignored when resuming from a
+    /// checkpoint.
+    pub past_page_size: Option<u64>,
+    /// The maximum number of pages of past logs to query concurrently.
+    pub past_page_concurrency: usize,
+}
+
+/// A log stream that retrieves logs block by block with `eth_getLogs` instead
+/// of relying on node log filters. The stream keeps track of the hashes of
+/// recent blocks in order to detect chain reorganizations, in which case the
+/// logs from orphaned blocks are emitted again marked as removed.
+///
+/// The stream provides a checkpoint of the last block that had all its logs
+/// emitted, which can be persisted in order to resume the stream without
+/// missing or duplicating any logs. If the checkpoint block was orphaned
+/// while the stream was not running, the resumed stream fails with an
+/// `ExecutionError::ReorgTooDeep` error, see its documentation for how to
+/// recover from it.
+#[must_use = "streams do nothing unless you poll them"]
+#[pin_project]
+pub struct BlockLogStream<T: Transport> {
+    web3: Web3<T>,
+    /// The log filter, the block range is set for each block.
+    filter: FilterBuilder,
+    params: BlockLogParams,
+    /// The recent blocks along with their logs, oldest first.
+    window: VecDeque<(BlockId, Vec<Log>)>,
+    /// The next block to retrieve when no block is being tracked yet.
+    next_block: Option<u64>,
+    /// Logs that are waiting to be emitted.
+    pending: VecDeque<Log>,
+    /// The checkpoint to commit once all pending logs have been emitted.
+    pending_checkpoint: Option<BlockId>,
+    /// The last block that had all its logs emitted.
+    checkpoint: Option<BlockId>,
+    /// The last block of the past logs page to commit once all pending logs
+    /// have been emitted.
+    pending_past_block: Option<u64>,
+    /// The last block of the last past logs page that had all its logs
+    /// emitted.
+    past_block: Option<u64>,
+    /// The page size for querying past logs, this is cleared once the past
+    /// logs query has started.
+    past_page_size: Option<u64>,
+    #[pin]
+    state: BlockLogState<T>,
+}
+
+/// The state of the block log stream.
+#[pin_project]
+enum BlockLogState<T: Transport> {
+    /// The stream needs to determine the next block to retrieve.
+    Idle,
+    /// The stream is retrieving the current block number to start from.
+    BlockNumber(#[pin] CompatCallFuture<T, U64>),
+    /// The stream is retrieving the current block number to determine the
+    /// range of past logs to query.
+    PastBlockNumber(#[pin] CompatCallFuture<T, U64>, u64),
+    /// The stream is retrieving pages of past logs.
+    PastLogs(#[pin] LogPageStream<T>),
+    /// The stream is retrieving the header of the next block.
+    Block(#[pin] CompatCallFuture<T, Option<BlockHeader>>, u64),
+    /// The stream is retrieving the logs of the next block.
+    Logs(#[pin] CompatCallFuture<T, Vec<Log>>, BlockId),
+    /// The stream is waiting for the next block to be mined.
+    Waiting(#[pin] MaybeReady<Delay>),
+}
+
+impl<T: Transport> BlockLogStream<T> {
+    /// Create a new block log stream from a given web3 provider, filter and
+    /// stream parameters.
+    pub fn new(web3: Web3<T>, filter: FilterBuilder, params: BlockLogParams) -> Self {
+        let mut window = VecDeque::new();
+        let mut next_block = params.from_block;
+        let mut past_page_size = params.past_page_size;
+        if let Some(checkpoint) = params.checkpoint {
+            window.push_back((checkpoint, Vec::new()));
+            next_block = None;
+            past_page_size = None;
+        }
+
+        BlockLogStream {
+            web3,
+            filter,
+            checkpoint: params.checkpoint,
+            params,
+            window,
+            next_block,
+            pending: VecDeque::new(),
+            pending_checkpoint: None,
+            pending_past_block: None,
+            past_block: None,
+            past_page_size,
+            state: BlockLogState::Idle,
+        }
+    }
+
+    /// Returns the last block that had all its logs emitted by the stream.
+    /// Resuming a stream from this checkpoint continues with the next log.
+    pub fn checkpoint(&self) -> Option<BlockId> {
+        self.checkpoint
+    }
+
+    /// Returns the last block of the past logs query that had all its logs
+    /// emitted, updated after each page. This can be used for tracking the
+    /// progress of the past logs query, for which no checkpoint is available.
+    ///
+    /// Since past logs are only queried for blocks older than the
+    /// reorganization window, resuming a stream with `from_block` set to the
+    /// following block continues the past logs query without missing or
+    /// duplicating any logs.
+    pub fn past_block(&self) -> Option<u64> {
+        self.past_block
+    }
+}
+
+impl<T: Transport> Stream for BlockLogStream<T> {
+    type Item = Result<Log, ExecutionError>;
+
+    #[project]
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        loop {
+            #[project]
+            let BlockLogStream {
+                web3,
+                filter,
+                params,
+                window,
+                next_block,
+                pending,
+                pending_checkpoint,
+                checkpoint,
+                pending_past_block,
+                past_block,
+                past_page_size,
+                state,
+            } = self.as_mut().project();
+
+            if let Some(log) = pending.pop_front() {
+                if pending.is_empty() {
+                    *checkpoint = pending_checkpoint.take();
+                    if let Some(block) = pending_past_block.take() {
+                        *past_block = Some(block);
+                    }
+                }
+                return Poll::Ready(Some(Ok(log)));
+            }
+
+            // NOTE: Errors reset the stream to the idle state, so that polling
+            //   it again retries the failed request.
+            let mut error = None;
+
+            #[project]
+            let next_state = match state.project() {
+                BlockLogState::Idle if past_page_size.is_some() => {
+                    let page_size = past_page_size.take().unwrap_or_default();
+                    BlockLogState::PastBlockNumber(web3.eth().block_number().compat(), page_size)
+                }
+                BlockLogState::Idle => {
+                    let number = window
+                        .back()
+                        .map(|(head, _)| head.number.as_u64() + 1)
+                        .or(*next_block);
+
+                    match number {
+                        Some(number) if matches!(params.to_block, Some(to) if number > to) => {
+                            return Poll::Ready(None);
+                        }
+                        Some(number) => BlockLogState::Block(fetch_block(web3, number), number),
+                        None => BlockLogState::BlockNumber(web3.eth().block_number().compat()),
+                    }
+                }
+                BlockLogState::BlockNumber(block_number) => {
+                    match ready!(block_number.poll(cx)) {
+                        Ok(number) => *next_block = Some(number.as_u64()),
+                        Err(err) => error = Some(err.into()),
+                    }
+                    BlockLogState::Idle
+                }
+                BlockLogState::PastBlockNumber(block_number, page_size) => {
+                    match ready!(block_number.poll(cx)) {
+                        Ok(block_number) => {
+                            // NOTE: Only query past logs for blocks that are
+                            //   older than the reorg window, the remaining
+                            //   blocks are retrieved one by one so that they
+                            //   are tracked for reorg detection.
+                            let from_block = next_block.unwrap_or(0);
+                            let mut end_block = block_number
+                                .as_u64()
+                                .saturating_sub(params.reorg_window as u64);
+                            if let Some(to_block) = params.to_block {
+                                end_block = end_block.min(to_block);
+                            }
+
+                            if from_block <= end_block {
+                                *next_block = Some(end_block + 1);
+                                BlockLogState::PastLogs(
+                                    LogPageStream::new(
+                                        web3.clone(),
+                                        filter.clone(),
+                                        from_block,
+                                        end_block,
+                                        *page_size,
+                                    )
+                                    .concurrency(params.past_page_concurrency),
+                                )
+                            } else {
+                                *next_block = Some(from_block);
+                                BlockLogState::Idle
+                            }
+                        }
+                        Err(err) => {
+                            *past_page_size = Some(*page_size);
+                            error = Some(err.into());
+                            BlockLogState::Idle
+                        }
+                    }
+                }
+                BlockLogState::PastLogs(mut pages) => match ready!(pages.as_mut().poll_next(cx)) {
+                    Some(Ok(logs)) => {
+                        if logs.is_empty() {
+                            *past_block = pages.last_block();
+                        } else {
+                            pending.extend(logs);
+                            *pending_past_block = pages.last_block();
+                        }
+                        continue;
+                    }
+                    Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                    None => BlockLogState::Idle,
+                },
+                BlockLogState::Block(block, number) => match ready!(block.poll(cx)) {
+                    Ok(block) => match (block_id(block, *number), window.back()) {
+                        // NOTE: The block has not been mined yet.
+                        (None, _) => BlockLogState::Waiting(delay(params.poll_interval)),
+                        (Some((_, parent_hash)), Some((head, _))) if head.hash != parent_hash => {
+                            // NOTE: The block we are tracking as the head of
+                            //   the chain was orphaned, remove it and emit its
+                            //   logs as removed in reverse order. Continue
+                            //   with its parent until a common ancestor with
+                            //   the new chain is found.
+                            let (orphaned, logs) = window.pop_back().expect("window is not empty");
+                            match window.back() {
+                                Some((ancestor, _)) => {
+                                    let ancestor = *ancestor;
+                                    commit(
+                                        pending,
+                                        pending_checkpoint,
+                                        checkpoint,
+                                        logs.into_iter()
+                                            .rev()
+                                            .map(|log| Log {
+                                                removed: Some(true),
+                                                ..log
+                                            })
+                                            .collect(),
+                                        ancestor,
+                                    );
+                                }
+                                None => {
+                                    window.push_back((orphaned, logs));
+                                    error = Some(ExecutionError::ReorgTooDeep(orphaned));
+                                }
+                            }
+                            BlockLogState::Idle
+                        }
+                        (Some((block, _)), _) => {
+                            BlockLogState::Logs(fetch_logs(web3, filter, *number, *number), block)
+                        }
+                    },
+                    Err(err) => {
+                        error = Some(err.into());
+                        BlockLogState::Idle
+                    }
+                },
+                BlockLogState::Logs(logs, block) => {
+                    match ready!(logs.poll(cx)) {
+                        // NOTE: Logs are queried by block number, so make sure
+                        //   that the block was not replaced in the meantime. If
+                        //   it was, just retrieve the block again.
+                        Ok(logs) if logs.iter().all(|log| log.block_hash == Some(block.hash)) => {
+                            window.push_back((*block, logs.clone()));
+                            while window.len() > params.reorg_window.max(1) {
+                                window.pop_front();
+                            }
+                            *next_block = None;
+                            commit(pending, pending_checkpoint, checkpoint, logs, *block);
+                        }
+                        Ok(_) => {}
+                        Err(err) => error = Some(err.into()),
+                    }
+                    BlockLogState::Idle
+                }
+                BlockLogState::Waiting(delay) => {
+                    ready!(delay.poll(cx));
+                    BlockLogState::Idle
+                }
+            };
+
+            self.as_mut().project().state.set(next_state);
+            if let Some(err) = error {
+                return Poll::Ready(Some(Err(err)));
+            }
+        }
+    }
+}
+
+/// Queues logs to be emitted by a block log stream, committing the specified
+/// checkpoint once they have all been emitted.
+fn commit(
+    pending: &mut VecDeque<Log>,
+    pending_checkpoint: &mut Option<BlockId>,
+    checkpoint: &mut Option<BlockId>,
+    logs: Vec<Log>,
+    block: BlockId,
+) {
+    if logs.is_empty() {
+        *checkpoint = Some(block);
+    } else {
+        pending.extend(logs);
+        *pending_checkpoint = Some(block);
+    }
+}
+
+/// The subset of block header fields needed for detecting reorganizations.
+/// This is used instead of the `web3` block type so that the stream does not
+/// depend on header fields that are not returned by all nodes.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlockHeader {
+    hash: Option<H256>,
+    parent_hash: H256,
+}
+
+/// Returns the block ID and parent hash of a retrieved block, or `None` if the
+/// block has not been mined yet.
+fn block_id(block: Option<BlockHeader>, number: u64) -> Option<(BlockId, H256)> {
+    let block = block?;
+    let id = BlockId {
+        number: number.into(),
+        hash: block.hash?,
+    };
+    Some((id, block.parent_hash))
+}
+
+/// Retrieves the header of the block with the specified number.
+fn fetch_block<T: Transport>(
+    web3: &Web3<T>,
+    number: u64,
+) -> CompatCallFuture<T, Option<BlockHeader>> {
+    let block = helpers::serialize(&BlockNumber::Number(number.into()));
+    let include_txs = helpers::serialize(&false);
+    CallFuture::new(
+        web3.transport()
+            .execute("eth_getBlockByNumber", vec![block, include_txs]),
+    )
+    .compat()
+}
+
+/// Retrieves the logs matching a filter for the specified block range.
+fn fetch_logs<T: Transport>(
+    web3: &Web3<T>,
+    filter: &FilterBuilder,
+    from_block: u64,
+    to_block: u64,
+) -> CompatCallFuture<T, Vec<Log>> {
+    let filter = filter
+        .clone()
+        .from_block(from_block.into())
+        .to_block(to_block.into())
+        .build();
+    web3.eth().logs(filter).compat()
+}
+
+/// Create a new delay that may resolve immediately when delayed for a zero
+/// duration.
+fn delay(duration: Duration) -> MaybeReady<Delay> {
+    if duration == Duration::default() {
+        MaybeReady::ready(())
+    } else {
+        MaybeReady::future(Delay::new(duration))
+    }
+}
+
 /// A type alias for a stream that emits logs.
 type CompatFilterStream<T, R> = Compat01As03<FilterStream<T, R>>;
 
+/// A type alias for a future that resolves with the changes of a log filter.
+type CompatFilterChanges<T> = CompatCallFuture<T, Option<Vec<Log>>>;
+
 /// A type alias for a future that resolves with the ID of a created log filter
 /// that can be queried in order to stream logs.
 type CompatCreateFilter<T, R> = Compat01As03<CreateFilter<T, R>>;
@@ -86,6 +940,7 @@ mod tests {
     use super::*;
     use crate::test::prelude::*;
     use futures::stream::StreamExt;
+    use jsonrpc_core::{Error as RpcError, ErrorCode};
     use serde_json::Value;
     use web3::types::{Address, H256};
 
@@ -126,4 +981,438 @@ mod tests {
         transport.assert_request("eth_getFilterChanges", &[json!("0xf0")]);
         transport.assert_no_more_requests();
     }
+
+    fn block(hash: u8, parent_hash: u8) -> Value {
+        json!({
+            "hash": H256::repeat_byte(hash),
+            "parentHash": H256::repeat_byte(parent_hash),
+        })
+    }
+
+    fn block_log(number: u64, hash: u8) -> Value {
+        json!({
+            "address": Address::zero(),
+            "topics": [],
+            "data": "0x",
+            "blockHash": H256::repeat_byte(hash),
+            "blockNumber": U64::from(number),
+            "transactionHash": H256::zero(),
+            "transactionIndex": "0x0",
+            "logIndex": "0x0",
+            "removed": false,
+        })
+    }
+
+    fn block_params() -> BlockLogParams {
+        BlockLogParams {
+            from_block: None,
+            to_block: None,
+            checkpoint: None,
+            poll_interval: Duration::from_secs(0),
+            reorg_window: DEFAULT_REORG_WINDOW,
+            past_page_size: None,
+            past_page_concurrency: 1,
+        }
+    }
+
+    fn assert_get_block(transport: &mut TestTransport, number: u64) {
+        transport.assert_request(
+            "eth_getBlockByNumber",
+            &[json!(U64::from(number)), json!(false)],
+        );
+    }
+
+    fn assert_get_logs(transport: &mut TestTransport, number: u64) {
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({
+                "fromBlock": U64::from(number),
+                "toBlock": U64::from(number),
+            })],
+        );
+    }
+
+    fn rpc_error(message: &str) -> web3::Error {
+        web3::Error::Rpc(RpcError {
+            code: ErrorCode::ServerError(-32005),
+            message: message.to_owned(),
+            data: None,
+        })
+    }
+
+    fn assert_get_logs_range(transport: &mut TestTransport, from: u64, to: u64) {
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({ "fromBlock": U64::from(from), "toBlock": U64::from(to) })],
+        );
+    }
+
+    #[test]
+    fn log_page_stream_adapts_page_size() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        transport.add_error(rpc_error("query returned more than 10000 results"));
+        transport.add_error(rpc_error("Log response size exceeded."));
+        transport.add_response(json!([block_log(1, 0x01)]));
+        transport.add_response(json!([block_log(2, 0x02), block_log(3, 0x03)]));
+        transport.add_response(json!([]));
+        transport.add_response(json!([]));
+
+        let pages = LogPageStream::new(web3, FilterBuilder::default(), 0, 9, 8)
+            .map(|page| page.expect("failed to get page").len())
+            .collect::<Vec<_>>()
+            .immediate();
+
+        assert_eq!(pages, [1, 2, 0, 0]);
+        assert_get_logs_range(&mut transport, 0, 7);
+        assert_get_logs_range(&mut transport, 0, 3);
+        assert_get_logs_range(&mut transport, 0, 1);
+        assert_get_logs_range(&mut transport, 2, 3);
+        assert_get_logs_range(&mut transport, 4, 7);
+        assert_get_logs_range(&mut transport, 8, 9);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn log_page_stream_concurrent_pages() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        transport.add_response(json!([block_log(1, 0x01)]));
+        transport.add_error(rpc_error("query returned more than 10000 results"));
+        transport.add_response(json!([block_log(9, 0x09)]));
+        transport.add_response(json!([block_log(4, 0x04)]));
+        transport.add_response(json!([block_log(6, 0x06)]));
+
+        let pages = LogPageStream::new(web3, FilterBuilder::default(), 0, 11, 4)
+            .concurrency(3)
+            .map(|page| {
+                page.expect("failed to get page")
+                    .iter()
+                    .map(|log| log.block_number.unwrap().as_u64())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+            .immediate();
+
+        assert_eq!(pages, [vec![1], vec![4], vec![6], vec![9]]);
+        assert_get_logs_range(&mut transport, 0, 3);
+        assert_get_logs_range(&mut transport, 4, 7);
+        assert_get_logs_range(&mut transport, 8, 11);
+        assert_get_logs_range(&mut transport, 4, 5);
+        assert_get_logs_range(&mut transport, 6, 7);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn log_page_stream_retries_failed_page() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        transport.add_error(rpc_error("internal error"));
+        transport.add_response(json!([block_log(1, 0x01)]));
+
+        let mut pages = LogPageStream::new(web3, FilterBuilder::default(), 0, 3, 8);
+        assert!(pages.next().immediate().unwrap().is_err());
+        assert_eq!(pages.next().immediate().unwrap().unwrap().len(), 1);
+        assert!(pages.next().immediate().is_none());
+
+        assert_get_logs_range(&mut transport, 0, 3);
+        assert_get_logs_range(&mut transport, 0, 3);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn too_many_results_errors() {
+        let is_too_many_results = |message| is_too_many_results(&rpc_error(message).into());
+
+        assert!(is_too_many_results(
+            "query returned more than 10000 results"
+        ));
+        assert!(is_too_many_results("Log response size exceeded."));
+        assert!(is_too_many_results("block range is too wide"));
+        assert!(is_too_many_results("exceed maximum block range: 5000"));
+        assert!(is_too_many_results("too many logs in range"));
+
+        assert!(!is_too_many_results("429 Too Many Requests"));
+        assert!(!is_too_many_results("too many concurrent requests"));
+        assert!(!is_too_many_results("rate limit exceeded"));
+        assert!(!is_too_many_results("internal error"));
+    }
+
+    #[test]
+    fn log_page_stream_does_not_split_rate_limited_page() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        transport.add_error(rpc_error("Too Many Requests"));
+
+        let mut pages = LogPageStream::new(web3, FilterBuilder::default(), 0, 3, 8);
+        assert!(pages.next().immediate().unwrap().is_err());
+
+        assert_get_logs_range(&mut transport, 0, 3);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn block_log_stream_reorg() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        // start at the current block
+        transport.add_response(json!("0x1"));
+        transport.add_response(block(0x01, 0x00));
+        transport.add_response(json!([block_log(1, 0x01)]));
+        // block 2 not yet mined
+        transport.add_response(json!(null));
+        transport.add_response(block(0x02, 0x01));
+        transport.add_response(json!([block_log(2, 0x02)]));
+        // block 3 is built on a different block 2
+        transport.add_response(block(0x33, 0x22));
+        transport.add_response(block(0x22, 0x01));
+        transport.add_response(json!([]));
+        transport.add_response(block(0x33, 0x22));
+        transport.add_response(json!([block_log(3, 0x33)]));
+
+        let mut stream = BlockLogStream::new(web3, FilterBuilder::default(), block_params());
+        let mut next = || {
+            let log = stream
+                .next()
+                .immediate()
+                .expect("block log stream ended")
+                .expect("failed to get log");
+            (
+                log.block_hash.unwrap(),
+                log.is_removed(),
+                stream.checkpoint(),
+            )
+        };
+        let checkpoint = |number: u64, hash: u8| {
+            Some(BlockId {
+                number: number.into(),
+                hash: H256::repeat_byte(hash),
+            })
+        };
+
+        assert_eq!(
+            next(),
+            (H256::repeat_byte(0x01), false, checkpoint(1, 0x01))
+        );
+        assert_eq!(
+            next(),
+            (H256::repeat_byte(0x02), false, checkpoint(2, 0x02))
+        );
+        assert_eq!(next(), (H256::repeat_byte(0x02), true, checkpoint(1, 0x01)));
+        assert_eq!(
+            next(),
+            (H256::repeat_byte(0x33), false, checkpoint(3, 0x33))
+        );
+
+        transport.assert_request("eth_blockNumber", &[]);
+        assert_get_block(&mut transport, 1);
+        assert_get_logs(&mut transport, 1);
+        assert_get_block(&mut transport, 2);
+        assert_get_block(&mut transport, 2);
+        assert_get_logs(&mut transport, 2);
+        assert_get_block(&mut transport, 3);
+        assert_get_block(&mut transport, 2);
+        assert_get_logs(&mut transport, 2);
+        assert_get_block(&mut transport, 3);
+        assert_get_logs(&mut transport, 3);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn block_log_stream_ends_at_to_block() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        transport.add_response(block(0x05, 0x04));
+        transport.add_response(json!([]));
+        transport.add_response(block(0x06, 0x05));
+        transport.add_response(json!([block_log(6, 0x06)]));
+
+        let params = BlockLogParams {
+            from_block: Some(5),
+            to_block: Some(6),
+            ..block_params()
+        };
+        let logs = BlockLogStream::new(web3, FilterBuilder::default(), params)
+            .collect::<Vec<_>>()
+            .immediate();
+
+        assert_eq!(logs.len(), 1);
+        assert_get_block(&mut transport, 5);
+        assert_get_logs(&mut transport, 5);
+        assert_get_block(&mut transport, 6);
+        assert_get_logs(&mut transport, 6);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn block_log_stream_past_and_live() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        // current block
+        transport.add_response(json!("0x8"));
+        // past log pages
+        transport.add_response(json!([block_log(3, 0x03)]));
+        transport.add_response(json!([block_log(5, 0x05), block_log(6, 0x06)]));
+        // new blocks in the reorg window
+        transport.add_response(block(0x07, 0x06));
+        transport.add_response(json!([block_log(7, 0x07)]));
+
+        let params = BlockLogParams {
+            from_block: Some(2),
+            reorg_window: 2,
+            past_page_size: Some(3),
+            ..block_params()
+        };
+        let mut stream = BlockLogStream::new(web3, FilterBuilder::default(), params);
+        let logs = (&mut stream)
+            .take(4)
+            .map(|log| log.unwrap().block_number.unwrap().as_u64())
+            .collect::<Vec<_>>()
+            .immediate();
+
+        assert_eq!(logs, [3, 5, 6, 7]);
+        assert_eq!(
+            stream.checkpoint(),
+            Some(BlockId {
+                number: 7.into(),
+                hash: H256::repeat_byte(0x07),
+            }),
+        );
+        transport.assert_request("eth_blockNumber", &[]);
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({ "fromBlock": U64::from(2), "toBlock": U64::from(4) })],
+        );
+        transport.assert_request(
+            "eth_getLogs",
+            &[json!({ "fromBlock": U64::from(5), "toBlock": U64::from(6) })],
+        );
+        assert_get_block(&mut transport, 7);
+        assert_get_logs(&mut transport, 7);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn block_log_stream_past_block_progress() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        // current block
+        transport.add_response(json!("0x9"));
+        // past log pages
+        transport.add_response(json!([block_log(2, 0x02)]));
+        transport.add_response(json!([]));
+        transport.add_response(json!([block_log(5, 0x05), block_log(6, 0x06)]));
+
+        let params = BlockLogParams {
+            from_block: Some(1),
+            reorg_window: 2,
+            past_page_size: Some(2),
+            ..block_params()
+        };
+        let mut stream = BlockLogStream::new(web3, FilterBuilder::default(), params);
+        let block_number = |log: Option<Result<Log, ExecutionError>>| {
+            log.unwrap().unwrap().block_number.unwrap().as_u64()
+        };
+
+        assert_eq!(stream.past_block(), None);
+        assert_eq!(block_number(stream.next().immediate()), 2);
+        assert_eq!(stream.past_block(), Some(2));
+        // NOTE: The empty page is committed right away, while the last page
+        //   is only committed once all of its logs have been emitted.
+        assert_eq!(block_number(stream.next().immediate()), 5);
+        assert_eq!(stream.past_block(), Some(4));
+        assert_eq!(block_number(stream.next().immediate()), 6);
+        assert_eq!(stream.past_block(), Some(6));
+        assert_eq!(stream.checkpoint(), None);
+        transport.assert_request("eth_blockNumber", &[]);
+        assert_get_logs_range(&mut transport, 1, 2);
+        assert_get_logs_range(&mut transport, 3, 4);
+        assert_get_logs_range(&mut transport, 5, 6);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn block_log_stream_orphaned_checkpoint() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        transport.add_response(block(0x06, 0x55));
+
+        let checkpoint = BlockId {
+            number: 5.into(),
+            hash: H256::repeat_byte(0x05),
+        };
+        let params = BlockLogParams {
+            from_block: Some(1),
+            checkpoint: Some(checkpoint),
+            ..block_params()
+        };
+        let result = BlockLogStream::new(web3, FilterBuilder::default(), params)
+            .next()
+            .immediate()
+            .expect("block log stream ended");
+
+        assert!(matches!(result, Err(ExecutionError::ReorgTooDeep(block)) if block == checkpoint));
+        assert_get_block(&mut transport, 6);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn block_log_stream_orphaned_checkpoint_retry_and_rewind() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let checkpoint = BlockId {
+            number: 5.into(),
+            hash: H256::repeat_byte(0x05),
+        };
+        let params = BlockLogParams {
+            checkpoint: Some(checkpoint),
+            ..block_params()
+        };
+        let mut stream = BlockLogStream::new(web3.clone(), FilterBuilder::default(), params);
+
+        // NOTE: Polling the stream again after the error retries the block
+        //   following the checkpoint, which fails in the same way.
+        for _ in 0..2 {
+            transport.add_response(block(0x06, 0x55));
+            let result = stream.next().immediate().expect("block log stream ended");
+            assert!(
+                matches!(result, Err(ExecutionError::ReorgTooDeep(block)) if block == checkpoint)
+            );
+            assert_get_block(&mut transport, 6);
+            transport.assert_no_more_requests();
+        }
+
+        // NOTE: Resuming from a rewound checkpoint that is still part of the
+        //   chain continues with the logs of the new chain.
+        transport.add_response(block(0x55, 0x04));
+        transport.add_response(json!([block_log(5, 0x55)]));
+
+        let params = BlockLogParams {
+            checkpoint: Some(BlockId {
+                number: 4.into(),
+                hash: H256::repeat_byte(0x04),
+            }),
+            ..block_params()
+        };
+        let log = BlockLogStream::new(web3, FilterBuilder::default(), params)
+            .next()
+            .immediate()
+            .expect("block log stream ended")
+            .expect("failed to get log");
+
+        assert_eq!(log.block_hash, Some(H256::repeat_byte(0x55)));
+        assert_get_block(&mut transport, 5);
+        assert_get_logs(&mut transport, 5);
+        transport.assert_no_more_requests();
+    }
 }
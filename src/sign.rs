@@ -3,18 +3,27 @@
 //! part of the missing `accounts` namespace.
 
 use crate::secret::PrivateKey;
+use crate::transaction::access_list::AccessList;
+use crate::transaction::gas_price::GasFees;
 use ethcontract_common::hash;
 use rlp::RlpStream;
 use secp256k1::recovery::RecoveryId;
 use secp256k1::{Message, Secp256k1};
 use web3::types::{Address, Bytes, U256};
 
+/// The EIP-2718 transaction type for EIP-2930 transactions.
+const EIP2930_TX_TYPE: u8 = 1;
+
+/// The EIP-2718 transaction type for EIP-1559 transactions.
+const EIP1559_TX_TYPE: u8 = 2;
+
 /// Raw transaction data to sign
 pub struct TransactionData<'a> {
     /// Nonce to use when signing this transaction.
     pub nonce: U256,
-    /// Gas price to use when signing this transaction.
-    pub gas_price: U256,
+    /// Gas fees to use when signing this transaction. Legacy gas prices produce
+    /// legacy transactions while EIP-1559 fees produce type 2 transactions.
+    pub gas_price: GasFees,
     /// Gas provided by the transaction.
     pub gas: U256,
     /// Receiver of the transaction.
@@ -23,44 +32,93 @@ pub struct TransactionData<'a> {
     pub value: U256,
     /// Call data of the transaction, can be empty for simple value transfers.
     pub data: &'a Bytes,
+    /// Optional access list of the transaction. Specifying an access list for
+    /// a transaction with a legacy gas price produces a type 1 transaction.
+    pub access_list: Option<&'a AccessList>,
 }
 
 impl<'a> TransactionData<'a> {
     /// Sign and return a raw transaction.
+    ///
+    /// Note that typed transactions always include the chain ID in the signed
+    /// payload, so a chain ID of `0` is used for them when none is specified.
     pub fn sign(&self, key: &PrivateKey, chain_id: Option<u64>) -> Bytes {
+        match (self.gas_price, self.access_list) {
+            (GasFees::Legacy(gas_price), None) => self.sign_legacy(key, gas_price, chain_id),
+            (GasFees::Legacy(gas_price), Some(_)) => {
+                self.sign_eip2930(key, gas_price, chain_id.unwrap_or_default())
+            }
+            (
+                GasFees::Eip1559 {
+                    max_fee_per_gas,
+                    max_priority_fee_per_gas,
+                },
+                _,
+            ) => self.sign_eip1559(
+                key,
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+                chain_id.unwrap_or_default(),
+            ),
+        }
+    }
+
+    /// Sign and return a raw legacy transaction.
+    fn sign_legacy(&self, key: &PrivateKey, gas_price: U256, chain_id: Option<u64>) -> Bytes {
         let mut rlp = RlpStream::new();
-        self.rlp_append_unsigned(&mut rlp, chain_id);
+        self.rlp_append_legacy_unsigned(&mut rlp, gas_price, chain_id);
 
-        let hash = hash::keccak256(&rlp.as_raw());
+        let (recovery_id, sig) = sign_payload(key, rlp.as_raw());
         rlp.clear();
-
-        // NOTE: secp256k1 messages for singing must be exactly 32 bytes long
-        //   and not be all `0`s. Because the message being signed here is a 32
-        //   byte hash that is computed from non-`0` data (because of RLP
-        //   encoding prefixes) the chance of the hash being `0` is
-        //   infinitesimally small, so it is OK to unwrap here.
-        let message = Message::from_slice(&hash).expect("hash is an invalid secp256k1 message");
-        let (recovery_id, sig) = Secp256k1::signing_only()
-            .sign_recoverable(&message, &key)
-            .serialize_compact();
-        self.rlp_append_signed(&mut rlp, recovery_id, sig, chain_id);
+        self.rlp_append_legacy_signed(&mut rlp, gas_price, recovery_id, sig, chain_id);
 
         rlp.out().into()
     }
 
-    /// RLP encode an unsigned transaction.
-    fn rlp_append_unsigned(&self, s: &mut RlpStream, chain_id: Option<u64>) {
+    /// Sign and return a raw EIP-2930 transaction.
+    fn sign_eip2930(&self, key: &PrivateKey, gas_price: U256, chain_id: u64) -> Bytes {
+        sign_typed(key, EIP2930_TX_TYPE, 8, |s| {
+            s.append(&chain_id);
+            s.append(&self.nonce);
+            s.append(&gas_price);
+            s.append(&self.gas);
+            self.rlp_append_to(s);
+            s.append(&self.value);
+            s.append(&self.data.0);
+            self.rlp_append_access_list(s);
+        })
+    }
+
+    /// Sign and return a raw EIP-1559 transaction.
+    fn sign_eip1559(
+        &self,
+        key: &PrivateKey,
+        max_fee_per_gas: U256,
+        max_priority_fee_per_gas: U256,
+        chain_id: u64,
+    ) -> Bytes {
+        sign_typed(key, EIP1559_TX_TYPE, 9, |s| {
+            s.append(&chain_id);
+            s.append(&self.nonce);
+            s.append(&max_priority_fee_per_gas);
+            s.append(&max_fee_per_gas);
+            s.append(&self.gas);
+            self.rlp_append_to(s);
+            s.append(&self.value);
+            s.append(&self.data.0);
+            self.rlp_append_access_list(s);
+        })
+    }
+
+    /// RLP encode an unsigned legacy transaction.
+    fn rlp_append_legacy_unsigned(
+        &self,
+        s: &mut RlpStream,
+        gas_price: U256,
+        chain_id: Option<u64>,
+    ) {
         s.begin_list(if chain_id.is_some() { 9 } else { 6 });
-        s.append(&self.nonce);
-        s.append(&self.gas_price);
-        s.append(&self.gas);
-        if let Some(to) = self.to {
-            s.append(&to);
-        } else {
-            s.append(&"");
-        }
-        s.append(&self.value);
-        s.append(&self.data.0);
+        self.rlp_append_legacy_fields(s, gas_price);
         if let Some(n) = chain_id {
             s.append(&n);
             s.append(&0u8);
@@ -68,39 +126,110 @@ impl<'a> TransactionData<'a> {
         }
     }
 
-    /// RLP encode a transaction with its signature.
-    fn rlp_append_signed(
+    /// RLP encode a legacy transaction with its signature.
+    fn rlp_append_legacy_signed(
         &self,
         s: &mut RlpStream,
+        gas_price: U256,
         recovery_id: RecoveryId,
         sig: [u8; 64],
         chain_id: Option<u64>,
     ) {
         let sig_v = add_chain_replay_protection(recovery_id, chain_id);
-        let (sig_r, sig_s) = {
-            let (mut r, mut s) = ([0u8; 32], [0u8; 32]);
-            r.copy_from_slice(&sig[..32]);
-            s.copy_from_slice(&sig[32..]);
-            (r, s)
-        };
 
         s.begin_list(9);
+        self.rlp_append_legacy_fields(s, gas_price);
+        rlp_append_signature(s, sig_v, sig);
+    }
+
+    /// RLP encode the common fields of a legacy transaction.
+    fn rlp_append_legacy_fields(&self, s: &mut RlpStream, gas_price: U256) {
         s.append(&self.nonce);
-        s.append(&self.gas_price);
+        s.append(&gas_price);
         s.append(&self.gas);
+        self.rlp_append_to(s);
+        s.append(&self.value);
+        s.append(&self.data.0);
+    }
+
+    /// RLP encode the access list of the transaction, an empty access list is
+    /// encoded if none is specified.
+    fn rlp_append_access_list(&self, s: &mut RlpStream) {
+        let access_list = self.access_list.map(Vec::as_slice).unwrap_or_default();
+        s.begin_list(access_list.len());
+        for item in access_list {
+            s.begin_list(2);
+            s.append(&item.address);
+            s.append_list(&item.storage_keys);
+        }
+    }
+
+    /// RLP encode the receiver of the transaction.
+    fn rlp_append_to(&self, s: &mut RlpStream) {
         if let Some(to) = self.to {
             s.append(&to);
         } else {
             s.append(&"");
         }
-        s.append(&self.value);
-        s.append(&self.data.0);
-        s.append(&sig_v);
-        s.append(&U256::from(sig_r));
-        s.append(&U256::from(sig_s));
     }
 }
 
+/// Sign and return a raw EIP-2718 typed transaction. The raw transaction is a
+/// typed transaction envelope, i.e. the RLP encoded transaction prefixed with
+/// its transaction type, where the signed payload is the envelope of the RLP
+/// encoded transaction fields without the signature.
+fn sign_typed<F>(key: &PrivateKey, tx_type: u8, field_count: usize, rlp_append_fields: F) -> Bytes
+where
+    F: Fn(&mut RlpStream),
+{
+    let mut rlp = RlpStream::new();
+    rlp.begin_list(field_count);
+    rlp_append_fields(&mut rlp);
+
+    let mut payload = vec![tx_type];
+    payload.extend_from_slice(rlp.as_raw());
+    let (recovery_id, sig) = sign_payload(key, &payload);
+
+    rlp.clear();
+    rlp.begin_list(field_count + 3);
+    rlp_append_fields(&mut rlp);
+    rlp_append_signature(&mut rlp, recovery_id.to_i32() as u64, sig);
+
+    let mut raw = vec![tx_type];
+    raw.extend_from_slice(&rlp.out());
+    raw.into()
+}
+
+/// Signs the hash of a transaction payload and returns the recoverable
+/// signature.
+fn sign_payload(key: &PrivateKey, payload: &[u8]) -> (RecoveryId, [u8; 64]) {
+    let hash = hash::keccak256(payload);
+
+    // NOTE: secp256k1 messages for singing must be exactly 32 bytes long
+    //   and not be all `0`s. Because the message being signed here is a 32
+    //   byte hash that is computed from non-`0` data (because of RLP
+    //   encoding prefixes) the chance of the hash being `0` is
+    //   infinitesimally small, so it is OK to unwrap here.
+    let message = Message::from_slice(&hash).expect("hash is an invalid secp256k1 message");
+    Secp256k1::signing_only()
+        .sign_recoverable(&message, &key)
+        .serialize_compact()
+}
+
+/// RLP encode the `v`, `r` and `s` signature values.
+fn rlp_append_signature(s: &mut RlpStream, sig_v: u64, sig: [u8; 64]) {
+    let (sig_r, sig_s) = {
+        let (mut r, mut s) = ([0u8; 32], [0u8; 32]);
+        r.copy_from_slice(&sig[..32]);
+        s.copy_from_slice(&sig[32..]);
+        (r, s)
+    };
+
+    s.append(&sig_v);
+    s.append(&U256::from(sig_r));
+    s.append(&U256::from(sig_s));
+}
+
 /// Encode chain ID based on (EIP-155)[https://github.com/ethereum/EIPs/blob/master/EIPS/eip-155.md)
 fn add_chain_replay_protection(recovery_id: RecoveryId, chain_id: Option<u64>) -> u64 {
     (recovery_id.to_i32() as u64)
@@ -114,6 +243,10 @@ fn add_chain_replay_protection(recovery_id: RecoveryId, chain_id: Option<u64>) -
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::transaction::AccessListItem;
+    use rlp::Rlp;
+    use secp256k1::recovery::RecoverableSignature;
+    use web3::types::H256;
 
     #[test]
     fn test_sign() {
@@ -123,7 +256,7 @@ mod tests {
         let tx = TransactionData {
             nonce: 0.into(),
             gas: 2_000_000.into(),
-            gas_price: 234_567_897_654_321u64.into(),
+            gas_price: GasFees::Legacy(234_567_897_654_321u64.into()),
             to: Some(
                 "F0109fC8DF283027b6285cc889F5aA624EaC1F55"
                     .parse()
@@ -131,6 +264,7 @@ mod tests {
             ),
             value: 1_000_000_000.into(),
             data: &Bytes::default(),
+            access_list: None,
         };
         let key = key!("0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318");
         let raw = tx.sign(&key, Some(1));
@@ -167,10 +301,11 @@ mod tests {
         let tx = TransactionData {
             nonce: 42.into(),
             gas: 2_000_000.into(),
-            gas_price: 6_000_000_000u64.into(),
+            gas_price: GasFees::Legacy(6_000_000_000u64.into()),
             to: None,
             value: 0.into(),
             data: &bytes!("0x600080fd"),
+            access_list: None,
         };
         let key = key!("0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318");
         let raw = tx.sign(&key, Some(5777));
@@ -179,4 +314,130 @@ mod tests {
 
         assert_eq!(raw, expected);
     }
+
+    #[test]
+    fn test_sign_eip1559() {
+        let tx = TransactionData {
+            nonce: 7.into(),
+            gas: 21_000.into(),
+            gas_price: GasFees::Eip1559 {
+                max_fee_per_gas: 100_000_000_000u64.into(),
+                max_priority_fee_per_gas: 2_000_000_000u64.into(),
+            },
+            to: Some(addr!("0xF0109fC8DF283027b6285cc889F5aA624EaC1F55")),
+            value: 1_000_000_000.into(),
+            data: &bytes!("0x01020304"),
+            access_list: None,
+        };
+        let key = key!("0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318");
+        let raw = tx.sign(&key, Some(1));
+
+        // test vector generated with an independent implementation of the
+        // EIP-2718 typed envelope and EIP-1559 payload (RLP, keccak256 and
+        // RFC6979 secp256k1 signing), checked against the web3.js vectors from
+        // the legacy tests above with the same key; the transaction hash is
+        // `keccak256(raw)`
+        let expected = bytes!("0x02f8730107847735940085174876e80082520894f0109fc8df283027b6285cc889f5aa624eac1f55843b9aca008401020304c080a0573228f05ed31d121b1eca97f74c4feef96e0ce700c97914e93daa5d52f3a0f5a02b6687716c2f34ed1511975e65a5ccef5d0daf248eff9ab1e107c6b884d3109d");
+        assert_eq!(raw, expected);
+        assert_eq!(
+            H256(hash::keccak256(&raw.0)),
+            hash!("0x7ad3ef51b8093eb477c68471d5701aad17bb8f0a22c33a3dfea94e84eac4a25b")
+        );
+
+        assert_eq!(raw.0[0], EIP1559_TX_TYPE);
+
+        let rlp = Rlp::new(&raw.0[1..]);
+        assert_eq!(rlp.item_count().unwrap(), 12);
+        assert_eq!(rlp.val_at::<u64>(0).unwrap(), 1);
+        assert_eq!(rlp.val_at::<U256>(1).unwrap(), tx.nonce);
+        assert_eq!(rlp.val_at::<U256>(2).unwrap(), 2_000_000_000u64.into());
+        assert_eq!(rlp.val_at::<U256>(3).unwrap(), 100_000_000_000u64.into());
+        assert_eq!(rlp.val_at::<U256>(4).unwrap(), tx.gas);
+        assert_eq!(rlp.val_at::<Address>(5).unwrap(), tx.to.unwrap());
+        assert_eq!(rlp.val_at::<U256>(6).unwrap(), tx.value);
+        assert_eq!(rlp.val_at::<Vec<u8>>(7).unwrap(), tx.data.0);
+        assert_eq!(rlp.at(8).unwrap().item_count().unwrap(), 0);
+
+        assert_eq!(recover_typed_signer(&raw, 9), key.public_address());
+    }
+
+    #[test]
+    fn test_sign_eip2930() {
+        let access_list = vec![AccessListItem {
+            address: addr!("0x0123456789012345678901234567890123456789"),
+            storage_keys: vec![H256::from_low_u64_be(1), H256::from_low_u64_be(2)],
+        }];
+        let tx = TransactionData {
+            nonce: 7.into(),
+            gas: 21_000.into(),
+            gas_price: GasFees::Legacy(6_000_000_000u64.into()),
+            to: None,
+            value: 0.into(),
+            data: &bytes!("0x600080fd"),
+            access_list: Some(&access_list),
+        };
+        let key = key!("0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318");
+        let raw = tx.sign(&key, Some(5777));
+
+        // test vector generated the same way as the EIP-1559 one above
+        let expected = bytes!("0x01f8b482169107850165a0bc00825208808084600080fdf85bf859940123456789012345678901234567890123456789f842a00000000000000000000000000000000000000000000000000000000000000001a0000000000000000000000000000000000000000000000000000000000000000201a02778d5967cd11295b5cac13417aaa93fc3ab890a05ca04fbf4040859a7901615a02e29c3eb5a0fb996e00134e7aa845af8f8ab4b32076933f42088aa3867fafee3");
+        assert_eq!(raw, expected);
+        assert_eq!(
+            H256(hash::keccak256(&raw.0)),
+            hash!("0x488d28660fe877e36bdd61dd0a4d96ae20a5b3e6bec6ef5907c767a35d9c3f0c")
+        );
+
+        assert_eq!(raw.0[0], EIP2930_TX_TYPE);
+
+        let rlp = Rlp::new(&raw.0[1..]);
+        assert_eq!(rlp.item_count().unwrap(), 11);
+        assert_eq!(rlp.val_at::<u64>(0).unwrap(), 5777);
+        assert_eq!(rlp.val_at::<U256>(1).unwrap(), tx.nonce);
+        assert_eq!(rlp.val_at::<U256>(2).unwrap(), 6_000_000_000u64.into());
+        assert_eq!(rlp.val_at::<U256>(3).unwrap(), tx.gas);
+        assert!(rlp.at(4).unwrap().is_empty());
+        assert_eq!(rlp.val_at::<U256>(5).unwrap(), tx.value);
+        assert_eq!(rlp.val_at::<Vec<u8>>(6).unwrap(), tx.data.0);
+
+        let item = rlp.at(7).unwrap().at(0).unwrap();
+        assert_eq!(item.val_at::<Address>(0).unwrap(), access_list[0].address);
+        assert_eq!(
+            item.list_at::<H256>(1).unwrap(),
+            access_list[0].storage_keys
+        );
+
+        assert_eq!(recover_typed_signer(&raw, 8), key.public_address());
+    }
+
+    /// Recovers the signer of a raw typed transaction with the specified
+    /// number of unsigned transaction fields.
+    fn recover_typed_signer(raw: &Bytes, field_count: usize) -> Address {
+        let rlp = Rlp::new(&raw.0[1..]);
+
+        let mut unsigned = RlpStream::new();
+        unsigned.begin_list(field_count);
+        for i in 0..field_count {
+            unsigned.append_raw(rlp.at(i).unwrap().as_raw(), 1);
+        }
+        let mut payload = vec![raw.0[0]];
+        payload.extend_from_slice(unsigned.as_raw());
+        let message = Message::from_slice(&hash::keccak256(&payload)).unwrap();
+
+        let mut sig = [0u8; 64];
+        rlp.val_at::<U256>(field_count + 1)
+            .unwrap()
+            .to_big_endian(&mut sig[..32]);
+        rlp.val_at::<U256>(field_count + 2)
+            .unwrap()
+            .to_big_endian(&mut sig[32..]);
+        let recovery_id =
+            RecoveryId::from_i32(rlp.val_at::<u8>(field_count).unwrap() as i32).unwrap();
+        let sig = RecoverableSignature::from_compact(&sig, recovery_id).unwrap();
+        let public_key = Secp256k1::verification_only()
+            .recover(&message, &sig)
+            .unwrap()
+            .serialize_uncompressed();
+
+        Address::from_slice(&hash::keccak256(&public_key[1..])[12..])
+    }
 }
@@ -6,8 +6,9 @@ use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
 use web3::error::Error;
 use web3::futures::future::{self, FutureResult};
+use web3::futures::Future;
 use web3::helpers;
-use web3::{RequestId, Transport};
+use web3::{BatchTransport, RequestId, Transport};
 
 /// Type alias for request method and value pairs
 type Requests = Vec<(String, Vec<Value>)>;
@@ -16,7 +17,8 @@ type Requests = Vec<(String, Vec<Value>)>;
 struct Inner {
     asserted: usize,
     requests: Requests,
-    responses: VecDeque<Value>,
+    responses: VecDeque<Result<Value, Error>>,
+    batches: Vec<usize>,
 }
 
 /// Test transport
@@ -38,7 +40,7 @@ impl Transport for TestTransport {
     fn send(&self, id: RequestId, request: Call) -> Self::Out {
         let mut inner = self.inner.lock().unwrap();
         match inner.responses.pop_front() {
-            Some(response) => future::ok(response),
+            Some(response) => future::result(response),
             None => {
                 println!("Unexpected request (id: {:?}): {:?}", id, request);
                 future::err(Error::Unreachable)
@@ -47,6 +49,24 @@ impl Transport for TestTransport {
     }
 }
 
+impl BatchTransport for TestTransport {
+    type Batch = FutureResult<Vec<Result<Value, Error>>, Error>;
+
+    fn send_batch<T>(&self, requests: T) -> Self::Batch
+    where
+        T: IntoIterator<Item = (RequestId, Call)>,
+    {
+        let requests = requests.into_iter().collect::<Vec<_>>();
+        self.inner.lock().unwrap().batches.push(requests.len());
+
+        let responses = requests
+            .into_iter()
+            .map(|(id, request)| self.send(id, request).wait())
+            .collect();
+        future::ok(responses)
+    }
+}
+
 impl TestTransport {
     /// Create a new test transport instance.
     pub fn new() -> Self {
@@ -56,7 +76,13 @@ impl TestTransport {
     /// Add a response to an eventual request.
     pub fn add_response(&mut self, value: Value) {
         let mut inner = self.inner.lock().unwrap();
-        inner.responses.push_back(value);
+        inner.responses.push_back(Ok(value));
+    }
+
+    /// Add an error response to an eventual request.
+    pub fn add_error(&mut self, err: Error) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.responses.push_back(Err(err));
     }
 
     /// Assert that a request was made.
@@ -80,4 +106,10 @@ impl TestTransport {
             &inner.requests[inner.asserted..]
         );
     }
+
+    /// Assert that batch requests of the specified sizes were sent.
+    pub fn assert_batches(&self, sizes: &[usize]) {
+        let inner = self.inner.lock().unwrap();
+        assert_eq!(&inner.batches[..], sizes);
+    }
 }
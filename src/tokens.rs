@@ -0,0 +1,475 @@
+//! Traits for converting Rust types to and from `ethabi` tokens. These traits
+//! mirror the ones defined in `web3::contract::tokens` but are implemented
+//! over the newer `ethabi` tokens which include support for tuples introduced
+//! by the experimental `ABIEncoderV2`.
+//!
+//! Rust tuples are tokenized into ABI tuples, this allows Solidity structs to
+//! be represented by tuples, as well as by generated types that are
+//! converted to and from tuples.
+
+use crate::abicompat::AbiCompat;
+use crate::errors::Web3ContractError;
+use crate::int::I256;
+use arrayvec::ArrayVec;
+use ethcontract_common::abi::Token;
+use web3::types::{Address, Bytes, H256, U256};
+
+/// A type that can be converted into a list of tokens, used for encoding
+/// method and constructor parameters.
+pub trait Tokenize {
+    /// Convert `self` into a list of tokens.
+    fn into_tokens(self) -> Vec<Token>;
+}
+
+impl Tokenize for () {
+    fn into_tokens(self) -> Vec<Token> {
+        Vec::new()
+    }
+}
+
+impl Tokenize for Vec<Token> {
+    fn into_tokens(self) -> Vec<Token> {
+        self
+    }
+}
+
+/// A type that can be converted to and from a single token.
+pub trait Tokenizable {
+    /// Converts a `Token` into the expected type.
+    fn from_token(token: Token) -> Result<Self, Web3ContractError>
+    where
+        Self: Sized;
+
+    /// Converts a specified type back into a token.
+    fn into_token(self) -> Token;
+
+    /// Returns the number of elements of the `Token::Tuple` this type is
+    /// converted to and from, or `None` if it is not a tuple type.
+    ///
+    /// This is used for deciding the shape of decoded event data, as events
+    /// with a single parameter can be decoded either directly into the type of
+    /// that parameter or into a one element tuple.
+    fn tuple_len() -> Option<usize>
+    where
+        Self: Sized,
+    {
+        None
+    }
+}
+
+/// Marker trait for `Tokenizable` types that can be tokenized to and from
+/// `Token::Array` and `Token::FixedArray` items.
+///
+/// This is used to differentiate `Vec<u8>` (which tokenizes to `Token::Bytes`)
+/// from other vectors.
+pub trait TokenizableItem: Tokenizable {}
+
+/// Creates an invalid output type error.
+fn invalid_output_type(expected: &str, token: Token) -> Web3ContractError {
+    Web3ContractError::InvalidOutputType(format!("Expected `{}`, got {:?}", expected, token))
+}
+
+impl Tokenizable for Token {
+    fn from_token(token: Token) -> Result<Self, Web3ContractError> {
+        Ok(token)
+    }
+
+    fn into_token(self) -> Token {
+        self
+    }
+}
+
+impl Tokenizable for String {
+    fn from_token(token: Token) -> Result<Self, Web3ContractError> {
+        match token {
+            Token::String(value) => Ok(value),
+            other => Err(invalid_output_type("String", other)),
+        }
+    }
+
+    fn into_token(self) -> Token {
+        Token::String(self)
+    }
+}
+
+impl Tokenizable for Bytes {
+    fn from_token(token: Token) -> Result<Self, Web3ContractError> {
+        match token {
+            Token::Bytes(value) => Ok(Bytes(value)),
+            other => Err(invalid_output_type("Bytes", other)),
+        }
+    }
+
+    fn into_token(self) -> Token {
+        Token::Bytes(self.0)
+    }
+}
+
+impl Tokenizable for Vec<u8> {
+    fn from_token(token: Token) -> Result<Self, Web3ContractError> {
+        match token {
+            Token::Bytes(value) | Token::FixedBytes(value) => Ok(value),
+            other => Err(invalid_output_type("bytes", other)),
+        }
+    }
+
+    fn into_token(self) -> Token {
+        Token::Bytes(self)
+    }
+}
+
+impl Tokenizable for H256 {
+    fn from_token(token: Token) -> Result<Self, Web3ContractError> {
+        match token {
+            Token::FixedBytes(value) if value.len() == 32 => Ok(H256::from_slice(&value)),
+            other => Err(invalid_output_type("H256", other)),
+        }
+    }
+
+    fn into_token(self) -> Token {
+        Token::FixedBytes(self.as_bytes().to_vec())
+    }
+}
+
+impl Tokenizable for Address {
+    fn from_token(token: Token) -> Result<Self, Web3ContractError> {
+        match token {
+            Token::Address(value) => Ok(value.compat()),
+            other => Err(invalid_output_type("Address", other)),
+        }
+    }
+
+    fn into_token(self) -> Token {
+        Token::Address(self.compat())
+    }
+}
+
+impl Tokenizable for U256 {
+    fn from_token(token: Token) -> Result<Self, Web3ContractError> {
+        // NOTE: All integer types accept both `Int` and `Uint` kind tokens.
+        match token {
+            Token::Int(value) | Token::Uint(value) => Ok(value.compat()),
+            other => Err(invalid_output_type("U256", other)),
+        }
+    }
+
+    fn into_token(self) -> Token {
+        Token::Uint(self.compat())
+    }
+}
+
+impl Tokenizable for I256 {
+    fn from_token(token: Token) -> Result<Self, Web3ContractError> {
+        match token {
+            Token::Int(value) | Token::Uint(value) => Ok(I256::from_raw(value.compat())),
+            other => Err(invalid_output_type("I256", other)),
+        }
+    }
+
+    fn into_token(self) -> Token {
+        Token::Int(self.into_raw().compat())
+    }
+}
+
+impl Tokenizable for bool {
+    fn from_token(token: Token) -> Result<Self, Web3ContractError> {
+        match token {
+            Token::Bool(value) => Ok(value),
+            other => Err(invalid_output_type("bool", other)),
+        }
+    }
+
+    fn into_token(self) -> Token {
+        Token::Bool(self)
+    }
+}
+
+macro_rules! impl_int_tokenizable {
+    ($($int:ident => $token:ident,)*) => {$(
+        impl Tokenizable for $int {
+            fn from_token(token: Token) -> Result<Self, Web3ContractError> {
+                match token {
+                    // NOTE: Truncating the two's complement representation
+                    //   yields the correct value for signed integers.
+                    Token::Int(value) | Token::Uint(value) => Ok(value.low_u128() as _),
+                    other => Err(invalid_output_type(stringify!($int), other)),
+                }
+            }
+
+            fn into_token(self) -> Token {
+                Token::$token(I256::from(self).into_raw().compat())
+            }
+        }
+    )*};
+}
+
+impl_int_tokenizable! {
+    i8 => Int, i16 => Int, i32 => Int, i64 => Int, i128 => Int,
+    u8 => Uint, u16 => Uint, u32 => Uint, u64 => Uint, u128 => Uint,
+}
+
+macro_rules! impl_tokenizable_item {
+    ($($type:ty,)*) => {$(
+        impl TokenizableItem for $type {}
+    )*};
+}
+
+impl_tokenizable_item! {
+    Token, String, Bytes, Vec<u8>, H256, Address, U256, I256, bool,
+    i8, i16, i32, i64, i128, u16, u32, u64, u128,
+}
+
+impl<T: TokenizableItem> Tokenizable for Vec<T> {
+    fn from_token(token: Token) -> Result<Self, Web3ContractError> {
+        match token {
+            Token::FixedArray(tokens) | Token::Array(tokens) => {
+                tokens.into_iter().map(Tokenizable::from_token).collect()
+            }
+            other => Err(invalid_output_type("Array", other)),
+        }
+    }
+
+    fn into_token(self) -> Token {
+        Token::Array(self.into_iter().map(Tokenizable::into_token).collect())
+    }
+}
+
+impl<T: TokenizableItem> TokenizableItem for Vec<T> {}
+
+macro_rules! impl_fixed_tokenizable {
+    ($($num:tt,)*) => {$(
+        impl Tokenizable for [u8; $num] {
+            fn from_token(token: Token) -> Result<Self, Web3ContractError> {
+                match token {
+                    Token::FixedBytes(bytes) if bytes.len() == $num => {
+                        let mut value = [0; $num];
+                        value.copy_from_slice(&bytes);
+                        Ok(value)
+                    }
+                    other => Err(invalid_output_type(concat!("FixedBytes(", $num, ")"), other)),
+                }
+            }
+
+            fn into_token(self) -> Token {
+                Token::FixedBytes(self.to_vec())
+            }
+        }
+
+        impl TokenizableItem for [u8; $num] {}
+
+        impl<T: TokenizableItem + Clone> Tokenizable for [T; $num] {
+            fn from_token(token: Token) -> Result<Self, Web3ContractError> {
+                match token {
+                    Token::FixedArray(tokens) if tokens.len() == $num => {
+                        let mut items = ArrayVec::<[T; $num]>::new();
+                        for token in tokens {
+                            items.push(T::from_token(token)?);
+                        }
+                        match items.into_inner() {
+                            Ok(value) => Ok(value),
+                            // NOTE: All elements were pushed so the array is
+                            //   guaranteed to be full.
+                            Err(_) => unreachable!(),
+                        }
+                    }
+                    other => Err(invalid_output_type(concat!("FixedArray(", $num, ")"), other)),
+                }
+            }
+
+            fn into_token(self) -> Token {
+                Token::FixedArray(
+                    ArrayVec::from(self)
+                        .into_iter()
+                        .map(Tokenizable::into_token)
+                        .collect(),
+                )
+            }
+        }
+
+        impl<T: TokenizableItem + Clone> TokenizableItem for [T; $num] {}
+    )*};
+}
+
+impl_fixed_tokenizable! {
+    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16,
+    17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32,
+    64, 128, 256, 512, 1024,
+}
+
+impl Tokenizable for () {
+    fn from_token(token: Token) -> Result<Self, Web3ContractError> {
+        match token {
+            Token::Tuple(tokens) if tokens.is_empty() => Ok(()),
+            other => Err(invalid_output_type("()", other)),
+        }
+    }
+
+    fn into_token(self) -> Token {
+        Token::Tuple(Vec::new())
+    }
+
+    fn tuple_len() -> Option<usize> {
+        Some(0)
+    }
+}
+
+macro_rules! impl_tuple_tokenize {
+    ($($len:tt => ($($ty:ident : $no:tt),*),)*) => {$(
+        impl<$($ty),*> Tokenize for ($($ty,)*)
+        where
+            $($ty: Tokenizable,)*
+        {
+            fn into_tokens(self) -> Vec<Token> {
+                vec![$(self.$no.into_token(),)*]
+            }
+        }
+
+        impl<$($ty),*> Tokenizable for ($($ty,)*)
+        where
+            $($ty: Tokenizable,)*
+        {
+            fn from_token(token: Token) -> Result<Self, Web3ContractError> {
+                match token {
+                    Token::Tuple(tokens) if tokens.len() == $len => {
+                        let mut tokens = tokens.into_iter();
+                        Ok(($(
+                            $ty::from_token(tokens.next().expect("tuple length checked"))?,
+                        )*))
+                    }
+                    other => Err(invalid_output_type(concat!("Tuple(", $len, ")"), other)),
+                }
+            }
+
+            fn into_token(self) -> Token {
+                Token::Tuple(self.into_tokens())
+            }
+
+            fn tuple_len() -> Option<usize> {
+                Some($len)
+            }
+        }
+
+        impl<$($ty),*> TokenizableItem for ($($ty,)*)
+        where
+            $($ty: Tokenizable,)*
+        {
+        }
+    )*};
+}
+
+impl_tuple_tokenize! {
+    1 => (A:0),
+    2 => (A:0, B:1),
+    3 => (A:0, B:1, C:2),
+    4 => (A:0, B:1, C:2, D:3),
+    5 => (A:0, B:1, C:2, D:3, E:4),
+    6 => (A:0, B:1, C:2, D:3, E:4, F:5),
+    7 => (A:0, B:1, C:2, D:3, E:4, F:5, G:6),
+    8 => (A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7),
+    9 => (A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7, I:8),
+    10 => (A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7, I:8, J:9),
+    11 => (A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7, I:8, J:9, K:10),
+    12 => (A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7, I:8, J:9, K:10, L:11),
+    13 => (A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7, I:8, J:9, K:10, L:11, M:12),
+    14 => (A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7, I:8, J:9, K:10, L:11, M:12, N:13),
+    15 => (A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7, I:8, J:9, K:10, L:11, M:12, N:13, O:14),
+    16 => (A:0, B:1, C:2, D:3, E:4, F:5, G:6, H:7, I:8, J:9, K:10, L:11, M:12, N:13, O:14, P:15),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethcontract_common::abi::{encode, ParamType};
+
+    fn round_trip<T>(value: T) -> T
+    where
+        T: Tokenizable + Clone,
+    {
+        T::from_token(value.into_token()).expect("failed to detokenize")
+    }
+
+    #[test]
+    fn primitive_round_trip() {
+        let address = addr!("0x0123456789012345678901234567890123456789");
+        assert_eq!(round_trip(address), address);
+        assert_eq!(round_trip(U256::from(42)), U256::from(42));
+        assert_eq!(round_trip(I256::minus_one()), I256::minus_one());
+        assert_eq!(round_trip(-42i8), -42);
+        assert_eq!(round_trip(1337u64), 1337);
+        assert!(round_trip(true));
+        assert_eq!(round_trip("hello".to_owned()), "hello");
+        assert_eq!(round_trip(vec![1u8, 2, 3]), vec![1, 2, 3]);
+        assert_eq!(round_trip([1u8; 4]), [1; 4]);
+        assert_eq!(round_trip([U256::one(); 2]), [U256::one(); 2]);
+    }
+
+    #[test]
+    fn signed_integers_are_twos_complement() {
+        assert_eq!(
+            (-1i32).into_token(),
+            Token::Int(I256::minus_one().into_raw().compat()),
+        );
+        assert_eq!(i16::from_token(I256::from(-2).into_token()).unwrap(), -2);
+    }
+
+    #[test]
+    fn tuple_tokenization() {
+        let value = (U256::from(1), (true, vec!["a".to_owned()]));
+        let token = value.clone().into_token();
+
+        assert_eq!(
+            token,
+            Token::Tuple(vec![
+                Token::Uint(1.into()),
+                Token::Tuple(vec![
+                    Token::Bool(true),
+                    Token::Array(vec![Token::String("a".to_owned())]),
+                ]),
+            ]),
+        );
+        assert_eq!(round_trip(value.clone()), value);
+
+        // NOTE: Make sure that tuples encode the same way `ethabi` would expect
+        //   them to for the matching parameter type.
+        let kind = ParamType::Tuple(vec![
+            Box::new(ParamType::Uint(256)),
+            Box::new(ParamType::Tuple(vec![
+                Box::new(ParamType::Bool),
+                Box::new(ParamType::Array(Box::new(ParamType::String))),
+            ])),
+        ]);
+        let encoded = encode(&[token]);
+        let decoded = ethcontract_common::abi::decode(&[kind], &encoded).unwrap();
+        assert_eq!(
+            <(U256, (bool, Vec<String>))>::from_token(decoded[0].clone()).unwrap(),
+            value,
+        );
+    }
+
+    #[test]
+    fn tuple_tokenize_params() {
+        assert_eq!(().into_tokens(), vec![]);
+        assert_eq!(
+            (true, (42u8,)).into_tokens(),
+            vec![
+                Token::Bool(true),
+                Token::Tuple(vec![Token::Uint(42.into())]),
+            ],
+        );
+    }
+
+    #[test]
+    fn tuple_length_mismatch() {
+        assert!(<(bool, bool)>::from_token(Token::Tuple(vec![Token::Bool(true)])).is_err());
+        assert!(<(bool,)>::from_token(Token::Bool(true)).is_err());
+    }
+
+    #[test]
+    fn tuple_len() {
+        assert_eq!(<()>::tuple_len(), Some(0));
+        assert_eq!(<(bool,)>::tuple_len(), Some(1));
+        assert_eq!(<(bool, U256, Address)>::tuple_len(), Some(3));
+        assert_eq!(U256::tuple_len(), None);
+        assert_eq!(Token::tuple_len(), None);
+    }
+}
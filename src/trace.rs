@@ -0,0 +1,673 @@
+//! Module for tracing the execution of contract calls and transactions into a
+//! call tree. This is useful for finding out where exactly a transaction
+//! interacting with multiple contracts failed.
+//!
+//! Call traces can be retrieved with Geth's `debug_traceCall` and
+//! `debug_traceTransaction` using the built-in call tracer, or with the
+//! Parity-style `trace_call` and `trace_transaction` APIs supported by
+//! OpenEthereum, Erigon and Nethermind.
+
+mod decode;
+
+pub use self::decode::{DecodedCallFrame, DecodedCustomError, TraceDecoder, TraceableContract};
+
+use crate::errors::{revert, ExecutionError};
+use crate::future::CompatCallFuture;
+use crate::transaction::estimate_gas::EstimateGasRequest;
+use futures::compat::Future01CompatExt;
+use futures::ready;
+use pin_project::{pin_project, project};
+use serde::Deserialize;
+use serde_json::json;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use web3::api::Web3;
+use web3::error::Error as Web3Error;
+use web3::helpers::{self, CallFuture};
+use web3::types::{Address, BlockNumber, Bytes, H256, U256};
+use web3::Transport;
+
+/// The tracing API to use for retrieving call traces.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Tracer {
+    /// Use the Geth `debug_traceCall` and `debug_traceTransaction` APIs with
+    /// the built-in call tracer.
+    Geth,
+    /// Use the Parity-style `trace_call` and `trace_transaction` APIs.
+    Parity,
+}
+
+/// The type of a call frame.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum CallType {
+    /// A regular message call.
+    Call,
+    /// A static message call that is not allowed to modify state.
+    StaticCall,
+    /// A call executing the callee's code in the context of the caller.
+    DelegateCall,
+    /// A legacy call executing the callee's code in the context of the
+    /// caller with the caller as the sender.
+    CallCode,
+    /// A contract creation.
+    Create,
+    /// A contract creation with a deterministic address.
+    Create2,
+    /// A contract self-destruct transferring its balance to a beneficiary.
+    SelfDestruct,
+}
+
+/// A frame of a call trace, representing a single call along with all of the
+/// sub-calls it made.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrame {
+    /// The type of call.
+    #[serde(rename = "type")]
+    pub call_type: CallType,
+    /// The caller.
+    pub from: Address,
+    /// The callee, or the created contract for contract creations. This is
+    /// `None` for contract creations that failed.
+    #[serde(default)]
+    pub to: Option<Address>,
+    /// The ETH value transferred with the call.
+    #[serde(default)]
+    pub value: Option<U256>,
+    /// The gas provided to the call.
+    #[serde(default)]
+    pub gas: U256,
+    /// The gas used by the call, including sub-calls.
+    #[serde(default)]
+    pub gas_used: U256,
+    /// The call data, or the init code for contract creations.
+    #[serde(default)]
+    pub input: Bytes,
+    /// The data returned by the call, or the revert data if the call reverted.
+    #[serde(default)]
+    pub output: Option<Bytes>,
+    /// The error message if the call failed.
+    #[serde(default)]
+    pub error: Option<String>,
+    /// The sub-calls made by this call, in execution order.
+    #[serde(default)]
+    pub calls: Vec<CallFrame>,
+}
+
+impl CallFrame {
+    /// Returns true if the call failed.
+    pub fn is_error(&self) -> bool {
+        self.error.is_some()
+    }
+
+    /// Retrieves the error that the call failed with. Returns `None` if the
+    /// call did not fail, or if it failed for a reason that cannot be
+    /// represented as an `ExecutionError`, in which case the raw error
+    /// message is still available in `error`.
+    pub fn execution_error(&self) -> Option<ExecutionError> {
+        let message = self.error.as_ref()?.to_lowercase();
+        match &self.output {
+            Some(output) if !output.0.is_empty() => Some(revert::decode_revert_data(&output.0)),
+            _ if message.contains("out of gas") => Some(ExecutionError::OutOfGas),
+            _ if message.contains("invalid opcode") || message.contains("bad instruction") => {
+                Some(ExecutionError::InvalidOpcode(None))
+            }
+            _ if message.contains("revert") => Some(ExecutionError::Revert(None, None)),
+            _ => None,
+        }
+    }
+
+    /// Retrieves the path of failed calls that lead to this call failing,
+    /// starting with this call and ending with the deepest failed call. The
+    /// path is empty if this call did not fail.
+    ///
+    /// Note that a call can fail after catching the failure of one of its
+    /// sub-calls, so the path follows the last failed sub-call of each failed
+    /// call, as this is what failures bubble up from in most cases.
+    pub fn error_path(&self) -> Vec<&CallFrame> {
+        let mut path = Vec::new();
+        if !self.is_error() {
+            return path;
+        }
+
+        let mut frame = self;
+        loop {
+            path.push(frame);
+            match frame.calls.iter().rev().find(|call| call.is_error()) {
+                Some(call) => frame = call,
+                None => return path,
+            }
+        }
+    }
+
+    /// Decodes the call tree with the contracts known to a trace decoder.
+    pub fn decode<'a>(&'a self, decoder: &'a TraceDecoder) -> DecodedCallFrame<'a> {
+        decoder.decode(self)
+    }
+}
+
+/// Future for retrieving a call trace.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[pin_project]
+pub struct TraceFuture<T: Transport>(#[pin] TraceState<T>);
+
+/// The state of the trace future, depending on the tracing API used.
+#[pin_project]
+enum TraceState<T: Transport> {
+    /// Retrieving a call tree with the Geth call tracer.
+    Geth(#[pin] CompatCallFuture<T, CallFrame>),
+    /// Retrieving the result of a Parity-style `trace_call`.
+    ParityCall(#[pin] CompatCallFuture<T, ParityTraceResults>),
+    /// Retrieving the flat traces of a Parity-style `trace_transaction`.
+    ParityTransaction(#[pin] CompatCallFuture<T, Vec<ParityTrace>>),
+}
+
+impl<T: Transport> TraceFuture<T> {
+    /// Create a future that traces executing a call at the specified block
+    /// without sending a transaction.
+    pub fn call(
+        web3: &Web3<T>,
+        request: EstimateGasRequest,
+        block: BlockNumber,
+        tracer: Tracer,
+    ) -> Self {
+        let request = helpers::serialize(&request);
+        let block = helpers::serialize(&block);
+        let state = match tracer {
+            Tracer::Geth => TraceState::Geth(execute(
+                web3,
+                "debug_traceCall",
+                vec![request, block, call_tracer()],
+            )),
+            Tracer::Parity => TraceState::ParityCall(execute(
+                web3,
+                "trace_call",
+                vec![request, json!(["trace"]), block],
+            )),
+        };
+
+        TraceFuture(state)
+    }
+
+    /// Create a future that traces a transaction that was already mined.
+    pub fn transaction(web3: &Web3<T>, tx: H256, tracer: Tracer) -> Self {
+        let tx = helpers::serialize(&tx);
+        let state = match tracer {
+            Tracer::Geth => TraceState::Geth(execute(
+                web3,
+                "debug_traceTransaction",
+                vec![tx, call_tracer()],
+            )),
+            Tracer::Parity => {
+                TraceState::ParityTransaction(execute(web3, "trace_transaction", vec![tx]))
+            }
+        };
+
+        TraceFuture(state)
+    }
+}
+
+/// Executes a JSON RPC request for retrieving traces.
+fn execute<T, R>(
+    web3: &Web3<T>,
+    method: &str,
+    params: Vec<serde_json::Value>,
+) -> CompatCallFuture<T, R>
+where
+    T: Transport,
+    R: serde::de::DeserializeOwned,
+{
+    CallFuture::new(web3.transport().execute(method, params)).compat()
+}
+
+/// The Geth tracer options for using the built-in call tracer.
+fn call_tracer() -> serde_json::Value {
+    json!({ "tracer": "callTracer" })
+}
+
+impl<T: Transport> Future for TraceFuture<T> {
+    type Output = Result<CallFrame, ExecutionError>;
+
+    #[project]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        #[project]
+        let result = match self.project().0.project() {
+            TraceState::Geth(trace) => ready!(trace.poll(cx))?,
+            TraceState::ParityCall(trace) => call_tree(ready!(trace.poll(cx))?.trace)?,
+            TraceState::ParityTransaction(trace) => call_tree(ready!(trace.poll(cx))?)?,
+        };
+
+        Poll::Ready(Ok(result))
+    }
+}
+
+/// The result of a Parity-style `trace_call` with the `trace` trace type.
+#[derive(Debug, Deserialize)]
+struct ParityTraceResults {
+    trace: Vec<ParityTrace>,
+}
+
+/// A flat Parity-style trace of a single call.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ParityTrace {
+    #[serde(rename = "type")]
+    kind: String,
+    action: ParityAction,
+    #[serde(default)]
+    result: Option<ParityResult>,
+    #[serde(default)]
+    error: Option<String>,
+    trace_address: Vec<usize>,
+}
+
+/// The action of a Parity-style trace. The fields that are set depend on the
+/// trace type.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ParityAction {
+    call_type: Option<String>,
+    creation_method: Option<String>,
+    from: Option<Address>,
+    to: Option<Address>,
+    value: Option<U256>,
+    gas: Option<U256>,
+    input: Option<Bytes>,
+    init: Option<Bytes>,
+    address: Option<Address>,
+    refund_address: Option<Address>,
+    balance: Option<U256>,
+}
+
+/// The result of a Parity-style trace. The fields that are set depend on the
+/// trace type.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ParityResult {
+    gas_used: Option<U256>,
+    output: Option<Bytes>,
+    address: Option<Address>,
+    code: Option<Bytes>,
+}
+
+/// Builds a call tree from flat Parity-style traces. The traces are ordered
+/// depth-first, with each trace address indicating the position of the call
+/// in the tree.
+fn call_tree(traces: Vec<ParityTrace>) -> Result<CallFrame, ExecutionError> {
+    let mut traces = traces.into_iter();
+    let mut root = match traces.next() {
+        Some(trace) if trace.trace_address.is_empty() => call_frame(trace)?,
+        _ => return Err(invalid_trace("missing root call trace")),
+    };
+
+    for trace in traces {
+        let (position, parents) = match trace.trace_address.split_last() {
+            Some((position, parents)) => (*position, parents.to_vec()),
+            None => return Err(invalid_trace("multiple root call traces")),
+        };
+
+        let mut parent = &mut root;
+        for &index in &parents {
+            parent = parent
+                .calls
+                .get_mut(index)
+                .ok_or_else(|| invalid_trace("call trace without a parent"))?;
+        }
+        if parent.calls.len() != position {
+            return Err(invalid_trace("call traces out of order"));
+        }
+
+        parent.calls.push(call_frame(trace)?);
+    }
+
+    Ok(root)
+}
+
+/// Converts a single Parity-style trace into a call frame without sub-calls.
+fn call_frame(trace: ParityTrace) -> Result<CallFrame, ExecutionError> {
+    let ParityTrace {
+        kind,
+        action,
+        result,
+        error,
+        ..
+    } = trace;
+    let result = result.unwrap_or_default();
+
+    let frame = match kind.as_str() {
+        "call" => CallFrame {
+            call_type: match action.call_type.as_deref() {
+                Some("staticcall") => CallType::StaticCall,
+                Some("delegatecall") => CallType::DelegateCall,
+                Some("callcode") => CallType::CallCode,
+                _ => CallType::Call,
+            },
+            from: action.from.unwrap_or_default(),
+            to: action.to,
+            value: action.value,
+            gas: action.gas.unwrap_or_default(),
+            gas_used: result.gas_used.unwrap_or_default(),
+            input: action.input.unwrap_or_default(),
+            output: result.output,
+            error,
+            calls: Vec::new(),
+        },
+        "create" => CallFrame {
+            call_type: match action.creation_method.as_deref() {
+                Some("create2") => CallType::Create2,
+                _ => CallType::Create,
+            },
+            from: action.from.unwrap_or_default(),
+            to: result.address,
+            value: action.value,
+            gas: action.gas.unwrap_or_default(),
+            gas_used: result.gas_used.unwrap_or_default(),
+            input: action.init.unwrap_or_default(),
+            output: result.code.or(result.output),
+            error,
+            calls: Vec::new(),
+        },
+        "suicide" => CallFrame {
+            call_type: CallType::SelfDestruct,
+            from: action.address.unwrap_or_default(),
+            to: action.refund_address,
+            value: action.balance,
+            gas: U256::zero(),
+            gas_used: U256::zero(),
+            input: Bytes::default(),
+            output: None,
+            error,
+            calls: Vec::new(),
+        },
+        _ => return Err(invalid_trace(&format!("unsupported trace type '{}'", kind))),
+    };
+
+    Ok(frame)
+}
+
+/// Creates an error for an invalid trace response.
+fn invalid_trace(message: &str) -> ExecutionError {
+    ExecutionError::Web3(Web3Error::InvalidResponse(format!(
+        "invalid call trace: {}",
+        message
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+
+    fn geth_trace() -> serde_json::Value {
+        json!({
+            "type": "CALL",
+            "from": "0x9876543210987654321098765432109876543210",
+            "to": "0x0123456789012345678901234567890123456789",
+            "value": "0x0",
+            "gas": "0x10000",
+            "gasUsed": "0x5000",
+            "input": "0x13371337",
+            "output": revert::encode_reason_hex("message"),
+            "error": "execution reverted",
+            "revertReason": "message",
+            "calls": [
+                {
+                    "type": "STATICCALL",
+                    "from": "0x0123456789012345678901234567890123456789",
+                    "to": "0x1111111111111111111111111111111111111111",
+                    "gas": "0x8000",
+                    "gasUsed": "0x100",
+                    "input": "0x",
+                    "output": "0x",
+                },
+                {
+                    "type": "DELEGATECALL",
+                    "from": "0x0123456789012345678901234567890123456789",
+                    "to": "0x2222222222222222222222222222222222222222",
+                    "gas": "0x8000",
+                    "gasUsed": "0x200",
+                    "input": "0x",
+                    "output": revert::encode_reason_hex("message"),
+                    "error": "execution reverted",
+                },
+            ],
+        })
+    }
+
+    #[test]
+    fn trace_call_with_geth() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let to = addr!("0x0123456789012345678901234567890123456789");
+
+        transport.add_response(geth_trace());
+        let trace = TraceFuture::call(
+            &web3,
+            EstimateGasRequest {
+                from: Some(from),
+                to: Some(to),
+                gas: None,
+                gas_price: None,
+                value: None,
+                data: Some(Bytes(vec![0x13, 0x37, 0x13, 0x37])),
+            },
+            BlockNumber::Latest,
+            Tracer::Geth,
+        )
+        .immediate()
+        .expect("trace failed");
+
+        transport.assert_request(
+            "debug_traceCall",
+            &[
+                json!({
+                    "from": from,
+                    "to": to,
+                    "data": "0x13371337",
+                }),
+                json!("latest"),
+                json!({ "tracer": "callTracer" }),
+            ],
+        );
+        transport.assert_no_more_requests();
+
+        assert_eq!(trace.call_type, CallType::Call);
+        assert_eq!(trace.to, Some(to));
+        assert_eq!(trace.calls.len(), 2);
+        assert_eq!(trace.calls[0].call_type, CallType::StaticCall);
+        assert_eq!(trace.calls[0].value, None);
+        assert!(
+            matches!(
+                trace.execution_error(),
+                Some(ExecutionError::Revert(Some(reason), _)) if reason == "message"
+            ),
+            "unexpected error {:?}",
+            trace.execution_error(),
+        );
+
+        let path = trace.error_path();
+        assert_eq!(path.len(), 2);
+        assert_eq!(
+            path[1].to,
+            Some(addr!("0x2222222222222222222222222222222222222222"))
+        );
+        assert!(trace.calls[0].error_path().is_empty());
+    }
+
+    #[test]
+    fn trace_transaction_with_parity() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let hash = H256::repeat_byte(0x42);
+        transport.add_response(json!([
+            {
+                "type": "call",
+                "action": {
+                    "callType": "call",
+                    "from": "0x9876543210987654321098765432109876543210",
+                    "to": "0x0123456789012345678901234567890123456789",
+                    "value": "0x1",
+                    "gas": "0x10000",
+                    "input": "0x13371337",
+                },
+                "result": null,
+                "error": "Reverted",
+                "subtraces": 2,
+                "traceAddress": [],
+            },
+            {
+                "type": "create",
+                "action": {
+                    "from": "0x0123456789012345678901234567890123456789",
+                    "value": "0x0",
+                    "gas": "0x8000",
+                    "init": "0x6000",
+                },
+                "result": {
+                    "address": "0x1111111111111111111111111111111111111111",
+                    "code": "0x00",
+                    "gasUsed": "0x1000",
+                },
+                "subtraces": 1,
+                "traceAddress": [0],
+            },
+            {
+                "type": "suicide",
+                "action": {
+                    "address": "0x1111111111111111111111111111111111111111",
+                    "refundAddress": "0x0123456789012345678901234567890123456789",
+                    "balance": "0x0",
+                },
+                "result": null,
+                "subtraces": 0,
+                "traceAddress": [0, 0],
+            },
+            {
+                "type": "call",
+                "action": {
+                    "callType": "staticcall",
+                    "from": "0x0123456789012345678901234567890123456789",
+                    "to": "0x2222222222222222222222222222222222222222",
+                    "gas": "0x8000",
+                    "input": "0x",
+                },
+                "result": {
+                    "gasUsed": "0x100",
+                    "output": "0x",
+                },
+                "subtraces": 0,
+                "traceAddress": [1],
+            },
+        ]));
+        let trace = TraceFuture::transaction(&web3, hash, Tracer::Parity)
+            .immediate()
+            .expect("trace failed");
+
+        transport.assert_request("trace_transaction", &[json!(hash)]);
+        transport.assert_no_more_requests();
+
+        assert_eq!(trace.call_type, CallType::Call);
+        assert_eq!(trace.value, Some(1.into()));
+        assert!(matches!(
+            trace.execution_error(),
+            Some(ExecutionError::Revert(None, _))
+        ));
+        assert_eq!(trace.calls.len(), 2);
+        assert_eq!(trace.calls[0].call_type, CallType::Create);
+        assert_eq!(
+            trace.calls[0].to,
+            Some(addr!("0x1111111111111111111111111111111111111111"))
+        );
+        assert_eq!(trace.calls[0].calls[0].call_type, CallType::SelfDestruct);
+        assert_eq!(trace.calls[1].call_type, CallType::StaticCall);
+        assert_eq!(trace.calls[1].gas_used, 0x100.into());
+        assert_eq!(trace.error_path().len(), 1);
+    }
+
+    #[test]
+    fn trace_call_with_parity() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let to = addr!("0x0123456789012345678901234567890123456789");
+
+        transport.add_response(json!({
+            "output": "0x",
+            "stateDiff": null,
+            "trace": [{
+                "type": "call",
+                "action": {
+                    "callType": "call",
+                    "from": "0x0000000000000000000000000000000000000000",
+                    "to": to,
+                    "value": "0x0",
+                    "gas": "0x10000",
+                    "input": "0x",
+                },
+                "result": {
+                    "gasUsed": "0x0",
+                    "output": "0x",
+                },
+                "subtraces": 0,
+                "traceAddress": [],
+            }],
+            "vmTrace": null,
+        }));
+        let trace = TraceFuture::call(
+            &web3,
+            EstimateGasRequest {
+                from: None,
+                to: Some(to),
+                gas: None,
+                gas_price: None,
+                value: None,
+                data: None,
+            },
+            BlockNumber::Number(42.into()),
+            Tracer::Parity,
+        )
+        .immediate()
+        .expect("trace failed");
+
+        transport.assert_request(
+            "trace_call",
+            &[json!({ "to": to }), json!(["trace"]), json!("0x2a")],
+        );
+        transport.assert_no_more_requests();
+
+        assert_eq!(trace.to, Some(to));
+        assert!(!trace.is_error());
+        assert!(trace.calls.is_empty());
+    }
+
+    #[test]
+    fn trace_with_parity_invalid_traces() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let trace = |web3: &Web3<TestTransport>| {
+            TraceFuture::transaction(web3, H256::zero(), Tracer::Parity)
+                .immediate()
+                .expect_err("unexpected trace success")
+        };
+        let call = |address: serde_json::Value| {
+            json!({
+                "type": "call",
+                "action": {},
+                "traceAddress": address,
+            })
+        };
+
+        transport.add_response(json!([]));
+        trace(&web3);
+        transport.add_response(json!([call(json!([])), call(json!([1]))]));
+        trace(&web3);
+        transport.add_response(json!([call(json!([])), call(json!([0, 0]))]));
+        trace(&web3);
+    }
+}
@@ -0,0 +1,423 @@
+//! Implementation of decoding call traces against known contract ABIs.
+
+use crate::errors::ExecutionError;
+use crate::trace::{CallFrame, CallType};
+use ethcontract_common::abi::{Function, Token};
+use ethcontract_common::abiext::FunctionExt;
+use ethcontract_common::hash::H32;
+use ethcontract_common::{Artifact, CustomError};
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use web3::types::Address;
+
+/// A contract with a known address and artifact. This is implemented by
+/// generated contract types so that they can be added to a `TraceDecoder`.
+pub trait TraceableContract {
+    /// The address the contract is deployed at.
+    fn address(&self) -> Address;
+
+    /// The artifact of the contract.
+    fn artifact(&self) -> &Artifact;
+}
+
+/// The functions and custom errors of a known contract, indexed by selector.
+#[derive(Clone, Debug)]
+struct KnownContract {
+    name: String,
+    functions: HashMap<H32, Function>,
+    errors: HashMap<H32, CustomError>,
+}
+
+impl KnownContract {
+    fn new(artifact: &Artifact) -> Self {
+        KnownContract {
+            name: artifact.contract_name.clone(),
+            functions: artifact
+                .abi
+                .functions()
+                .map(|function| (function.selector(), function.clone()))
+                .collect(),
+            errors: artifact
+                .errors
+                .values()
+                .flatten()
+                .map(|error| (error.selector(), error.clone()))
+                .collect(),
+        }
+    }
+}
+
+/// A decoder for call traces that decodes the input, output and errors of
+/// calls to known contracts.
+#[derive(Clone, Debug, Default)]
+pub struct TraceDecoder {
+    /// Contracts known to be deployed at specific addresses.
+    contracts: HashMap<Address, KnownContract>,
+    /// Contract interfaces used for decoding calls to any other address, for
+    /// example for decoding calls to tokens implementing a standard interface.
+    interfaces: Vec<KnownContract>,
+}
+
+impl TraceDecoder {
+    /// Creates a new decoder without any known contracts.
+    pub fn new() -> Self {
+        TraceDecoder::default()
+    }
+
+    /// Add a generated contract instance to the decoder, so that calls to its
+    /// address are decoded with its ABI.
+    pub fn contract<C: TraceableContract>(self, contract: &C) -> Self {
+        self.contract_at(contract.address(), contract.artifact())
+    }
+
+    /// Add a contract artifact deployed at the specified address to the
+    /// decoder, so that calls to the address are decoded with its ABI.
+    pub fn contract_at(mut self, address: Address, artifact: &Artifact) -> Self {
+        self.contracts.insert(address, KnownContract::new(artifact));
+        self
+    }
+
+    /// Add a contract interface to the decoder, that is used for decoding
+    /// calls to addresses that are not known to the decoder with matching
+    /// function selectors.
+    pub fn interface(mut self, artifact: &Artifact) -> Self {
+        self.interfaces.push(KnownContract::new(artifact));
+        self
+    }
+
+    /// Decodes a call tree.
+    pub fn decode<'a>(&'a self, frame: &'a CallFrame) -> DecodedCallFrame<'a> {
+        let selector = match frame.call_type {
+            CallType::Create | CallType::Create2 | CallType::SelfDestruct => None,
+            _ => selector(&frame.input.0),
+        };
+        let contract = self.find_contract(frame.to, selector);
+        let function = contract.and_then(|contract| contract.functions.get(&selector?));
+
+        let inputs = function.and_then(|function| function.decode_input(&frame.input.0[4..]).ok());
+        let outputs = match (function, &frame.output) {
+            (Some(function), Some(output)) if !frame.is_error() => {
+                function.decode_output(&output.0).ok()
+            }
+            _ => None,
+        };
+
+        let error = frame.execution_error();
+        let custom_error = match &error {
+            Some(ExecutionError::CustomError(data)) => self.decode_custom_error(contract, data),
+            _ => None,
+        };
+
+        DecodedCallFrame {
+            frame,
+            contract: contract.map(|contract| contract.name.as_str()),
+            function,
+            inputs,
+            outputs,
+            error,
+            custom_error,
+            calls: frame.calls.iter().map(|call| self.decode(call)).collect(),
+        }
+    }
+
+    /// Finds the known contract for the callee of a call. Contracts known to
+    /// be deployed at the callee address take precedence over interfaces.
+    fn find_contract(&self, to: Option<Address>, selector: Option<H32>) -> Option<&KnownContract> {
+        if let Some(contract) = to.and_then(|to| self.contracts.get(&to)) {
+            return Some(contract);
+        }
+
+        let selector = selector?;
+        self.interfaces
+            .iter()
+            .find(|interface| interface.functions.contains_key(&selector))
+    }
+
+    /// Decodes custom error revert data, preferring the errors declared by the
+    /// callee. Since errors bubble up from sub-calls, the errors of all other
+    /// known contracts are also considered.
+    fn decode_custom_error<'a>(
+        &'a self,
+        callee: Option<&'a KnownContract>,
+        data: &[u8],
+    ) -> Option<DecodedCustomError<'a>> {
+        let selector = selector(data)?;
+        let error = callee
+            .into_iter()
+            .chain(self.contracts.values())
+            .chain(&self.interfaces)
+            .find_map(|contract| contract.errors.get(&selector))?;
+        let params = error.decode(data).ok()?;
+
+        Some(DecodedCustomError { error, params })
+    }
+}
+
+/// Retrieves the 4-byte selector prefixing call data or revert data.
+fn selector(data: &[u8]) -> Option<H32> {
+    let mut selector = H32::default();
+    selector.copy_from_slice(data.get(..4)?);
+    Some(selector)
+}
+
+/// A call frame decoded with the contracts known to a `TraceDecoder`.
+#[derive(Debug)]
+pub struct DecodedCallFrame<'a> {
+    /// The raw call frame.
+    pub frame: &'a CallFrame,
+    /// The name of the called contract, if it is known.
+    pub contract: Option<&'a str>,
+    /// The called function, if the called contract and function are known.
+    pub function: Option<&'a Function>,
+    /// The decoded function parameters.
+    pub inputs: Option<Vec<Token>>,
+    /// The decoded function return values, only set if the call succeeded.
+    pub outputs: Option<Vec<Token>>,
+    /// The error the call failed with.
+    pub error: Option<ExecutionError>,
+    /// The decoded custom error the call reverted with, if the error is
+    /// declared by a known contract.
+    pub custom_error: Option<DecodedCustomError<'a>>,
+    /// The decoded sub-calls.
+    pub calls: Vec<DecodedCallFrame<'a>>,
+}
+
+/// A decoded Solidity custom error.
+#[derive(Clone, Debug)]
+pub struct DecodedCustomError<'a> {
+    /// The custom error declaration.
+    pub error: &'a CustomError,
+    /// The decoded error parameters.
+    pub params: Vec<Token>,
+}
+
+impl DecodedCallFrame<'_> {
+    /// Retrieves the path of failed calls that lead to this call failing. See
+    /// `CallFrame::error_path` for more details.
+    pub fn error_path(&self) -> Vec<&Self> {
+        let mut path = Vec::new();
+        if !self.frame.is_error() {
+            return path;
+        }
+
+        let mut frame = self;
+        loop {
+            path.push(frame);
+            match frame.calls.iter().rev().find(|call| call.frame.is_error()) {
+                Some(call) => frame = call,
+                None => return path,
+            }
+        }
+    }
+
+    fn fmt_indented(&self, f: &mut Formatter, depth: usize) -> fmt::Result {
+        write!(
+            f,
+            "{:indent$}{:?} ",
+            "",
+            self.frame.call_type,
+            indent = depth * 2
+        )?;
+        match self.contract {
+            Some(contract) => write!(f, "{}", contract)?,
+            None => write!(f, "{:?}", self.frame.to.unwrap_or_default())?,
+        }
+        match (self.function, &self.inputs) {
+            (Some(function), Some(inputs)) => {
+                write!(f, ".{}({})", function.name, join_tokens(inputs))?
+            }
+            (Some(function), None) => write!(f, ".{}(..)", function.name)?,
+            _ => {}
+        }
+
+        if let Some(outputs) = &self.outputs {
+            write!(f, " -> ({})", join_tokens(outputs))?;
+        }
+        match (&self.custom_error, &self.error, &self.frame.error) {
+            (Some(custom), _, _) => write!(
+                f,
+                " !! {}({})",
+                custom.error.name,
+                join_tokens(&custom.params)
+            )?,
+            (None, Some(error), _) => write!(f, " !! {}", error)?,
+            (None, None, Some(error)) => write!(f, " !! {}", error)?,
+            _ => {}
+        }
+        writeln!(f)?;
+
+        for call in &self.calls {
+            call.fmt_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats tokens as a comma separated list.
+fn join_tokens(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|token| token.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl Display for DecodedCallFrame<'_> {
+    /// Formats the decoded call tree with one indented line per call.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::revert;
+    use crate::tokens::Tokenizable;
+    use web3::types::Bytes;
+
+    fn artifact() -> Artifact {
+        Artifact::from_json(
+            r#"{
+                "contractName": "Token",
+                "abi": [
+                    {
+                        "type": "function",
+                        "name": "transfer",
+                        "inputs": [
+                            { "name": "to", "type": "address" },
+                            { "name": "amount", "type": "uint256" }
+                        ],
+                        "outputs": [{ "name": "", "type": "bool" }],
+                        "stateMutability": "nonpayable"
+                    },
+                    {
+                        "type": "error",
+                        "name": "InsufficientBalance",
+                        "inputs": [
+                            { "name": "available", "type": "uint256" },
+                            { "name": "required", "type": "uint256" }
+                        ]
+                    }
+                ]
+            }"#,
+        )
+        .expect("valid artifact")
+    }
+
+    fn call(to: Address, input: Vec<u8>, output: Vec<u8>, error: Option<&str>) -> CallFrame {
+        CallFrame {
+            call_type: CallType::Call,
+            from: Address::zero(),
+            to: Some(to),
+            value: None,
+            gas: 0.into(),
+            gas_used: 0.into(),
+            input: Bytes(input),
+            output: Some(Bytes(output)),
+            error: error.map(String::from),
+            calls: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn decode_call_tree() {
+        let artifact = artifact();
+        let token = addr!("0x0123456789012345678901234567890123456789");
+        let other = addr!("0x1111111111111111111111111111111111111111");
+        let unknown = addr!("0x2222222222222222222222222222222222222222");
+
+        let transfer = artifact.abi.function("transfer").unwrap();
+        let input = transfer
+            .encode_input(&[other.into_token(), 100u64.into_token()])
+            .unwrap();
+        let error = &artifact.errors["InsufficientBalance"][0];
+        let revert_data = [
+            &error.selector()[..],
+            &ethcontract_common::abi::encode(&[1u64.into_token(), 100u64.into_token()]),
+        ]
+        .concat();
+
+        let mut frame = call(
+            unknown,
+            vec![0x13, 0x37],
+            vec![],
+            Some("execution reverted"),
+        );
+        frame.calls = vec![
+            call(
+                other,
+                input.clone(),
+                ethcontract_common::abi::encode(&[true.into_token()]),
+                None,
+            ),
+            call(token, input, revert_data, Some("execution reverted")),
+        ];
+
+        let decoder = TraceDecoder::new()
+            .contract_at(token, &artifact)
+            .interface(&artifact);
+        let decoded = frame.decode(&decoder);
+
+        assert_eq!(decoded.contract, None);
+        assert!(matches!(
+            decoded.error,
+            Some(ExecutionError::Revert(None, _))
+        ));
+
+        let success = &decoded.calls[0];
+        assert_eq!(success.contract, Some("Token"));
+        assert_eq!(success.function.map(|f| f.name.as_str()), Some("transfer"));
+        assert_eq!(
+            success.inputs,
+            Some(vec![other.into_token(), 100u64.into_token()])
+        );
+        assert_eq!(success.outputs, Some(vec![true.into_token()]));
+        assert!(success.error.is_none());
+
+        let failure = &decoded.calls[1];
+        assert_eq!(failure.outputs, None);
+        let custom_error = failure.custom_error.as_ref().expect("custom error");
+        assert_eq!(custom_error.error.name, "InsufficientBalance");
+        assert_eq!(
+            custom_error.params,
+            vec![1u64.into_token(), 100u64.into_token()]
+        );
+
+        let path = decoded.error_path();
+        assert_eq!(path.len(), 2);
+        assert_eq!(path[1].frame.to, Some(token));
+
+        assert_eq!(
+            decoded.to_string(),
+            format!(
+                "Call {:?} !! contract call reverted with message: None\n  \
+                 Call Token.transfer({}, 64) -> (true)\n  \
+                 Call Token.transfer({}, 64) !! InsufficientBalance(1, 64)\n",
+                unknown,
+                hex::encode(other),
+                hex::encode(other),
+            )
+        );
+    }
+
+    #[test]
+    fn decode_reason_and_unknown_calls() {
+        let frame = call(
+            Address::zero(),
+            vec![0x13, 0x37, 0x13, 0x37],
+            revert::encode_reason("message"),
+            Some("execution reverted"),
+        );
+        let decoder = TraceDecoder::new().interface(&artifact());
+        let decoded = decoder.decode(&frame);
+
+        assert_eq!(decoded.contract, None);
+        assert!(decoded.function.is_none());
+        assert!(decoded.inputs.is_none());
+        assert!(decoded.custom_error.is_none());
+        assert!(
+            matches!(&decoded.error, Some(ExecutionError::Revert(Some(reason), _)) if reason == "message")
+        );
+    }
+}
@@ -1,21 +1,33 @@
 //! Implementation for setting up, signing, estimating gas and sending
 //! transactions on the Ethereum network.
 
+pub mod access_list;
 pub mod build;
+pub mod cancel;
 pub mod confirm;
+pub mod escalate;
 pub mod estimate_gas;
 pub mod gas_price;
+pub mod nonce;
 pub mod send;
+pub mod simulate;
 
 use crate::secret::{Password, PrivateKey};
-use crate::transaction::build::BuildFuture;
+use crate::trace::{TraceFuture, Tracer};
+use crate::transaction::access_list::CreateAccessListFuture;
+pub use crate::transaction::access_list::{AccessList, AccessListItem};
+use crate::transaction::build::{BuildFuture, TransactionRequest};
+use crate::transaction::cancel::CancelFuture;
 use crate::transaction::confirm::ConfirmParams;
-use crate::transaction::estimate_gas::EstimateGasFuture;
-pub use crate::transaction::gas_price::GasPrice;
+use crate::transaction::escalate::EscalationParams;
+use crate::transaction::estimate_gas::{EstimateGasFuture, EstimateGasRequest};
+pub use crate::transaction::gas_price::{GasFees, GasPrice};
+pub use crate::transaction::nonce::NonceManager;
 use crate::transaction::send::SendFuture;
+use crate::transaction::simulate::SimulateFuture;
 use web3::api::Web3;
 use web3::types::{
-    Address, Bytes, TransactionCondition, TransactionReceipt, TransactionRequest, H256, U256,
+    Address, BlockNumber, Bytes, TransactionCondition, TransactionReceipt, H256, U256,
 };
 use web3::Transport;
 
@@ -29,6 +41,9 @@ pub enum Account {
     /// Do offline signing with private key and optionally specify chain ID. If
     /// no chain ID is specified, then it will default to the network ID.
     Offline(PrivateKey, Option<u64>),
+    /// Sign with the inner account and reserve the nonces of transactions
+    /// without an explicit nonce with the nonce manager.
+    Managed(Box<Account>, NonceManager),
 }
 
 impl Account {
@@ -38,6 +53,22 @@ impl Account {
             Account::Local(address, _) => *address,
             Account::Locked(address, _, _) => *address,
             Account::Offline(key, _) => key.public_address(),
+            Account::Managed(account, _) => account.address(),
+        }
+    }
+
+    /// Attach a nonce manager to the account. This allows concurrently sending
+    /// multiple transactions from the account, for example when it is used as
+    /// the default account of a contract instance.
+    pub fn with_nonce_manager(self, nonce_manager: NonceManager) -> Self {
+        Account::Managed(Box::new(self), nonce_manager)
+    }
+
+    /// Returns the nonce manager attached to the account, if any.
+    pub fn nonce_manager(&self) -> Option<&NonceManager> {
+        match self {
+            Account::Managed(_, nonce_manager) => Some(nonce_manager),
+            _ => None,
         }
     }
 }
@@ -57,6 +88,14 @@ pub enum ResolveCondition {
     /// See `ConfirmParams` documentation for more details on the exact
     /// semantics confirmation.
     Confirmed(ConfirmParams),
+    /// Wait for the transaction to be mined, replacing it with transactions
+    /// with the same nonce and an escalating gas price when it remains pending
+    /// for too long. The transaction's `SendFuture` resolves to the receipt of
+    /// whichever transaction was mined.
+    ///
+    /// See `EscalationParams` documentation for more details on the exact
+    /// semantics of the escalation.
+    Escalated(EscalationParams),
 }
 
 impl Default for ResolveCondition {
@@ -148,6 +187,13 @@ impl TransactionResult {
             _ => None,
         }
     }
+
+    /// Cancel the transaction if it is still pending by replacing it with a
+    /// zero-value transfer from the account that sent it to itself. See
+    /// `CancelFuture` for more details.
+    pub fn cancel<T: Transport>(&self, web3: &Web3<T>, account: Account) -> CancelFuture<T> {
+        CancelFuture::new(web3, account, self.hash())
+    }
 }
 
 /// Data used for building a transaction that modifies the blockchain. These
@@ -167,6 +213,13 @@ pub struct TransactionBuilder<T: Transport> {
     /// Optional gas price to use for transaction. Defaults to estimated gas
     /// price from the node (i.e. `GasPrice::Standard`).
     pub gas_price: Option<GasPrice>,
+    /// Optional maximum total fee per gas for an EIP-1559 transaction.
+    /// Specifying either this or the max priority fee per gas makes the
+    /// transaction use EIP-1559 fees, where the missing fee is estimated from
+    /// the node's fee history.
+    pub max_fee_per_gas: Option<U256>,
+    /// Optional maximum priority fee per gas for an EIP-1559 transaction.
+    pub max_priority_fee_per_gas: Option<U256>,
     /// The ETH value to send with the transaction. Defaults to 0.
     pub value: Option<U256>,
     /// The data for the transaction. Defaults to empty data.
@@ -174,9 +227,20 @@ pub struct TransactionBuilder<T: Transport> {
     /// Optional nonce to use. Defaults to the signing account's current
     /// transaction count.
     pub nonce: Option<U256>,
+    /// Optional nonce manager to reserve the nonce with when sending the
+    /// transaction. It is only used if no nonce and a sender were specified,
+    /// and takes precedence over a nonce manager attached to the sender.
+    pub nonce_manager: Option<NonceManager>,
+    /// Optional EIP-2930 access list. Specifying an access list for a
+    /// transaction with a legacy gas price makes it a type 1 transaction.
+    pub access_list: Option<AccessList>,
     /// Optional resolve conditions. Defaults to waiting the transaction to be
     /// mined without any extra confirmation blocks.
     pub resolve: Option<ResolveCondition>,
+    /// Whether to simulate the transaction with an `eth_call` against the
+    /// pending block before sending it. If the simulation fails, the
+    /// transaction is not sent. Defaults to `false`.
+    pub simulate_before_send: Option<bool>,
 }
 
 impl<T: Transport> TransactionBuilder<T> {
@@ -188,10 +252,15 @@ impl<T: Transport> TransactionBuilder<T> {
             to: None,
             gas: None,
             gas_price: None,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
             value: None,
             data: None,
             nonce: None,
+            nonce_manager: None,
+            access_list: None,
             resolve: None,
+            simulate_before_send: None,
         }
     }
 
@@ -223,6 +292,20 @@ impl<T: Transport> TransactionBuilder<T> {
         self
     }
 
+    /// Specify the maximum total fee per gas for an EIP-1559 transaction. This
+    /// takes precedence over the gas price.
+    pub fn max_fee_per_gas(mut self, value: U256) -> Self {
+        self.max_fee_per_gas = Some(value);
+        self
+    }
+
+    /// Specify the maximum priority fee per gas for an EIP-1559 transaction.
+    /// This takes precedence over the gas price.
+    pub fn max_priority_fee_per_gas(mut self, value: U256) -> Self {
+        self.max_priority_fee_per_gas = Some(value);
+        self
+    }
+
     /// Specify what how much ETH to transfer with the transaction, if not
     /// specified then no ETH will be sent.
     pub fn value(mut self, value: U256) -> Self {
@@ -244,6 +327,22 @@ impl<T: Transport> TransactionBuilder<T> {
         self
     }
 
+    /// Specify the nonce manager to reserve the nonce with when sending the
+    /// transaction. This allows concurrently sending multiple transactions
+    /// from the same account. Note that the nonce manager is not used when a
+    /// nonce was explicitly specified or no sender was specified.
+    pub fn nonce_manager(mut self, value: NonceManager) -> Self {
+        self.nonce_manager = Some(value);
+        self
+    }
+
+    /// Specify the access list for the transaction, if not specified then no
+    /// access list will be used.
+    pub fn access_list(mut self, value: AccessList) -> Self {
+        self.access_list = Some(value);
+        self
+    }
+
     /// Specify the resolve condition, if not specified will default to waiting
     /// for the transaction to be mined (but not confirmed by any extra blocks).
     pub fn resolve(mut self, value: ResolveCondition) -> Self {
@@ -251,6 +350,15 @@ impl<T: Transport> TransactionBuilder<T> {
         self
     }
 
+    /// Specify whether to simulate the transaction with an `eth_call` against
+    /// the pending block before signing and sending it. When enabled, a
+    /// transaction that would revert fails with the decoded error without
+    /// being sent, so no gas is wasted on it.
+    pub fn simulate_before_send(mut self, value: bool) -> Self {
+        self.simulate_before_send = Some(value);
+        self
+    }
+
     /// Specify the number of confirmations to use for the confirmation options.
     /// This is a utility method for specifying the resolve condition.
     pub fn confirmations(mut self, value: usize) -> Self {
@@ -273,6 +381,38 @@ impl<T: Transport> TransactionBuilder<T> {
         EstimateGasFuture::from_builder(self)
     }
 
+    /// Create an access list for this transaction with the node. Note that the
+    /// access list is not attached to the transaction.
+    pub fn create_access_list(self) -> CreateAccessListFuture<T> {
+        CreateAccessListFuture::from_builder(self)
+    }
+
+    /// Simulate the transaction with an `eth_call` against the pending block.
+    /// This can be used to check whether the transaction would revert before
+    /// sending it.
+    pub fn simulate(self) -> SimulateFuture<T> {
+        SimulateFuture::from_builder(self, BlockNumber::Pending)
+    }
+
+    /// Trace the transaction with the specified tracing API against the latest
+    /// block without sending it, resolving to its call tree.
+    pub fn trace(self, tracer: Tracer) -> TraceFuture<T> {
+        TraceFuture::call(&self.web3, self.call_request(), BlockNumber::Latest, tracer)
+    }
+
+    /// Creates a request for executing the transaction as a call, using only
+    /// the address of the sender.
+    pub(crate) fn call_request(&self) -> EstimateGasRequest {
+        EstimateGasRequest {
+            from: self.from.as_ref().map(|account| account.address()),
+            to: self.to,
+            gas: self.gas,
+            gas_price: self.gas_price.and_then(|gas_price| gas_price.value()),
+            value: self.value,
+            data: self.data.clone(),
+        }
+    }
+
     /// Build a prepared transaction that is ready to send.
     pub fn build(self) -> BuildFuture<T> {
         BuildFuture::from_builder(self)
@@ -283,6 +423,20 @@ impl<T: Transport> TransactionBuilder<T> {
     pub fn send(self) -> SendFuture<T> {
         SendFuture::from_builder(self)
     }
+
+    /// Cancel a pending transaction that was sent from the builder's account
+    /// by replacing it with a zero-value transfer to itself. The cancellation
+    /// transaction is escalated with the builder's escalation parameters if
+    /// it resolves with `ResolveCondition::Escalated`, and the sender of the
+    /// transaction is used as an unlocked account of the node if no account
+    /// was specified. All other builder parameters are ignored.
+    pub fn cancel(self, tx: H256) -> CancelFuture<T> {
+        let params = match self.resolve {
+            Some(ResolveCondition::Escalated(params)) => params,
+            _ => cancel::default_params(),
+        };
+        CancelFuture::from_parts(&self.web3, self.from, tx, params)
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,173 @@
+//! Implementation of EIP-2930 access lists and access list creation with
+//! `eth_createAccessList`.
+
+use crate::errors::ExecutionError;
+use crate::future::CompatCallFuture;
+use crate::transaction::TransactionBuilder;
+use futures::compat::Future01CompatExt;
+use pin_project::pin_project;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use web3::error::Error as Web3Error;
+use web3::helpers::{self, CallFuture};
+use web3::types::{Address, BlockNumber, H256};
+use web3::Transport;
+
+/// An EIP-2930 access list. Access lists specify the addresses and storage
+/// keys that a transaction plans to access, making these accesses cheaper.
+pub type AccessList = Vec<AccessListItem>;
+
+/// An entry of an access list.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessListItem {
+    /// The accessed address.
+    pub address: Address,
+    /// The accessed storage keys of the address.
+    pub storage_keys: Vec<H256>,
+}
+
+/// The result of an `eth_createAccessList` call.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateAccessListResult {
+    /// The created access list.
+    access_list: AccessList,
+    /// The error that occured when executing the transaction, if any.
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// Future for creating an access list for a transaction.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[pin_project]
+pub struct CreateAccessListFuture<T: Transport>(#[pin] CompatCallFuture<T, CreateAccessListResult>);
+
+impl<T: Transport> CreateAccessListFuture<T> {
+    /// Create a instance from a `TransactionBuilder`.
+    pub fn from_builder(builder: TransactionBuilder<T>) -> Self {
+        let request = builder.call_request();
+        CreateAccessListFuture(
+            CallFuture::new(builder.web3.transport().execute(
+                "eth_createAccessList",
+                vec![
+                    helpers::serialize(&request),
+                    helpers::serialize(&BlockNumber::Pending),
+                ],
+            ))
+            .compat(),
+        )
+    }
+}
+
+impl<T: Transport> Future for CreateAccessListFuture<T> {
+    type Output = Result<AccessList, ExecutionError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        self.project().0.poll(cx).map(|result| {
+            let result = result?;
+            match result.error {
+                Some(err) => Err(execution_error(err)),
+                None => Ok(result.access_list),
+            }
+        })
+    }
+}
+
+/// Converts the execution error reported by the node when creating an access
+/// list into an `ExecutionError`.
+fn execution_error(err: String) -> ExecutionError {
+    const REVERTED: &str = "execution reverted";
+
+    if err == REVERTED {
+        ExecutionError::Revert(None, None)
+    } else if err.starts_with(REVERTED) && err[REVERTED.len()..].starts_with(": ") {
+        ExecutionError::Revert(Some(err[REVERTED.len() + 2..].to_owned()), None)
+    } else {
+        ExecutionError::Web3(Web3Error::InvalidResponse(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use crate::transaction::Account;
+
+    #[test]
+    fn create_access_list() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let to = addr!("0x0123456789012345678901234567890123456789");
+        let access_list = vec![AccessListItem {
+            address: to,
+            storage_keys: vec![H256::from_low_u64_be(1), H256::from_low_u64_be(2)],
+        }];
+
+        transport.add_response(json!({
+            "accessList": access_list,
+            "gasUsed": "0x5208",
+        }));
+        let result = TransactionBuilder::new(web3)
+            .from(Account::Local(from, None))
+            .to(to)
+            .data(bytes!("0x42"))
+            .create_access_list()
+            .immediate()
+            .expect("failed to create access list");
+
+        transport.assert_request(
+            "eth_createAccessList",
+            &[
+                json!({
+                    "from": from,
+                    "to": to,
+                    "data": "0x42",
+                }),
+                json!("pending"),
+            ],
+        );
+        transport.assert_no_more_requests();
+
+        assert_eq!(result, access_list);
+        assert_eq!(
+            json!(access_list),
+            json!([{
+                "address": to,
+                "storageKeys": [
+                    "0x0000000000000000000000000000000000000000000000000000000000000001",
+                    "0x0000000000000000000000000000000000000000000000000000000000000002",
+                ],
+            }]),
+        );
+    }
+
+    #[test]
+    fn create_access_list_reverted() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        transport.add_response(json!({
+            "accessList": [],
+            "gasUsed": "0x5208",
+            "error": "execution reverted: not allowed",
+        }));
+        let err = TransactionBuilder::new(web3)
+            .create_access_list()
+            .immediate()
+            .expect_err("unexpected success creating access list");
+
+        assert!(
+            match &err {
+                ExecutionError::Revert(Some(reason), _) => reason == "not allowed",
+                _ => false,
+            },
+            "unexpected error {:?}",
+            err
+        );
+    }
+}
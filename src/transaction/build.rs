@@ -8,23 +8,23 @@ use crate::errors::ExecutionError;
 use crate::future::{CompatCallFuture, MaybeReady};
 use crate::secret::{Password, PrivateKey};
 use crate::sign::TransactionData;
+use crate::transaction::access_list::AccessList;
 use crate::transaction::estimate_gas::{EstimateGasFuture, EstimateGasRequest};
 use crate::transaction::gas_price::{
-    GasPrice, ResolveGasPriceFuture, ResolveTransactionRequestGasPriceFuture,
+    GasFees, GasPrice, ResolveGasFeesFuture, ResolveTransactionRequestGasFeesFuture,
 };
 use crate::transaction::{Account, Transaction, TransactionBuilder};
 use futures::compat::Future01CompatExt;
 use futures::future::{self, Join, TryJoin4};
 use futures::ready;
 use pin_project::{pin_project, project};
+use serde::Serialize;
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use web3::api::Web3;
-use web3::helpers::CallFuture;
-use web3::types::{
-    Address, Bytes, RawTransaction, TransactionCondition, TransactionRequest, U256, U64,
-};
+use web3::helpers::{self, CallFuture};
+use web3::types::{Address, Bytes, RawTransaction, TransactionCondition, U256, U64};
 use web3::Transport;
 
 /// Shared transaction options that are used when finalizing transactions into
@@ -41,6 +41,65 @@ pub struct TransactionOptions {
     pub data: Option<Bytes>,
     /// The transaction nonce.
     pub nonce: Option<U256>,
+    /// The maximum total fee per gas for EIP-1559 transactions.
+    pub max_fee_per_gas: Option<U256>,
+    /// The maximum priority fee per gas for EIP-1559 transactions.
+    pub max_priority_fee_per_gas: Option<U256>,
+    /// The EIP-2930 access list for the transaction.
+    pub access_list: Option<AccessList>,
+}
+
+impl TransactionOptions {
+    /// Resolves the gas fees for the transaction with the specified gas price
+    /// setting, taking into account explicitly specified EIP-1559 fees.
+    fn resolve_fees<T: Transport>(
+        &self,
+        web3: &Web3<T>,
+        gas_price: GasPrice,
+    ) -> ResolveGasFeesFuture<T> {
+        gas_price.resolve_fees(web3, self.max_fee_per_gas, self.max_priority_fee_per_gas)
+    }
+}
+
+/// A transaction request to be signed by the node.
+///
+/// Note that this is similar to `web3::types::TransactionRequest` with the
+/// notable exception that it supports EIP-1559 fees and EIP-2930 access lists.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionRequest {
+    /// The sender of the transaction.
+    pub from: Address,
+    /// The receiver of the transaction, `None` for contract deployments.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<Address>,
+    /// The amount of gas supplied to the transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas: Option<U256>,
+    /// The legacy gas price for the transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_price: Option<U256>,
+    /// The maximum total fee per gas for EIP-1559 transactions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_fee_per_gas: Option<U256>,
+    /// The maximum priority fee per gas for EIP-1559 transactions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_priority_fee_per_gas: Option<U256>,
+    /// The ETH value to send with the transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<U256>,
+    /// The data for the transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Bytes>,
+    /// The transaction nonce.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<U256>,
+    /// The EIP-2930 access list for the transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_list: Option<AccessList>,
+    /// The condition for when the node should send the transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<TransactionCondition>,
 }
 
 /// Transaction options specific to `TransactionRequests` since they may also
@@ -52,15 +111,27 @@ pub struct TransactionRequestOptions(pub TransactionOptions, pub Option<Transact
 impl TransactionRequestOptions {
     /// Builds a `TransactionRequest` from a `TransactionRequestOptions` by
     /// specifying the missing parameters.
-    fn build_request(self, from: Address, gas_price: Option<U256>) -> TransactionRequest {
+    fn build_request(self, from: Address, fees: Option<GasFees>) -> TransactionRequest {
+        let (gas_price, max_fee_per_gas, max_priority_fee_per_gas) = match fees {
+            None => (None, None, None),
+            Some(GasFees::Legacy(gas_price)) => (Some(gas_price), None, None),
+            Some(GasFees::Eip1559 {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            }) => (None, Some(max_fee_per_gas), Some(max_priority_fee_per_gas)),
+        };
+
         TransactionRequest {
             from,
             to: self.0.to,
             gas: self.0.gas,
             gas_price,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
             value: self.0.value,
             data: self.0.data,
             nonce: self.0.nonce,
+            access_list: self.0.access_list,
             condition: self.1,
         }
     }
@@ -91,18 +162,32 @@ impl<T: Transport> BuildFuture<T> {
             value: builder.value,
             data: builder.data,
             nonce: builder.nonce,
+            max_fee_per_gas: builder.max_fee_per_gas,
+            max_priority_fee_per_gas: builder.max_priority_fee_per_gas,
+            access_list: builder.access_list,
         };
 
-        match builder.from {
+        BuildFuture::with_account(builder.web3, builder.from, gas_price, options)
+    }
+
+    /// Create an instance for building a transaction signed by the specified
+    /// account.
+    fn with_account(
+        web3: Web3<T>,
+        from: Option<Account>,
+        gas_price: GasPrice,
+        options: TransactionOptions,
+    ) -> Self {
+        match from {
             None => BuildFuture::Local(BuildTransactionRequestForLocalSigningFuture::new(
-                &builder.web3,
+                &web3,
                 None,
                 gas_price,
                 TransactionRequestOptions(options, None),
             )),
             Some(Account::Local(from, condition)) => {
                 BuildFuture::Local(BuildTransactionRequestForLocalSigningFuture::new(
-                    &builder.web3,
+                    &web3,
                     Some(from),
                     gas_price,
                     TransactionRequestOptions(options, condition),
@@ -110,21 +195,18 @@ impl<T: Transport> BuildFuture<T> {
             }
             Some(Account::Locked(from, password, condition)) => {
                 BuildFuture::Locked(BuildTransactionSignedWithLockedAccountFuture::new(
-                    builder.web3,
+                    web3,
                     from,
                     password,
                     gas_price,
                     TransactionRequestOptions(options, condition),
                 ))
             }
-            Some(Account::Offline(key, chain_id)) => {
-                BuildFuture::Offline(BuildOfflineSignedTransactionFuture::new(
-                    &builder.web3,
-                    key,
-                    chain_id,
-                    gas_price,
-                    options,
-                ))
+            Some(Account::Offline(key, chain_id)) => BuildFuture::Offline(
+                BuildOfflineSignedTransactionFuture::new(&web3, key, chain_id, gas_price, options),
+            ),
+            Some(Account::Managed(account, _)) => {
+                BuildFuture::with_account(web3, Some(*account), gas_price, options)
             }
         }
     }
@@ -160,7 +242,7 @@ type MaybeCallFuture<T, R> = MaybeReady<CompatCallFuture<T, R>>;
 
 /// Type alias for future retrieving default local account parameters.
 type LocalParamsFuture<T> =
-    Join<MaybeCallFuture<T, Vec<Address>>, ResolveTransactionRequestGasPriceFuture<T>>;
+    Join<MaybeCallFuture<T, Vec<Address>>, ResolveTransactionRequestGasFeesFuture<T>>;
 
 /// A future for building a locally signed transaction.
 #[must_use = "futures do nothing unless you `.await` or poll them"]
@@ -173,7 +255,8 @@ pub struct BuildTransactionRequestForLocalSigningFuture<T: Transport> {
     /// The inner future for retrieving the missing parameters required for
     /// finalizing the transaction request: the list of accounts on the node
     /// (in order determine the default account if none was specified) and the
-    /// resolved gas price (in case a scaled gas price is used).
+    /// resolved gas fees (in case a scaled gas price or EIP-1559 fees are
+    /// used).
     #[pin]
     params: LocalParamsFuture<T>,
 }
@@ -187,13 +270,17 @@ impl<T: Transport> BuildTransactionRequestForLocalSigningFuture<T> {
         gas_price: GasPrice,
         options: TransactionRequestOptions,
     ) -> Self {
-        let options = Some(options);
         let params = {
             let eth = web3.eth();
             let accounts = maybe!(from.map(|from| vec![from]), eth.accounts().compat());
-            let gas_price = gas_price.resolve_for_transaction_request(web3);
-            future::join(accounts, gas_price)
+            let fees = gas_price.resolve_fees_for_transaction_request(
+                web3,
+                options.0.max_fee_per_gas,
+                options.0.max_priority_fee_per_gas,
+            );
+            future::join(accounts, fees)
         };
+        let options = Some(options);
 
         BuildTransactionRequestForLocalSigningFuture { options, params }
     }
@@ -205,15 +292,15 @@ impl<T: Transport> Future for BuildTransactionRequestForLocalSigningFuture<T> {
     fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
         let mut this = self.project();
 
-        this.params.as_mut().poll(cx).map(|(accounts, gas_price)| {
-            let (accounts, gas_price) = (accounts?, gas_price.transpose()?);
+        this.params.as_mut().poll(cx).map(|(accounts, fees)| {
+            let (accounts, fees) = (accounts?, fees.transpose()?);
             let from = match accounts.get(0) {
                 Some(address) => *address,
                 None => return Err(ExecutionError::NoLocalAccounts),
             };
 
             let options = this.options.take().expect("future polled more than once");
-            let request = options.build_request(from, gas_price);
+            let request = options.build_request(from, fees);
 
             Ok(request)
         })
@@ -241,8 +328,8 @@ pub struct BuildTransactionSignedWithLockedAccountFuture<T: Transport> {
 /// The inner state of the future for building locally signed transactions.
 #[pin_project]
 enum BuildTransactionSignedWithLockedAccountState<T: Transport> {
-    /// The gas price is being resolved.
-    ResolvingGasPrice(#[pin] ResolveTransactionRequestGasPriceFuture<T>),
+    /// The gas fees are being resolved.
+    ResolvingGasFees(#[pin] ResolveTransactionRequestGasFeesFuture<T>),
     /// Signing the transaction with the locked account.
     Signing(#[pin] CompatCallFuture<T, RawTransaction>),
 }
@@ -257,8 +344,12 @@ impl<T: Transport> BuildTransactionSignedWithLockedAccountFuture<T> {
         gas_price: GasPrice,
         options: TransactionRequestOptions,
     ) -> Self {
-        let state = BuildTransactionSignedWithLockedAccountState::ResolvingGasPrice(
-            gas_price.resolve_for_transaction_request(&web3),
+        let state = BuildTransactionSignedWithLockedAccountState::ResolvingGasFees(
+            gas_price.resolve_fees_for_transaction_request(
+                &web3,
+                options.0.max_fee_per_gas,
+                options.0.max_priority_fee_per_gas,
+            ),
         );
 
         BuildTransactionSignedWithLockedAccountFuture {
@@ -281,21 +372,21 @@ impl<T: Transport> Future for BuildTransactionSignedWithLockedAccountFuture<T> {
         loop {
             #[project]
             let next_state = match this.state.as_mut().project() {
-                BuildTransactionSignedWithLockedAccountState::ResolvingGasPrice(gas_price) => {
-                    let gas_price = match ready!(gas_price.poll(cx)).transpose() {
-                        Ok(gas_price) => gas_price,
+                BuildTransactionSignedWithLockedAccountState::ResolvingGasFees(fees) => {
+                    let fees = match ready!(fees.poll(cx)).transpose() {
+                        Ok(fees) => fees,
                         Err(err) => return Poll::Ready(Err(err.into())),
                     };
 
                     let options = this.options.take().expect("future called more than once");
-                    let request = options.build_request(*this.from, gas_price);
-                    let password = &this.password;
+                    let request = options.build_request(*this.from, fees);
+                    let password: &str = this.password;
 
-                    let sign = this
-                        .web3
-                        .personal()
-                        .sign_transaction(request, password)
-                        .compat();
+                    let sign = CallFuture::new(this.web3.transport().execute(
+                        "personal_signTransaction",
+                        vec![helpers::serialize(&request), helpers::serialize(&password)],
+                    ))
+                    .compat();
 
                     BuildTransactionSignedWithLockedAccountState::Signing(sign)
                 }
@@ -313,7 +404,7 @@ impl<T: Transport> Future for BuildTransactionSignedWithLockedAccountFuture<T> {
 /// been specified by the transaction builder but are required for signing.
 type OfflineParamsFuture<T> = TryJoin4<
     MaybeCallFuture<T, U256>,
-    ResolveGasPriceFuture<T>,
+    ResolveGasFeesFuture<T>,
     MaybeCallFuture<T, U256>,
     MaybeCallFuture<T, U64>,
 >;
@@ -330,7 +421,9 @@ pub struct BuildOfflineSignedTransactionFuture<T: Transport> {
     value: U256,
     /// The ABI encoded call parameters,
     data: Bytes,
-    /// Future for retrieving gas, gas price, nonce and chain ID when they
+    /// The access list for the transaction.
+    access_list: Option<AccessList>,
+    /// Future for retrieving gas, gas fees, nonce and chain ID when they
     /// where not specified.
     #[pin]
     params: OfflineParamsFuture<T>,
@@ -368,14 +461,14 @@ impl<T: Transport> BuildOfflineSignedTransactionFuture<T> {
                 )
                 .into_inner()
             );
-            let gas_price = gas_price.resolve(web3);
+            let fees = options.resolve_fees(web3, gas_price);
             let nonce = maybe!(options.nonce, eth.transaction_count(from, None).compat());
             let chain_id = maybe!(
                 chain_id.map(U64::from),
                 CallFuture::new(transport.execute("eth_chainId", vec![])).compat()
             );
 
-            future::try_join4(gas, gas_price, nonce, chain_id)
+            future::try_join4(gas, fees, nonce, chain_id)
         };
 
         let data = options.data.unwrap_or_else(Bytes::default);
@@ -385,6 +478,7 @@ impl<T: Transport> BuildOfflineSignedTransactionFuture<T> {
             to: options.to,
             value,
             data,
+            access_list: options.access_list,
             params,
         }
     }
@@ -396,14 +490,15 @@ impl<T: Transport> Future for BuildOfflineSignedTransactionFuture<T> {
     fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
         let mut this = self.project();
         this.params.as_mut().poll(cx).map(|params| {
-            let (gas, gas_price, nonce, chain_id) = params?;
+            let (gas, fees, nonce, chain_id) = params?;
             let tx = TransactionData {
                 nonce,
-                gas_price,
+                gas_price: fees,
                 gas,
                 to: *this.to,
                 value: *this.value,
                 data: &this.data,
+                access_list: this.access_list.as_ref(),
             };
 
             let raw = tx.sign(&this.key, Some(chain_id.as_u64()));
@@ -759,4 +854,98 @@ mod tests {
         assert_eq!(tx1, tx2);
         assert_eq!(tx2, tx3);
     }
+
+    #[test]
+    fn tx_build_local_with_eip1559_fees() {
+        let transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let from = addr!("0xffffffffffffffffffffffffffffffffffffffff");
+
+        let tx = BuildTransactionRequestForLocalSigningFuture::new(
+            &web3,
+            Some(from),
+            GasPrice::Value(1337.into()),
+            TransactionRequestOptions(
+                TransactionOptions {
+                    max_fee_per_gas: Some(100.into()),
+                    max_priority_fee_per_gas: Some(2.into()),
+                    ..Default::default()
+                },
+                None,
+            ),
+        )
+        .immediate()
+        .expect("failed to build local transaction");
+
+        transport.assert_no_more_requests();
+
+        assert_eq!(tx.gas_price, None);
+        assert_eq!(tx.max_fee_per_gas, Some(100.into()));
+        assert_eq!(tx.max_priority_fee_per_gas, Some(2.into()));
+        assert_eq!(
+            helpers::serialize(&tx),
+            json!({
+                "from": from,
+                "maxFeePerGas": "0x64",
+                "maxPriorityFeePerGas": "0x2",
+            })
+        );
+    }
+
+    #[test]
+    fn tx_build_offline_with_fee_history() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let key = key!("0x0102030405060708091011121314151617181920212223242526272829303132");
+        let to = addr!("0x0000000000000000000000000000000000000000");
+        let options = TransactionOptions {
+            to: Some(to),
+            gas: Some(21000.into()),
+            nonce: Some(0.into()),
+            ..Default::default()
+        };
+
+        transport.add_response(json!({
+            "oldestBlock": "0x1",
+            "baseFeePerGas": ["0x64", "0x6e"],
+            "gasUsedRatio": [0.5],
+            "reward": [["0x2"]],
+        }));
+        let tx1 = BuildOfflineSignedTransactionFuture::new(
+            &web3,
+            key.clone(),
+            Some(1),
+            GasPrice::fee_history(),
+            options.clone(),
+        )
+        .immediate()
+        .expect("failed to build offline transaction");
+
+        transport.assert_request(
+            "eth_feeHistory",
+            &[json!("0xa"), json!("latest"), json!([50.0])],
+        );
+        transport.assert_no_more_requests();
+
+        let tx2 = BuildOfflineSignedTransactionFuture::new(
+            &web3,
+            key,
+            Some(1),
+            GasPrice::Standard,
+            TransactionOptions {
+                max_fee_per_gas: Some((110 * 2 + 2).into()),
+                max_priority_fee_per_gas: Some(2.into()),
+                ..options
+            },
+        )
+        .immediate()
+        .expect("failed to build offline transaction");
+
+        transport.assert_no_more_requests();
+
+        assert_eq!(tx1.0[0], 2);
+        assert_eq!(tx1, tx2);
+    }
 }
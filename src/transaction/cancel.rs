@@ -0,0 +1,539 @@
+//! Implementation of a future for cancelling a pending transaction by replacing
+//! it with a zero-value transfer to the sender with the same nonce and a
+//! higher gas price.
+
+use crate::errors::ExecutionError;
+use crate::future::CompatCallFuture;
+use crate::transaction::escalate::{EscalateFuture, EscalationParams};
+use crate::transaction::gas_price::GasFees;
+use crate::transaction::{Account, GasPrice, TransactionBuilder};
+use futures::compat::Future01CompatExt;
+use futures::ready;
+use pin_project::{pin_project, project};
+use serde::Deserialize;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use web3::api::Web3;
+use web3::helpers::{self, CallFuture};
+use web3::types::{Address, TransactionReceipt, H256, U256, U64};
+use web3::Transport;
+
+/// The amount of gas used by the zero-value transfer cancelling a transaction.
+const CANCEL_GAS: u64 = 21_000;
+
+/// The result of cancelling a transaction.
+#[derive(Clone, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum CancelResult {
+    /// The cancellation transaction was mined, so the original transaction
+    /// will never be mined.
+    Cancelled(TransactionReceipt),
+    /// The original transaction was mined before it could be cancelled.
+    Mined(TransactionReceipt),
+}
+
+impl CancelResult {
+    /// Returns true if the transaction was successfully cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, CancelResult::Cancelled(_))
+    }
+
+    /// Get the receipt of the transaction that was mined, either the
+    /// cancellation or the original transaction.
+    pub fn receipt(&self) -> &TransactionReceipt {
+        match self {
+            CancelResult::Cancelled(receipt) => receipt,
+            CancelResult::Mined(receipt) => receipt,
+        }
+    }
+}
+
+/// The parts of a pending transaction that are needed for cancelling it.
+///
+/// Note that this is used instead of `web3::types::Transaction` since it does
+/// not include EIP-1559 fees.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PendingTransaction {
+    from: Address,
+    nonce: U256,
+    #[serde(default)]
+    gas_price: Option<U256>,
+    #[serde(default)]
+    max_fee_per_gas: Option<U256>,
+    #[serde(default)]
+    max_priority_fee_per_gas: Option<U256>,
+    #[serde(default)]
+    block_number: Option<U64>,
+}
+
+impl PendingTransaction {
+    /// The gas fees that were used for the transaction.
+    fn fees(&self) -> GasFees {
+        match (self.max_fee_per_gas, self.max_priority_fee_per_gas) {
+            (Some(max_fee_per_gas), Some(max_priority_fee_per_gas)) => GasFees::Eip1559 {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            },
+            _ => GasFees::Legacy(self.gas_price.unwrap_or_default()),
+        }
+    }
+}
+
+/// The escalation parameters for cancellation transactions that use gas fees
+/// increased by the default escalation factor and never get escalated.
+pub(crate) fn default_params() -> EscalationParams {
+    EscalationParams {
+        blocks: usize::MAX,
+        ..EscalationParams::new(0, U256::MAX)
+    }
+}
+
+/// Future for cancelling a pending transaction. The transaction is replaced by
+/// a zero-value transfer from the account to itself with the same nonce and
+/// increased gas fees. The future resolves once either the cancellation or
+/// the original transaction is mined.
+///
+/// Note that the account must be the sender of the original transaction,
+/// otherwise the future resolves to an `ExecutionError::UnexpectedSender`
+/// error. Errors sending the cancellation transaction are returned, unless it
+/// is a replacement of an already sent cancellation transaction that failed
+/// because one of the transactions was mined in the meantime.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[pin_project]
+pub struct CancelFuture<T: Transport> {
+    web3: Web3<T>,
+    /// The account that sent the transaction being cancelled, or `None` for
+    /// cancelling it with the sender of the transaction as an unlocked account
+    /// of the node.
+    account: Option<Account>,
+    /// The hash of the transaction being cancelled.
+    tx: H256,
+    /// The escalation parameters for the cancellation transaction.
+    params: EscalationParams,
+    /// The current state of the future.
+    #[pin]
+    state: CancelState<T>,
+}
+
+/// The state of the cancel future.
+#[pin_project]
+enum CancelState<T: Transport> {
+    /// The transaction being cancelled is being retrieved.
+    FetchingTransaction(#[pin] CompatCallFuture<T, Option<PendingTransaction>>),
+    /// The transaction was already mined and its receipt is being retrieved.
+    FetchingReceipt(#[pin] CompatCallFuture<T, Option<TransactionReceipt>>),
+    /// The cancellation transaction is being sent and confirmed.
+    Cancelling(Pin<Box<EscalateFuture<T>>>),
+}
+
+impl<T: Transport> CancelFuture<T> {
+    /// Create a new future for cancelling a pending transaction that was sent
+    /// from the specified account. The cancellation transaction uses gas fees
+    /// that are increased by the default escalation factor.
+    pub fn new(web3: &Web3<T>, account: Account, tx: H256) -> Self {
+        CancelFuture::with_params(web3, account, tx, default_params())
+    }
+
+    /// Create a new future for cancelling a pending transaction, where the
+    /// cancellation transaction itself gets escalated according to the
+    /// specified parameters.
+    pub fn with_params(
+        web3: &Web3<T>,
+        account: Account,
+        tx: H256,
+        params: EscalationParams,
+    ) -> Self {
+        CancelFuture::from_parts(web3, Some(account), tx, params)
+    }
+
+    /// Create a new future for cancelling a pending transaction with an
+    /// optional account, where the sender of the transaction is used as an
+    /// unlocked account of the node if none is specified.
+    pub(crate) fn from_parts(
+        web3: &Web3<T>,
+        account: Option<Account>,
+        tx: H256,
+        params: EscalationParams,
+    ) -> Self {
+        let state = CancelState::FetchingTransaction(
+            CallFuture::new(
+                web3.transport()
+                    .execute("eth_getTransactionByHash", vec![helpers::serialize(&tx)]),
+            )
+            .compat(),
+        );
+
+        CancelFuture {
+            web3: web3.clone(),
+            account,
+            tx,
+            params,
+            state,
+        }
+    }
+}
+
+impl<T: Transport> Future for CancelFuture<T> {
+    type Output = Result<CancelResult, ExecutionError>;
+
+    #[project]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        loop {
+            #[project]
+            let CancelFuture {
+                web3,
+                account,
+                tx,
+                params,
+                state,
+            } = self.as_mut().project();
+
+            #[project]
+            let next_state = match state.project() {
+                CancelState::FetchingTransaction(pending) => {
+                    let pending = match ready!(pending.poll(cx))? {
+                        Some(pending) => pending,
+                        None => return Poll::Ready(Err(ExecutionError::MissingTransaction(*tx))),
+                    };
+
+                    let account = match account.take() {
+                        Some(account) if account.address() != pending.from => {
+                            return Poll::Ready(Err(ExecutionError::UnexpectedSender(
+                                *tx,
+                                pending.from,
+                            )))
+                        }
+                        Some(account) => account,
+                        None => Account::Local(pending.from, None),
+                    };
+
+                    if pending.block_number.is_some() {
+                        CancelState::FetchingReceipt(web3.eth().transaction_receipt(*tx).compat())
+                    } else {
+                        let address = account.address();
+                        let builder = TransactionBuilder::new(web3.clone())
+                            .from(account)
+                            .to(address)
+                            .value(U256::zero())
+                            .gas(CANCEL_GAS.into())
+                            .nonce(pending.nonce);
+                        let builder = match params.escalate(pending.fees()) {
+                            GasFees::Legacy(gas_price) => {
+                                builder.gas_price(GasPrice::Value(gas_price))
+                            }
+                            GasFees::Eip1559 {
+                                max_fee_per_gas,
+                                max_priority_fee_per_gas,
+                            } => builder
+                                .max_fee_per_gas(max_fee_per_gas)
+                                .max_priority_fee_per_gas(max_priority_fee_per_gas),
+                        };
+
+                        CancelState::Cancelling(Box::pin(EscalateFuture::replacing(
+                            builder,
+                            *tx,
+                            params.clone(),
+                        )))
+                    }
+                }
+                CancelState::FetchingReceipt(receipt) => {
+                    return Poll::Ready(match ready!(receipt.poll(cx))? {
+                        Some(receipt) => Ok(CancelResult::Mined(receipt)),
+                        None => Err(ExecutionError::PendingTransaction(*tx)),
+                    });
+                }
+                CancelState::Cancelling(cancel) => {
+                    let receipt = ready!(cancel.as_mut().poll(cx))?;
+                    return Poll::Ready(Ok(if receipt.transaction_hash == *tx {
+                        CancelResult::Mined(receipt)
+                    } else {
+                        CancelResult::Cancelled(receipt)
+                    }));
+                }
+            };
+
+            self.as_mut().project().state.set(next_state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use crate::transaction::build::{BuildOfflineSignedTransactionFuture, TransactionOptions};
+    use crate::transaction::TransactionResult;
+    use jsonrpc_core::{Error as RpcError, ErrorCode};
+    use serde_json::Value;
+    use web3::error::Error as Web3Error;
+    use web3::types::H2048;
+
+    fn generate_tx_receipt(hash: H256, block_num: u64) -> Value {
+        json!({
+            "transactionHash": hash,
+            "transactionIndex": "0x1",
+            "blockNumber": U64::from(block_num),
+            "blockHash": H256::zero(),
+            "cumulativeGasUsed": "0x1337",
+            "gasUsed": "0x1337",
+            "logsBloom": H2048::zero(),
+            "logs": [],
+            "status": "0x1",
+        })
+    }
+
+    #[test]
+    fn cancel_pending_transaction() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let key = key!("0x0102030405060708091011121314151617181920212223242526272829303132");
+        let from = key.public_address();
+        let original = H256::repeat_byte(1);
+        let cancellation = H256::repeat_byte(2);
+
+        let raw = BuildOfflineSignedTransactionFuture::new(
+            &web3,
+            key.clone(),
+            Some(1),
+            GasPrice::Value(225.into()),
+            TransactionOptions {
+                to: Some(from),
+                gas: Some(21000.into()),
+                value: Some(0.into()),
+                nonce: Some(42.into()),
+                ..Default::default()
+            },
+        )
+        .immediate()
+        .expect("failed to build transaction");
+
+        transport.add_response(json!({
+            "from": from,
+            "nonce": "0x2a",
+            "gasPrice": "0xc8",
+            "blockNumber": null,
+        }));
+        transport.add_response(json!(cancellation));
+        transport.add_response(json!("0x1"));
+        transport.add_response(json!(null));
+        transport.add_response(generate_tx_receipt(cancellation, 1));
+
+        let result = CancelFuture::new(&web3, Account::Offline(key, Some(1)), original)
+            .immediate()
+            .expect("failed to cancel transaction");
+
+        assert!(result.is_cancelled());
+        assert_eq!(result.receipt().transaction_hash, cancellation);
+
+        transport.assert_request("eth_getTransactionByHash", &[json!(original)]);
+        transport.assert_request("eth_sendRawTransaction", &[json!(raw)]);
+        transport.assert_request("eth_blockNumber", &[]);
+        transport.assert_request("eth_getTransactionReceipt", &[json!(original)]);
+        transport.assert_request("eth_getTransactionReceipt", &[json!(cancellation)]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn cancel_mined_transaction() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let original = H256::repeat_byte(1);
+
+        transport.add_response(json!({
+            "from": from,
+            "nonce": "0x2a",
+            "maxFeePerGas": "0xc8",
+            "maxPriorityFeePerGas": "0x2",
+            "blockNumber": "0x1",
+        }));
+        transport.add_response(generate_tx_receipt(original, 1));
+
+        let result = CancelFuture::new(&web3, Account::Local(from, None), original)
+            .immediate()
+            .expect("failed to cancel transaction");
+
+        assert!(!result.is_cancelled());
+        assert_eq!(result.receipt().transaction_hash, original);
+
+        transport.assert_request("eth_getTransactionByHash", &[json!(original)]);
+        transport.assert_request("eth_getTransactionReceipt", &[json!(original)]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn cancel_missing_transaction() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let original = H256::repeat_byte(1);
+
+        transport.add_response(json!(null));
+        let err = CancelFuture::new(&web3, Account::Local(from, None), original)
+            .immediate()
+            .expect_err("unexpected success cancelling transaction");
+
+        assert!(
+            matches!(err, ExecutionError::MissingTransaction(hash) if hash == original),
+            "unexpected error {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn cancel_transaction_from_other_account() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let sender = addr!("0x0123456789012345678901234567890123456789");
+        let original = H256::repeat_byte(1);
+
+        transport.add_response(json!({
+            "from": sender,
+            "nonce": "0x2a",
+            "gasPrice": "0xc8",
+            "blockNumber": null,
+        }));
+        let err = CancelFuture::new(&web3, Account::Local(from, None), original)
+            .immediate()
+            .expect_err("unexpected success cancelling transaction");
+
+        assert!(
+            matches!(
+                err,
+                ExecutionError::UnexpectedSender(hash, address)
+                    if hash == original && address == sender
+            ),
+            "unexpected error {:?}",
+            err
+        );
+
+        transport.assert_request("eth_getTransactionByHash", &[json!(original)]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn cancel_send_error() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let original = H256::repeat_byte(1);
+
+        transport.add_response(json!({
+            "from": from,
+            "nonce": "0x2a",
+            "gasPrice": "0xc8",
+            "blockNumber": null,
+        }));
+        transport.add_error(Web3Error::Rpc(RpcError {
+            code: ErrorCode::ServerError(-32000),
+            message: "nonce too low".to_owned(),
+            data: None,
+        }));
+        let err = CancelFuture::new(&web3, Account::Local(from, None), original)
+            .immediate()
+            .expect_err("unexpected success cancelling transaction");
+
+        assert!(
+            matches!(&err, ExecutionError::Web3(Web3Error::Rpc(err)) if err.message == "nonce too low"),
+            "unexpected error {:?}",
+            err
+        );
+
+        transport.assert_request("eth_getTransactionByHash", &[json!(original)]);
+        transport.assert_request(
+            "eth_sendTransaction",
+            &[json!({
+                "from": from,
+                "to": from,
+                "gas": "0x5208",
+                "gasPrice": "0xe1",
+                "value": "0x0",
+                "nonce": "0x2a",
+            })],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn cancel_with_builder_and_transaction_sender() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let original = H256::repeat_byte(1);
+        let cancellation = H256::repeat_byte(2);
+
+        transport.add_response(json!({
+            "from": from,
+            "nonce": "0x2a",
+            "gasPrice": "0xc8",
+            "blockNumber": null,
+        }));
+        transport.add_response(json!(cancellation));
+        transport.add_response(json!("0x1"));
+        transport.add_response(json!(null));
+        transport.add_response(generate_tx_receipt(cancellation, 1));
+
+        let result = TransactionBuilder::new(web3.clone())
+            .cancel(original)
+            .immediate()
+            .expect("failed to cancel transaction");
+
+        assert!(result.is_cancelled());
+        assert_eq!(result.receipt().transaction_hash, cancellation);
+
+        transport.assert_request("eth_getTransactionByHash", &[json!(original)]);
+        transport.assert_request(
+            "eth_sendTransaction",
+            &[json!({
+                "from": from,
+                "to": from,
+                "gas": "0x5208",
+                "gasPrice": "0xe1",
+                "value": "0x0",
+                "nonce": "0x2a",
+            })],
+        );
+        transport.assert_request("eth_blockNumber", &[]);
+        transport.assert_request("eth_getTransactionReceipt", &[json!(original)]);
+        transport.assert_request("eth_getTransactionReceipt", &[json!(cancellation)]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn cancel_transaction_result() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let original = H256::repeat_byte(1);
+
+        transport.add_response(json!({
+            "from": from,
+            "nonce": "0x2a",
+            "gasPrice": "0xc8",
+            "blockNumber": "0x1",
+        }));
+        transport.add_response(generate_tx_receipt(original, 1));
+
+        let result = TransactionResult::Hash(original)
+            .cancel(&web3, Account::Local(from, None))
+            .immediate()
+            .expect("failed to cancel transaction");
+
+        assert!(!result.is_cancelled());
+        assert_eq!(result.receipt().transaction_hash, original);
+
+        transport.assert_request("eth_getTransactionByHash", &[json!(original)]);
+        transport.assert_request("eth_getTransactionReceipt", &[json!(original)]);
+        transport.assert_no_more_requests();
+    }
+}
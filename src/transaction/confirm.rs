@@ -8,6 +8,7 @@
 
 use crate::errors::ExecutionError;
 use crate::future::{CompatCallFuture, MaybeReady};
+use futures::channel::mpsc::UnboundedSender;
 use futures::compat::{Compat01As03, Future01CompatExt};
 use futures::future::{self, TryJoin};
 use futures::ready;
@@ -25,7 +26,14 @@ use web3::types::{TransactionReceipt, H256, U64};
 use web3::Transport;
 
 /// A struct with the confirmation parameters.
+///
+/// Confirmation parameters can not be created with struct literals outside of
+/// this crate so that new parameters can be added without breaking existing
+/// code. Instead, create them with one of the constructors and customize them
+/// with the `poll_interval`, `block_timeout` and `reorg` builder methods, for
+/// example `ConfirmParams::with_confirmations(1).reorg(ReorgHandling::Fail)`.
 #[derive(Clone, Debug)]
+#[non_exhaustive]
 pub struct ConfirmParams {
     /// The number of blocks to confirm the transaction with. This is the number
     /// of blocks mined on top of the block where the transaction was mined.
@@ -41,6 +49,54 @@ pub struct ConfirmParams {
     pub poll_interval: Duration,
     /// The maximum number of blocks to wait for a transaction to get confirmed.
     pub block_timeout: Option<usize>,
+    /// How to handle chain reorganizations that drop the transaction from the
+    /// chain or move it to a different block while it is being confirmed.
+    pub reorg: ReorgHandling,
+}
+
+/// The way chain reorganizations affecting a transaction that is being
+/// confirmed are handled.
+#[derive(Clone, Debug)]
+pub enum ReorgHandling {
+    /// Keep waiting for the transaction to be included again and confirmed
+    /// with the required number of blocks from its new block.
+    Wait,
+    /// Same as `Wait` but additionally send an event for every reorg to the
+    /// specified channel.
+    Notify(UnboundedSender<ReorgEvent>),
+    /// Fail confirmation with an `ExecutionError::Reorg` error.
+    Fail,
+}
+
+/// An event describing how a chain reorganization affected a transaction that
+/// is being confirmed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ReorgEvent {
+    /// The transaction was removed from the chain and is pending again.
+    Dropped {
+        /// The transaction hash.
+        tx: H256,
+        /// The block the transaction was previously included in.
+        block: BlockId,
+    },
+    /// The transaction was included in a different block.
+    Moved {
+        /// The transaction hash.
+        tx: H256,
+        /// The block the transaction was previously included in.
+        from: BlockId,
+        /// The block the transaction is now included in.
+        to: BlockId,
+    },
+}
+
+/// The number and hash of a block.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BlockId {
+    /// The block number.
+    pub number: U64,
+    /// The block hash.
+    pub hash: H256,
 }
 
 /// The default poll interval to use for confirming transactions.
@@ -69,8 +125,30 @@ impl ConfirmParams {
             confirmations: count,
             poll_interval: DEFAULT_POLL_INTERVAL,
             block_timeout: DEFAULT_BLOCK_TIMEOUT,
+            reorg: ReorgHandling::Wait,
         }
     }
+
+    /// Specify the polling interval to use for checking confirmations.
+    pub fn poll_interval(mut self, value: Duration) -> Self {
+        self.poll_interval = value;
+        self
+    }
+
+    /// Specify the maximum number of blocks to wait for the transaction to
+    /// get confirmed, `None` to wait indefinitely.
+    pub fn block_timeout(mut self, value: Option<usize>) -> Self {
+        self.block_timeout = value;
+        self
+    }
+
+    /// Specify how chain reorganizations affecting the transaction are
+    /// handled, if not specified then confirmation keeps waiting for the
+    /// transaction to be included again.
+    pub fn reorg(mut self, value: ReorgHandling) -> Self {
+        self.reorg = value;
+        self
+    }
 }
 
 impl Default for ConfirmParams {
@@ -92,6 +170,9 @@ pub struct ConfirmFuture<T: Transport> {
     /// The current block number when confirmation started. This is used for
     /// timeouts.
     starting_block_num: Option<U64>,
+    /// The block the transaction was included in when it was last checked.
+    /// This is used for detecting chain reorganizations.
+    included_block: Option<BlockId>,
     /// The current state of the confirmation.
     #[pin]
     state: ConfirmState<T>,
@@ -134,6 +215,7 @@ impl<T: Transport> ConfirmFuture<T> {
             tx,
             params,
             starting_block_num: None,
+            included_block: None,
             state: ConfirmState::Check,
         }
     }
@@ -151,6 +233,7 @@ impl<T: Transport> Future for ConfirmFuture<T> {
                 tx,
                 params,
                 starting_block_num,
+                included_block,
                 state,
             } = self.as_mut().project();
 
@@ -161,11 +244,48 @@ impl<T: Transport> Future for ConfirmFuture<T> {
                     web3.eth().transaction_receipt(*tx).compat(),
                 )),
                 ConfirmState::Checking(check) => {
+                    let tx_hash = tx;
                     let (block_num, tx) = match ready!(check.poll(cx)) {
                         Ok(result) => result,
                         Err(err) => return Poll::Ready(Err(err.into())),
                     };
 
+                    let block = tx.as_ref().and_then(|tx| {
+                        Some(BlockId {
+                            number: tx.block_number?,
+                            hash: tx.block_hash?,
+                        })
+                    });
+                    let reorg = match (*included_block, block) {
+                        (Some(previous), None) => Some(ReorgEvent::Dropped {
+                            tx: *tx_hash,
+                            block: previous,
+                        }),
+                        (Some(previous), Some(current)) if previous != current => {
+                            Some(ReorgEvent::Moved {
+                                tx: *tx_hash,
+                                from: previous,
+                                to: current,
+                            })
+                        }
+                        _ => None,
+                    };
+                    *included_block = block;
+
+                    if let Some(reorg) = reorg {
+                        match &params.reorg {
+                            ReorgHandling::Wait => {}
+                            ReorgHandling::Notify(events) => {
+                                // NOTE: Ignore errors from a closed channel,
+                                //   the receiver is no longer interested.
+                                let _ = events.unbounded_send(reorg);
+                            }
+                            ReorgHandling::Fail => {
+                                return Poll::Ready(Err(ExecutionError::Reorg(Box::new(reorg))))
+                            }
+                        }
+                    }
+
                     // NOTE: If the transaction hasn't been mined, then assume
                     //   it will be picked up in the next block.
                     let tx_block_num = tx
@@ -312,11 +432,12 @@ mod tests {
     use web3::types::H2048;
 
     fn generate_tx_receipt<U: Into<U64>>(hash: H256, block_num: U) -> Value {
+        let block_num = block_num.into();
         json!({
             "transactionHash": hash,
             "transactionIndex": "0x1",
-            "blockNumber": block_num.into(),
-            "blockHash": H256::zero(),
+            "blockNumber": block_num,
+            "blockHash": block_hash(block_num),
             "cumulativeGasUsed": "0x1337",
             "gasUsed": "0x1337",
             "logsBloom": H2048::zero(),
@@ -324,6 +445,10 @@ mod tests {
         })
     }
 
+    fn block_hash(block_num: U64) -> H256 {
+        H256::from_low_u64_be(block_num.as_u64())
+    }
+
     #[test]
     fn confirm_mined_transaction() {
         let mut transport = TestTransport::new();
@@ -701,4 +826,93 @@ mod tests {
         transport.assert_request("eth_getTransactionReceipt", &[json!(hash)]);
         transport.assert_no_more_requests();
     }
+
+    #[test]
+    fn confirmation_fails_on_reorg() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let hash = H256::repeat_byte(0xff);
+        let params = ConfirmParams::with_confirmations(1).reorg(ReorgHandling::Fail);
+
+        // transaction mined on block 1
+        transport.add_response(json!("0x1"));
+        transport.add_response(generate_tx_receipt(hash, 1));
+        // wait for 1 block
+        transport.add_response(json!("0xf0"));
+        transport.add_response(json!([H256::repeat_byte(2)]));
+        // check confirmation - reorg happened, tx mined on block 2!
+        transport.add_response(json!("0x2"));
+        transport.add_response(generate_tx_receipt(hash, 2));
+
+        let err = ConfirmFuture::new(&web3, hash, params)
+            .immediate()
+            .expect_err("unexpected transaction confirmation");
+
+        let block = |number: u64| BlockId {
+            number: number.into(),
+            hash: block_hash(number.into()),
+        };
+        assert!(
+            match &err {
+                ExecutionError::Reorg(reorg) => {
+                    **reorg
+                        == ReorgEvent::Moved {
+                            tx: hash,
+                            from: block(1),
+                            to: block(2),
+                        }
+                }
+                _ => false,
+            },
+            "expected reorg error but got {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn confirmation_notifies_reorgs() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let hash = H256::repeat_byte(0xff);
+        let (sender, mut receiver) = futures::channel::mpsc::unbounded();
+        let params = ConfirmParams::with_confirmations(1).reorg(ReorgHandling::Notify(sender));
+
+        // transaction mined on block 1
+        transport.add_response(json!("0x1"));
+        transport.add_response(generate_tx_receipt(hash, 1));
+        // wait for 1 block
+        transport.add_response(json!("0xf0"));
+        transport.add_response(json!([H256::repeat_byte(2)]));
+        // check confirmation - reorg happened, tx is pending again
+        transport.add_response(json!("0x2"));
+        transport.add_response(json!(null));
+        // wait for 2 blocks
+        transport.add_response(json!("0xf1"));
+        transport.add_response(json!([H256::repeat_byte(3), H256::repeat_byte(4)]));
+        // check confirmation - tx was included again on block 3
+        transport.add_response(json!("0x4"));
+        transport.add_response(generate_tx_receipt(hash, 3));
+
+        let confirm = ConfirmFuture::new(&web3, hash, params)
+            .immediate()
+            .expect("transaction confirmation failed");
+
+        assert_eq!(confirm.block_number, Some(3.into()));
+        assert_eq!(
+            receiver.try_recv().expect("missing reorg event"),
+            ReorgEvent::Dropped {
+                tx: hash,
+                block: BlockId {
+                    number: 1.into(),
+                    hash: block_hash(1.into()),
+                },
+            }
+        );
+        assert!(
+            receiver.try_recv().is_err(),
+            "unexpected additional reorg events"
+        );
+    }
 }
@@ -0,0 +1,745 @@
+//! Implementation of gas price escalation for transactions that remain pending
+//! for too long. Stuck transactions are replaced by transactions with the same
+//! nonce and an increased gas price until one of them gets mined.
+
+use crate::errors::ExecutionError;
+use crate::future::{CompatCallFuture, MaybeReady};
+use crate::transaction::confirm::{ConfirmFuture, ConfirmParams};
+use crate::transaction::gas_price::{self, GasFees, GasPrice, ResolveGasFeesFuture};
+use crate::transaction::send::SendFuture;
+use crate::transaction::{Account, ResolveCondition, TransactionBuilder};
+use futures::compat::Future01CompatExt;
+use futures::future::{self, TryJoin, TryJoinAll};
+use futures::ready;
+use futures_timer::Delay;
+use pin_project::{pin_project, project};
+use std::cmp;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use web3::api::Web3;
+use web3::error::Error as Web3Error;
+use web3::types::{Address, BlockNumber, TransactionReceipt, H256, U256, U64};
+use web3::Transport;
+
+/// The default factor by which the gas price is increased for each
+/// replacement transaction. Note that nodes usually require replacement
+/// transactions to increase the gas price by at least 10%.
+pub const DEFAULT_ESCALATION_FACTOR: f64 = 1.125;
+
+/// The minimum gas price increase in percent that nodes require for accepting
+/// a replacement transaction.
+const MIN_REPLACEMENT_BUMP: u64 = 10;
+
+/// Error messages returned by nodes when rejecting a replacement transaction
+/// because one of the transactions that were already sent was mined or
+/// because the same transaction was already sent.
+const REPLACED_ERROR_MESSAGES: &[&str] = &[
+    "nonce too low",
+    "nonce is too low",
+    "already known",
+    "already imported",
+];
+
+/// A struct with the gas price escalation parameters.
+#[derive(Clone, Debug)]
+pub struct EscalationParams {
+    /// The number of blocks to wait for the transaction to get mined before
+    /// replacing it with a transaction with an increased gas price.
+    pub blocks: usize,
+    /// The factor by which the gas price is increased for each replacement
+    /// transaction. For EIP-1559 transactions both the max fee and the max
+    /// priority fee are increased. Note that factors below `1.1` never produce
+    /// replacements that nodes accept.
+    pub factor: f64,
+    /// The maximum gas price to escalate to. For EIP-1559 transactions this is
+    /// the cap for the max fee per gas. Escalation stops once the capped gas
+    /// price no longer increases the gas price by the 10% nodes require for
+    /// replacement transactions.
+    pub max_gas_price: U256,
+    /// The parameters used for confirming the transaction that gets mined.
+    /// The poll interval is used for checking whether one of the transactions
+    /// was mined and the block timeout is counted from the first transaction
+    /// that was sent.
+    pub confirm: ConfirmParams,
+}
+
+impl EscalationParams {
+    /// Create new escalation parameters that replace the transaction every
+    /// specified number of blocks, using the default escalation factor, up to
+    /// a maximum gas price.
+    pub fn new(blocks: usize, max_gas_price: U256) -> Self {
+        EscalationParams {
+            blocks,
+            factor: DEFAULT_ESCALATION_FACTOR,
+            max_gas_price,
+            confirm: ConfirmParams::mined(),
+        }
+    }
+
+    /// Computes the gas fees for the replacement of a transaction with the
+    /// specified fees.
+    pub(crate) fn escalate(&self, fees: GasFees) -> GasFees {
+        let scale = |value| {
+            cmp::min(
+                gas_price::scale_gas_price(value, self.factor),
+                self.max_gas_price,
+            )
+        };
+        match fees {
+            GasFees::Legacy(gas_price) => GasFees::Legacy(scale(gas_price)),
+            GasFees::Eip1559 {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            } => {
+                let max_fee_per_gas = scale(max_fee_per_gas);
+                GasFees::Eip1559 {
+                    max_fee_per_gas,
+                    max_priority_fee_per_gas: cmp::min(
+                        scale(max_priority_fee_per_gas),
+                        max_fee_per_gas,
+                    ),
+                }
+            }
+        }
+    }
+}
+
+/// Returns true if nodes accept a replacement transaction with the next gas
+/// fees for a transaction with the current gas fees, that is if all its fees
+/// are increased by at least 10%.
+fn is_valid_replacement(current: GasFees, next: GasFees) -> bool {
+    let bumped = |current: U256, next: U256| {
+        next >= current.saturating_mul((100 + MIN_REPLACEMENT_BUMP).into()) / 100
+    };
+    match (current, next) {
+        (GasFees::Legacy(current), GasFees::Legacy(next)) => {
+            next > current && bumped(current, next)
+        }
+        (
+            GasFees::Eip1559 {
+                max_fee_per_gas: current_max_fee,
+                max_priority_fee_per_gas: current_priority_fee,
+            },
+            GasFees::Eip1559 {
+                max_fee_per_gas: next_max_fee,
+                max_priority_fee_per_gas: next_priority_fee,
+            },
+        ) => {
+            next_max_fee > current_max_fee
+                && bumped(current_max_fee, next_max_fee)
+                && bumped(current_priority_fee, next_priority_fee)
+        }
+        _ => false,
+    }
+}
+
+/// Returns true if the error indicates that a replacement transaction was
+/// rejected because one of the already sent transactions was mined or because
+/// it was already sent.
+fn is_replaced_error(err: &ExecutionError) -> bool {
+    let message = match err {
+        ExecutionError::Web3(Web3Error::Rpc(err)) => err.message.to_lowercase(),
+        _ => return false,
+    };
+
+    REPLACED_ERROR_MESSAGES
+        .iter()
+        .any(|pattern| message.contains(pattern))
+}
+
+/// Future for sending a transaction and replacing it with transactions with
+/// an escalating gas price until one of them is mined. The future resolves to
+/// the receipt of the transaction that was mined, whether it is the original
+/// transaction or one of its replacements.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[pin_project]
+pub struct EscalateFuture<T: Transport> {
+    web3: Web3<T>,
+    /// The transaction builder used for building the original transaction and
+    /// its replacements.
+    builder: TransactionBuilder<T>,
+    /// The escalation parameters.
+    params: EscalationParams,
+    /// The nonce used for all the transactions.
+    nonce: U256,
+    /// The gas fees for the last sent transaction.
+    fees: Option<GasFees>,
+    /// The hashes of the transactions that were sent.
+    hashes: Vec<H256>,
+    /// Whether this future sent at least one transaction. This differs from
+    /// `hashes` being non-empty when replacing an already sent transaction.
+    sent: bool,
+    /// The block number when checking for the first time, used for timeouts.
+    starting_block_num: Option<U64>,
+    /// The block number when the last transaction was sent.
+    sent_block_num: Option<U64>,
+    /// The current state of the future.
+    #[pin]
+    state: EscalateState<T>,
+}
+
+/// The state of the escalation future.
+#[pin_project]
+#[allow(clippy::large_enum_variant)]
+enum EscalateState<T: Transport> {
+    /// The default account of the node is being retrieved since no sender was
+    /// specified.
+    ResolvingSender(#[pin] CompatCallFuture<T, Vec<Address>>),
+    /// The nonce and initial gas fees are being resolved.
+    Preparing(#[pin] PrepareFuture<T>),
+    /// A transaction is being sent.
+    Sending(Pin<Box<SendFuture<T>>>),
+    /// Checking whether one of the transactions was mined.
+    Checking(#[pin] CheckFuture<T>),
+    /// Waiting for the poll interval before checking again.
+    Waiting(#[pin] MaybeReady<Delay>),
+    /// One of the transactions was mined and is being confirmed.
+    Confirming(#[pin] ConfirmFuture<T>),
+}
+
+/// Type alias for a future resolving the nonce and initial gas fees.
+type PrepareFuture<T> = TryJoin<MaybeReady<CompatCallFuture<T, U256>>, ResolveGasFeesFuture<T>>;
+
+/// Type alias for a future retrieving the current block number and the
+/// receipts of all the sent transactions.
+type CheckFuture<T> =
+    TryJoin<CompatCallFuture<T, U64>, TryJoinAll<CompatCallFuture<T, Option<TransactionReceipt>>>>;
+
+impl<T: Transport> EscalateFuture<T> {
+    /// Create a new future from a `TransactionBuilder` and escalation
+    /// parameters.
+    pub fn from_builder(mut builder: TransactionBuilder<T>, params: EscalationParams) -> Self {
+        let web3 = builder.web3.clone();
+        builder.resolve = Some(ResolveCondition::Pending);
+        builder.nonce_manager = None;
+
+        let state = match &builder.from {
+            Some(from) => EscalateState::Preparing(prepare(&web3, &builder, from.address())),
+            None => EscalateState::ResolvingSender(web3.eth().accounts().compat()),
+        };
+
+        EscalateFuture {
+            web3,
+            builder,
+            params,
+            nonce: U256::zero(),
+            fees: None,
+            hashes: Vec::new(),
+            sent: false,
+            starting_block_num: None,
+            sent_block_num: None,
+            state,
+        }
+    }
+
+    /// Create a new future for replacing an already sent transaction with the
+    /// transaction from a `TransactionBuilder`. The future resolves to the
+    /// receipt of whichever transaction was mined.
+    pub(crate) fn replacing(
+        builder: TransactionBuilder<T>,
+        tx: H256,
+        params: EscalationParams,
+    ) -> Self {
+        let mut escalate = EscalateFuture::from_builder(builder, params);
+        escalate.hashes.push(tx);
+        escalate
+    }
+
+    /// Returns true if at least one transaction was sent.
+    pub(crate) fn is_sent(&self) -> bool {
+        self.sent
+    }
+}
+
+/// Creates a future for resolving the nonce and initial gas fees.
+fn prepare<T: Transport>(
+    web3: &Web3<T>,
+    builder: &TransactionBuilder<T>,
+    from: Address,
+) -> PrepareFuture<T> {
+    let nonce = match builder.nonce {
+        Some(nonce) => MaybeReady::ready(Ok(nonce)),
+        None => MaybeReady::future(
+            web3.eth()
+                .transaction_count(from, Some(BlockNumber::Pending))
+                .compat(),
+        ),
+    };
+    let fees = builder.gas_price.unwrap_or_default().resolve_fees(
+        web3,
+        builder.max_fee_per_gas,
+        builder.max_priority_fee_per_gas,
+    );
+
+    future::try_join(nonce, fees)
+}
+
+/// Creates a future for sending a transaction with the specified nonce and
+/// gas fees.
+fn send<T: Transport>(
+    builder: &TransactionBuilder<T>,
+    nonce: U256,
+    fees: GasFees,
+) -> SendFuture<T> {
+    let mut builder = builder.clone().nonce(nonce);
+    match fees {
+        GasFees::Legacy(gas_price) => {
+            builder.gas_price = Some(GasPrice::Value(gas_price));
+            builder.max_fee_per_gas = None;
+            builder.max_priority_fee_per_gas = None;
+        }
+        GasFees::Eip1559 {
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+        } => {
+            builder.max_fee_per_gas = Some(max_fee_per_gas);
+            builder.max_priority_fee_per_gas = Some(max_priority_fee_per_gas);
+        }
+    }
+
+    builder.send()
+}
+
+impl<T: Transport> Future for EscalateFuture<T> {
+    type Output = Result<TransactionReceipt, ExecutionError>;
+
+    #[project]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        loop {
+            #[project]
+            let EscalateFuture {
+                web3,
+                builder,
+                params,
+                nonce,
+                fees,
+                hashes,
+                sent,
+                starting_block_num,
+                sent_block_num,
+                state,
+            } = self.as_mut().project();
+
+            #[project]
+            let next_state = match state.project() {
+                EscalateState::ResolvingSender(accounts) => {
+                    let from = match ready!(accounts.poll(cx))?.first() {
+                        Some(address) => *address,
+                        None => return Poll::Ready(Err(ExecutionError::NoLocalAccounts)),
+                    };
+                    builder.from = Some(Account::Local(from, None));
+
+                    EscalateState::Preparing(prepare(web3, builder, from))
+                }
+                EscalateState::Preparing(prepare) => {
+                    let (resolved_nonce, resolved_fees) = ready!(prepare.poll(cx))?;
+                    *nonce = resolved_nonce;
+                    *fees = Some(resolved_fees);
+
+                    EscalateState::Sending(Box::pin(send(builder, resolved_nonce, resolved_fees)))
+                }
+                EscalateState::Sending(send) => {
+                    match ready!(send.as_mut().poll(cx)) {
+                        Ok(result) => {
+                            hashes.push(result.hash());
+                            *sent = true;
+                        }
+                        // NOTE: Replacement transactions fail to send if the
+                        //   original transaction was mined in the meantime. In
+                        //   this case keep checking the transactions that were
+                        //   already sent. Errors sending the first transaction
+                        //   are always returned, even when it replaces an
+                        //   already sent transaction.
+                        Err(err) if *sent && is_replaced_error(&err) => {}
+                        Err(err) => return Poll::Ready(Err(err)),
+                    }
+                    *sent_block_num = None;
+
+                    EscalateState::Checking(check(web3, hashes))
+                }
+                EscalateState::Checking(check) => {
+                    let (block_num, receipts) = ready!(check.poll(cx))?;
+
+                    let mined = receipts
+                        .into_iter()
+                        .flatten()
+                        .find(|receipt| receipt.block_number.is_some());
+                    if let Some(receipt) = mined {
+                        if params.confirm.confirmations == 0 {
+                            return Poll::Ready(Ok(receipt));
+                        }
+
+                        EscalateState::Confirming(ConfirmFuture::new(
+                            web3,
+                            receipt.transaction_hash,
+                            params.confirm.clone(),
+                        ))
+                    } else {
+                        if let Some(block_timeout) = params.confirm.block_timeout {
+                            let starting_block_num = *starting_block_num.get_or_insert(block_num);
+                            let elapsed_blocks = block_num.saturating_sub(starting_block_num);
+
+                            if elapsed_blocks > U64::from(block_timeout) {
+                                return Poll::Ready(Err(ExecutionError::ConfirmTimeout));
+                            }
+                        }
+
+                        let sent_block_num = *sent_block_num.get_or_insert(block_num);
+                        let current_fees = fees.expect("transaction sent without fees");
+                        let next_fees = params.escalate(current_fees);
+
+                        if block_num.saturating_sub(sent_block_num) >= U64::from(params.blocks)
+                            && is_valid_replacement(current_fees, next_fees)
+                        {
+                            *fees = Some(next_fees);
+                            EscalateState::Sending(Box::pin(send(builder, *nonce, next_fees)))
+                        } else {
+                            EscalateState::Waiting(delay(params.confirm.poll_interval))
+                        }
+                    }
+                }
+                EscalateState::Waiting(delay) => {
+                    ready!(delay.poll(cx));
+                    EscalateState::Checking(check(web3, hashes))
+                }
+                EscalateState::Confirming(confirm) => return confirm.poll(cx),
+            };
+
+            self.as_mut().project().state.set(next_state);
+        }
+    }
+}
+
+/// Creates a future for checking whether one of the transactions was mined.
+fn check<T: Transport>(web3: &Web3<T>, hashes: &[H256]) -> CheckFuture<T> {
+    let eth = web3.eth();
+    future::try_join(
+        eth.block_number().compat(),
+        future::try_join_all(
+            hashes
+                .iter()
+                .map(|hash| eth.transaction_receipt(*hash).compat()),
+        ),
+    )
+}
+
+/// Create a new delay that resolves immediately when delayed for a zero
+/// duration.
+fn delay(duration: Duration) -> MaybeReady<Delay> {
+    if duration == Duration::default() {
+        MaybeReady::ready(())
+    } else {
+        MaybeReady::future(Delay::new(duration))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret::PrivateKey;
+    use crate::test::prelude::*;
+    use crate::transaction::build::{BuildOfflineSignedTransactionFuture, TransactionOptions};
+    use jsonrpc_core::{Error as RpcError, ErrorCode};
+    use serde_json::Value;
+    use web3::types::{Bytes, H2048};
+
+    fn generate_tx_receipt(hash: H256, block_num: u64) -> Value {
+        json!({
+            "transactionHash": hash,
+            "transactionIndex": "0x1",
+            "blockNumber": U64::from(block_num),
+            "blockHash": H256::zero(),
+            "cumulativeGasUsed": "0x1337",
+            "gasUsed": "0x1337",
+            "logsBloom": H2048::zero(),
+            "logs": [],
+            "status": "0x1",
+        })
+    }
+
+    #[test]
+    fn escalate_fees() {
+        let params = EscalationParams {
+            factor: 2.0,
+            ..EscalationParams::new(1, 300.into())
+        };
+
+        assert_eq!(
+            params.escalate(GasFees::Legacy(100.into())),
+            GasFees::Legacy(200.into())
+        );
+        assert_eq!(
+            params.escalate(GasFees::Legacy(200.into())),
+            GasFees::Legacy(300.into())
+        );
+        assert_eq!(
+            params.escalate(GasFees::Eip1559 {
+                max_fee_per_gas: 200.into(),
+                max_priority_fee_per_gas: 100.into(),
+            }),
+            GasFees::Eip1559 {
+                max_fee_per_gas: 300.into(),
+                max_priority_fee_per_gas: 200.into(),
+            }
+        );
+    }
+
+    fn build_raw_tx(web3: &Web3<TestTransport>, key: &PrivateKey, gas_price: u64) -> Bytes {
+        BuildOfflineSignedTransactionFuture::new(
+            web3,
+            key.clone(),
+            Some(1),
+            GasPrice::Value(gas_price.into()),
+            TransactionOptions {
+                to: Some(addr!("0x0123456789012345678901234567890123456789")),
+                gas: Some(21000.into()),
+                nonce: Some(42.into()),
+                ..Default::default()
+            },
+        )
+        .immediate()
+        .expect("failed to build transaction")
+    }
+
+    fn escalated_builder(
+        web3: &Web3<TestTransport>,
+        key: &PrivateKey,
+        params: EscalationParams,
+    ) -> TransactionBuilder<TestTransport> {
+        TransactionBuilder::new(web3.clone())
+            .from(Account::Offline(key.clone(), Some(1)))
+            .to(addr!("0x0123456789012345678901234567890123456789"))
+            .gas(21000.into())
+            .gas_price(100.into())
+            .nonce(42.into())
+            .resolve(ResolveCondition::Escalated(params))
+    }
+
+    fn rpc_error(message: &str) -> Web3Error {
+        Web3Error::Rpc(RpcError {
+            code: ErrorCode::ServerError(-32000),
+            message: message.to_owned(),
+            data: None,
+        })
+    }
+
+    #[test]
+    fn valid_replacements() {
+        let legacy = |gas_price: u64| GasFees::Legacy(gas_price.into());
+        let eip1559 = |max_fee: u64, priority_fee: u64| GasFees::Eip1559 {
+            max_fee_per_gas: max_fee.into(),
+            max_priority_fee_per_gas: priority_fee.into(),
+        };
+
+        assert!(is_valid_replacement(legacy(100), legacy(110)));
+        assert!(!is_valid_replacement(legacy(100), legacy(109)));
+        assert!(!is_valid_replacement(legacy(0), legacy(0)));
+        assert!(is_valid_replacement(eip1559(100, 10), eip1559(110, 11)));
+        assert!(is_valid_replacement(eip1559(100, 0), eip1559(110, 0)));
+        assert!(!is_valid_replacement(eip1559(100, 10), eip1559(110, 10)));
+        assert!(!is_valid_replacement(eip1559(100, 10), eip1559(105, 20)));
+        assert!(!is_valid_replacement(legacy(100), eip1559(200, 20)));
+    }
+
+    #[test]
+    fn replaced_errors() {
+        for message in &[
+            "nonce too low",
+            "Transaction nonce is too low. Try incrementing the nonce.",
+            "already known",
+            "Transaction with the same hash was already imported.",
+        ] {
+            let err = ExecutionError::from(rpc_error(message));
+            assert!(is_replaced_error(&err), "{}", message);
+        }
+
+        for message in &[
+            "insufficient funds for gas * price + value",
+            "replacement transaction underpriced",
+            "nonce too high",
+        ] {
+            let err = ExecutionError::from(rpc_error(message));
+            assert!(!is_replaced_error(&err), "{}", message);
+        }
+    }
+
+    #[test]
+    fn escalate_until_mined() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let key = key!("0x0102030405060708091011121314151617181920212223242526272829303132");
+        let hashes = [H256::repeat_byte(1), H256::repeat_byte(2)];
+        let raw_txs = [
+            build_raw_tx(&web3, &key, 100),
+            build_raw_tx(&web3, &key, 200),
+        ];
+
+        // original transaction sent and pending for one block
+        transport.add_response(json!(hashes[0]));
+        transport.add_response(json!("0x1"));
+        transport.add_response(json!(null));
+        transport.add_response(json!("0x2"));
+        transport.add_response(json!(null));
+        // replacement transaction sent and mined in the next block
+        transport.add_response(json!(hashes[1]));
+        transport.add_response(json!("0x2"));
+        transport.add_response(json!(null));
+        transport.add_response(json!(null));
+        transport.add_response(json!("0x3"));
+        transport.add_response(json!(null));
+        transport.add_response(generate_tx_receipt(hashes[1], 3));
+
+        let params = EscalationParams {
+            factor: 2.0,
+            ..EscalationParams::new(1, 1000.into())
+        };
+        let receipt = escalated_builder(&web3, &key, params)
+            .send()
+            .immediate()
+            .expect("transaction failed");
+
+        assert_eq!(receipt.hash(), hashes[1]);
+
+        transport.assert_request("eth_sendRawTransaction", &[json!(raw_txs[0])]);
+        transport.assert_request("eth_blockNumber", &[]);
+        transport.assert_request("eth_getTransactionReceipt", &[json!(hashes[0])]);
+        transport.assert_request("eth_blockNumber", &[]);
+        transport.assert_request("eth_getTransactionReceipt", &[json!(hashes[0])]);
+        transport.assert_request("eth_sendRawTransaction", &[json!(raw_txs[1])]);
+        for _ in 0..2 {
+            transport.assert_request("eth_blockNumber", &[]);
+            transport.assert_request("eth_getTransactionReceipt", &[json!(hashes[0])]);
+            transport.assert_request("eth_getTransactionReceipt", &[json!(hashes[1])]);
+        }
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn escalate_replacement_nonce_too_low() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let key = key!("0x0102030405060708091011121314151617181920212223242526272829303132");
+        let hash = H256::repeat_byte(1);
+        let raw_txs = [
+            build_raw_tx(&web3, &key, 100),
+            build_raw_tx(&web3, &key, 200),
+        ];
+
+        // original transaction sent and pending for one block
+        transport.add_response(json!(hash));
+        transport.add_response(json!("0x1"));
+        transport.add_response(json!(null));
+        transport.add_response(json!("0x2"));
+        transport.add_response(json!(null));
+        // replacement rejected since the original transaction was mined
+        transport.add_error(rpc_error("nonce too low"));
+        transport.add_response(json!("0x2"));
+        transport.add_response(generate_tx_receipt(hash, 2));
+
+        let params = EscalationParams {
+            factor: 2.0,
+            ..EscalationParams::new(1, 1000.into())
+        };
+        let receipt = escalated_builder(&web3, &key, params)
+            .send()
+            .immediate()
+            .expect("transaction failed");
+
+        assert_eq!(receipt.hash(), hash);
+
+        transport.assert_request("eth_sendRawTransaction", &[json!(raw_txs[0])]);
+        for _ in 0..2 {
+            transport.assert_request("eth_blockNumber", &[]);
+            transport.assert_request("eth_getTransactionReceipt", &[json!(hash)]);
+        }
+        transport.assert_request("eth_sendRawTransaction", &[json!(raw_txs[1])]);
+        transport.assert_request("eth_blockNumber", &[]);
+        transport.assert_request("eth_getTransactionReceipt", &[json!(hash)]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn escalate_replacement_error() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let key = key!("0x0102030405060708091011121314151617181920212223242526272829303132");
+        let hash = H256::repeat_byte(1);
+        let raw_txs = [
+            build_raw_tx(&web3, &key, 100),
+            build_raw_tx(&web3, &key, 200),
+        ];
+
+        transport.add_response(json!(hash));
+        transport.add_response(json!("0x1"));
+        transport.add_response(json!(null));
+        transport.add_response(json!("0x2"));
+        transport.add_response(json!(null));
+        transport.add_error(rpc_error("insufficient funds for gas * price + value"));
+
+        let params = EscalationParams {
+            factor: 2.0,
+            ..EscalationParams::new(1, 1000.into())
+        };
+        let err = escalated_builder(&web3, &key, params)
+            .send()
+            .immediate()
+            .expect_err("replacement error was ignored");
+
+        match err {
+            ExecutionError::Web3(Web3Error::Rpc(err)) => {
+                assert_eq!(err.message, "insufficient funds for gas * price + value")
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+
+        transport.assert_request("eth_sendRawTransaction", &[json!(raw_txs[0])]);
+        for _ in 0..2 {
+            transport.assert_request("eth_blockNumber", &[]);
+            transport.assert_request("eth_getTransactionReceipt", &[json!(hash)]);
+        }
+        transport.assert_request("eth_sendRawTransaction", &[json!(raw_txs[1])]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn escalate_stops_below_minimum_bump() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let key = key!("0x0102030405060708091011121314151617181920212223242526272829303132");
+        let hash = H256::repeat_byte(1);
+        let raw_tx = build_raw_tx(&web3, &key, 100);
+
+        // the capped gas price of 105 is not a valid replacement so the
+        // original transaction is kept until the block timeout
+        transport.add_response(json!(hash));
+        for block_num in 1..=3 {
+            transport.add_response(json!(U64::from(block_num)));
+            transport.add_response(json!(null));
+        }
+
+        let params = EscalationParams {
+            factor: 2.0,
+            confirm: ConfirmParams::mined().block_timeout(Some(1)),
+            ..EscalationParams::new(1, 105.into())
+        };
+        let err = escalated_builder(&web3, &key, params)
+            .send()
+            .immediate()
+            .expect_err("transaction did not time out");
+
+        assert!(matches!(err, ExecutionError::ConfirmTimeout));
+
+        transport.assert_request("eth_sendRawTransaction", &[json!(raw_tx)]);
+        for _ in 1..=3 {
+            transport.assert_request("eth_blockNumber", &[]);
+            transport.assert_request("eth_getTransactionReceipt", &[json!(hash)]);
+        }
+        transport.assert_no_more_requests();
+    }
+}
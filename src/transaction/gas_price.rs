@@ -5,14 +5,23 @@ use crate::future::CompatCallFuture;
 use futures::compat::Future01CompatExt;
 use futures::future::OptionFuture;
 use pin_project::{pin_project, project};
+use serde::Deserialize;
+use std::cmp;
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use web3::api::Web3;
 use web3::error::Error as Web3Error;
-use web3::types::U256;
+use web3::helpers::{self, CallFuture};
+use web3::types::{BlockNumber, U256};
 use web3::Transport;
 
+/// The default number of blocks to sample priority fees from.
+const DEFAULT_FEE_HISTORY_BLOCK_COUNT: u64 = 10;
+
+/// The default percentile of priority fees paid in a block to sample.
+const DEFAULT_FEE_HISTORY_REWARD_PERCENTILE: f64 = 50.0;
+
 /// The gas price setting to use.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum GasPrice {
@@ -30,6 +39,17 @@ pub enum GasPrice {
     /// the transaction `SendFuture` to not query the node for a gas price
     /// estimation.
     Value(U256),
+    /// Use EIP-1559 fees estimated from the node's fee history with
+    /// `eth_feeHistory`. The max priority fee per gas is the median of the
+    /// `reward_percentile`th percentile priority fees paid in each of the last
+    /// `block_count` blocks, and the max fee per gas allows for the base fee
+    /// to double before the transaction gets mined.
+    FeeHistory {
+        /// The number of blocks to sample priority fees from.
+        block_count: u64,
+        /// The percentile of priority fees paid in a block to sample.
+        reward_percentile: f64,
+    },
 }
 
 impl GasPrice {
@@ -45,6 +65,15 @@ impl GasPrice {
         GasPrice::Scaled(6.0)
     }
 
+    /// EIP-1559 fees based on the median priority fee paid over the last 10
+    /// blocks.
+    pub fn fee_history() -> Self {
+        GasPrice::FeeHistory {
+            block_count: DEFAULT_FEE_HISTORY_BLOCK_COUNT,
+            reward_percentile: DEFAULT_FEE_HISTORY_REWARD_PERCENTILE,
+        }
+    }
+
     /// Returns `Some(value)` if the gas price is explicitly specified, `None`
     /// otherwise.
     pub fn value(&self) -> Option<U256> {
@@ -56,12 +85,28 @@ impl GasPrice {
 
     /// Resolves the gas price into a value. Returns a future that resolves once
     /// the gas price is calculated as this may require contacting the node for
-    /// gas price estimates in the case of `GasPrice::Standard` and
-    /// `GasPrice::Scaled`.
+    /// gas price estimates in the case of `GasPrice::Standard`,
+    /// `GasPrice::Scaled` and `GasPrice::FeeHistory`.
+    ///
+    /// Note that `GasPrice::FeeHistory` resolves to the effective gas price of
+    /// the next block, that is the sum of its base fee and the estimated
+    /// priority fee.
     pub fn resolve<T: Transport>(self, web3: &Web3<T>) -> ResolveGasPriceFuture<T> {
         ResolveGasPriceFuture::new(web3, self)
     }
 
+    /// Resolves the gas price into the gas fees for a transaction. Explicitly
+    /// specified EIP-1559 fees take precedence over the gas price setting, any
+    /// fee that is missing is then estimated from the node's fee history.
+    pub fn resolve_fees<T: Transport>(
+        self,
+        web3: &Web3<T>,
+        max_fee_per_gas: Option<U256>,
+        max_priority_fee_per_gas: Option<U256>,
+    ) -> ResolveGasFeesFuture<T> {
+        ResolveGasFeesFuture::new(web3, self, max_fee_per_gas, max_priority_fee_per_gas)
+    }
+
     /// Resolves the gas price into an `Option<U256>` intendend to be used by a
     /// `TransactionRequest`. Note that `TransactionRequest`s gas price default
     /// to the node's estimate (i.e. `GasPrice::Standard`) when omitted, so this
@@ -76,6 +121,23 @@ impl GasPrice {
         };
         future.into()
     }
+
+    /// Resolves the gas price into optional gas fees intended to be used by a
+    /// `TransactionRequest`. Similarly to `resolve_for_transaction_request`,
+    /// this skips any JSON RPC requests when the node's standard gas price is
+    /// used and no EIP-1559 fees were specified.
+    pub fn resolve_fees_for_transaction_request<T: Transport>(
+        self,
+        web3: &Web3<T>,
+        max_fee_per_gas: Option<U256>,
+        max_priority_fee_per_gas: Option<U256>,
+    ) -> ResolveTransactionRequestGasFeesFuture<T> {
+        let future = match (self, max_fee_per_gas, max_priority_fee_per_gas) {
+            (GasPrice::Standard, None, None) => None,
+            _ => Some(self.resolve_fees(web3, max_fee_per_gas, max_priority_fee_per_gas)),
+        };
+        future.into()
+    }
 }
 
 impl Default for GasPrice {
@@ -125,6 +187,9 @@ enum ResolveGasPriceState<T: Transport> {
     /// The gas price estimate is being queried with the node. Optinally, the
     /// gas price will be scaled once retrieved.
     Estimating(#[pin] CompatCallFuture<T, U256>, Option<f64>),
+    /// The fee history is being queried with the node and the effective gas
+    /// price will be computed once retrieved.
+    FeeHistory(#[pin] CompatCallFuture<T, FeeHistory>),
 }
 
 impl<T: Transport> ResolveGasPriceFuture<T> {
@@ -138,6 +203,12 @@ impl<T: Transport> ResolveGasPriceFuture<T> {
                 ResolveGasPriceState::Estimating(web3.eth().gas_price().compat(), Some(factor))
             }
             GasPrice::Value(value) => ResolveGasPriceState::Ready(value),
+            GasPrice::FeeHistory {
+                block_count,
+                reward_percentile,
+            } => {
+                ResolveGasPriceState::FeeHistory(fee_history(web3, block_count, reward_percentile))
+            }
         };
 
         ResolveGasPriceFuture { state }
@@ -163,6 +234,9 @@ impl<T: Transport> Future for ResolveGasPriceFuture<T> {
                     }
                 })
             }
+            ResolveGasPriceState::FeeHistory(fee_history) => fee_history
+                .poll(cx)
+                .map(|fee_history| fee_history?.effective_gas_price()),
         }
     }
 }
@@ -171,8 +245,229 @@ impl<T: Transport> Future for ResolveGasPriceFuture<T> {
 /// price for a `TransactionRequest`.
 pub type ResolveTransactionRequestGasPriceFuture<T> = OptionFuture<ResolveGasPriceFuture<T>>;
 
+/// The resolved gas fees for a transaction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GasFees {
+    /// A legacy gas price.
+    Legacy(U256),
+    /// EIP-1559 fees for a type 2 transaction.
+    Eip1559 {
+        /// The maximum total fee per gas the sender is willing to pay,
+        /// including the base fee.
+        max_fee_per_gas: U256,
+        /// The maximum fee per gas paid to the miner on top of the base fee.
+        max_priority_fee_per_gas: U256,
+    },
+}
+
+impl GasFees {
+    /// Returns the legacy gas price if these are legacy fees, `None`
+    /// otherwise.
+    pub fn gas_price(&self) -> Option<U256> {
+        match self {
+            GasFees::Legacy(gas_price) => Some(*gas_price),
+            _ => None,
+        }
+    }
+
+    /// Returns the maximum amount of wei that can be paid per unit of gas.
+    pub fn max_gas_price(&self) -> U256 {
+        match self {
+            GasFees::Legacy(gas_price) => *gas_price,
+            GasFees::Eip1559 {
+                max_fee_per_gas, ..
+            } => *max_fee_per_gas,
+        }
+    }
+}
+
+/// Future for resolving gas fees.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[pin_project]
+pub struct ResolveGasFeesFuture<T: Transport> {
+    /// The state of the future.
+    #[pin]
+    state: ResolveGasFeesState<T>,
+}
+
+/// The state of the `ResolveGasFeesFuture`.
+#[pin_project]
+enum ResolveGasFeesState<T: Transport> {
+    /// The gas fees are known before hand.
+    Ready(GasFees),
+    /// A legacy gas price is being resolved.
+    GasPrice(#[pin] ResolveGasPriceFuture<T>),
+    /// The fee history is being queried with the node. The explicitly
+    /// specified fees override the estimated ones once retrieved.
+    FeeHistory(
+        #[pin] CompatCallFuture<T, FeeHistory>,
+        Option<U256>,
+        Option<U256>,
+    ),
+}
+
+impl<T: Transport> ResolveGasFeesFuture<T> {
+    /// Creates a new future that resolves once the gas fees are computed.
+    pub fn new(
+        web3: &Web3<T>,
+        gas_price: GasPrice,
+        max_fee_per_gas: Option<U256>,
+        max_priority_fee_per_gas: Option<U256>,
+    ) -> Self {
+        let state = match (gas_price, max_fee_per_gas, max_priority_fee_per_gas) {
+            (_, Some(max_fee_per_gas), Some(max_priority_fee_per_gas)) => {
+                ResolveGasFeesState::Ready(GasFees::Eip1559 {
+                    max_fee_per_gas,
+                    max_priority_fee_per_gas,
+                })
+            }
+            (
+                GasPrice::FeeHistory {
+                    block_count,
+                    reward_percentile,
+                },
+                _,
+                _,
+            ) => ResolveGasFeesState::FeeHistory(
+                fee_history(web3, block_count, reward_percentile),
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            ),
+            (_, None, None) => ResolveGasFeesState::GasPrice(gas_price.resolve(web3)),
+            _ => ResolveGasFeesState::FeeHistory(
+                fee_history(
+                    web3,
+                    DEFAULT_FEE_HISTORY_BLOCK_COUNT,
+                    DEFAULT_FEE_HISTORY_REWARD_PERCENTILE,
+                ),
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            ),
+        };
+
+        ResolveGasFeesFuture { state }
+    }
+}
+
+impl<T: Transport> Future for ResolveGasFeesFuture<T> {
+    type Output = Result<GasFees, Web3Error>;
+
+    #[project]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        #[project]
+        match this.state.project() {
+            ResolveGasFeesState::Ready(fees) => Poll::Ready(Ok(*fees)),
+            ResolveGasFeesState::GasPrice(gas_price) => gas_price
+                .poll(cx)
+                .map(|gas_price| Ok(GasFees::Legacy(gas_price?))),
+            ResolveGasFeesState::FeeHistory(
+                fee_history,
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            ) => fee_history.poll(cx).map(|fee_history| {
+                fee_history?.estimate_fees(*max_fee_per_gas, *max_priority_fee_per_gas)
+            }),
+        }
+    }
+}
+
+/// A type alias for an optional `ResolveGasFeesFuture` when resolving the gas
+/// fees for a `TransactionRequest`.
+pub type ResolveTransactionRequestGasFeesFuture<T> = OptionFuture<ResolveGasFeesFuture<T>>;
+
+/// Queries the fee history of the latest blocks with the node.
+fn fee_history<T: Transport>(
+    web3: &Web3<T>,
+    block_count: u64,
+    reward_percentile: f64,
+) -> CompatCallFuture<T, FeeHistory> {
+    CallFuture::new(web3.transport().execute(
+        "eth_feeHistory",
+        vec![
+            helpers::serialize(&U256::from(block_count)),
+            helpers::serialize(&BlockNumber::Latest),
+            helpers::serialize(&[reward_percentile]),
+        ],
+    ))
+    .compat()
+}
+
+/// The result of an `eth_feeHistory` query. Only the fields that are used for
+/// estimating fees are included.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FeeHistory {
+    /// The base fees per gas of the queried blocks as well as the base fee of
+    /// the next block.
+    base_fee_per_gas: Vec<U256>,
+    /// The requested priority fee percentiles for each queried block.
+    #[serde(default)]
+    reward: Vec<Vec<U256>>,
+}
+
+impl FeeHistory {
+    /// Returns the base fee of the next block.
+    fn next_base_fee_per_gas(&self) -> Result<U256, Web3Error> {
+        self.base_fee_per_gas.last().copied().ok_or_else(|| {
+            Web3Error::InvalidResponse("fee history is missing base fees".to_owned())
+        })
+    }
+
+    /// Returns the median of the sampled priority fees.
+    fn median_priority_fee_per_gas(&self) -> U256 {
+        let mut rewards = self
+            .reward
+            .iter()
+            .filter_map(|reward| reward.first().copied())
+            .collect::<Vec<_>>();
+        rewards.sort();
+        rewards.get(rewards.len() / 2).copied().unwrap_or_default()
+    }
+
+    /// Returns the gas price a transaction is expected to pay in the next
+    /// block, that is the sum of its base fee and the estimated priority fee.
+    fn effective_gas_price(&self) -> Result<U256, Web3Error> {
+        Ok(self
+            .next_base_fee_per_gas()?
+            .saturating_add(self.median_priority_fee_per_gas()))
+    }
+
+    /// Estimates EIP-1559 fees from the fee history, using the explicitly
+    /// specified fees instead of the estimated ones when provided.
+    ///
+    /// Note that an estimated priority fee is capped to an explicitly
+    /// specified max fee, since nodes reject transactions with a priority fee
+    /// that exceeds their max fee.
+    fn estimate_fees(
+        self,
+        max_fee_per_gas: Option<U256>,
+        max_priority_fee_per_gas: Option<U256>,
+    ) -> Result<GasFees, Web3Error> {
+        let base_fee_per_gas = self.next_base_fee_per_gas()?;
+        let max_priority_fee_per_gas = max_priority_fee_per_gas.unwrap_or_else(|| {
+            let estimate = self.median_priority_fee_per_gas();
+            match max_fee_per_gas {
+                Some(max_fee_per_gas) => cmp::min(estimate, max_fee_per_gas),
+                None => estimate,
+            }
+        });
+        let max_fee_per_gas = max_fee_per_gas.unwrap_or_else(|| {
+            base_fee_per_gas
+                .saturating_mul(2.into())
+                .saturating_add(max_priority_fee_per_gas)
+        });
+
+        Ok(GasFees::Eip1559 {
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+        })
+    }
+}
+
 /// Apply a scaling factor to a gas price.
-fn scale_gas_price(gas_price: U256, factor: f64) -> U256 {
+pub(crate) fn scale_gas_price(gas_price: U256, factor: f64) -> U256 {
     // NOTE: U256 does not support floating point multiplication we have to
     //   convert everything to floats to multiply the factor and then convert
     //   back. We are OK with the loss of precision here.
@@ -270,4 +565,153 @@ mod tests {
         );
         transport.assert_no_more_requests();
     }
+
+    #[test]
+    fn resolve_gas_price_from_fee_history() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        transport.add_response(json!({
+            "oldestBlock": "0x1",
+            "baseFeePerGas": ["0x64", "0x6e", "0x78"],
+            "gasUsedRatio": [0.5, 0.75],
+            "reward": [["0x5"], ["0x3"]],
+        }));
+        assert_eq!(
+            GasPrice::FeeHistory {
+                block_count: 2,
+                reward_percentile: 25.0,
+            }
+            .resolve(&web3)
+            .immediate()
+            .expect("error resolving gas price"),
+            U256::from(120 + 5),
+        );
+        transport.assert_request(
+            "eth_feeHistory",
+            &[json!("0x2"), json!("latest"), json!([25.0])],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn resolve_gas_fees() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let fee_history = json!({
+            "oldestBlock": "0x1",
+            "baseFeePerGas": ["0x64", "0x6e", "0x78", "0x82"],
+            "gasUsedRatio": [0.5, 0.75, 1.0],
+            "reward": [["0x5"], ["0x1"], ["0x3"]],
+        });
+
+        transport.add_response(fee_history.clone());
+        assert_eq!(
+            GasPrice::fee_history()
+                .resolve_fees(&web3, None, None)
+                .immediate()
+                .expect("error resolving gas fees"),
+            GasFees::Eip1559 {
+                max_fee_per_gas: (130 * 2 + 3).into(),
+                max_priority_fee_per_gas: 3.into(),
+            },
+        );
+        transport.assert_request(
+            "eth_feeHistory",
+            &[json!("0xa"), json!("latest"), json!([50.0])],
+        );
+        transport.assert_no_more_requests();
+
+        transport.add_response(fee_history.clone());
+        assert_eq!(
+            GasPrice::Standard
+                .resolve_fees(&web3, None, Some(42.into()))
+                .immediate()
+                .expect("error resolving gas fees"),
+            GasFees::Eip1559 {
+                max_fee_per_gas: (130 * 2 + 42).into(),
+                max_priority_fee_per_gas: 42.into(),
+            },
+        );
+        transport.assert_request(
+            "eth_feeHistory",
+            &[json!("0xa"), json!("latest"), json!([50.0])],
+        );
+        transport.assert_no_more_requests();
+
+        assert_eq!(
+            GasPrice::Standard
+                .resolve_fees(&web3, Some(1337.into()), Some(42.into()))
+                .immediate()
+                .expect("error resolving gas fees"),
+            GasFees::Eip1559 {
+                max_fee_per_gas: 1337.into(),
+                max_priority_fee_per_gas: 42.into(),
+            },
+        );
+        transport.assert_no_more_requests();
+
+        transport.add_response(fee_history.clone());
+        assert_eq!(
+            GasPrice::Standard
+                .resolve_fees(&web3, Some(1337.into()), None)
+                .immediate()
+                .expect("error resolving gas fees"),
+            GasFees::Eip1559 {
+                max_fee_per_gas: 1337.into(),
+                max_priority_fee_per_gas: 3.into(),
+            },
+        );
+        transport.assert_request(
+            "eth_feeHistory",
+            &[json!("0xa"), json!("latest"), json!([50.0])],
+        );
+        transport.assert_no_more_requests();
+
+        // NOTE: The estimated priority fee of 3 exceeds the max fee.
+        transport.add_response(fee_history);
+        assert_eq!(
+            GasPrice::Standard
+                .resolve_fees(&web3, Some(2.into()), None)
+                .immediate()
+                .expect("error resolving gas fees"),
+            GasFees::Eip1559 {
+                max_fee_per_gas: 2.into(),
+                max_priority_fee_per_gas: 2.into(),
+            },
+        );
+        transport.assert_request(
+            "eth_feeHistory",
+            &[json!("0xa"), json!("latest"), json!([50.0])],
+        );
+        transport.assert_no_more_requests();
+
+        transport.add_response(json!("0x2a"));
+        assert_eq!(
+            GasPrice::Standard
+                .resolve_fees(&web3, None, None)
+                .immediate()
+                .expect("error resolving gas fees"),
+            GasFees::Legacy(42.into()),
+        );
+        transport.assert_request("eth_gasPrice", &[]);
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn resolve_gas_fees_for_transaction_request() {
+        let transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        assert_eq!(
+            GasPrice::Standard
+                .resolve_fees_for_transaction_request(&web3, None, None)
+                .immediate()
+                .transpose()
+                .expect("error resolving gas fees"),
+            None
+        );
+        transport.assert_no_more_requests();
+    }
 }
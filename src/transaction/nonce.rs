@@ -0,0 +1,422 @@
+//! Implementation of a nonce manager that hands out transaction nonces locally
+//! so that multiple transactions can be sent concurrently from one account.
+
+use crate::errors::ExecutionError;
+use crate::future::CompatCallFuture;
+use futures::compat::Future01CompatExt;
+use futures::ready;
+use pin_project::{pin_project, project};
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use web3::api::Web3;
+use web3::error::Error as Web3Error;
+use web3::types::{Address, BlockNumber, U256};
+use web3::Transport;
+
+/// A nonce manager that hands out sequential nonces for accounts locally
+/// instead of querying the node for the transaction count of the account for
+/// every transaction. This prevents concurrently sent transactions from the
+/// same account from being assigned the same nonce.
+///
+/// The nonce manager is synchronized with the pending transaction count of an
+/// account the first time a nonce is requested for it and whenever sending a
+/// transaction fails with a nonce error. Nonces for transactions that fail to
+/// build or send are released and get reused by subsequent transactions so
+/// that no nonce gaps are created.
+///
+/// Note that the nonce manager is shared between its clones.
+#[derive(Clone, Debug, Default)]
+pub struct NonceManager {
+    accounts: Arc<Mutex<HashMap<Address, AccountNonces>>>,
+}
+
+/// The nonce state for a single account.
+#[derive(Debug, Default)]
+struct AccountNonces {
+    /// The next nonce to hand out, `None` if the account needs to be
+    /// synchronized with the node.
+    next: Option<U256>,
+    /// Nonces below `next` that were released and should be reused.
+    released: BTreeSet<U256>,
+    /// The number of times the account was reset, used to ignore releases of
+    /// nonces that were reserved before the account was last reset.
+    epoch: u64,
+}
+
+impl NonceManager {
+    /// Creates a new nonce manager.
+    pub fn new() -> Self {
+        NonceManager::default()
+    }
+
+    /// Reserve the next nonce for the specified account. The pending
+    /// transaction count is queried from the node if the nonce manager is not
+    /// synchronized for the account.
+    pub fn reserve<T: Transport>(&self, web3: &Web3<T>, address: Address) -> ReserveNonceFuture<T> {
+        ReserveNonceFuture::new(self.clone(), web3, address)
+    }
+
+    /// Reset the nonce state for an account so that it gets synchronized with
+    /// the node the next time a nonce is reserved.
+    pub fn reset(&self, address: Address) {
+        let mut accounts = self.accounts.lock().unwrap();
+        let account = accounts.entry(address).or_default();
+        account.next = None;
+        account.released.clear();
+        account.epoch += 1;
+    }
+
+    /// Synchronize the nonce state for an account with its transaction count
+    /// if it is not already synchronized.
+    fn sync(&self, address: Address, transaction_count: U256) {
+        let mut accounts = self.accounts.lock().unwrap();
+        let account = accounts.entry(address).or_default();
+        if account.next.is_none() {
+            account.next = Some(transaction_count);
+        }
+    }
+
+    /// Try to reserve a nonce for an account, returning `None` if the account
+    /// needs to be synchronized with the node first.
+    fn try_reserve(&self, address: Address) -> Option<NonceReservation> {
+        let mut accounts = self.accounts.lock().unwrap();
+        let account = accounts.get_mut(&address)?;
+        let next = account.next?;
+
+        let nonce = match account.released.iter().next().copied() {
+            Some(nonce) => {
+                account.released.remove(&nonce);
+                nonce
+            }
+            None => {
+                account.next = Some(next + 1);
+                next
+            }
+        };
+
+        Some(NonceReservation {
+            manager: self.clone(),
+            address,
+            nonce,
+            epoch: account.epoch,
+            active: true,
+        })
+    }
+
+    /// Release a reserved nonce so that it can be reused.
+    fn release(&self, address: Address, nonce: U256, epoch: u64) {
+        let mut accounts = self.accounts.lock().unwrap();
+        let account = match accounts.get_mut(&address) {
+            Some(account) if account.epoch == epoch => account,
+            _ => return,
+        };
+        let mut next = match account.next {
+            Some(next) => next,
+            None => return,
+        };
+
+        account.released.insert(nonce);
+        while next > U256::zero() && account.released.remove(&(next - 1)) {
+            next -= U256::one();
+        }
+        account.next = Some(next);
+    }
+}
+
+/// A nonce reserved with a `NonceManager`. The nonce is released when the
+/// reservation is dropped unless it was committed.
+#[derive(Debug)]
+pub struct NonceReservation {
+    manager: NonceManager,
+    address: Address,
+    nonce: U256,
+    epoch: u64,
+    active: bool,
+}
+
+impl NonceReservation {
+    /// The reserved nonce.
+    pub fn nonce(&self) -> U256 {
+        self.nonce
+    }
+
+    /// The account the nonce was reserved for.
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// Mark the nonce as used by a transaction that was sent.
+    pub fn commit(mut self) {
+        self.active = false;
+    }
+
+    /// Release the nonce so it can be reused by another transaction.
+    pub fn release(self) {
+        // NOTE: The nonce is released when the reservation is dropped.
+    }
+
+    /// Reset the nonce state of the account, this should be used when the
+    /// nonce was rejected by the node.
+    pub fn resync(mut self) {
+        self.active = false;
+        self.manager.reset(self.address);
+    }
+
+    /// Handle the result of sending a transaction with the reserved nonce:
+    /// the nonce is committed on success, the account is resynchronized on
+    /// nonce errors and the nonce is released on any other error.
+    pub(crate) fn finish<R>(self, result: &Result<R, ExecutionError>) {
+        match result {
+            Ok(_) => self.commit(),
+            Err(err) if is_nonce_error(err) => self.resync(),
+            Err(_) => self.release(),
+        }
+    }
+}
+
+impl Drop for NonceReservation {
+    fn drop(&mut self) {
+        if self.active {
+            self.manager.release(self.address, self.nonce, self.epoch);
+        }
+    }
+}
+
+/// Error messages returned by nodes when rejecting a transaction because of
+/// its nonce. Geth and Erigon use "nonce too low", "nonce too high" and
+/// "already known", Parity and OpenEthereum use "nonce is too low" and
+/// "already imported", and Ganache uses "correct nonce".
+///
+/// A "replacement transaction underpriced" error also means that the nonce is
+/// already used by a pending transaction, usually one sent by another process
+/// with the same account, so it is treated as a nonce conflict as well.
+const NONCE_ERROR_MESSAGES: &[&str] = &[
+    "nonce too low",
+    "nonce too high",
+    "nonce is too low",
+    "already known",
+    "already imported",
+    "correct nonce",
+    "replacement transaction underpriced",
+];
+
+/// Returns true if the error indicates that the node rejected the nonce of a
+/// transaction.
+///
+/// Note that this relies on the node specific revert decoders used when
+/// converting a `web3::Error` into an `ExecutionError` never matching these
+/// errors, so that they are always kept as `ExecutionError::Web3` RPC errors.
+/// The decoders only match errors that report a failed execution, which
+/// nonce errors never are; the `nonce_errors` test checks this by going
+/// through the same conversion.
+fn is_nonce_error(err: &ExecutionError) -> bool {
+    let message = match err {
+        ExecutionError::Web3(Web3Error::Rpc(err)) => err.message.to_lowercase(),
+        _ => return false,
+    };
+
+    NONCE_ERROR_MESSAGES
+        .iter()
+        .any(|pattern| message.contains(pattern))
+}
+
+/// Future for reserving a nonce with a `NonceManager`.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[pin_project]
+pub struct ReserveNonceFuture<T: Transport> {
+    manager: NonceManager,
+    address: Address,
+    #[pin]
+    state: ReserveNonceState<T>,
+}
+
+/// The state of the reserve nonce future.
+#[pin_project]
+enum ReserveNonceState<T: Transport> {
+    /// The nonce was reserved without querying the node.
+    Reserved(Option<NonceReservation>),
+    /// The transaction count of the account is being queried.
+    Syncing(#[pin] CompatCallFuture<T, U256>),
+}
+
+impl<T: Transport> ReserveNonceFuture<T> {
+    fn new(manager: NonceManager, web3: &Web3<T>, address: Address) -> Self {
+        let state = match manager.try_reserve(address) {
+            Some(reservation) => ReserveNonceState::Reserved(Some(reservation)),
+            None => ReserveNonceState::Syncing(
+                web3.eth()
+                    .transaction_count(address, Some(BlockNumber::Pending))
+                    .compat(),
+            ),
+        };
+
+        ReserveNonceFuture {
+            manager,
+            address,
+            state,
+        }
+    }
+}
+
+impl<T: Transport> Future for ReserveNonceFuture<T> {
+    type Output = Result<NonceReservation, ExecutionError>;
+
+    #[project]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+
+        #[project]
+        match this.state.project() {
+            ReserveNonceState::Reserved(reservation) => Poll::Ready(Ok(reservation
+                .take()
+                .expect("future polled more than once"))),
+            ReserveNonceState::Syncing(count) => {
+                let count = ready!(count.poll(cx))?;
+                this.manager.sync(*this.address, count);
+
+                // NOTE: The account can only be unsynchronized here if it was
+                //   reset concurrently, in which case we just use the queried
+                //   transaction count.
+                let reservation = match this.manager.try_reserve(*this.address) {
+                    Some(reservation) => reservation,
+                    None => {
+                        this.manager.sync(*this.address, count);
+                        this.manager
+                            .try_reserve(*this.address)
+                            .expect("account was just synchronized")
+                    }
+                };
+
+                Poll::Ready(Ok(reservation))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use jsonrpc_core::{Error as RpcError, ErrorCode};
+
+    #[test]
+    fn reserve_sequential_nonces() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let manager = NonceManager::new();
+
+        let address = addr!("0x9876543210987654321098765432109876543210");
+
+        transport.add_response(json!("0x2a"));
+        let first = manager.reserve(&web3, address).immediate().unwrap();
+        let second = manager.reserve(&web3, address).immediate().unwrap();
+
+        transport.assert_request(
+            "eth_getTransactionCount",
+            &[json!(address), json!("pending")],
+        );
+        transport.assert_no_more_requests();
+
+        assert_eq!(first.nonce(), 42.into());
+        assert_eq!(second.nonce(), 43.into());
+    }
+
+    #[test]
+    fn released_nonces_are_reused() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let manager = NonceManager::new();
+
+        let address = addr!("0x9876543210987654321098765432109876543210");
+
+        transport.add_response(json!("0x0"));
+        let first = manager.reserve(&web3, address).immediate().unwrap();
+        let second = manager.reserve(&web3, address).immediate().unwrap();
+        let third = manager.reserve(&web3, address).immediate().unwrap();
+
+        // NOTE: Releasing a nonce in the middle leaves a gap that is filled by
+        //   the next reservation.
+        second.release();
+        let reused = manager.reserve(&web3, address).immediate().unwrap();
+        assert_eq!(reused.nonce(), 1.into());
+
+        // NOTE: Releasing the last nonces rewinds the next nonce.
+        reused.release();
+        drop(third);
+        first.commit();
+        let next = manager.reserve(&web3, address).immediate().unwrap();
+        assert_eq!(next.nonce(), 1.into());
+
+        transport.assert_request(
+            "eth_getTransactionCount",
+            &[json!(address), json!("pending")],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    fn rpc_error(message: &str) -> ExecutionError {
+        ExecutionError::from(Web3Error::Rpc(RpcError {
+            code: ErrorCode::ServerError(-32000),
+            message: message.to_owned(),
+            data: None,
+        }))
+    }
+
+    #[test]
+    fn nonce_errors() {
+        for message in &[
+            "nonce too low",
+            "nonce too high",
+            "Transaction nonce is too low. Try incrementing the nonce.",
+            "already known",
+            "Transaction with the same hash was already imported.",
+            "the tx doesn't have the correct nonce. account has nonce of: 5 tx has nonce of: 4",
+            "replacement transaction underpriced",
+        ] {
+            assert!(is_nonce_error(&rpc_error(message)), "{}", message);
+        }
+
+        for message in &[
+            "insufficient funds for gas * price + value",
+            "max fee per gas less than block base fee",
+            "invalid sender: nonce manager not configured",
+        ] {
+            assert!(!is_nonce_error(&rpc_error(message)), "{}", message);
+        }
+        assert!(!is_nonce_error(&ExecutionError::NoLocalAccounts));
+    }
+
+    #[test]
+    fn resync_on_nonce_error() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let manager = NonceManager::new();
+
+        let address = addr!("0x9876543210987654321098765432109876543210");
+
+        transport.add_response(json!("0x1"));
+        let first = manager.reserve(&web3, address).immediate().unwrap();
+        let second = manager.reserve(&web3, address).immediate().unwrap();
+
+        first.finish::<()>(&Err(rpc_error("nonce too low")));
+        // NOTE: Nonces reserved before the reset are not released.
+        drop(second);
+
+        transport.add_response(json!("0x5"));
+        let next = manager.reserve(&web3, address).immediate().unwrap();
+        assert_eq!(next.nonce(), 5.into());
+
+        transport.assert_request(
+            "eth_getTransactionCount",
+            &[json!(address), json!("pending")],
+        );
+        transport.assert_request(
+            "eth_getTransactionCount",
+            &[json!(address), json!("pending")],
+        );
+        transport.assert_no_more_requests();
+    }
+}
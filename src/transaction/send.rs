@@ -5,7 +5,12 @@ use crate::errors::ExecutionError;
 use crate::future::CompatCallFuture;
 use crate::transaction::build::BuildFuture;
 use crate::transaction::confirm::ConfirmFuture;
-use crate::transaction::{ResolveCondition, Transaction, TransactionBuilder, TransactionResult};
+use crate::transaction::escalate::EscalateFuture;
+use crate::transaction::nonce::{NonceReservation, ReserveNonceFuture};
+use crate::transaction::simulate::SimulateFuture;
+use crate::transaction::{
+    Account, ResolveCondition, Transaction, TransactionBuilder, TransactionResult,
+};
 use futures::compat::Future01CompatExt;
 use futures::ready;
 use pin_project::{pin_project, project};
@@ -13,7 +18,8 @@ use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use web3::api::Web3;
-use web3::types::{H256, U64};
+use web3::helpers::{self, CallFuture};
+use web3::types::{BlockNumber, TransactionReceipt, H256, U64};
 use web3::Transport;
 
 /// Future for optionally signing and then sending a transaction.
@@ -24,6 +30,12 @@ pub struct SendFuture<T: Transport> {
     /// The confirmation options to use for the transaction once it has been
     /// sent. Stored as an option as we require transfer of ownership.
     resolve: Option<ResolveCondition>,
+    /// The transaction builder, stored while the transaction is being
+    /// simulated or the nonce is being reserved.
+    builder: Option<TransactionBuilder<T>>,
+    /// The nonce reserved for the transaction with the builder's nonce
+    /// manager. It is released if the transaction fails to build or send.
+    nonce: Option<NonceReservation>,
     /// Internal execution state.
     #[pin]
     state: SendState<T>,
@@ -31,7 +43,12 @@ pub struct SendFuture<T: Transport> {
 
 /// The state of the send future.
 #[pin_project]
+#[allow(clippy::large_enum_variant)]
 enum SendState<T: Transport> {
+    /// The transaction is being simulated before sending it.
+    Simulating(#[pin] SimulateFuture<T>),
+    /// The nonce for the transaction is being reserved with a nonce manager.
+    ReservingNonce(#[pin] ReserveNonceFuture<T>),
     /// The transaction is being built into a request or a signed raw
     /// transaction.
     Building(#[pin] BuildFuture<T>),
@@ -39,6 +56,45 @@ enum SendState<T: Transport> {
     Sending(#[pin] CompatCallFuture<T, H256>),
     /// The transaction is being confirmed.
     Confirming(#[pin] ConfirmFuture<T>),
+    /// The transaction is being sent and replaced with transactions with an
+    /// escalating gas price until one of them is mined.
+    Escalating(Pin<Box<EscalateFuture<T>>>),
+}
+
+impl<T: Transport> SendState<T> {
+    /// Creates the state for preparing the transaction to be sent, reserving
+    /// a nonce with the builder's nonce manager if required. Returns the
+    /// builder if it is still needed once the nonce is reserved.
+    fn preparing(
+        mut builder: TransactionBuilder<T>,
+        resolve: &Option<ResolveCondition>,
+    ) -> (Option<TransactionBuilder<T>>, Self) {
+        let nonce_manager = builder.nonce_manager.take().or_else(|| {
+            builder
+                .from
+                .as_ref()
+                .and_then(Account::nonce_manager)
+                .cloned()
+        });
+        match (&builder.from, builder.nonce, nonce_manager) {
+            (Some(from), None, Some(nonce_manager)) => {
+                let reserve = nonce_manager.reserve(&builder.web3, from.address());
+                (Some(builder), SendState::ReservingNonce(reserve))
+            }
+            _ => (None, SendState::sending(builder, resolve)),
+        }
+    }
+
+    /// Creates the state for building and sending the transaction, or for
+    /// escalating it when an escalated resolve condition is used.
+    fn sending(builder: TransactionBuilder<T>, resolve: &Option<ResolveCondition>) -> Self {
+        match resolve {
+            Some(ResolveCondition::Escalated(params)) => SendState::Escalating(Box::pin(
+                EscalateFuture::from_builder(builder, params.clone()),
+            )),
+            _ => SendState::Building(BuildFuture::from_builder(builder)),
+        }
+    }
 }
 
 impl<T: Transport> SendFuture<T> {
@@ -46,11 +102,22 @@ impl<T: Transport> SendFuture<T> {
     pub fn from_builder(mut builder: TransactionBuilder<T>) -> Self {
         let web3 = builder.web3.clone();
         let resolve = Some(builder.resolve.take().unwrap_or_default());
-        let state = SendState::Building(BuildFuture::from_builder(builder));
+
+        let (builder, state) = if builder.simulate_before_send.unwrap_or(false) {
+            // NOTE: Simulate the transaction before reserving a nonce so that
+            //   reverting transactions don't hold up other transactions.
+            builder.simulate_before_send = Some(false);
+            let simulation = SimulateFuture::from_builder(builder.clone(), BlockNumber::Pending);
+            (Some(builder), SendState::Simulating(simulation))
+        } else {
+            SendState::preparing(builder, &resolve)
+        };
 
         SendFuture {
             web3,
             resolve,
+            builder,
+            nonce: None,
             state,
         }
     }
@@ -66,29 +133,70 @@ impl<T: Transport> Future for SendFuture<T> {
             let SendFuture {
                 web3,
                 resolve,
+                builder,
+                nonce,
                 state,
             } = self.as_mut().project();
 
             #[project]
             let next_state = match state.project() {
+                SendState::Simulating(simulation) => {
+                    if let Err(err) = ready!(simulation.poll(cx)) {
+                        return Poll::Ready(Err(err));
+                    }
+
+                    let (prepared, next_state) = SendState::preparing(
+                        builder.take().expect("simulated more than once"),
+                        resolve,
+                    );
+                    *builder = prepared;
+
+                    next_state
+                }
+                SendState::ReservingNonce(reserve) => {
+                    let reservation = match ready!(reserve.poll(cx)) {
+                        Ok(reservation) => reservation,
+                        Err(err) => return Poll::Ready(Err(err)),
+                    };
+
+                    let builder = builder
+                        .take()
+                        .expect("nonce reserved more than once")
+                        .nonce(reservation.nonce());
+                    *nonce = Some(reservation);
+
+                    SendState::sending(builder, resolve)
+                }
                 SendState::Building(build) => {
                     let tx = match ready!(build.poll(cx)) {
                         Ok(tx) => tx,
-                        Err(err) => return Poll::Ready(Err(err)),
+                        Err(err) => {
+                            if let Some(reservation) = nonce.take() {
+                                reservation.release();
+                            }
+                            return Poll::Ready(Err(err));
+                        }
                     };
 
-                    let eth = web3.eth();
                     let send = match tx {
-                        Transaction::Request(tx) => eth.send_transaction(tx).compat(),
-                        Transaction::Raw(tx) => eth.send_raw_transaction(tx).compat(),
+                        Transaction::Request(tx) => CallFuture::new(
+                            web3.transport()
+                                .execute("eth_sendTransaction", vec![helpers::serialize(&tx)]),
+                        )
+                        .compat(),
+                        Transaction::Raw(tx) => web3.eth().send_raw_transaction(tx).compat(),
                     };
 
                     SendState::Sending(send)
                 }
                 SendState::Sending(send) => {
-                    let tx_hash = match ready!(send.poll(cx)) {
+                    let result = ready!(send.poll(cx)).map_err(ExecutionError::from);
+                    if let Some(reservation) = nonce.take() {
+                        reservation.finish(&result);
+                    }
+                    let tx_hash = match result {
                         Ok(tx_hash) => tx_hash,
-                        Err(err) => return Poll::Ready(Err(err.into())),
+                        Err(err) => return Poll::Ready(Err(err)),
                     };
 
                     let confirm = match resolve.take().expect("confirmation called more than once")
@@ -99,18 +207,29 @@ impl<T: Transport> Future for SendFuture<T> {
                         ResolveCondition::Confirmed(params) => {
                             ConfirmFuture::new(&web3, tx_hash, params)
                         }
+                        ResolveCondition::Escalated(_) => {
+                            unreachable!("escalated transactions are sent by the escalate future")
+                        }
                     };
 
                     SendState::Confirming(confirm)
                 }
                 SendState::Confirming(confirm) => {
-                    return confirm.poll(cx).map(|result| {
-                        let tx = result?;
-                        match tx.status {
-                            Some(U64([1])) => Ok(TransactionResult::Receipt(tx)),
-                            _ => Err(ExecutionError::Failure(Box::new(tx))),
+                    return confirm.poll(cx).map(|result| receipt_result(result?))
+                }
+                SendState::Escalating(escalate) => {
+                    let result = ready!(escalate.as_mut().poll(cx));
+                    if let Some(reservation) = nonce.take() {
+                        // NOTE: Once a transaction was sent its nonce is used
+                        //   even if none of the transactions got mined.
+                        if escalate.is_sent() {
+                            reservation.commit();
+                        } else {
+                            reservation.finish(&result);
                         }
-                    })
+                    }
+
+                    return Poll::Ready(receipt_result(result?));
                 }
             };
 
@@ -118,3 +237,142 @@ impl<T: Transport> Future for SendFuture<T> {
         }
     }
 }
+
+/// Converts the receipt of a mined transaction into a result, failing if the
+/// transaction execution failed.
+fn receipt_result(tx: TransactionReceipt) -> Result<TransactionResult, ExecutionError> {
+    match tx.status {
+        Some(U64([1])) => Ok(TransactionResult::Receipt(tx)),
+        _ => Err(ExecutionError::Failure(Box::new(tx))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use crate::transaction::{Account, NonceManager};
+    use web3::types::Address;
+
+    fn send_local(
+        web3: &Web3<TestTransport>,
+        from: Address,
+        nonce_manager: &NonceManager,
+    ) -> SendFuture<TestTransport> {
+        TransactionBuilder::new(web3.clone())
+            .from(Account::Local(from, None))
+            .gas(1.into())
+            .gas_price(2.into())
+            .nonce_manager(nonce_manager.clone())
+            .resolve(ResolveCondition::Pending)
+            .send()
+    }
+
+    #[test]
+    fn send_with_nonce_manager() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let nonce_manager = NonceManager::new();
+
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let hash = hash!("0x4242424242424242424242424242424242424242424242424242424242424242");
+
+        transport.add_response(json!("0x2a"));
+        transport.add_response(json!(hash));
+        transport.add_response(json!(hash));
+        send_local(&web3, from, &nonce_manager)
+            .immediate()
+            .expect("transaction success");
+        send_local(&web3, from, &nonce_manager)
+            .immediate()
+            .expect("transaction success");
+
+        transport.assert_request("eth_getTransactionCount", &[json!(from), json!("pending")]);
+        for nonce in &["0x2a", "0x2b"] {
+            transport.assert_request(
+                "eth_sendTransaction",
+                &[json!({
+                    "from": from,
+                    "gas": "0x1",
+                    "gasPrice": "0x2",
+                    "nonce": nonce,
+                })],
+            );
+        }
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn send_with_nonce_manager_attached_to_account() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let nonce_manager = NonceManager::new();
+
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let hash = hash!("0x4242424242424242424242424242424242424242424242424242424242424242");
+        let account = Account::Local(from, None).with_nonce_manager(nonce_manager);
+        assert_eq!(account.address(), from);
+
+        transport.add_response(json!("0x2a"));
+        transport.add_response(json!(hash));
+        transport.add_response(json!(hash));
+        for _ in 0..2 {
+            TransactionBuilder::new(web3.clone())
+                .from(account.clone())
+                .gas(1.into())
+                .gas_price(2.into())
+                .resolve(ResolveCondition::Pending)
+                .send()
+                .immediate()
+                .expect("transaction success");
+        }
+
+        transport.assert_request("eth_getTransactionCount", &[json!(from), json!("pending")]);
+        for nonce in &["0x2a", "0x2b"] {
+            transport.assert_request(
+                "eth_sendTransaction",
+                &[json!({
+                    "from": from,
+                    "gas": "0x1",
+                    "gasPrice": "0x2",
+                    "nonce": nonce,
+                })],
+            );
+        }
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn send_with_nonce_manager_releases_failed_nonce() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+        let nonce_manager = NonceManager::new();
+
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let hash = hash!("0x4242424242424242424242424242424242424242424242424242424242424242");
+        let send = |nonce| {
+            json!({
+                "from": from,
+                "gas": "0x1",
+                "gasPrice": "0x2",
+                "nonce": nonce,
+            })
+        };
+
+        // NOTE: The test transport fails requests without responses, so the
+        //   nonce is released and reused by the next transaction.
+        transport.add_response(json!("0x2a"));
+        send_local(&web3, from, &nonce_manager)
+            .immediate()
+            .expect_err("unexpected transaction success");
+        transport.add_response(json!(hash));
+        send_local(&web3, from, &nonce_manager)
+            .immediate()
+            .expect("transaction success");
+
+        transport.assert_request("eth_getTransactionCount", &[json!(from), json!("pending")]);
+        transport.assert_request("eth_sendTransaction", &[send("0x2a")]);
+        transport.assert_request("eth_sendTransaction", &[send("0x2a")]);
+        transport.assert_no_more_requests();
+    }
+}
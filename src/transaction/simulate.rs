@@ -0,0 +1,169 @@
+//! Implementation of a future for simulating a transaction with an `eth_call`
+//! in order to detect reverts without sending it.
+
+use crate::errors::{revert, ExecutionError};
+use crate::future::CompatCallFuture;
+use crate::transaction::TransactionBuilder;
+use futures::compat::Future01CompatExt;
+use pin_project::pin_project;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use web3::helpers::{self, CallFuture};
+use web3::types::{BlockNumber, Bytes};
+use web3::Transport;
+
+/// Future for simulating a transaction with an `eth_call`. It resolves to the
+/// data returned by the call or the error that the transaction would fail
+/// with, for example a decoded revert reason.
+///
+/// Note that the simulation does not sign the transaction, so only the address
+/// of the sender is used.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[pin_project]
+pub struct SimulateFuture<T: Transport> {
+    /// Whether the simulated transaction is a contract deployment.
+    deployment: bool,
+    #[pin]
+    call: CompatCallFuture<T, Bytes>,
+}
+
+impl<T: Transport> SimulateFuture<T> {
+    /// Create a instance from a `TransactionBuilder` that simulates the
+    /// transaction at the specified block.
+    pub fn from_builder(builder: TransactionBuilder<T>, block: BlockNumber) -> Self {
+        let request = builder.call_request();
+        SimulateFuture {
+            deployment: request.to.is_none(),
+            call: CallFuture::new(builder.web3.transport().execute(
+                "eth_call",
+                vec![helpers::serialize(&request), helpers::serialize(&block)],
+            ))
+            .compat(),
+        }
+    }
+}
+
+impl<T: Transport> Future for SimulateFuture<T> {
+    type Output = Result<Bytes, ExecutionError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+        let deployment = *this.deployment;
+        this.call.poll(cx).map(|result| {
+            let bytes = result?;
+
+            // NOTE: Older Geth nodes return the revert data as a successful
+            //   result, so check for encoded revert data.
+            match revert::decode_call_result(&bytes.0, deployment) {
+                Some(err) => Err(err),
+                None => Ok(bytes),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::prelude::*;
+    use crate::transaction::Account;
+    use jsonrpc_core::{Error as RpcError, ErrorCode};
+    use web3::error::Error as Web3Error;
+
+    #[test]
+    fn simulate_transaction() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let from = addr!("0x9876543210987654321098765432109876543210");
+        let to = addr!("0x0123456789012345678901234567890123456789");
+
+        transport.add_response(json!("0x42"));
+        let result = TransactionBuilder::new(web3)
+            .from(Account::Local(from, None))
+            .to(to)
+            .value(28.into())
+            .data(Bytes(vec![0x13, 0x37]))
+            .simulate()
+            .immediate()
+            .expect("simulation failed");
+
+        assert_eq!(result, Bytes(vec![0x42]));
+        transport.assert_request(
+            "eth_call",
+            &[
+                json!({
+                    "from": from,
+                    "to": to,
+                    "value": "0x1c",
+                    "data": "0x1337",
+                }),
+                json!("pending"),
+            ],
+        );
+        transport.assert_no_more_requests();
+    }
+
+    #[test]
+    fn simulate_reverting_transaction() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        transport.add_response(json!(revert::encode_reason_hex("message")));
+        let err = TransactionBuilder::new(web3.clone())
+            .simulate()
+            .immediate()
+            .expect_err("unexpected simulation success");
+        assert!(
+            matches!(&err, ExecutionError::Revert(Some(reason), _) if reason == "message"),
+            "unexpected error {:?}",
+            err
+        );
+
+        transport.add_error(Web3Error::Rpc(RpcError {
+            code: ErrorCode::ServerError(3),
+            message: "execution reverted".to_owned(),
+            data: Some(json!(Bytes(revert::encode_panic(0x01.into())))),
+        }));
+        let err = TransactionBuilder::new(web3)
+            .simulate()
+            .immediate()
+            .expect_err("unexpected simulation success");
+        assert!(
+            matches!(err, ExecutionError::Panic(revert::PanicCode::Assert)),
+            "unexpected error {:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn simulate_transaction_with_custom_error() {
+        let mut transport = TestTransport::new();
+        let web3 = Web3::new(transport.clone());
+
+        let to = addr!("0x0123456789012345678901234567890123456789");
+
+        transport.add_response(json!("0xcf479181"));
+        let err = TransactionBuilder::new(web3.clone())
+            .to(to)
+            .simulate()
+            .immediate()
+            .expect_err("unexpected simulation success");
+        assert!(
+            matches!(&err, ExecutionError::CustomError(data) if data == &[0xcf, 0x47, 0x91, 0x81]),
+            "unexpected error {:?}",
+            err
+        );
+
+        // NOTE: Deployments return the deployed code, which can be of any
+        //   length, so it is not mistaken for a custom error.
+        transport.add_response(json!("0xcf479181"));
+        let code = TransactionBuilder::new(web3)
+            .data(Bytes(vec![0x60, 0x80]))
+            .simulate()
+            .immediate()
+            .expect("simulation failed");
+        assert_eq!(code, Bytes(vec![0xcf, 0x47, 0x91, 0x81]));
+    }
+}
@@ -5,15 +5,27 @@
 //! to help create a more ergonimic experience by making the generated struct not
 //! be generic on the underlying transport (at the small cost of some dynamic
 //! dispatch and extra allocations).
+//!
+//! This module also includes `BatchingTransport` which gathers requests into
+//! JSON-RPC batch requests in order to reduce the number of requests made to
+//! the node.
 
+use futures::compat::{Compat, Future01CompatExt};
+use futures::future::{BoxFuture, FutureExt, Shared};
+use futures_timer::Delay;
 use jsonrpc_core::Call;
 use serde_json::Value;
 use std::any::Any;
-use std::fmt::Debug;
-use std::sync::Arc;
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future as StdFuture;
+use std::mem;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
 use web3::error::Error as Web3Error;
 use web3::futures::Future;
-use web3::{RequestId, Transport};
+use web3::{BatchTransport, RequestId, Transport};
 
 /// Type alias for the output future in for the `DynTransport`'s `Transport`
 /// implementation.
@@ -53,6 +65,9 @@ where
 
 /// Dynamic `Transport` implementation to allow for a generic-free contract API.
 /// This type wraps any `Transport` type and implements `Transport` itself.
+///
+/// Note that generated contracts can opt into JSON-RPC batching by wrapping
+/// their transport in a `BatchingTransport` before creating the `DynTransport`.
 #[derive(Debug)]
 pub struct DynTransport {
     inner: Arc<dyn TransportBoxed + Sync + Send + 'static>,
@@ -105,6 +120,330 @@ impl Transport for DynTransport {
     }
 }
 
+/// A `Transport` implementation that gathers requests into JSON-RPC batch
+/// requests and demultiplexes the responses.
+///
+/// Requests are gathered into a batch until either the batching window elapses
+/// or the batch is explicitly sent with `flush` or by dropping a `BatchScope`.
+/// This allows multiple concurrent requests, for example from contract
+/// `CallFuture`s that are joined together, to be sent to the node with a
+/// single request.
+///
+/// Note that the batching window is always required, so that requests that
+/// are awaited one at a time are still sent once their window elapses instead
+/// of waiting for a flush that never comes.
+pub struct BatchingTransport<T> {
+    inner: Arc<BatchingInner<T>>,
+}
+
+/// The shared state of a `BatchingTransport`.
+struct BatchingInner<T> {
+    /// The underlying transport used for sending batch requests.
+    transport: T,
+    /// The duration to gather requests for before automatically sending them.
+    window: Duration,
+    /// The batch that new requests are currently added to.
+    current: Mutex<Option<Arc<Batch>>>,
+}
+
+impl<T> BatchingTransport<T>
+where
+    T: BatchTransport + Send + Sync + 'static,
+    T::Batch: Send + 'static,
+{
+    /// Wrap a `BatchTransport` in a `BatchingTransport` that automatically
+    /// sends batches once the specified window has elapsed since the first
+    /// request of the batch was made.
+    pub fn new(transport: T, window: Duration) -> Self {
+        BatchingTransport {
+            inner: Arc::new(BatchingInner {
+                transport,
+                window,
+                current: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Retrieve a reference to the underlying transport.
+    pub fn transport(&self) -> &T {
+        &self.inner.transport
+    }
+
+    /// Send all requests that are gathered in the current batch. Requests
+    /// that are made afterwards are gathered in a new batch.
+    pub fn flush(&self) {
+        let batch = self.inner.current.lock().unwrap().take();
+        if let Some(batch) = batch {
+            self.inner.send_batch(&batch);
+        }
+    }
+
+    /// Create a scope that sends the current batch when it is dropped. This
+    /// allows requests that are made within the scope to be sent as soon as
+    /// they were all made instead of waiting for the batching window to
+    /// elapse.
+    pub fn scope(&self) -> BatchScope<T> {
+        BatchScope {
+            transport: self.clone(),
+        }
+    }
+}
+
+/// A guard that sends the current batch of a `BatchingTransport` when it is
+/// dropped.
+#[must_use = "the batch is sent when the scope is dropped"]
+pub struct BatchScope<T>
+where
+    T: BatchTransport + Send + Sync + 'static,
+    T::Batch: Send + 'static,
+{
+    transport: BatchingTransport<T>,
+}
+
+impl<T> Drop for BatchScope<T>
+where
+    T: BatchTransport + Send + Sync + 'static,
+    T::Batch: Send + 'static,
+{
+    fn drop(&mut self) {
+        self.transport.flush();
+    }
+}
+
+impl<T> Debug for BatchScope<T>
+where
+    T: BatchTransport + Debug + Send + Sync + 'static,
+    T::Batch: Send + 'static,
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("BatchScope")
+            .field("transport", &self.transport)
+            .finish()
+    }
+}
+
+impl<T> BatchingInner<T>
+where
+    T: BatchTransport,
+    T::Batch: Send + 'static,
+{
+    /// Send the specified batch, removing it from the current batch so that
+    /// no more requests get added to it.
+    fn flush_batch(&self, batch: &Arc<Batch>) {
+        {
+            let mut current = self.current.lock().unwrap();
+            if let Some(true) = current.as_ref().map(|current| Arc::ptr_eq(current, batch)) {
+                *current = None;
+            }
+        }
+        self.send_batch(batch);
+    }
+
+    /// Send the gathered requests of a batch if they have not already been
+    /// sent.
+    fn send_batch(&self, batch: &Batch) {
+        let mut state = batch.state.lock().unwrap();
+        let (requests, wakers) = match &mut *state {
+            BatchState::Pending { requests, wakers } => (mem::take(requests), mem::take(wakers)),
+            BatchState::Sent(_) => return,
+        };
+
+        let future = self.transport.send_batch(requests).compat().map(|result| {
+            Arc::new(result.map(|responses| Mutex::new(responses.into_iter().map(Some).collect())))
+        });
+        *state = BatchState::Sent(future.boxed().shared());
+        drop(state);
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Clone for BatchingTransport<T> {
+    fn clone(&self) -> Self {
+        BatchingTransport {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: Debug> Debug for BatchingTransport<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("BatchingTransport")
+            .field("transport", &self.inner.transport)
+            .field("window", &self.inner.window)
+            .finish()
+    }
+}
+
+impl<T> Transport for BatchingTransport<T>
+where
+    T: BatchTransport + Send + Sync + 'static,
+    T::Batch: Send + 'static,
+{
+    type Out = Compat<BatchingCallFuture<T>>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+        self.inner.transport.prepare(method, params)
+    }
+
+    fn send(&self, id: RequestId, request: Call) -> Self::Out {
+        let (batch, index) = {
+            let mut current = self.inner.current.lock().unwrap();
+            let window = self.inner.window;
+            let batch = current
+                .get_or_insert_with(|| Arc::new(Batch::new(window)))
+                .clone();
+            let index = batch.push(id, request);
+            (batch, index)
+        };
+
+        let delay = Delay::new(batch.deadline.saturating_duration_since(Instant::now()));
+        Compat::new(BatchingCallFuture {
+            transport: self.inner.clone(),
+            batch,
+            index,
+            delay,
+            future: None,
+        })
+    }
+}
+
+/// The result of sending a batch request, shared between all the requests
+/// in the batch. Responses are taken out of the vector by their requests.
+type BatchResult = Arc<Result<Mutex<Vec<Option<Result<Value, Web3Error>>>>, Web3Error>>;
+
+/// A batch of requests that are sent together.
+struct Batch {
+    /// The instant at which the batch should automatically be sent.
+    deadline: Instant,
+    /// The state of the batch.
+    state: Mutex<BatchState>,
+}
+
+/// The state of a batch of requests.
+enum BatchState {
+    /// The batch is gathering requests.
+    Pending {
+        /// The requests in the batch.
+        requests: Vec<(RequestId, Call)>,
+        /// The wakers of the tasks waiting for the batch to be sent.
+        wakers: Vec<Waker>,
+    },
+    /// The batch was sent and is waiting for the responses.
+    Sent(Shared<BoxFuture<'static, BatchResult>>),
+}
+
+impl Batch {
+    /// Create a new empty batch.
+    fn new(window: Duration) -> Self {
+        Batch {
+            deadline: Instant::now() + window,
+            state: Mutex::new(BatchState::Pending {
+                requests: Vec::new(),
+                wakers: Vec::new(),
+            }),
+        }
+    }
+
+    /// Add a request to the batch, returning its index within the batch.
+    fn push(&self, id: RequestId, request: Call) -> usize {
+        match &mut *self.state.lock().unwrap() {
+            BatchState::Pending { requests, .. } => {
+                requests.push((id, request));
+                requests.len() - 1
+            }
+            BatchState::Sent(_) => unreachable!("request added to a batch that was already sent"),
+        }
+    }
+}
+
+/// Future for a request made with a `BatchingTransport` that resolves once
+/// the batch it was added to is sent and the response is received.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct BatchingCallFuture<T> {
+    /// The transport, used for sending the batch once the window elapses.
+    transport: Arc<BatchingInner<T>>,
+    /// The batch the request was added to.
+    batch: Arc<Batch>,
+    /// The index of the request in the batch.
+    index: usize,
+    /// The delay until the batch should be sent.
+    delay: Delay,
+    /// The future resolving to the batch response once it was sent.
+    future: Option<Shared<BoxFuture<'static, BatchResult>>>,
+}
+
+impl<T> StdFuture for BatchingCallFuture<T>
+where
+    T: BatchTransport,
+    T::Batch: Send + 'static,
+{
+    type Output = Result<Value, Web3Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        if this.future.is_none() {
+            let elapsed = Pin::new(&mut this.delay).poll(cx).is_ready();
+
+            let mut state = this.batch.state.lock().unwrap();
+            match &mut *state {
+                BatchState::Pending { wakers, .. } if !elapsed => {
+                    if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                        wakers.push(cx.waker().clone());
+                    }
+                    return Poll::Pending;
+                }
+                BatchState::Pending { .. } => {
+                    drop(state);
+                    this.transport.flush_batch(&this.batch);
+                    state = this.batch.state.lock().unwrap();
+                }
+                BatchState::Sent(_) => {}
+            }
+
+            match &*state {
+                BatchState::Sent(future) => this.future = Some(future.clone()),
+                BatchState::Pending { .. } => unreachable!("batch was not sent after flushing"),
+            }
+        }
+
+        let future = this.future.as_mut().expect("batch future is set");
+        let result = match Pin::new(future).poll(cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => return Poll::Pending,
+        };
+
+        Poll::Ready(match &*result {
+            Ok(responses) => responses
+                .lock()
+                .unwrap()
+                .get_mut(this.index)
+                .and_then(Option::take)
+                .unwrap_or_else(|| {
+                    Err(Web3Error::InvalidResponse(
+                        "missing response in batch".to_owned(),
+                    ))
+                }),
+            Err(err) => Err(Web3Error::Transport(format!(
+                "batch request failed: {}",
+                err
+            ))),
+        })
+    }
+}
+
+impl<T> Debug for BatchingCallFuture<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("BatchingCallFuture")
+            .field("index", &self.index)
+            .field("delay", &self.delay)
+            .finish()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -161,4 +500,124 @@ mod tests {
             let _ = dyn_transport.prepare("test", vec![json!(28)]);
         });
     }
+
+    /// A batching window that does not elapse during tests, so that batches
+    /// are only sent when they are explicitly flushed.
+    const LONG_WINDOW: Duration = Duration::from_secs(3600);
+
+    #[test]
+    fn batching_transport() {
+        let mut transport = TestTransport::new();
+        let batching = BatchingTransport::new(transport.clone(), LONG_WINDOW);
+
+        let first = batching.execute("test", vec![json!(1)]).compat();
+        let second = batching.execute("test", vec![json!(2)]).compat();
+        let mut joined = futures::future::join(first, second);
+        assert!((&mut joined).now_or_never().is_none());
+
+        transport.add_response(json!(true));
+        transport.add_response(json!(false));
+        batching.flush();
+
+        let (first, second) = joined.immediate();
+        assert_eq!(first.expect("success"), json!(true));
+        assert_eq!(second.expect("success"), json!(false));
+
+        transport.assert_request("test", &[json!(1)]);
+        transport.assert_request("test", &[json!(2)]);
+        transport.assert_no_more_requests();
+        transport.assert_batches(&[2]);
+    }
+
+    #[test]
+    fn batching_transport_starts_new_batch_after_flush() {
+        let mut transport = TestTransport::new();
+        let batching = BatchingTransport::new(transport.clone(), LONG_WINDOW);
+
+        transport.add_response(json!(1));
+        transport.add_response(json!(2));
+        transport.add_response(json!(3));
+
+        let first = batching.execute("test", vec![json!(1)]).compat();
+        batching.flush();
+        let second = batching.execute("test", vec![json!(2)]).compat();
+        let third = batching.execute("test", vec![json!(3)]).compat();
+        batching.flush();
+
+        let (first, second, third) = futures::future::join3(first, second, third).immediate();
+        assert_eq!(first.expect("success"), json!(1));
+        assert_eq!(second.expect("success"), json!(2));
+        assert_eq!(third.expect("success"), json!(3));
+
+        transport.assert_batches(&[1, 2]);
+    }
+
+    #[test]
+    fn batching_transport_window() {
+        let mut transport = TestTransport::new();
+        let batching = DynTransport::new(BatchingTransport::new(
+            transport.clone(),
+            Duration::from_millis(10),
+        ));
+
+        transport.add_response(json!(true));
+        transport.add_response(json!(false));
+        let (first, second) = futures::executor::block_on(futures::future::join(
+            batching.execute("test", vec![json!(1)]).compat(),
+            batching.execute("test", vec![json!(2)]).compat(),
+        ));
+        assert_eq!(first.expect("success"), json!(true));
+        assert_eq!(second.expect("success"), json!(false));
+
+        transport.assert_batches(&[2]);
+    }
+
+    #[test]
+    fn batching_transport_sequential_requests() {
+        let mut transport = TestTransport::new();
+        let batching = BatchingTransport::new(transport.clone(), Duration::from_millis(10));
+
+        transport.add_response(json!(1));
+        transport.add_response(json!(2));
+        let first = futures::executor::block_on(batching.execute("test", vec![json!(1)]).compat());
+        let second = futures::executor::block_on(batching.execute("test", vec![json!(2)]).compat());
+        assert_eq!(first.expect("success"), json!(1));
+        assert_eq!(second.expect("success"), json!(2));
+
+        transport.assert_batches(&[1, 1]);
+    }
+
+    #[test]
+    fn batching_transport_scope() {
+        let mut transport = TestTransport::new();
+        let batching = BatchingTransport::new(transport.clone(), LONG_WINDOW);
+
+        transport.add_response(json!(true));
+        transport.add_response(json!(false));
+        let (first, second) = {
+            let _scope = batching.scope();
+            (
+                batching.execute("test", vec![json!(1)]).compat(),
+                batching.execute("test", vec![json!(2)]).compat(),
+            )
+        };
+
+        let (first, second) = futures::future::join(first, second).immediate();
+        assert_eq!(first.expect("success"), json!(true));
+        assert_eq!(second.expect("success"), json!(false));
+
+        transport.assert_batches(&[2]);
+    }
+
+    #[test]
+    fn batching_transport_propagates_errors() {
+        let transport = TestTransport::new();
+        let batching = BatchingTransport::new(transport.clone(), LONG_WINDOW);
+
+        let request = batching.execute("test", vec![json!(1)]).compat();
+        batching.flush();
+        request.immediate().expect_err("failed");
+
+        transport.assert_batches(&[1]);
+    }
 }